@@ -0,0 +1,115 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mpc_core::{
+    channel::{run_contributor, run_evaluator, Channel},
+    states::{Contributor, Evaluator, Msg, SecurityLevel},
+    Circuit, Error, Gate,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// A channel that delivers every message only after a fixed one-way delay, like a network link
+/// with the specified latency but unlimited bandwidth.
+struct DelayedChannel {
+    sender: Sender<(Instant, Msg)>,
+    receiver: Receiver<(Instant, Msg)>,
+    delay: Duration,
+}
+
+fn delayed_pair(delay: Duration) -> (DelayedChannel, DelayedChannel) {
+    let (to_a, from_b) = mpsc::channel();
+    let (to_b, from_a) = mpsc::channel();
+    let a = DelayedChannel {
+        sender: to_b,
+        receiver: from_b,
+        delay,
+    };
+    let b = DelayedChannel {
+        sender: to_a,
+        receiver: from_a,
+        delay,
+    };
+    (a, b)
+}
+
+impl Channel for DelayedChannel {
+    fn send(&mut self, msg: Msg) -> Result<(), Error> {
+        let delivery = Instant::now() + self.delay;
+        self.sender
+            .send((delivery, msg))
+            .map_err(|_| Error::ChannelClosed)
+    }
+
+    fn recv(&mut self) -> Result<Msg, Error> {
+        let (delivery, msg) = self.receiver.recv().map_err(|_| Error::ChannelClosed)?;
+        thread::sleep(delivery.saturating_duration_since(Instant::now()));
+        Ok(msg)
+    }
+}
+
+fn and(iterations: u32) -> Circuit {
+    let mut gates = vec![Gate::InContrib];
+    let output_gates = vec![iterations * 2];
+    for i in 0..iterations {
+        gates.append(&mut vec![Gate::InEval, Gate::And(i * 2, i * 2 + 1)]);
+    }
+    Circuit::new(gates, output_gates)
+}
+
+/// Runs both parties in their own thread, connected by a channel with the specified delay.
+fn execute(circuit: &Circuit, security_level: SecurityLevel, delay: Duration) -> Result<(), Error> {
+    let (mut channel_contrib, mut channel_eval) = delayed_pair(delay);
+    let contrib_circuit = circuit.clone();
+    let contributor = thread::spawn(move || {
+        let (contrib, msg) = Contributor::with_security_level(
+            contrib_circuit,
+            vec![true],
+            security_level,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        run_contributor(contrib, msg, &mut channel_contrib).map(|_| ())
+    });
+    let input_eval = vec![true; circuit.eval_inputs()];
+    let eval = Evaluator::with_security_level(
+        circuit,
+        input_eval,
+        security_level,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let output = run_evaluator(eval, &mut channel_eval)?;
+    contributor.join().expect("contributor panicked")?;
+    assert_eq!(output, vec![true]);
+    Ok(())
+}
+
+/// Small circuits are dominated by the round trips of the protocol once the parties are not on
+/// the same machine, which is what the one-way delays of 0, 10 and 50ms simulate.
+fn latency_benchmarks(c: &mut Criterion) {
+    let circuit = and(10);
+    for (name, security_level) in [
+        ("malicious", SecurityLevel::Malicious),
+        ("semi-honest", SecurityLevel::SemiHonest),
+    ] {
+        let mut group = c.benchmark_group(format!("and_10 {name} with delay"));
+        group.sample_size(10);
+        for delay_ms in [0, 10, 50] {
+            let delay = Duration::from_millis(delay_ms);
+            group.bench_with_input(BenchmarkId::from_parameter(delay_ms), &delay, |b, delay| {
+                b.iter(|| execute(&circuit, security_level, *delay).unwrap());
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default();
+  targets = latency_benchmarks
+}
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mpc_core::{simulate_with_security_level, states::SecurityLevel, Circuit, Gate};
+
+fn and(iterations: u32, security_level: SecurityLevel) -> Result<(), mpc_core::Error> {
+    let mut gates = vec![Gate::InContrib];
+    let output_gates = vec![iterations * 2];
+    for i in 0..iterations {
+        gates.append(&mut vec![Gate::InEval, Gate::And(i * 2, i * 2 + 1)]);
+    }
+
+    let program = Circuit::new(gates, output_gates);
+
+    let input_a = vec![true];
+    let input_b = vec![true; iterations as usize];
+
+    let result = simulate_with_security_level(&program, &input_a, &input_b, security_level)?;
+
+    assert_eq!(result, vec![true]);
+
+    Ok(())
+}
+
+fn security_levels_benchmarks(c: &mut Criterion) {
+    for (name, security_level) in [
+        ("malicious", SecurityLevel::Malicious),
+        ("semi-honest", SecurityLevel::SemiHonest),
+    ] {
+        let mut group = c.benchmark_group(format!("AND gates mpc_core {name}"));
+        for iterations in [10, 100, 1_000, 10_000].iter() {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(iterations),
+                iterations,
+                |b, &iterations| {
+                    b.iter(|| and(iterations, security_level));
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default();
+  targets = security_levels_benchmarks
+}
+criterion_main!(benches);
@@ -0,0 +1,820 @@
+//! Arithmetic secret sharing over `Z_2^64`, for computations dominated by additions and
+//! multiplications of 64-bit integers.
+//!
+//! An [`ArithCircuit`] computes on values that are additively shared between the contributor and
+//! the evaluator, with every value being the wrapping sum of the shares of both parties. Additions,
+//! subtractions and multiplications with public constants are local and free. Multiplications of
+//! two shared values consume a multiplication triple each (Beaver, "Efficient Multiparty Protocols
+//! Using Circuit Randomization", 1991) and cost one round of interaction for every layer of the
+//! circuit, regardless of the number of multiplications in the layer.
+//!
+//! The triples are generated before the first multiplication from OTs (Gilboa, "Two Party RSA Key
+//! Generation", 1999): the cross terms of a triple are computed with 64 OTs each, extended with
+//! the same base OTs and OT extension that the other protocols use. The contributor chooses the
+//! bits of its shares of the triples as the choice bits of the extended OTs and the evaluator
+//! turns the resulting random OTs into correlated ones.
+//!
+//! # Conversions
+//!
+//! Computations can switch between arithmetic shares and garbled circuits in both directions:
+//!
+//! - Arithmetic to boolean: each party provides its share of a value as 64 input bits of a
+//!   [`Circuit`](crate::Circuit), which adds them up again using
+//!   [`CircuitBuilder::input_arith`](crate::gadgets::CircuitBuilder::input_arith).
+//! - Boolean to arithmetic: the contributor chooses a random mask as 64 additional input bits and
+//!   the circuit reveals the value minus the mask to the evaluator using
+//!   [`CircuitBuilder::output_arith`](crate::gadgets::CircuitBuilder::output_arith). The revealed
+//!   difference is the share of the evaluator, the mask the share of the contributor, both of which
+//!   can be used as [`ArithGate::InShared`] in a subsequent arithmetic circuit.
+//!
+//! # Security
+//!
+//! The arithmetic engine is secure against semi-honest parties only: the shares are not
+//! authenticated and a party that deviates from the protocol can add arbitrary errors to the
+//! result of a multiplication without being detected. The garbled circuits of a mixed computation
+//! keep the security of the protocol that executes them, but cannot detect errors in the shares
+//! that they receive as input.
+//!
+//! # Examples
+//!
+//! ```
+//! use mpc_core::arithmetic::{ArithCircuit, ArithGate, Contributor, Evaluator};
+//! use rand::SeedableRng;
+//! use rand_chacha::ChaCha20Rng;
+//!
+//! // The dot product of the contributor's and the evaluator's 2-dimensional vectors:
+//! let circuit = ArithCircuit::new(
+//!     vec![
+//!         ArithGate::InContrib,
+//!         ArithGate::InContrib,
+//!         ArithGate::InEval,
+//!         ArithGate::InEval,
+//!         ArithGate::Mul(0, 2),
+//!         ArithGate::Mul(1, 3),
+//!         ArithGate::Add(4, 5),
+//!     ],
+//!     vec![6],
+//! );
+//!
+//! let rng = ChaCha20Rng::from_entropy();
+//! let (mut contrib, msg) = Contributor::new(&circuit, &[3, 4][..], &[][..], rng).unwrap();
+//! let rng = ChaCha20Rng::from_entropy();
+//! let mut eval = Evaluator::new(&circuit, &[5, 6][..], &[][..], rng).unwrap();
+//!
+//! let mut msg = Some(msg);
+//! while let Some(m) = msg.take() {
+//!     let (next_state, reply) = eval.run(&m).unwrap();
+//!     eval = next_state;
+//!     if let Some(reply) = reply {
+//!         let (next_state, reply) = contrib.run(&reply).unwrap();
+//!         contrib = next_state;
+//!         msg = reply;
+//!     }
+//! }
+//!
+//! let contrib_shares = contrib.output().unwrap();
+//! let eval_shares = eval.output().unwrap();
+//! assert_eq!(contrib_shares[0].wrapping_add(eval_shares[0]), 3 * 5 + 4 * 6);
+//! ```
+
+use std::borrow::Borrow;
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::RngCore;
+
+use crate::{
+    hash::{domain, hash_keys, DomainKey},
+    leakydelta_ot::{
+        message::{OtInitReply, SerializedOtInit},
+        LeakyOtReceiver, LeakyOtSender, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+    },
+    message::{self, ArithOpenings, ArithOtExtension, ArithTriples, Step},
+    states::Msg,
+    types::{Delta, KeyType, MacType},
+    Error,
+};
+
+/// The index of a gate in an [`ArithCircuit`].
+pub type ArithIndex = u32;
+
+/// The number of bits of an arithmetic value.
+pub const ARITH_BITS: usize = 64;
+
+/// A single gate of an [`ArithCircuit`], with all values in `Z_2^64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithGate {
+    /// A value that the contributor provides as input.
+    InContrib,
+    /// A value that the evaluator provides as input.
+    InEval,
+    /// A value that is already shared between the parties, for example by a conversion from a
+    /// garbled circuit, with each party providing its share as input.
+    InShared,
+    /// A public constant.
+    Const(u64),
+    /// The wrapping sum of two values.
+    Add(ArithIndex, ArithIndex),
+    /// The wrapping difference of two values.
+    Sub(ArithIndex, ArithIndex),
+    /// The wrapping product of two values, which consumes a multiplication triple.
+    Mul(ArithIndex, ArithIndex),
+    /// The wrapping product of a value and a public constant.
+    MulConst(ArithIndex, u64),
+}
+
+/// A circuit of additions and multiplications over `Z_2^64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArithCircuit {
+    gates: Vec<ArithGate>,
+    output_gates: Vec<ArithIndex>,
+}
+
+impl ArithCircuit {
+    /// Creates a new circuit from its gates and the indices of its outputs.
+    pub fn new(gates: Vec<ArithGate>, output_gates: Vec<ArithIndex>) -> Self {
+        Self {
+            gates,
+            output_gates,
+        }
+    }
+
+    /// Returns all gates of the circuit.
+    pub fn gates(&self) -> &[ArithGate] {
+        &self.gates
+    }
+
+    /// Returns the indices of the outputs of the circuit.
+    pub fn output_gates(&self) -> &[ArithIndex] {
+        &self.output_gates
+    }
+
+    /// The number of multiplications of two shared values, each of which consumes a triple.
+    pub fn mul_gates(&self) -> usize {
+        self.count(|g| matches!(g, ArithGate::Mul(_, _)))
+    }
+
+    /// The number of input values of the contributor.
+    pub fn contrib_inputs(&self) -> usize {
+        self.count(|g| *g == ArithGate::InContrib)
+    }
+
+    /// The number of input values of the evaluator.
+    pub fn eval_inputs(&self) -> usize {
+        self.count(|g| *g == ArithGate::InEval)
+    }
+
+    /// The number of shared input values, for which each party provides its share.
+    pub fn shared_inputs(&self) -> usize {
+        self.count(|g| *g == ArithGate::InShared)
+    }
+
+    fn count(&self, f: impl Fn(&ArithGate) -> bool) -> usize {
+        self.gates.iter().filter(|g| f(g)).count()
+    }
+
+    /// Checks that every gate only refers to preceding gates and every output to an existing gate.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (i, gate) in self.gates.iter().enumerate() {
+            let i = i as ArithIndex;
+            let valid = match *gate {
+                ArithGate::InContrib
+                | ArithGate::InEval
+                | ArithGate::InShared
+                | ArithGate::Const(_) => true,
+                ArithGate::Add(x, y) | ArithGate::Sub(x, y) | ArithGate::Mul(x, y) => {
+                    x < i && y < i
+                }
+                ArithGate::MulConst(x, _) => x < i,
+            };
+            if !valid {
+                return Err(Error::InvalidCircuit);
+            }
+        }
+        if self.gates.len() > ArithIndex::MAX as usize
+            || self.output_gates.is_empty()
+            || self
+                .output_gates
+                .iter()
+                .any(|o| *o as usize >= self.gates.len())
+        {
+            return Err(Error::InvalidCircuit);
+        }
+        Ok(())
+    }
+
+    /// The multiplicative depth of every gate, which is the round in which it can be computed.
+    fn depths(&self) -> Vec<usize> {
+        let mut depths: Vec<usize> = Vec::with_capacity(self.gates.len());
+        for gate in self.gates.iter() {
+            let depth = match *gate {
+                ArithGate::InContrib
+                | ArithGate::InEval
+                | ArithGate::InShared
+                | ArithGate::Const(_) => 0,
+                ArithGate::Add(x, y) | ArithGate::Sub(x, y) => {
+                    depths[x as usize].max(depths[y as usize])
+                }
+                ArithGate::Mul(x, y) => depths[x as usize].max(depths[y as usize]) + 1,
+                ArithGate::MulConst(x, _) => depths[x as usize],
+            };
+            depths.push(depth);
+        }
+        depths
+    }
+
+    /// The number of layers of multiplications, each of which costs one round of interaction.
+    pub fn mul_depth(&self) -> usize {
+        self.depths().into_iter().max().unwrap_or(0)
+    }
+
+    /// Evaluates the circuit in plaintext, with the shared inputs given as their sum.
+    pub fn eval(
+        &self,
+        contrib_input: &[u64],
+        eval_input: &[u64],
+        shared_input: &[u64],
+    ) -> Result<Vec<u64>, Error> {
+        self.validate()?;
+        self.validate_inputs(contrib_input, eval_input, shared_input)?;
+        let mut contrib_input = contrib_input.iter();
+        let mut eval_input = eval_input.iter();
+        let mut shared_input = shared_input.iter();
+        let mut values: Vec<u64> = Vec::with_capacity(self.gates.len());
+        for gate in self.gates.iter() {
+            let value = match *gate {
+                ArithGate::InContrib => *contrib_input.next().ok_or(Error::InsufficientInput)?,
+                ArithGate::InEval => *eval_input.next().ok_or(Error::InsufficientInput)?,
+                ArithGate::InShared => *shared_input.next().ok_or(Error::InsufficientInput)?,
+                ArithGate::Const(c) => c,
+                ArithGate::Add(x, y) => values[x as usize].wrapping_add(values[y as usize]),
+                ArithGate::Sub(x, y) => values[x as usize].wrapping_sub(values[y as usize]),
+                ArithGate::Mul(x, y) => values[x as usize].wrapping_mul(values[y as usize]),
+                ArithGate::MulConst(x, c) => values[x as usize].wrapping_mul(c),
+            };
+            values.push(value);
+        }
+        Ok(self
+            .output_gates
+            .iter()
+            .map(|o| values[*o as usize])
+            .collect())
+    }
+
+    fn validate_inputs(
+        &self,
+        contrib_input: &[u64],
+        eval_input: &[u64],
+        shared_input: &[u64],
+    ) -> Result<(), Error> {
+        if contrib_input.len() != self.contrib_inputs()
+            || eval_input.len() != self.eval_inputs()
+            || shared_input.len() != self.shared_inputs()
+        {
+            return Err(Error::InsufficientInput);
+        }
+        Ok(())
+    }
+
+    /// The hash of the circuit, to which the hashes of the triple generation are bound.
+    fn blake3_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        for gate in self.gates.iter() {
+            let (tag, x, y) = match *gate {
+                ArithGate::InContrib => (0u8, 0, 0),
+                ArithGate::InEval => (1, 0, 0),
+                ArithGate::InShared => (2, 0, 0),
+                ArithGate::Const(c) => (3, c, 0),
+                ArithGate::Add(x, y) => (4, x as u64, y as u64),
+                ArithGate::Sub(x, y) => (5, x as u64, y as u64),
+                ArithGate::Mul(x, y) => (6, x as u64, y as u64),
+                ArithGate::MulConst(x, c) => (7, x as u64, c),
+            };
+            hasher.update(&[tag]);
+            hasher.update(&x.to_le_bytes());
+            hasher.update(&y.to_le_bytes());
+        }
+        for output_gate in self.output_gates.iter() {
+            hasher.update(&output_gate.to_le_bytes());
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
+/// The contributor of an arithmetic computation, which starts the protocol.
+pub struct Contributor<C: Borrow<ArithCircuit>> {
+    engine: Engine<C>,
+}
+
+/// The evaluator of an arithmetic computation.
+///
+/// Unlike in the garbled protocol, both parties play almost the same role and receive shares of
+/// the output, the roles only determine the direction of the OTs and who starts.
+pub struct Evaluator<C: Borrow<ArithCircuit>> {
+    engine: Engine<C>,
+}
+
+impl<C: Borrow<ArithCircuit>> Contributor<C> {
+    /// Initializes the contributor with its input values and its shares of the shared inputs,
+    /// returning the contributor and the initial message for the evaluator.
+    pub fn new(
+        circuit: C,
+        input: &[u64],
+        shares: &[u64],
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        let (r_init, ot_msg) = ReceiverInitializer::init(&mut rng);
+        let state = EngineState::OtInit { rng, r_init };
+        let engine = Engine::new(circuit, Role::Contributor, input, shares, state)?;
+        let msg = message::seal(Step::ArithOtInit, None, &ot_msg.serialize())?;
+        Ok((Self { engine }, msg))
+    }
+
+    /// Executes the next step of the protocol, returning the next state and the reply for the
+    /// evaluator, or `None` if the protocol has ended for both parties.
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Option<Msg>), Error> {
+        let (engine, reply) = self.engine.run(msg)?;
+        Ok((Self { engine }, reply))
+    }
+
+    /// Returns the own shares of the outputs of the circuit, once the protocol has ended.
+    pub fn output(&self) -> Result<Vec<u64>, Error> {
+        self.engine.output()
+    }
+}
+
+impl<C: Borrow<ArithCircuit>> Evaluator<C> {
+    /// Initializes the evaluator with its input values and its shares of the shared inputs.
+    pub fn new(circuit: C, input: &[u64], shares: &[u64], rng: ChaCha20Rng) -> Result<Self, Error> {
+        let state = EngineState::Init { rng };
+        let engine = Engine::new(circuit, Role::Evaluator, input, shares, state)?;
+        Ok(Self { engine })
+    }
+
+    /// Executes the next step of the protocol, returning the next state and the reply for the
+    /// contributor, or `None` if the protocol has ended for both parties.
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Option<Msg>), Error> {
+        let (engine, reply) = self.engine.run(msg)?;
+        Ok((Self { engine }, reply))
+    }
+
+    /// Returns the own shares of the outputs of the circuit, once the protocol has ended.
+    pub fn output(&self) -> Result<Vec<u64>, Error> {
+        self.engine.output()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Contributor,
+    Evaluator,
+}
+
+/// The own shares `(a, b, c)` of a multiplication triple with `c = a * b`.
+#[derive(Clone, Copy)]
+struct Triple {
+    a: u64,
+    b: u64,
+    c: u64,
+}
+
+/// The own shares `(x - a, y - b)` of the masked inputs of a multiplication.
+type Opening = (u64, u64);
+
+/// The state shared by both parties.
+struct Engine<C: Borrow<ArithCircuit>> {
+    circuit: C,
+    role: Role,
+    /// The own shares of the values of all gates computed so far.
+    values: Vec<Option<u64>>,
+    depths: Vec<usize>,
+    mul_depth: usize,
+    state: EngineState,
+}
+
+enum EngineState {
+    /// The contributor has started the base OTs.
+    OtInit {
+        rng: ChaCha20Rng,
+        r_init: ReceiverInitializer,
+    },
+    /// The evaluator waits for the base OTs of the contributor.
+    Init { rng: ChaCha20Rng },
+    /// The evaluator has answered the base OTs and waits for the extended OTs.
+    OtResponse {
+        rng: ChaCha20Rng,
+        delta: Delta,
+        s_init: SenderInitializer,
+    },
+    /// The contributor has sent its extended OTs and waits for the corrections of the triples.
+    Extended {
+        triples: Vec<Triple>,
+        macs: Vec<MacType>,
+    },
+    /// Both parties compute the multiplications layer by layer.
+    Multiplying(Layers),
+}
+
+/// The progress of the multiplications, one layer of the circuit at a time.
+struct Layers {
+    triples: Vec<Triple>,
+    /// The number of layers whose multiplications have been computed.
+    completed: usize,
+    /// The own openings of every layer computed so far.
+    own: Vec<Vec<Opening>>,
+    /// The number of layers whose own openings have been sent to the other party.
+    sent: usize,
+    /// The openings of the other party received so far.
+    other: Vec<Vec<Opening>>,
+}
+
+impl<C: Borrow<ArithCircuit>> Engine<C> {
+    fn new(
+        circuit: C,
+        role: Role,
+        input: &[u64],
+        shares: &[u64],
+        state: EngineState,
+    ) -> Result<Self, Error> {
+        let c = circuit.borrow();
+        c.validate()?;
+        let (contrib_input, eval_input) = match role {
+            Role::Contributor => (input, &[][..]),
+            Role::Evaluator => (&[][..], input),
+        };
+        let (contribs, evals) = match role {
+            Role::Contributor => (c.contrib_inputs(), 0),
+            Role::Evaluator => (0, c.eval_inputs()),
+        };
+        if contrib_input.len() != contribs
+            || eval_input.len() != evals
+            || shares.len() != c.shared_inputs()
+        {
+            return Err(Error::InsufficientInput);
+        }
+
+        // inputs are shared as the value itself and a share of zero for the other party, which
+        // reveals nothing, constants are added by the contributor only:
+        let is_contrib = role == Role::Contributor;
+        let mut input = input.iter();
+        let mut shares = shares.iter();
+        let mut values = Vec::with_capacity(c.gates().len());
+        for gate in c.gates().iter() {
+            values.push(match *gate {
+                ArithGate::InContrib if is_contrib => input.next().copied(),
+                ArithGate::InEval if !is_contrib => input.next().copied(),
+                ArithGate::InContrib | ArithGate::InEval => Some(0),
+                ArithGate::InShared => shares.next().copied(),
+                ArithGate::Const(c) => Some(if is_contrib { c } else { 0 }),
+                _ => None,
+            });
+        }
+        let depths = c.depths();
+        let mul_depth = depths.iter().copied().max().unwrap_or(0);
+        Ok(Self {
+            circuit,
+            role,
+            values,
+            depths,
+            mul_depth,
+            state,
+        })
+    }
+
+    fn run(self, msg: &[u8]) -> Result<(Self, Option<Msg>), Error> {
+        let Self {
+            circuit,
+            role,
+            mut values,
+            depths,
+            mul_depth,
+            state,
+        } = self;
+        let c = circuit.borrow();
+        let domain = DomainKey::new(domain::ARITHMETIC, &c.blake3_hash());
+        let triples = c.mul_gates();
+        let (state, reply) = match (role, state) {
+            (Role::Evaluator, EngineState::Init { mut rng }) => {
+                let ot_msg: SerializedOtInit = message::open(msg, Step::ArithOtInit, None)?;
+                let delta = Delta::gen_random(&mut rng);
+                let (s_init, reply) =
+                    SenderInitializer::init(&mut rng, delta.clone(), &ot_msg.deserialize()?);
+                let reply = message::seal(Step::ArithOtResponse, None, &reply.serialize())?;
+                let state = EngineState::OtResponse { rng, delta, s_init };
+                (state, Some(reply))
+            }
+            (Role::Contributor, EngineState::OtInit { mut rng, r_init }) => {
+                let ot_msg: SerializedOtInit = message::open(msg, Step::ArithOtResponse, None)?;
+                let (mut receiver, reply) = r_init.recv(&ot_msg.deserialize()?);
+                let (triples, macs, blocks) = extend(&mut rng, &mut receiver, triples);
+                let reply = ArithOtExtension {
+                    reply: reply.serialize(),
+                    blocks,
+                };
+                let reply = message::seal(Step::ArithOtExtension, None, &reply)?;
+                (EngineState::Extended { triples, macs }, Some(reply))
+            }
+            (
+                Role::Evaluator,
+                EngineState::OtResponse {
+                    mut rng,
+                    delta,
+                    s_init,
+                },
+            ) => {
+                let ArithOtExtension { reply, blocks } =
+                    message::open(msg, Step::ArithOtExtension, None)?;
+                if blocks.len() != triples {
+                    return Err(Error::unexpected_length(triples, blocks.len()));
+                }
+                let mut sender = s_init.recv(&OtInitReply::deserialize(reply)?);
+                let (triples, corrections) =
+                    correlate(&mut rng, &mut sender, &domain, &delta, blocks)?;
+                let mut layers = Layers::new(triples);
+                layers.advance(c, &mut values, &depths, role, mul_depth);
+                let openings = layers.unsent();
+                let reply = ArithTriples {
+                    corrections,
+                    openings,
+                };
+                let reply = message::seal(Step::ArithTriples, None, &reply)?;
+                (EngineState::Multiplying(layers), Some(reply))
+            }
+            (Role::Contributor, EngineState::Extended { mut triples, macs }) => {
+                let ArithTriples {
+                    corrections,
+                    openings,
+                } = message::open(msg, Step::ArithTriples, None)?;
+                complete_triples(&domain, &mut triples, &macs, &corrections)?;
+                let mut layers = Layers::new(triples);
+                layers.receive(c, &depths, openings)?;
+                layers.advance(c, &mut values, &depths, role, mul_depth);
+                let reply = layers.reply()?;
+                (EngineState::Multiplying(layers), reply)
+            }
+            (_, EngineState::Multiplying(mut layers)) => {
+                if layers.completed == mul_depth && layers.sent == mul_depth {
+                    return Err(Error::ProtocolEnded);
+                }
+                let ArithOpenings { openings } = message::open(msg, Step::ArithOpenings, None)?;
+                layers.receive(c, &depths, openings)?;
+                layers.advance(c, &mut values, &depths, role, mul_depth);
+                let reply = layers.reply()?;
+                (EngineState::Multiplying(layers), reply)
+            }
+            _ => return Err(Error::ProtocolEnded),
+        };
+        let engine = Self {
+            circuit,
+            role,
+            values,
+            depths,
+            mul_depth,
+            state,
+        };
+        Ok((engine, reply))
+    }
+
+    fn output(&self) -> Result<Vec<u64>, Error> {
+        match &self.state {
+            EngineState::Multiplying(layers) if layers.completed == self.mul_depth => {
+                let outputs = self.circuit.borrow().output_gates().iter();
+                outputs
+                    .map(|o| self.values[*o as usize].ok_or(Error::ProtocolStillInProgress))
+                    .collect()
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+}
+
+/// The tweak of the hash of an OT, unique within the circuit.
+fn tweak(triple: usize, ot: usize) -> KeyType {
+    KeyType((triple * BLOCK_SIZE + ot) as u128)
+}
+
+fn hash(domain: &DomainKey, key: MacType, triple: usize, ot: usize) -> u64 {
+    hash_keys(domain, KeyType(key.0), tweak(triple, ot)).0 as u64
+}
+
+/// Generates the contributor's shares of the triples and extends one block of OTs for each of
+/// them, choosing the bits of `a` and `b` of the triple.
+fn extend(
+    rng: &mut ChaCha20Rng,
+    receiver: &mut LeakyOtReceiver,
+    triples: usize,
+) -> (Vec<Triple>, Vec<MacType>, Vec<Vec<MacType>>) {
+    let mut shares = Vec::with_capacity(triples);
+    let mut macs = Vec::with_capacity(triples * BLOCK_SIZE);
+    let mut blocks = Vec::with_capacity(triples);
+    for _ in 0..triples {
+        let (a, b) = (rng.next_u64(), rng.next_u64());
+        let bits = a as u128 | ((b as u128) << ARITH_BITS);
+        let mut macs_out = [MacType(0); BLOCK_SIZE];
+        let mut ot_out = [MacType(0); BLOCK_SIZE];
+        receiver.new_batch(bits, &mut macs_out, &mut ot_out);
+        shares.push(Triple {
+            a,
+            b,
+            c: a.wrapping_mul(b),
+        });
+        macs.extend(macs_out);
+        blocks.push(ot_out.to_vec());
+    }
+    (shares, macs, blocks)
+}
+
+/// Generates the evaluator's shares of the triples and turns the random OTs into correlated ones,
+/// so that the contributor learns `s + bit * x * 2^i` for the evaluator's `-s` and the factor `x`
+/// of the cross term, which is `b` of the evaluator for the bits of `a` of the contributor and `a`
+/// for the bits of `b`.
+fn correlate(
+    rng: &mut ChaCha20Rng,
+    sender: &mut LeakyOtSender,
+    domain: &DomainKey,
+    delta: &Delta,
+    blocks: Vec<Vec<MacType>>,
+) -> Result<(Vec<Triple>, Vec<u64>), Error> {
+    let mut triples = Vec::with_capacity(blocks.len());
+    let mut corrections = Vec::with_capacity(blocks.len() * BLOCK_SIZE);
+    for (t, block) in blocks.into_iter().enumerate() {
+        let ot_rx: [MacType; BLOCK_SIZE] = block
+            .try_into()
+            .map_err(|_| Error::OtBlockDeserializationError)?;
+        let mut keys = [MacType(0); BLOCK_SIZE];
+        sender.send(&ot_rx, &mut keys);
+        let (a, b) = (rng.next_u64(), rng.next_u64());
+        let mut c = a.wrapping_mul(b);
+        for (i, key) in keys.iter().enumerate() {
+            let factor = if i < ARITH_BITS { b } else { a };
+            let shift = i % ARITH_BITS;
+            let s0 = hash(domain, *key, t, i);
+            let s1 = hash(domain, delta.xor(*key), t, i);
+            corrections.push(s0.wrapping_sub(s1).wrapping_add(factor << shift));
+            c = c.wrapping_sub(s0);
+        }
+        triples.push(Triple { a, b, c });
+    }
+    Ok((triples, corrections))
+}
+
+/// Completes the contributor's shares of the triples with the correlated OTs.
+fn complete_triples(
+    domain: &DomainKey,
+    triples: &mut [Triple],
+    macs: &[MacType],
+    corrections: &[u64],
+) -> Result<(), Error> {
+    if corrections.len() != macs.len() {
+        return Err(Error::unexpected_length(macs.len(), corrections.len()));
+    }
+    for (t, triple) in triples.iter_mut().enumerate() {
+        for i in 0..BLOCK_SIZE {
+            let bits = if i < ARITH_BITS { triple.a } else { triple.b };
+            let bit = (bits >> (i % ARITH_BITS)) & 1 == 1;
+            let k = t * BLOCK_SIZE + i;
+            let mut share = hash(domain, macs[k], t, i);
+            if bit {
+                share = share.wrapping_add(corrections[k]);
+            }
+            triple.c = triple.c.wrapping_add(share);
+        }
+    }
+    Ok(())
+}
+
+impl Layers {
+    fn new(triples: Vec<Triple>) -> Self {
+        Self {
+            triples,
+            completed: 0,
+            own: vec![],
+            sent: 0,
+            other: vec![],
+        }
+    }
+
+    /// Stores the openings of the other party, which must contain one opening for every
+    /// multiplication of each layer.
+    fn receive(
+        &mut self,
+        circuit: &ArithCircuit,
+        depths: &[usize],
+        openings: Vec<Vec<Opening>>,
+    ) -> Result<(), Error> {
+        for layer in openings {
+            let depth = self.other.len() + 1;
+            let expected = mul_gates_of(circuit, depths, depth).count();
+            if expected == 0 || layer.len() != expected {
+                return Err(Error::unexpected_length(expected, layer.len()));
+            }
+            self.other.push(layer);
+        }
+        Ok(())
+    }
+
+    /// Computes as many layers as possible with the openings received so far.
+    fn advance(
+        &mut self,
+        circuit: &ArithCircuit,
+        values: &mut [Option<u64>],
+        depths: &[usize],
+        role: Role,
+        mul_depth: usize,
+    ) {
+        evaluate_linear(circuit, values, depths, self.completed);
+        loop {
+            let next = self.completed + 1;
+            if next > mul_depth {
+                return;
+            }
+            if self.own.len() < next {
+                let openings = mul_gates_of(circuit, depths, next)
+                    .map(|(_, t, x, y)| {
+                        let triple = &self.triples[t];
+                        let (x, y) = (values[x].unwrap_or(0), values[y].unwrap_or(0));
+                        (x.wrapping_sub(triple.a), y.wrapping_sub(triple.b))
+                    })
+                    .collect();
+                self.own.push(openings);
+            }
+            let Some(other) = self.other.get(self.completed) else {
+                return;
+            };
+            let own = &self.own[self.completed];
+            let gates = mul_gates_of(circuit, depths, next);
+            for ((gate, t, _, _), (own, other)) in gates.zip(own.iter().zip(other.iter())) {
+                let triple = &self.triples[t];
+                let d = own.0.wrapping_add(other.0);
+                let e = own.1.wrapping_add(other.1);
+                // `x * y = (d + a) * (e + b) = c + d * b + e * a + d * e`:
+                let mut z = triple
+                    .c
+                    .wrapping_add(d.wrapping_mul(triple.b))
+                    .wrapping_add(e.wrapping_mul(triple.a));
+                if role == Role::Contributor {
+                    z = z.wrapping_add(d.wrapping_mul(e));
+                }
+                values[gate] = Some(z);
+            }
+            self.completed = next;
+            evaluate_linear(circuit, values, depths, self.completed);
+        }
+    }
+
+    /// The own openings that have not been sent to the other party yet.
+    fn unsent(&mut self) -> Vec<Vec<Opening>> {
+        let unsent = self.own[self.sent..].to_vec();
+        self.sent = self.own.len();
+        unsent
+    }
+
+    /// The reply to the other party, or `None` if it already has all own openings.
+    fn reply(&mut self) -> Result<Option<Msg>, Error> {
+        if self.sent == self.own.len() {
+            return Ok(None);
+        }
+        let openings = self.unsent();
+        let msg = message::seal(Step::ArithOpenings, None, &ArithOpenings { openings })?;
+        Ok(Some(msg))
+    }
+}
+
+/// The multiplications of the specified layer, as their gate, triple and input gates.
+fn mul_gates_of<'a>(
+    circuit: &'a ArithCircuit,
+    depths: &'a [usize],
+    depth: usize,
+) -> impl Iterator<Item = (usize, usize, usize, usize)> + 'a {
+    let muls = circuit
+        .gates()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, g)| match *g {
+            ArithGate::Mul(x, y) => Some((i, x as usize, y as usize)),
+            _ => None,
+        });
+    muls.enumerate()
+        .filter(move |(_, (i, _, _))| depths[*i] == depth)
+        .map(|(t, (i, x, y))| (i, t, x, y))
+}
+
+/// Computes the own shares of all linear gates up to the specified depth.
+fn evaluate_linear(
+    circuit: &ArithCircuit,
+    values: &mut [Option<u64>],
+    depths: &[usize],
+    depth: usize,
+) {
+    for (i, gate) in circuit.gates().iter().enumerate() {
+        if values[i].is_some() || depths[i] > depth {
+            continue;
+        }
+        let value = |g: ArithIndex| values[g as usize].unwrap_or(0);
+        values[i] = match *gate {
+            ArithGate::Add(x, y) => Some(value(x).wrapping_add(value(y))),
+            ArithGate::Sub(x, y) => Some(value(x).wrapping_sub(value(y))),
+            ArithGate::MulConst(x, c) => Some(value(x).wrapping_mul(c)),
+            _ => None,
+        };
+    }
+}
@@ -0,0 +1,152 @@
+//! Batched execution of the same circuit on many independent inputs.
+//!
+//! Executing a circuit once per input pair repeats the base OT initialization, the coin tossing and
+//! the bucketing setup for every single execution. A [`Batch`] instead places `n` independent
+//! copies of the circuit side by side in a single circuit, so that all instances are evaluated by
+//! one execution of the protocol, with the setup and the generation of AND triples running once for
+//! `n` times the AND gates of the circuit. Since the batch is an ordinary circuit, the execution
+//! has exactly the same security guarantees as `n` separate executions.
+//!
+//! The inputs of each party are joined in the order of the instances, the output of the batch is
+//! split into one output per instance:
+//!
+//! ```
+//! use mpc_core::{batch::Batch, simulate, Circuit, Error, Gate};
+//!
+//! # fn main() -> Result<(), Error> {
+//! let and = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)], vec![2]);
+//! let batch = Batch::new(&and, 3)?;
+//! let input_contributor = batch.join_contributor_inputs(&[[true], [true], [false]])?;
+//! let input_evaluator = batch.join_evaluator_inputs(&[[true], [false], [true]])?;
+//! let output = simulate(batch.circuit(), &input_contributor, &input_evaluator)?;
+//! assert_eq!(batch.split_output(&output)?, vec![[true], [false], [false]]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Borrow;
+
+use crate::{simulate, Circuit, Error, Gate, GateIndex};
+
+/// A circuit consisting of several independent instances of the same circuit.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    circuit: Circuit,
+    instances: usize,
+    contrib_inputs: usize,
+    eval_inputs: usize,
+    outputs: usize,
+}
+
+impl Batch {
+    /// Builds a batch of `instances` copies of the circuit.
+    ///
+    /// Fails with [`Error::EmptyBatch`] if `instances` is 0 and with
+    /// [`Error::MaxCircuitSizeExceeded`] if the batch has too many gates to be executed as a single
+    /// circuit, in which case the instances need to be split into several batches.
+    pub fn new(circuit: &Circuit, instances: usize) -> Result<Self, Error> {
+        circuit.validate()?;
+        if instances == 0 {
+            return Err(Error::EmptyBatch);
+        }
+        let gates_per_instance = circuit.gates().len();
+        let total_gates = gates_per_instance
+            .checked_mul(instances)
+            .filter(|total| *total <= GateIndex::MAX as usize)
+            .ok_or(Error::MaxCircuitSizeExceeded)?;
+
+        let mut gates = Vec::with_capacity(total_gates);
+        let mut output_gates = Vec::with_capacity(circuit.output_gates().len() * instances);
+        for instance in 0..instances {
+            let offset = (instance * gates_per_instance) as GateIndex;
+            for gate in circuit.gates() {
+                gates.push(match *gate {
+                    Gate::InContrib => Gate::InContrib,
+                    Gate::InEval => Gate::InEval,
+                    Gate::InParty(p) => Gate::InParty(p),
+                    Gate::Xor(x, y) => Gate::Xor(x + offset, y + offset),
+                    Gate::And(x, y) => Gate::And(x + offset, y + offset),
+                    Gate::Not(x) => Gate::Not(x + offset),
+                });
+            }
+            output_gates.extend(circuit.output_gates().iter().map(|o| o + offset));
+        }
+        let batch = Circuit::new(gates, output_gates);
+        batch.validate()?;
+        Ok(Self {
+            circuit: batch,
+            instances,
+            contrib_inputs: circuit.contrib_inputs(),
+            eval_inputs: circuit.eval_inputs(),
+            outputs: circuit.output_gates().len(),
+        })
+    }
+
+    /// The circuit evaluating all instances of the batch.
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// The number of instances in the batch.
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// Joins the inputs of the contributor for all instances into the input of the batch.
+    pub fn join_contributor_inputs<I: Borrow<[bool]>>(
+        &self,
+        inputs: &[I],
+    ) -> Result<Vec<bool>, Error> {
+        self.join(inputs, self.contrib_inputs)
+    }
+
+    /// Joins the inputs of the evaluator for all instances into the input of the batch.
+    pub fn join_evaluator_inputs<I: Borrow<[bool]>>(
+        &self,
+        inputs: &[I],
+    ) -> Result<Vec<bool>, Error> {
+        self.join(inputs, self.eval_inputs)
+    }
+
+    /// Splits the output of the batch into the outputs of the individual instances.
+    pub fn split_output(&self, output: &[bool]) -> Result<Vec<Vec<bool>>, Error> {
+        if output.len() != self.outputs * self.instances {
+            return Err(Error::unexpected_length(
+                self.outputs * self.instances,
+                output.len(),
+            ));
+        }
+        Ok(output.chunks(self.outputs).map(<[bool]>::to_vec).collect())
+    }
+
+    fn join<I: Borrow<[bool]>>(&self, inputs: &[I], bits: usize) -> Result<Vec<bool>, Error> {
+        if inputs.len() != self.instances {
+            return Err(Error::InsufficientInput);
+        }
+        let mut joined = Vec::with_capacity(bits * self.instances);
+        for input in inputs {
+            if input.borrow().len() != bits {
+                return Err(Error::InsufficientInput);
+            }
+            joined.extend_from_slice(input.borrow());
+        }
+        Ok(joined)
+    }
+}
+
+/// Simulates the local execution of a circuit on the inputs of several instances, using a single
+/// execution of the 2-party secure computation protocol for all of them.
+///
+/// The `i`-th output is the output of the circuit for the `i`-th input of each party, exactly as if
+/// [`simulate`] had been called separately for every instance.
+pub fn simulate_batch<I: Borrow<[bool]>>(
+    circuit: &Circuit,
+    inputs_contributor: &[I],
+    inputs_evaluator: &[I],
+) -> Result<Vec<Vec<bool>>, Error> {
+    let batch = Batch::new(circuit, inputs_contributor.len())?;
+    let input_contributor = batch.join_contributor_inputs(inputs_contributor)?;
+    let input_evaluator = batch.join_evaluator_inputs(inputs_evaluator)?;
+    let output = simulate(batch.circuit(), &input_contributor, &input_evaluator)?;
+    batch.split_output(&output)
+}
@@ -0,0 +1,496 @@
+//! Transports for the messages of the protocol and drivers that execute a whole protocol over them.
+//!
+//! The parties in [`states`](crate::states) are sans-IO and leave the sending and receiving of
+//! messages to the user of this crate. Most users however just want to run the protocol to the
+//! end over some connection, which is what [`run_contributor`] and [`run_evaluator`] do over any
+//! blocking [`Channel`], and [`run_contributor_async`] and [`run_evaluator_async`] over any
+//! [`AsyncChannel`]. The drivers expect the contributor to open the protocol and both parties to
+//! be created with their inputs; a protocol opened by [`Evaluator::initiate`] or inputs provided
+//! late must be run step by step instead.
+//!
+//! Ready-made channels are available for [`std::sync::mpsc`] ([`MpscChannel`]), for in-memory
+//! pipes between two parties of the same process ([`duplex`]) and for byte streams such as TCP
+//! connections ([`StreamChannel`]).
+//!
+//! A [`multiparty`](crate::multiparty) party needs a channel to every other party and is run by
+//! [`run_party`] or [`run_party_async`], for example over the in-memory pipes of a [`mesh`].
+
+use std::{
+    borrow::Borrow,
+    collections::VecDeque,
+    future::Future,
+    io::{Read, Write},
+    pin::Pin,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    multiparty::{Msgs, Party},
+    states::{Contributor, Evaluator, Msg},
+    Circuit, Error, MessageFault, PartyIndex,
+};
+
+/// A blocking, reliable and ordered connection to the other party.
+pub trait Channel {
+    /// Sends a message to the other party.
+    fn send(&mut self, msg: Msg) -> Result<(), Error>;
+
+    /// Waits for the next message of the other party.
+    fn recv(&mut self) -> Result<Msg, Error>;
+}
+
+/// The boxed future returned by the methods of an [`AsyncChannel`].
+///
+/// The future is not required to be `Send`, so that channels can be implemented on top of
+/// single-threaded runtimes such as the ones of browsers.
+pub type ChannelFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + 'a>>;
+
+/// An asynchronous, reliable and ordered connection to the other party, independent of any
+/// specific async runtime.
+pub trait AsyncChannel {
+    /// Sends a message to the other party.
+    fn send(&mut self, msg: Msg) -> ChannelFuture<'_, ()>;
+
+    /// Waits for the next message of the other party.
+    fn recv(&mut self) -> ChannelFuture<'_, Msg>;
+}
+
+/// Sends the initial message of the contributor and runs all of its steps over the `channel`.
+///
+/// Returns the contributor after its last message, which can still be used to compute its output
+/// shares or to learn the output sent by [`run_evaluator_revealing`], by calling
+/// [`Contributor::output`] on the next message of the `channel`.
+pub fn run_contributor<C, I, Ch>(
+    mut contrib: Contributor<C, I>,
+    msg: Msg,
+    channel: &mut Ch,
+) -> Result<Contributor<C, I>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: Channel + ?Sized,
+{
+    channel.send(msg)?;
+    for _ in 0..contrib.steps() {
+        let msg = channel.recv()?;
+        let (next_state, reply) = contrib.run(&msg)?;
+        channel.send(reply)?;
+        contrib = next_state;
+    }
+    Ok(contrib)
+}
+
+/// Runs all steps of the evaluator over the `channel` and returns the output of the circuit, which
+/// is not revealed to the contributor.
+pub fn run_evaluator<C, I, Ch>(
+    mut eval: Evaluator<C, I>,
+    channel: &mut Ch,
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: Channel + ?Sized,
+{
+    for _ in 0..eval.steps() {
+        let msg = channel.recv()?;
+        let (next_state, reply) = eval.run(&msg)?;
+        channel.send(reply)?;
+        eval = next_state;
+    }
+    let msg = channel.recv()?;
+    eval.output(&msg)
+}
+
+/// Runs all steps of the evaluator over the `channel` like [`run_evaluator`], but also reveals the
+/// output of the circuit to the contributor, by sending it the message of
+/// [`Evaluator::reveal_output`].
+pub fn run_evaluator_revealing<C, I, Ch>(
+    mut eval: Evaluator<C, I>,
+    channel: &mut Ch,
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: Channel + ?Sized,
+{
+    for _ in 0..eval.steps() {
+        let msg = channel.recv()?;
+        let (next_state, reply) = eval.run(&msg)?;
+        channel.send(reply)?;
+        eval = next_state;
+    }
+    let msg = channel.recv()?;
+    let (output, reply) = eval.reveal_output(&msg)?;
+    channel.send(reply)?;
+    Ok(output)
+}
+
+/// The asynchronous version of [`run_contributor`].
+pub async fn run_contributor_async<C, I, Ch>(
+    mut contrib: Contributor<C, I>,
+    msg: Msg,
+    channel: &mut Ch,
+) -> Result<Contributor<C, I>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: AsyncChannel + ?Sized,
+{
+    channel.send(msg).await?;
+    for _ in 0..contrib.steps() {
+        let msg = channel.recv().await?;
+        let (next_state, reply) = contrib.run(&msg)?;
+        channel.send(reply).await?;
+        contrib = next_state;
+    }
+    Ok(contrib)
+}
+
+/// The asynchronous version of [`run_evaluator`].
+pub async fn run_evaluator_async<C, I, Ch>(
+    mut eval: Evaluator<C, I>,
+    channel: &mut Ch,
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: AsyncChannel + ?Sized,
+{
+    for _ in 0..eval.steps() {
+        let msg = channel.recv().await?;
+        let (next_state, reply) = eval.run(&msg)?;
+        channel.send(reply).await?;
+        eval = next_state;
+    }
+    let msg = channel.recv().await?;
+    eval.output(&msg)
+}
+
+/// The asynchronous version of [`run_evaluator_revealing`].
+pub async fn run_evaluator_revealing_async<C, I, Ch>(
+    mut eval: Evaluator<C, I>,
+    channel: &mut Ch,
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: AsyncChannel + ?Sized,
+{
+    for _ in 0..eval.steps() {
+        let msg = channel.recv().await?;
+        let (next_state, reply) = eval.run(&msg)?;
+        channel.send(reply).await?;
+        eval = next_state;
+    }
+    let msg = channel.recv().await?;
+    let (output, reply) = eval.reveal_output(&msg)?;
+    channel.send(reply).await?;
+    Ok(output)
+}
+
+/// Runs all steps of a [`Party`] of the multi-party protocol and returns the output of the circuit.
+///
+/// The `channels` connect the party to every other party, each together with the index of the
+/// party at its other end. In every step, the party first sends its messages to all other parties
+/// and only then receives their messages, so the channels must buffer at least one message
+/// (which all channels of this module do).
+pub fn run_party<C, I, Ch>(
+    mut party: Party<C, I>,
+    msgs: Msgs,
+    channels: &mut [(PartyIndex, Ch)],
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: Channel,
+{
+    let mut msgs = msgs;
+    for _ in 0..party.steps() {
+        for (to, msg) in msgs {
+            channel_to(channels, to)?.send(msg)?;
+        }
+        let mut received = Vec::with_capacity(channels.len());
+        for (from, channel) in channels.iter_mut() {
+            received.push((*from, channel.recv()?));
+        }
+        let (next_state, reply) = party.run(received)?;
+        party = next_state;
+        msgs = reply;
+    }
+    for (to, msg) in msgs {
+        channel_to(channels, to)?.send(msg)?;
+    }
+    let mut received = Vec::with_capacity(channels.len());
+    for (from, channel) in channels.iter_mut() {
+        received.push((*from, channel.recv()?));
+    }
+    party.output(received)
+}
+
+/// The asynchronous version of [`run_party`].
+pub async fn run_party_async<C, I, Ch>(
+    mut party: Party<C, I>,
+    msgs: Msgs,
+    channels: &mut [(PartyIndex, Ch)],
+) -> Result<Vec<bool>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    Ch: AsyncChannel,
+{
+    let mut msgs = msgs;
+    for _ in 0..party.steps() {
+        for (to, msg) in msgs {
+            channel_to(channels, to)?.send(msg).await?;
+        }
+        let mut received = Vec::with_capacity(channels.len());
+        for (from, channel) in channels.iter_mut() {
+            received.push((*from, channel.recv().await?));
+        }
+        let (next_state, reply) = party.run(received)?;
+        party = next_state;
+        msgs = reply;
+    }
+    for (to, msg) in msgs {
+        channel_to(channels, to)?.send(msg).await?;
+    }
+    let mut received = Vec::with_capacity(channels.len());
+    for (from, channel) in channels.iter_mut() {
+        received.push((*from, channel.recv().await?));
+    }
+    party.output(received)
+}
+
+fn channel_to<Ch>(channels: &mut [(PartyIndex, Ch)], party: PartyIndex) -> Result<&mut Ch, Error> {
+    let channel = channels.iter_mut().find(|(p, _)| *p == party);
+    channel
+        .map(|(_, channel)| channel)
+        .ok_or(Error::UnexpectedMessageType {
+            step: None,
+            fault: MessageFault::Sender(party),
+        })
+}
+
+/// Creates in-memory pipes between every pair of the specified number of `parties`.
+///
+/// Returns the channels of every party to all other parties, in the form expected by
+/// [`run_party`].
+pub fn mesh(parties: usize) -> Vec<Vec<(PartyIndex, DuplexPipe)>> {
+    let mut channels: Vec<Vec<(PartyIndex, DuplexPipe)>> = (0..parties).map(|_| vec![]).collect();
+    for i in 0..parties {
+        for j in (i + 1)..parties {
+            let (a, b) = duplex();
+            channels[i].push((j as PartyIndex, a));
+            channels[j].push((i as PartyIndex, b));
+        }
+    }
+    channels
+}
+
+/// A channel over a pair of [`std::sync::mpsc`] channels, one for each direction.
+pub struct MpscChannel {
+    sender: Sender<Msg>,
+    receiver: Receiver<Msg>,
+}
+
+impl MpscChannel {
+    /// Creates a channel that sends to `sender` and receives from `receiver`.
+    pub fn new(sender: Sender<Msg>, receiver: Receiver<Msg>) -> Self {
+        Self { sender, receiver }
+    }
+
+    /// Creates the two connected ends of a channel, one for each party.
+    pub fn pair() -> (Self, Self) {
+        let (to_a, from_b) = mpsc::channel();
+        let (to_b, from_a) = mpsc::channel();
+        (Self::new(to_b, from_b), Self::new(to_a, from_a))
+    }
+}
+
+impl Channel for MpscChannel {
+    fn send(&mut self, msg: Msg) -> Result<(), Error> {
+        self.sender.send(msg).map_err(|_| Error::ChannelClosed)
+    }
+
+    fn recv(&mut self) -> Result<Msg, Error> {
+        self.receiver.recv().map_err(|_| Error::ChannelClosed)
+    }
+}
+
+/// One end of an in-memory pipe, which can be used both as a blocking [`Channel`] and as an
+/// [`AsyncChannel`].
+///
+/// Dropping one end closes the pipe, after which the other end fails with
+/// [`Error::ChannelClosed`] once all messages sent before have been received.
+pub struct DuplexPipe {
+    outgoing: Arc<Pipe>,
+    incoming: Arc<Pipe>,
+}
+
+/// Creates the two connected ends of an in-memory pipe, one for each party.
+pub fn duplex() -> (DuplexPipe, DuplexPipe) {
+    let a_to_b = Arc::new(Pipe::default());
+    let b_to_a = Arc::new(Pipe::default());
+    let a = DuplexPipe {
+        outgoing: a_to_b.clone(),
+        incoming: b_to_a.clone(),
+    };
+    let b = DuplexPipe {
+        outgoing: b_to_a,
+        incoming: a_to_b,
+    };
+    (a, b)
+}
+
+/// The messages sent in one direction of a [`DuplexPipe`].
+#[derive(Default)]
+struct Pipe {
+    queue: Mutex<PipeQueue>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct PipeQueue {
+    msgs: VecDeque<Msg>,
+    closed: bool,
+    /// The task waiting for the next message, if the pipe is used asynchronously.
+    waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn push(&self, msg: Msg) -> Result<(), Error> {
+        let mut queue = self.queue.lock().map_err(|_| Error::ChannelClosed)?;
+        if queue.closed {
+            return Err(Error::ChannelClosed);
+        }
+        queue.msgs.push_back(msg);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+        self.available.notify_one();
+        Ok(())
+    }
+
+    fn close(&self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.closed = true;
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+        self.available.notify_all();
+    }
+
+    fn pop(&self) -> Result<Msg, Error> {
+        let mut queue = self.queue.lock().map_err(|_| Error::ChannelClosed)?;
+        loop {
+            if let Some(msg) = queue.msgs.pop_front() {
+                return Ok(msg);
+            }
+            if queue.closed {
+                return Err(Error::ChannelClosed);
+            }
+            queue = self
+                .available
+                .wait(queue)
+                .map_err(|_| Error::ChannelClosed)?;
+        }
+    }
+
+    fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Result<Msg, Error>> {
+        let mut queue = match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(_) => return Poll::Ready(Err(Error::ChannelClosed)),
+        };
+        if let Some(msg) = queue.msgs.pop_front() {
+            Poll::Ready(Ok(msg))
+        } else if queue.closed {
+            Poll::Ready(Err(Error::ChannelClosed))
+        } else {
+            queue.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for DuplexPipe {
+    fn drop(&mut self) {
+        self.outgoing.close();
+        self.incoming.close();
+    }
+}
+
+impl Channel for DuplexPipe {
+    fn send(&mut self, msg: Msg) -> Result<(), Error> {
+        self.outgoing.push(msg)
+    }
+
+    fn recv(&mut self) -> Result<Msg, Error> {
+        self.incoming.pop()
+    }
+}
+
+impl AsyncChannel for DuplexPipe {
+    fn send(&mut self, msg: Msg) -> ChannelFuture<'_, ()> {
+        let sent = self.outgoing.push(msg);
+        Box::pin(async move { sent })
+    }
+
+    fn recv(&mut self) -> ChannelFuture<'_, Msg> {
+        Box::pin(PipeRecv(&self.incoming))
+    }
+}
+
+/// Waits for the next message of a [`Pipe`].
+struct PipeRecv<'a>(&'a Pipe);
+
+impl Future for PipeRecv<'_> {
+    type Output = Result<Msg, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_pop(cx)
+    }
+}
+
+/// A channel over a byte stream such as a TCP connection, which prefixes every message with its
+/// length as 8 bytes (little endian).
+pub struct StreamChannel<S: Read + Write> {
+    stream: S,
+}
+
+impl<S: Read + Write> StreamChannel<S> {
+    /// Creates a channel that sends and receives messages over the `stream`.
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Read + Write> Channel for StreamChannel<S> {
+    fn send(&mut self, msg: Msg) -> Result<(), Error> {
+        self.stream.write_all(&(msg.len() as u64).to_le_bytes())?;
+        self.stream.write_all(&msg)?;
+        Ok(self.stream.flush()?)
+    }
+
+    fn recv(&mut self) -> Result<Msg, Error> {
+        let mut len = [0; 8];
+        self.stream.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        // the buffer only grows as the message arrives, a bogus length cannot exhaust the memory:
+        let mut msg = vec![];
+        (&mut self.stream).take(len).read_to_end(&mut msg)?;
+        if msg.len() as u64 != len {
+            return Err(Error::ChannelClosed);
+        }
+        Ok(msg)
+    }
+}
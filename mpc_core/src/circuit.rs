@@ -5,6 +5,9 @@ use crate::Error;
 /// The index of the gate in the circuit, representing its output wire.
 pub type GateIndex = u32;
 
+/// The index of a party in a circuit for more than two parties, see [`Gate::InParty`].
+pub type PartyIndex = u8;
+
 /// A circuit of AND, XOR and NOT gates for secure computation.
 #[derive(Clone, Debug)]
 pub struct Circuit {
@@ -85,6 +88,14 @@ impl Circuit {
         *hasher.finalize().as_bytes()
     }
 
+    /// number of input bits by the specified party of a circuit for more than two parties
+    pub fn party_inputs(&self, party: PartyIndex) -> usize {
+        self.gates
+            .iter()
+            .filter(|g| **g == Gate::InParty(party))
+            .count()
+    }
+
     /// Performs a syntax check of the circuit.
     ///
     /// A circuit is invalid if any of the following is true:
@@ -93,12 +104,36 @@ impl Circuit {
     ///   - the output gate indexes do not occur in the circuit
     ///   - the number of gates exceeds the maximum number supported
     ///   - the number of AND gates exceeds the maximum number supported
+    ///   - it contains inputs of more than two parties ([`Gate::InParty`])
     pub fn validate(&self) -> Result<(), Error> {
+        if self.gates.iter().any(|g| matches!(g, Gate::InParty(_))) {
+            return Err(Error::InvalidCircuit);
+        }
+        self.validate_gates()
+    }
+
+    /// Performs a syntax check of a circuit for the specified number of parties.
+    ///
+    /// In addition to the checks of [`Circuit::validate`], the circuit is invalid if it contains
+    /// the inputs of the two-party protocol ([`Gate::InContrib`] or [`Gate::InEval`]) or the input
+    /// of a party with an index of `parties` or more.
+    pub fn validate_parties(&self, parties: usize) -> Result<(), Error> {
+        for g in self.gates.iter() {
+            match *g {
+                Gate::InContrib | Gate::InEval => return Err(Error::InvalidCircuit),
+                Gate::InParty(p) if p as usize >= parties => return Err(Error::InvalidCircuit),
+                _ => {}
+            }
+        }
+        self.validate_gates()
+    }
+
+    fn validate_gates(&self) -> Result<(), Error> {
         let mut num_and_gates = 0;
         for (i, g) in self.gates.iter().enumerate() {
             let i = i as u32;
             match g {
-                Gate::InContrib | Gate::InEval => {}
+                Gate::InContrib | Gate::InEval | Gate::InParty(_) => {}
                 &Gate::Xor(x, y) => {
                     if x >= i || y >= i {
                         return Err(Error::InvalidCircuit);
@@ -134,6 +169,73 @@ impl Circuit {
         Ok(())
     }
 
+    /// Evaluates the circuit in plaintext, without any secure computation.
+    ///
+    /// This is useful as a reference when testing circuits, as the result must always match the
+    /// output of a secure execution of the same circuit on the same inputs.
+    pub fn eval(&self, contrib_input: &[bool], eval_input: &[bool]) -> Result<Vec<bool>, Error> {
+        self.validate()?;
+        self.validate_contributor_input(contrib_input)?;
+        self.validate_evaluator_input(eval_input)?;
+
+        let mut contrib_input = contrib_input.iter();
+        let mut eval_input = eval_input.iter();
+        let mut wires = vec![false; self.gates.len()];
+        for (i, gate) in self.gates.iter().enumerate() {
+            wires[i] = match *gate {
+                Gate::InContrib => *contrib_input.next().ok_or(Error::InsufficientInput)?,
+                Gate::InEval => *eval_input.next().ok_or(Error::InsufficientInput)?,
+                Gate::InParty(_) => return Err(Error::InvalidCircuit),
+                Gate::Xor(x, y) => wires[x as usize] ^ wires[y as usize],
+                Gate::And(x, y) => wires[x as usize] & wires[y as usize],
+                Gate::Not(x) => !wires[x as usize],
+            };
+        }
+        Ok(self
+            .output_gates
+            .iter()
+            .map(|&o| wires[o as usize])
+            .collect())
+    }
+
+    /// Evaluates a circuit for more than two parties in plaintext, with the input of every party
+    /// at its index in `inputs`.
+    pub fn eval_parties(&self, inputs: &[&[bool]]) -> Result<Vec<bool>, Error> {
+        self.validate_parties(inputs.len())?;
+        for (party, input) in inputs.iter().enumerate() {
+            self.validate_party_input(party as PartyIndex, input)?;
+        }
+
+        let mut inputs: Vec<_> = inputs.iter().map(|input| input.iter()).collect();
+        let mut wires = vec![false; self.gates.len()];
+        for (i, gate) in self.gates.iter().enumerate() {
+            wires[i] = match *gate {
+                Gate::InParty(p) => *inputs[p as usize].next().ok_or(Error::InsufficientInput)?,
+                Gate::InContrib | Gate::InEval => return Err(Error::InvalidCircuit),
+                Gate::Xor(x, y) => wires[x as usize] ^ wires[y as usize],
+                Gate::And(x, y) => wires[x as usize] & wires[y as usize],
+                Gate::Not(x) => !wires[x as usize],
+            };
+        }
+        Ok(self
+            .output_gates
+            .iter()
+            .map(|&o| wires[o as usize])
+            .collect())
+    }
+
+    pub(crate) fn validate_party_input(
+        &self,
+        party: PartyIndex,
+        input: &[bool],
+    ) -> Result<(), Error> {
+        if self.party_inputs(party) == input.len() {
+            Ok(())
+        } else {
+            Err(Error::InsufficientInput)
+        }
+    }
+
     pub(crate) fn validate_contributor_input(&self, input: &[bool]) -> Result<(), Error> {
         if self
             .gates
@@ -170,6 +272,9 @@ pub enum Gate {
     InContrib,
     /// A single input bit coming from the circuit evaluator.
     InEval,
+    /// A single input bit coming from the specified party, in a circuit for more than two parties
+    /// (see [`multiparty`](crate::multiparty)).
+    InParty(PartyIndex),
     /// A gate computing the XOR of the two specified gates.
     Xor(GateIndex, GateIndex),
     /// A gate computing the AND of the two specified gates.
@@ -202,6 +307,10 @@ impl Gate {
                 hasher.update(&x.to_be_bytes());
                 4
             }
+            Gate::InParty(p) => {
+                hasher.update(&[*p]);
+                5
+            }
         };
         hasher.update(&[type_byte]);
     }
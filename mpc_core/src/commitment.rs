@@ -0,0 +1,242 @@
+//! Commitments to the input of a party, which bind the party to the same input across executions.
+//!
+//! In repeated computations, such as a scoring model of the contributor that is applied to a new
+//! input of the evaluator every day, nothing prevents a party from using a different input in
+//! every execution. A party can therefore publish an [`InputCommitment`] once, keeping the
+//! [`CommittedInput`] with the opening of the commitment secret, and prove in every later
+//! execution that it uses exactly the committed input:
+//!
+//! 1. [`InputCommitment::circuit`] extends the circuit of an execution so that it hashes the
+//!    committed input bits together with the opening and compares the hash to the commitment. The
+//!    committed bits are the first input bits of the party in the circuit, the opening becomes an
+//!    additional 256-bit input of the party at the end of its input.
+//! 2. The committing party provides [`CommittedInput::execution_input`] as its input, the other
+//!    party provides its input unchanged.
+//! 3. [`check_output`] checks the additional last output of the extended circuit, which is `true`
+//!    if and only if the commitment was opened correctly. If it is `false`, all other outputs are
+//!    `false` as well, so that an execution with a different input reveals nothing but the failed
+//!    check.
+//!
+//! The commitment is a keyed blake3 hash of a random 256-bit nonce, the number of committed bits
+//! and the bits themselves, which hides the input and binds the party to it. Proving the opening
+//! inside of the circuit needs about 10,000 AND gates for every started block of 64 bytes, with
+//! the nonce and the length taking up the first 40 bytes.
+//!
+//! The check is only as reliable as the output of the execution, which cannot be modified by a
+//! malicious party at the default
+//! [`SecurityLevel::Malicious`](crate::states::SecurityLevel::Malicious). Since only the evaluator
+//! learns the output by default, a committing evaluator must reveal the output to the contributor
+//! using [`Evaluator::reveal_output`](crate::states::Evaluator::reveal_output).
+//!
+//! # Examples
+//!
+//! ```
+//! use mpc_core::{
+//!     commitment::{check_output, CommittedInput, InputCommitment},
+//!     gadgets::{int, to_bits, CircuitBuilder, Party},
+//!     simulate, Error,
+//! };
+//! use rand::SeedableRng;
+//! use rand_chacha::ChaCha20Rng;
+//!
+//! # fn main() -> Result<(), Error> {
+//! // The contributor commits to a secret 8-bit threshold once and publishes the commitment:
+//! let mut rng = ChaCha20Rng::from_entropy();
+//! let threshold = CommittedInput::new(to_bits(100, 8), &mut rng);
+//! let commitment = threshold.commitment();
+//! let published = commitment.to_bytes()?;
+//!
+//! // Every execution compares a new value of the evaluator to the committed threshold:
+//! let mut b = CircuitBuilder::new();
+//! let t = b.input_word(Party::Contributor, 8);
+//! let x = b.input_word(Party::Evaluator, 8);
+//! let above = int::lt(&mut b, &t, &x);
+//! let circuit = b.build(vec![above]);
+//!
+//! let commitment = InputCommitment::from_bytes(&published)?;
+//! let circuit = commitment.circuit(&circuit, Party::Contributor)?;
+//! let input = threshold.execution_input(&[]);
+//! let output = simulate(&circuit, &input, &to_bits(150, 8))?;
+//! assert_eq!(check_output(output)?, vec![true]);
+//! # Ok(())
+//! # }
+//! ```
+
+use bincode::{deserialize, serialize};
+use rand_chacha::ChaCha20Rng;
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gadgets::{hash::blake3_keyed, to_bits, CircuitBuilder, Party, Word},
+    hash::domain,
+    Circuit, Error, Gate, GateIndex, MacCheck,
+};
+
+/// The number of bits of the random nonce that hides the committed input.
+const NONCE_BITS: usize = 256;
+
+/// The public commitment of a party to its input, which can be published and stored by the other
+/// party.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InputCommitment {
+    hash: [u8; 32],
+    bits: usize,
+}
+
+/// The committed input of a party together with the opening of its commitment.
+///
+/// The committed input must be stored as securely as the input itself, since the opening would
+/// otherwise allow the other party to check guesses of the input against the commitment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CommittedInput {
+    input: Vec<bool>,
+    nonce: [u8; 32],
+}
+
+impl InputCommitment {
+    /// The hash that identifies the commitment, for example when storing several commitments.
+    pub fn id(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
+    /// The number of committed input bits.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Serializes the commitment, for example to publish or to store it.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(serialize(self)?)
+    }
+
+    /// Deserializes a commitment that was serialized using [`InputCommitment::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(deserialize(bytes)?)
+    }
+
+    /// Extends a circuit so that it checks that the first input bits of `party` open this
+    /// commitment, adding the opening as the last 256 input bits of `party`.
+    ///
+    /// The outputs of the extended circuit are the outputs of `circuit`, which are all `false`
+    /// unless the commitment is opened, followed by an output that is `true` if and only if the
+    /// commitment is opened. Fails with [`Error::InvalidCircuit`] if `circuit` is not a valid
+    /// circuit for two parties or if `party` has fewer input bits than the commitment.
+    pub fn circuit(&self, circuit: &Circuit, party: Party) -> Result<Circuit, Error> {
+        circuit.validate()?;
+        let input_gate = match party {
+            Party::Contributor => Gate::InContrib,
+            Party::Evaluator => Gate::InEval,
+        };
+        let committed: Word = circuit
+            .gates()
+            .iter()
+            .enumerate()
+            .filter(|(_, gate)| **gate == input_gate)
+            .map(|(i, _)| i as GateIndex)
+            .take(self.bits)
+            .collect();
+        if committed.len() < self.bits {
+            return Err(Error::InvalidCircuit);
+        }
+        let mut b = CircuitBuilder::from_circuit(circuit);
+        let nonce = b.input_word(party, NONCE_BITS);
+        let len = b.constant_word(self.bits as u64, 64);
+        let zero = b.zero();
+        let mut message = [nonce, len, committed].concat();
+        message.resize((message.len() + 7) / 8 * 8, zero);
+        let hash = blake3_keyed(&mut b, &key(), &message);
+        let expected: Word = bytes_to_bits(&self.hash)
+            .into_iter()
+            .map(|bit| b.constant(bit))
+            .collect();
+        let opened = b.eq(&hash, &expected);
+        let mut outputs: Vec<GateIndex> = circuit
+            .output_gates()
+            .iter()
+            .map(|&output| b.and(output, opened))
+            .collect();
+        outputs.push(opened);
+        Ok(b.build(outputs))
+    }
+}
+
+impl CommittedInput {
+    /// Commits to an input, using a fresh random nonce to hide it.
+    pub fn new(input: Vec<bool>, rng: &mut ChaCha20Rng) -> Self {
+        let mut nonce = [0; 32];
+        rng.fill_bytes(&mut nonce);
+        Self { input, nonce }
+    }
+
+    /// The public commitment to the input.
+    pub fn commitment(&self) -> InputCommitment {
+        let mut message = self.nonce.to_vec();
+        message.extend_from_slice(&(self.input.len() as u64).to_le_bytes());
+        for byte in self.input.chunks(8) {
+            let byte = byte
+                .iter()
+                .rev()
+                .fold(0, |acc, &bit| (acc << 1) | u8::from(bit));
+            message.push(byte);
+        }
+        InputCommitment {
+            hash: *blake3::keyed_hash(&key(), &message).as_bytes(),
+            bits: self.input.len(),
+        }
+    }
+
+    /// The committed input bits.
+    pub fn input(&self) -> &[bool] {
+        &self.input
+    }
+
+    /// The input of the party for an execution of a circuit that was extended by
+    /// [`InputCommitment::circuit`]: the committed input, followed by the `remaining` input of the
+    /// party in this execution and the opening of the commitment.
+    pub fn execution_input(&self, remaining: &[bool]) -> Vec<bool> {
+        let mut input = self.input.clone();
+        input.extend_from_slice(remaining);
+        input.extend(bytes_to_bits(&self.nonce));
+        input
+    }
+
+    /// Serializes the committed input, for example to store it between executions.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(serialize(self)?)
+    }
+
+    /// Deserializes a committed input that was serialized using [`CommittedInput::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(deserialize(bytes)?)
+    }
+}
+
+/// Checks the last output of a circuit that was extended by [`InputCommitment::circuit`],
+/// returning the remaining outputs if the commitment was opened and failing with
+/// [`Error::MacError`] otherwise.
+///
+/// Circuits that check the commitments of both parties are checked by calling this function once
+/// for every commitment, in the reverse order in which the circuit was extended.
+pub fn check_output(mut output: Vec<bool>) -> Result<Vec<bool>, Error> {
+    match output.pop() {
+        Some(true) => Ok(output),
+        _ => Err(Error::MacError {
+            step: None,
+            check: MacCheck::InputCommitment,
+            failed: 1,
+            index: None,
+        }),
+    }
+}
+
+fn key() -> [u8; 32] {
+    blake3::derive_key(domain::INPUT_COMMITMENT, &[])
+}
+
+fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|&byte| to_bits(byte as u64, 8))
+        .collect()
+}
@@ -0,0 +1,291 @@
+//! Dual execution of the half-gates protocol, which lets a malicious party learn at most a single
+//! bit about the input of the other party (Mohassel and Franklin, "Efficiency Tradeoffs for
+//! Malicious Two-Party Computation", 2006; Huang, Katz and Evans, "Quid-Pro-Quo-tocols:
+//! Strengthening Semi-Honest Protocols with Dual Execution", 2012).
+//!
+//! Both parties garble the circuit once and evaluate the circuit garbled by the other party, using
+//! the semi-honest half-gates protocol in both directions. In the second execution the evaluator
+//! garbles a copy of the circuit in which the inputs of both parties have swapped their roles.
+//! The two executions are interleaved, so that the whole protocol needs only 2 messages more than
+//! a single execution.
+//!
+//! Each party then holds the output labels of both executions for the output it computed: the
+//! labels it obtained by evaluating the circuit of the other party and the labels that encode this
+//! output in its own circuit, which it can derive from its `Delta`. These labels are only equal if
+//! both parties computed the same output with correctly garbled circuits. The parties compare a
+//! digest of the labels, with the evaluator committing to its digest before the contributor sends
+//! its own, and abort on a mismatch.
+//!
+//! A party that garbles a wrong circuit or sends wrong labels can only make the other party abort,
+//! and whether the other party aborts depends on its input, which leaks at most one bit. A party
+//! that does not know the labels of the other circuit for anything but the actual output cannot
+//! pass the equality check with a different output, so the output is always correct.
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash::domain,
+    leakydelta_ot::message::SerializedOtInit,
+    message::{GarbledCircuit, InputLabels, OtBlocks, OtCompletion},
+    semihonest::{
+        EvaluatorInit, EvaluatorOt, EvaluatorOutput, GarblerDone, GarblerInit, GarblerOt,
+    },
+    types::WireLabel,
+    Circuit, Error, Gate, MacCheck,
+};
+
+/// The contributor after garbling its circuit, waiting for the circuit of the evaluator.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ContribDual1 {
+    garbler: GarblerInit,
+    rng: ChaCha20Rng,
+}
+
+/// The contributor waiting for the extended OTs of its execution and the base OT answers of the
+/// other execution.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ContribDual2 {
+    garbler: GarblerOt,
+    evaluator: EvaluatorOt,
+}
+
+/// The contributor waiting for the labels of its input in the circuit of the evaluator and for the
+/// commitment of the evaluator.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ContribDual3 {
+    garbler: GarblerDone,
+    evaluator: EvaluatorOutput,
+}
+
+/// The contributor waiting for the evaluator to open its commitment.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ContribDualDone {
+    output: Vec<bool>,
+    digest: [u8; 32],
+    commitment: [u8; 32],
+}
+
+/// The evaluator waiting for the circuit of the contributor.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvalDual1 {
+    rng: ChaCha20Rng,
+}
+
+/// The evaluator waiting for the base OT answers of the contributor's execution and the base OTs
+/// of its own execution.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvalDual2 {
+    nonce: [u8; 32],
+    garbler: GarblerInit,
+    evaluator: EvaluatorOt,
+}
+
+/// The evaluator waiting for the labels of its input and the extended OTs of the contributor.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvalDual3 {
+    nonce: [u8; 32],
+    garbler: GarblerOt,
+    evaluator: EvaluatorOutput,
+}
+
+/// The evaluator after committing to its digest, waiting for the digest of the contributor.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvalDualOutput {
+    output: Vec<bool>,
+    digest: [u8; 32],
+    nonce: [u8; 32],
+}
+
+/// The copy of the circuit that the evaluator garbles, with the inputs of both parties swapped.
+fn swapped(circuit: &Circuit) -> Circuit {
+    let gates = circuit
+        .gates()
+        .iter()
+        .map(|gate| match gate {
+            Gate::InContrib => Gate::InEval,
+            Gate::InEval => Gate::InContrib,
+            gate => gate.clone(),
+        })
+        .collect();
+    Circuit::new(gates, circuit.output_gates().to_vec())
+}
+
+/// Digests the output labels of the contributor's execution followed by those of the evaluator's.
+fn digest(circuit: &Circuit, contrib: &[WireLabel], eval: &[WireLabel]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key(domain::DUAL_EXECUTION);
+    hasher.update(&circuit.blake3_hash());
+    for label in contrib.iter().chain(eval) {
+        hasher.update(&label.0.to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn commitment(nonce: &[u8; 32], digest: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key(domain::DUAL_EXECUTION_COMMITMENT);
+    hasher.update(nonce);
+    hasher.update(digest);
+    *hasher.finalize().as_bytes()
+}
+
+const MISMATCH: Error = Error::MacError {
+    step: None,
+    check: MacCheck::OutputEquality,
+    failed: 1,
+    index: None,
+};
+
+/// The pair of messages that extends the OTs of one execution.
+type OtExtension = (OtCompletion, OtBlocks);
+
+/// Splits off an independent RNG for one of the two executions.
+fn fork(rng: &mut ChaCha20Rng) -> ChaCha20Rng {
+    let mut seed = [0; 32];
+    rng.fill_bytes(&mut seed);
+    ChaCha20Rng::from_seed(seed)
+}
+
+impl ContribDual1 {
+    /// Garbles the circuit of the contributor, which is the initial message for the evaluator.
+    pub(crate) fn garble(
+        circuit: &Circuit,
+        input: &[bool],
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, GarbledCircuit), Error> {
+        let (garbler, msg) = GarblerInit::garble(circuit, input, fork(&mut rng))?;
+        Ok((Self { garbler, rng }, msg))
+    }
+
+    /// Answers the base OTs of the evaluator and starts the base OTs for the circuit of the
+    /// evaluator.
+    pub(crate) fn run(
+        self,
+        (ot_init, garbled): (SerializedOtInit, GarbledCircuit),
+        circuit: &Circuit,
+    ) -> Result<(ContribDual2, (SerializedOtInit, SerializedOtInit)), Error> {
+        let (garbler, ot_response) = self.garbler.run(ot_init)?;
+        let evaluator = EvaluatorInit::new(self.rng);
+        let (evaluator, ot_init) = evaluator.run(garbled, &swapped(circuit))?;
+        Ok((ContribDual2 { garbler, evaluator }, (ot_response, ot_init)))
+    }
+}
+
+impl ContribDual2 {
+    /// Sends the labels of the evaluator's input and extends the OTs for its own input.
+    pub(crate) fn run(
+        self,
+        (ot_ext, ot_response): (OtExtension, SerializedOtInit),
+        input: &[bool],
+    ) -> Result<(ContribDual3, (InputLabels, OtExtension)), Error> {
+        let (garbler, labels) = self.garbler.run(ot_ext)?;
+        let (evaluator, ot_ext) = self.evaluator.run(ot_response, input)?;
+        Ok((ContribDual3 { garbler, evaluator }, (labels, ot_ext)))
+    }
+}
+
+impl ContribDual3 {
+    /// Evaluates the circuit of the evaluator and returns the digest of the labels of the output.
+    pub(crate) fn run(
+        self,
+        (labels, commitment): (InputLabels, [u8; 32]),
+        circuit: &Circuit,
+    ) -> Result<(ContribDualDone, [u8; 32]), Error> {
+        let (output, eval_labels) = self.evaluator.run(labels, &swapped(circuit))?;
+        let contrib_labels = self.garbler.output_labels(&output);
+        let digest = digest(circuit, &contrib_labels, &eval_labels.labels);
+        let state = ContribDualDone {
+            output,
+            digest,
+            commitment,
+        };
+        Ok((state, digest))
+    }
+}
+
+impl ContribDualDone {
+    /// Checks that the opened commitment of the evaluator matches the own digest, returning the
+    /// output.
+    pub(crate) fn run(self, nonce: [u8; 32]) -> Result<Vec<bool>, Error> {
+        if commitment(&nonce, &self.digest) != self.commitment {
+            return Err(MISMATCH);
+        }
+        Ok(self.output)
+    }
+}
+
+impl EvalDual1 {
+    pub(crate) fn new(rng: ChaCha20Rng) -> Self {
+        Self { rng }
+    }
+
+    /// Starts the base OTs for the circuit of the contributor and garbles the own circuit.
+    pub(crate) fn run(
+        mut self,
+        garbled: GarbledCircuit,
+        circuit: &Circuit,
+        input: &[bool],
+    ) -> Result<(EvalDual2, (SerializedOtInit, GarbledCircuit)), Error> {
+        let mut nonce = [0; 32];
+        self.rng.fill_bytes(&mut nonce);
+        let evaluator = EvaluatorInit::new(fork(&mut self.rng));
+        let (evaluator, ot_init) = evaluator.run(garbled, circuit)?;
+        let (garbler, garbled) = GarblerInit::garble(&swapped(circuit), input, self.rng)?;
+        let state = EvalDual2 {
+            nonce,
+            garbler,
+            evaluator,
+        };
+        Ok((state, (ot_init, garbled)))
+    }
+}
+
+impl EvalDual2 {
+    /// Extends the OTs for the own input and answers the base OTs of the contributor.
+    pub(crate) fn run(
+        self,
+        (ot_response, ot_init): (SerializedOtInit, SerializedOtInit),
+        input: &[bool],
+    ) -> Result<(EvalDual3, (OtExtension, SerializedOtInit)), Error> {
+        let (evaluator, ot_ext) = self.evaluator.run(ot_response, input)?;
+        let (garbler, ot_response) = self.garbler.run(ot_init)?;
+        let state = EvalDual3 {
+            nonce: self.nonce,
+            garbler,
+            evaluator,
+        };
+        Ok((state, (ot_ext, ot_response)))
+    }
+}
+
+impl EvalDual3 {
+    /// Evaluates the circuit of the contributor, sends the labels of the contributor's input and
+    /// commits to the digest of the labels of the output.
+    pub(crate) fn run(
+        self,
+        (labels, ot_ext): (InputLabels, OtExtension),
+        circuit: &Circuit,
+    ) -> Result<(EvalDualOutput, (InputLabels, [u8; 32])), Error> {
+        let (output, contrib_labels) = self.evaluator.run(labels, circuit)?;
+        let (garbler, labels) = self.garbler.run(ot_ext)?;
+        let eval_labels = garbler.output_labels(&output);
+        let digest = digest(circuit, &contrib_labels.labels, &eval_labels);
+        let state = EvalDualOutput {
+            output,
+            digest,
+            nonce: self.nonce,
+        };
+        Ok((state, (labels, commitment(&self.nonce, &digest))))
+    }
+}
+
+impl EvalDualOutput {
+    /// Compares the digest of the contributor with the own digest, returning the output together
+    /// with the opening of the commitment.
+    pub(crate) fn run(self, digest: [u8; 32]) -> Result<(Vec<bool>, [u8; 32]), Error> {
+        if digest != self.digest {
+            return Err(MISMATCH);
+        }
+        Ok((self.output, self.nonce))
+    }
+}
@@ -0,0 +1,279 @@
+//! Oblivious array access, for reading and writing at a secret index.
+//!
+//! An [`ObliviousArray`] is a list of equally wide [`Word`]s, each of which may belong to the
+//! contributor, the evaluator or be derived from both. Reading at a secret index never reveals
+//! the index, which makes the array usable both as an oblivious ROM (by only reading) and as an
+//! oblivious RAM (by also writing).
+//!
+//! The costs of all operations are linear in the size of the array and can be calculated in
+//! advance using [`ObliviousArray::read_cost`] and [`ObliviousArray::write_cost`].
+
+use super::{CircuitBuilder, Party, Word};
+use crate::GateIndex;
+
+/// The strategy used to read an element at a secret index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadStrategy {
+    /// A balanced tree of multiplexers, selecting one index bit per level.
+    ///
+    /// Costs `(len - 1) * width` AND gates if the array length is a power of 2.
+    MuxTree,
+    /// Decodes the index into a one-hot vector and combines it with the elements.
+    ///
+    /// Costs `len * width` AND gates plus the cost of the decoder, but the decoder could be
+    /// shared with other operations at the same index.
+    OneHot,
+}
+
+/// An array of fixed-width elements that can be accessed at a secret index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObliviousArray {
+    elements: Vec<Word>,
+    width: usize,
+}
+
+impl ObliviousArray {
+    /// Creates an array from existing words, which must all have the same width.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the words are not of equal width.
+    pub fn new(elements: Vec<Word>, width: usize) -> Self {
+        assert!(
+            elements.iter().all(|e| e.len() == width),
+            "all array elements must be {width} bits wide"
+        );
+        Self { elements, width }
+    }
+
+    /// Creates an array of `len` elements that are provided as input by the specified party.
+    pub fn from_inputs(b: &mut CircuitBuilder, party: Party, len: usize, width: usize) -> Self {
+        let elements = (0..len).map(|_| b.input_word(party, width)).collect();
+        Self { elements, width }
+    }
+
+    /// Creates an array of publicly known elements.
+    pub fn from_constants(b: &mut CircuitBuilder, values: &[u64], width: usize) -> Self {
+        let elements = values.iter().map(|&v| b.constant_word(v, width)).collect();
+        Self { elements, width }
+    }
+
+    /// The current elements of the array.
+    pub fn elements(&self) -> &[Word] {
+        &self.elements
+    }
+
+    /// The number of elements in the array.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the array does not contain any elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The width of each element in bits.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Reads the element at a secret index.
+    ///
+    /// Reading at an index that is out of bounds yields a word of all zeros.
+    pub fn read(
+        &self,
+        b: &mut CircuitBuilder,
+        index: &[GateIndex],
+        strategy: ReadStrategy,
+    ) -> Word {
+        match strategy {
+            ReadStrategy::MuxTree => self.read_mux_tree(b, index),
+            ReadStrategy::OneHot => {
+                let one_hot = one_hot(b, index, self.len());
+                self.read_one_hot(b, &one_hot)
+            }
+        }
+    }
+
+    /// Reads an element using a one-hot vector as obtained from [`one_hot`].
+    pub fn read_one_hot(&self, b: &mut CircuitBuilder, one_hot: &[GateIndex]) -> Word {
+        assert_eq!(one_hot.len(), self.len());
+        let mut result = vec![b.zero(); self.width];
+        for (element, &selected) in self.elements.iter().zip(one_hot.iter()) {
+            for (r, &bit) in result.iter_mut().zip(element.iter()) {
+                let bit = b.and(selected, bit);
+                *r = b.xor(*r, bit);
+            }
+        }
+        result
+    }
+
+    fn read_mux_tree(&self, b: &mut CircuitBuilder, index: &[GateIndex]) -> Word {
+        let reachable = self.len().min(capacity(index.len()));
+        // `None` stands for an all-zero word beyond the end of the array:
+        let mut level: Vec<Option<Word>> = self.elements[..reachable]
+            .iter()
+            .cloned()
+            .map(Some)
+            .collect();
+        for &bit in index {
+            let mut next = Vec::with_capacity((level.len() + 1) / 2);
+            for pair in level.chunks(2) {
+                let if_false = pair[0].as_ref();
+                let if_true = pair.get(1).and_then(|e| e.as_ref());
+                next.push(match (if_false, if_true) {
+                    (Some(f), Some(t)) => Some(b.mux_word(bit, f, t)),
+                    (Some(f), None) => {
+                        let not_bit = b.not(bit);
+                        Some(b.and_word(not_bit, f))
+                    }
+                    (None, Some(t)) => Some(b.and_word(bit, t)),
+                    (None, None) => None,
+                });
+            }
+            level = next;
+        }
+        match level.into_iter().next().flatten() {
+            Some(word) => word,
+            None => vec![b.zero(); self.width],
+        }
+    }
+
+    /// Overwrites the element at a secret index with `value`.
+    ///
+    /// Writing at an index that is out of bounds leaves the array unchanged.
+    pub fn write(&mut self, b: &mut CircuitBuilder, index: &[GateIndex], value: &[GateIndex]) {
+        let one_hot = one_hot(b, index, self.len());
+        self.write_one_hot(b, &one_hot, value);
+    }
+
+    /// Overwrites the element at a secret index with `value` if (and only if) `enable` is set.
+    pub fn cond_write(
+        &mut self,
+        b: &mut CircuitBuilder,
+        enable: GateIndex,
+        index: &[GateIndex],
+        value: &[GateIndex],
+    ) {
+        let one_hot: Vec<GateIndex> = one_hot(b, index, self.len())
+            .into_iter()
+            .map(|selected| b.and(enable, selected))
+            .collect();
+        self.write_one_hot(b, &one_hot, value);
+    }
+
+    /// Overwrites the elements selected by a one-hot vector as obtained from [`one_hot`].
+    pub fn write_one_hot(
+        &mut self,
+        b: &mut CircuitBuilder,
+        one_hot: &[GateIndex],
+        value: &[GateIndex],
+    ) {
+        assert_eq!(one_hot.len(), self.len());
+        assert_eq!(value.len(), self.width);
+        for (element, &selected) in self.elements.iter_mut().zip(one_hot.iter()) {
+            *element = b.mux_word(selected, element, value);
+        }
+    }
+
+    /// The number of AND gates needed by [`ObliviousArray::read`] for an index of the given width.
+    pub fn read_cost(&self, strategy: ReadStrategy, index_width: usize) -> usize {
+        match strategy {
+            ReadStrategy::MuxTree => {
+                let mut level = self.len().min(capacity(index_width));
+                let mut muxes = 0;
+                for _ in 0..index_width {
+                    level = (level + 1) / 2;
+                    muxes += level;
+                }
+                muxes * self.width
+            }
+            ReadStrategy::OneHot => one_hot_cost(index_width, self.len()) + self.len() * self.width,
+        }
+    }
+
+    /// The number of AND gates needed by [`ObliviousArray::write`] for an index of the given width.
+    ///
+    /// A [`ObliviousArray::cond_write`] needs `len` additional AND gates.
+    pub fn write_cost(&self, index_width: usize) -> usize {
+        one_hot_cost(index_width, self.len()) + self.len() * self.width
+    }
+}
+
+/// Decodes a secret index into `len` bits, of which only the bit at the index is set.
+///
+/// All bits are unset if the index is out of bounds, including the bits beyond the `2^n` values
+/// that an index of `n` bits can represent. The decoder needs [`one_hot_cost`] AND gates.
+pub fn one_hot(b: &mut CircuitBuilder, index: &[GateIndex], len: usize) -> Vec<GateIndex> {
+    let mut bits = decode(b, index, len.min(capacity(index.len())));
+    if bits.len() < len {
+        let zero = b.zero();
+        bits.resize(len, zero);
+    }
+    bits
+}
+
+/// Decodes a secret index into `len` bits, where `len` is at most the capacity of the index.
+fn decode(b: &mut CircuitBuilder, index: &[GateIndex], len: usize) -> Vec<GateIndex> {
+    match index.len() {
+        0 => (0..len).map(|_| b.one()).collect(),
+        1 => {
+            let not_bit = b.not(index[0]);
+            let mut result = vec![not_bit, index[0]];
+            result.truncate(len);
+            result
+        }
+        n => {
+            // split the index into 2 halves and combine their (smaller) one-hot vectors:
+            let (lo_bits, hi_bits) = index.split_at(n / 2);
+            let lo_len = capacity(lo_bits.len());
+            let lo = decode(b, lo_bits, len.min(lo_len));
+            let hi = decode(b, hi_bits, (len + lo_len - 1) / lo_len);
+            (0..len)
+                .map(|i| b.and(lo[i % lo_len], hi[i / lo_len]))
+                .collect()
+        }
+    }
+}
+
+/// The number of AND gates needed by [`one_hot`] for an index of the given width.
+pub fn one_hot_cost(index_width: usize, len: usize) -> usize {
+    let len = len.min(capacity(index_width));
+    if index_width <= 1 {
+        return 0;
+    }
+    let lo_width = index_width / 2;
+    let lo_len = capacity(lo_width);
+    len + one_hot_cost(lo_width, len.min(lo_len))
+        + one_hot_cost(index_width - lo_width, (len + lo_len - 1) / lo_len)
+}
+
+/// The number of values that can be represented using `bits` bits, saturating at `usize::MAX`.
+fn capacity(bits: usize) -> usize {
+    if bits >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        1 << bits
+    }
+}
+
+#[test]
+fn test_one_hot() {
+    use super::{from_bits, to_bits};
+
+    for len in [1, 3, 4, 7, 16, 20] {
+        let mut b = CircuitBuilder::new();
+        let index = b.input_word(Party::Evaluator, 4);
+        let bits = one_hot(&mut b, &index, len);
+        assert_eq!(b.and_gates(), one_hot_cost(4, len));
+        let circuit = b.build(bits);
+
+        for i in 0..16 {
+            let output = circuit.eval(&[], &to_bits(i, 4)).unwrap();
+            let expected = if (i as usize) < len { 1 << i } else { 0 };
+            assert_eq!(output.len(), len);
+            assert_eq!(from_bits(&output), expected);
+        }
+    }
+}
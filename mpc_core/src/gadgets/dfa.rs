@@ -0,0 +1,710 @@
+//! Deterministic finite automata over bytes, for matching secret strings against patterns.
+//!
+//! A [`Dfa`] can be evaluated on a secret string in two ways:
+//!
+//!   - [`Dfa::matches_public`] treats the automaton as public, so that only the string is hidden.
+//!     The current state is tracked as a one-hot vector and each byte costs roughly 300 AND gates
+//!     plus one AND gate for every pair of distinct transitions between states.
+//!   - [`PrivateDfa`] reads the transition table as a private input of one of the parties, only
+//!     revealing an upper bound on the number of states. Each byte then costs a lookup in the whole
+//!     transition table, which is `states * 256 * log2(states)` AND gates.
+//!
+//! [`Dfa::from_regex`] compiles a regular expression into a (minimal) automaton that accepts a
+//! string if and only if the regular expression matches somewhere in the string, like
+//! `regex::bytes::Regex::is_match` does for a pattern with Unicode support disabled. The supported
+//! syntax consists of literals, escapes (including `\d`, `\w`, `\s`, `\xHH` and their negations),
+//! `.`, character classes, groups, alternation, the repetitions `*`, `+`, `?`, `{n}`, `{n,}` and
+//! `{n,m}` as well as the anchors `^` and `$`.
+
+use std::collections::HashMap;
+
+use super::{
+    array::{one_hot, ObliviousArray, ReadStrategy},
+    strings::BYTE,
+    to_bits, CircuitBuilder, Party, Word,
+};
+use crate::{Error, GateIndex};
+
+/// The maximum number of states of an automaton compiled from a regular expression.
+const MAX_STATES: usize = 10_000;
+
+/// A deterministic finite automaton over bytes, starting in state 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    transitions: Vec<[usize; 256]>,
+    accepting: Vec<bool>,
+}
+
+impl Dfa {
+    /// Creates an automaton from its transition table and its accepting states.
+    ///
+    /// Returns [`Error::InvalidPattern`] if there are no states, if the number of states differs
+    /// between the table and the accepting states or if a transition leads to a missing state.
+    pub fn new(transitions: Vec<[usize; 256]>, accepting: Vec<bool>) -> Result<Self, Error> {
+        let states = transitions.len();
+        if states == 0
+            || accepting.len() != states
+            || transitions.iter().flatten().any(|&next| next >= states)
+        {
+            return Err(Error::InvalidPattern);
+        }
+        Ok(Self {
+            transitions,
+            accepting,
+        })
+    }
+
+    /// Compiles a regular expression into a minimal automaton for its unanchored matches.
+    ///
+    /// Returns [`Error::InvalidPattern`] if the pattern is malformed, uses unsupported syntax or
+    /// results in more than 10,000 states.
+    pub fn from_regex(pattern: &str) -> Result<Self, Error> {
+        let ast = Parser::new(pattern.as_bytes()).parse()?;
+        let mut nfa = Nfa::default();
+        // searching for a match anywhere: skip any prefix before the match and any suffix after it
+        let start = nfa.state();
+        nfa.states[start].bytes.push((ByteSet::full(), start));
+        let (pattern_start, pattern_end) = nfa.compile(&ast)?;
+        nfa.states[start].eps.push(pattern_start);
+        let end = nfa.state();
+        nfa.states[pattern_end].eps.push(end);
+        nfa.states[end].bytes.push((ByteSet::full(), end));
+        Ok(nfa.to_dfa(start, end)?.minimize())
+    }
+
+    /// The number of states of the automaton.
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// The state that the automaton moves to from `state` when reading `byte`.
+    pub fn next(&self, state: usize, byte: u8) -> usize {
+        self.transitions[state][byte as usize]
+    }
+
+    /// Whether the state is accepting.
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.accepting[state]
+    }
+
+    /// Runs the automaton in plaintext and returns whether it accepts the input.
+    pub fn matches(&self, input: &[u8]) -> bool {
+        let state = input.iter().fold(0, |state, &byte| self.next(state, byte));
+        self.accepting[state]
+    }
+
+    /// Returns an equivalent automaton with the minimal number of states.
+    pub fn minimize(&self) -> Self {
+        // refine the partition into accepting / non-accepting states until it is stable:
+        let mut class: Vec<usize> = self.accepting.iter().map(|&a| a as usize).collect();
+        let mut classes = 0;
+        loop {
+            let mut ids = HashMap::new();
+            let next_class: Vec<usize> = (0..self.states())
+                .map(|q| {
+                    let signature: Vec<usize> = std::iter::once(class[q])
+                        .chain(self.transitions[q].iter().map(|&next| class[next]))
+                        .collect();
+                    let id = ids.len();
+                    *ids.entry(signature).or_insert(id)
+                })
+                .collect();
+            class = next_class;
+            if ids.len() == classes {
+                break;
+            }
+            classes = ids.len();
+        }
+
+        // renumber the classes in the order they are reached from the start state:
+        let mut renumbered = vec![usize::MAX; classes];
+        let mut representatives = vec![0];
+        renumbered[class[0]] = 0;
+        let mut i = 0;
+        while i < representatives.len() {
+            for &next in self.transitions[representatives[i]].iter() {
+                if renumbered[class[next]] == usize::MAX {
+                    renumbered[class[next]] = representatives.len();
+                    representatives.push(next);
+                }
+            }
+            i += 1;
+        }
+        let transitions = representatives
+            .iter()
+            .map(|&q| self.transitions[q].map(|next| renumbered[class[next]]))
+            .collect();
+        let accepting = representatives.iter().map(|&q| self.accepting[q]).collect();
+        Self {
+            transitions,
+            accepting,
+        }
+    }
+
+    /// Evaluates the (public) automaton on a secret string, returning whether it accepts.
+    pub fn matches_public(&self, b: &mut CircuitBuilder, input: &[Word]) -> GateIndex {
+        // bytes that lead to the same states from every state can be treated as a single class:
+        let mut class_of_byte = [0; 256];
+        let mut class_representatives: Vec<usize> = vec![];
+        let mut ids = HashMap::new();
+        for byte in 0..256 {
+            let column: Vec<usize> = self.transitions.iter().map(|t| t[byte]).collect();
+            let id = ids.len();
+            class_of_byte[byte] = *ids.entry(column).or_insert(id);
+            if class_of_byte[byte] == class_representatives.len() {
+                class_representatives.push(byte);
+            }
+        }
+
+        let zero = b.zero();
+        let mut state = vec![zero; self.states()];
+        state[0] = b.one();
+        for byte in input {
+            assert_eq!(byte.len(), BYTE);
+            let one_hot = one_hot(b, byte, 256);
+            // since exactly one byte is set, the OR of the bytes in a class is a (free) XOR:
+            let mut classes = vec![zero; class_representatives.len()];
+            for (byte, &bit) in one_hot.iter().enumerate() {
+                let class = &mut classes[class_of_byte[byte]];
+                *class = b.xor(*class, bit);
+            }
+
+            let mut next_state = vec![zero; self.states()];
+            for (q, &active) in state.iter().enumerate() {
+                let mut targets: Vec<(usize, GateIndex)> = vec![];
+                for (class, &byte) in class_representatives.iter().enumerate() {
+                    let target = self.transitions[q][byte];
+                    match targets.iter_mut().find(|(t, _)| *t == target) {
+                        Some((_, bit)) => *bit = b.xor(*bit, classes[class]),
+                        None => targets.push((target, classes[class])),
+                    }
+                }
+                if let [(target, _)] = targets[..] {
+                    next_state[target] = b.xor(next_state[target], active);
+                } else {
+                    for (target, bit) in targets {
+                        let moved = b.and(active, bit);
+                        next_state[target] = b.xor(next_state[target], moved);
+                    }
+                }
+            }
+            state = next_state;
+        }
+        let mut accepted = zero;
+        for (&active, &accepting) in state.iter().zip(self.accepting.iter()) {
+            if accepting {
+                accepted = b.xor(accepted, active);
+            }
+        }
+        accepted
+    }
+
+    /// Encodes the automaton as a private input for a [`PrivateDfa`] with the specified number of
+    /// states, padding the automaton with unreachable states.
+    ///
+    /// Returns [`Error::InvalidPattern`] if the automaton has more states than that.
+    pub fn to_private_input(&self, states: usize) -> Result<Vec<bool>, Error> {
+        if self.states() > states {
+            return Err(Error::InvalidPattern);
+        }
+        let state_width = state_width(states);
+        let mut bits = Vec::with_capacity(PrivateDfa::input_len(states));
+        for q in 0..states {
+            for byte in 0..256 {
+                let next = self.transitions.get(q).map_or(0, |t| t[byte]);
+                bits.extend(to_bits(next as u64, state_width));
+            }
+        }
+        for q in 0..states {
+            bits.push(self.accepting.get(q).copied().unwrap_or(false));
+        }
+        Ok(bits)
+    }
+}
+
+/// An automaton whose transition table is a private input of one of the parties.
+#[derive(Debug, Clone)]
+pub struct PrivateDfa {
+    transitions: ObliviousArray,
+    accepting: ObliviousArray,
+    state_width: usize,
+}
+
+impl PrivateDfa {
+    /// Adds an automaton with (at most) the specified number of states as an input of a party.
+    ///
+    /// The input bits are obtained using [`Dfa::to_private_input`].
+    pub fn from_inputs(b: &mut CircuitBuilder, party: Party, states: usize) -> Self {
+        let state_width = state_width(states);
+        let transitions = ObliviousArray::from_inputs(b, party, states * 256, state_width);
+        let accepting = ObliviousArray::from_inputs(b, party, states, 1);
+        Self {
+            transitions,
+            accepting,
+            state_width,
+        }
+    }
+
+    /// The number of input bits of an automaton with the specified number of states.
+    pub fn input_len(states: usize) -> usize {
+        states * 256 * state_width(states) + states
+    }
+
+    /// Evaluates the automaton on a secret string, returning whether it accepts.
+    pub fn matches(&self, b: &mut CircuitBuilder, input: &[Word]) -> GateIndex {
+        let mut state = b.constant_word(0, self.state_width);
+        for byte in input {
+            assert_eq!(byte.len(), BYTE);
+            let index: Word = byte.iter().chain(state.iter()).copied().collect();
+            state = self.transitions.read(b, &index, ReadStrategy::MuxTree);
+        }
+        self.accepting.read(b, &state, ReadStrategy::MuxTree)[0]
+    }
+}
+
+fn state_width(states: usize) -> usize {
+    (usize::BITS - states.saturating_sub(1).leading_zeros()).max(1) as usize
+}
+
+/// A set of bytes, as a bit mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    fn empty() -> Self {
+        Self([0; 4])
+    }
+
+    fn full() -> Self {
+        Self([u64::MAX; 4])
+    }
+
+    fn byte(byte: u8) -> Self {
+        let mut set = Self::empty();
+        set.insert(byte);
+        set
+    }
+
+    fn insert(&mut self, byte: u8) {
+        self.0[byte as usize / 64] |= 1 << (byte % 64);
+    }
+
+    fn contains(&self, byte: usize) -> bool {
+        (self.0[byte / 64] >> (byte % 64)) & 1 == 1
+    }
+
+    fn range(&mut self, from: u8, to: u8) {
+        for byte in from..=to {
+            self.insert(byte);
+        }
+    }
+
+    fn union(&mut self, other: &ByteSet) {
+        for (b, &o) in self.0.iter_mut().zip(other.0.iter()) {
+            *b |= o;
+        }
+    }
+
+    fn negate(&self) -> Self {
+        Self(self.0.map(|b| !b))
+    }
+
+    /// The only byte in the set, or `None` if the set does not contain exactly one byte.
+    fn single(&self) -> Option<u8> {
+        let count: u32 = self.0.iter().map(|b| b.count_ones()).sum();
+        (count == 1).then(|| (0..256).find(|&b| self.contains(b)).unwrap() as u8)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Empty,
+    Bytes(ByteSet),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(pattern: &'a [u8]) -> Self {
+        Self { pattern, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<Node, Error> {
+        let node = self.alt()?;
+        if self.pos == self.pattern.len() {
+            Ok(node)
+        } else {
+            Err(Error::InvalidPattern)
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, Error> {
+        let byte = self.peek().ok_or(Error::InvalidPattern)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alt(&mut self) -> Result<Node, Error> {
+        let mut branches = vec![self.concat()?];
+        while self.eat(b'|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, Error> {
+        let mut nodes = vec![];
+        while let Some(byte) = self.peek() {
+            if byte == b'|' || byte == b')' {
+                break;
+            }
+            nodes.push(self.repeat()?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Node, Error> {
+        let mut node = self.atom()?;
+        loop {
+            let (min, max) = match self.peek() {
+                Some(b'*') => (0, None),
+                Some(b'+') => (1, None),
+                Some(b'?') => (0, Some(1)),
+                Some(b'{') => {
+                    self.pos += 1;
+                    let min = self.number()?;
+                    let max = if self.eat(b',') {
+                        match self.peek() {
+                            Some(b'}') => None,
+                            _ => Some(self.number()?),
+                        }
+                    } else {
+                        Some(min)
+                    };
+                    if self.peek() != Some(b'}') || max.map_or(false, |max| max < min) {
+                        return Err(Error::InvalidPattern);
+                    }
+                    (min, max)
+                }
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            // lazy repetitions only differ in which match is found, not whether one is found:
+            self.eat(b'?');
+            node = Node::Repeat(Box::new(node), min, max);
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, Error> {
+        let start = self.pos;
+        while self.peek().map_or(false, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.pattern[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .filter(|&n| n <= 1000)
+            .ok_or(Error::InvalidPattern)
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        match self.next()? {
+            b'(' => {
+                if self.eat(b'?') && !(self.eat(b':')) {
+                    return Err(Error::InvalidPattern);
+                }
+                let node = self.alt()?;
+                if !self.eat(b')') {
+                    return Err(Error::InvalidPattern);
+                }
+                Ok(node)
+            }
+            b'[' => self.class(),
+            b'.' => Ok(Node::Bytes(ByteSet::byte(b'\n').negate())),
+            b'^' => Ok(Node::Start),
+            b'$' => Ok(Node::End),
+            b'\\' => self.escape().map(Node::Bytes),
+            b'*' | b'+' | b'?' | b'{' | b')' => Err(Error::InvalidPattern),
+            byte => Ok(Node::Bytes(ByteSet::byte(byte))),
+        }
+    }
+
+    fn escape(&mut self) -> Result<ByteSet, Error> {
+        let mut set = ByteSet::empty();
+        match self.next()? {
+            b'd' => set.range(b'0', b'9'),
+            b'D' => return Ok(self.escape_class(b'd')?.negate()),
+            b'w' => {
+                set.range(b'0', b'9');
+                set.range(b'a', b'z');
+                set.range(b'A', b'Z');
+                set.insert(b'_');
+            }
+            b'W' => return Ok(self.escape_class(b'w')?.negate()),
+            b's' => {
+                for byte in [b'\t', b'\n', 0x0b, 0x0c, b'\r', b' '] {
+                    set.insert(byte);
+                }
+            }
+            b'S' => return Ok(self.escape_class(b's')?.negate()),
+            b'n' => set = ByteSet::byte(b'\n'),
+            b'r' => set = ByteSet::byte(b'\r'),
+            b't' => set = ByteSet::byte(b'\t'),
+            b'0' => set = ByteSet::byte(0),
+            b'x' => {
+                let hex = [self.next()?, self.next()?];
+                let byte = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(Error::InvalidPattern)?;
+                set = ByteSet::byte(byte);
+            }
+            byte if byte.is_ascii_punctuation() => set = ByteSet::byte(byte),
+            _ => return Err(Error::InvalidPattern),
+        }
+        Ok(set)
+    }
+
+    fn escape_class(&mut self, class: u8) -> Result<ByteSet, Error> {
+        Parser::new(&[class]).escape()
+    }
+
+    fn class(&mut self) -> Result<Node, Error> {
+        let negated = self.eat(b'^');
+        let mut set = ByteSet::empty();
+        let mut first = true;
+        loop {
+            let byte = self.next()?;
+            if byte == b']' && !first {
+                break;
+            }
+            first = false;
+            let from = match byte {
+                b'\\' => {
+                    let escaped = self.escape()?;
+                    match escaped.single() {
+                        Some(byte) => byte,
+                        None => {
+                            set.union(&escaped);
+                            continue;
+                        }
+                    }
+                }
+                b'[' => return Err(Error::InvalidPattern),
+                byte => byte,
+            };
+            if self.peek() == Some(b'-') && self.pattern.get(self.pos + 1) != Some(&b']') {
+                self.pos += 1;
+                let to = match self.next()? {
+                    b'\\' => self.escape()?.single().ok_or(Error::InvalidPattern)?,
+                    to => to,
+                };
+                if to < from {
+                    return Err(Error::InvalidPattern);
+                }
+                set.range(from, to);
+            } else {
+                set.insert(from);
+            }
+        }
+        Ok(Node::Bytes(if negated { set.negate() } else { set }))
+    }
+}
+
+#[derive(Debug, Default)]
+struct NfaState {
+    eps: Vec<usize>,
+    bytes: Vec<(ByteSet, usize)>,
+    start: Vec<usize>,
+    end: Vec<usize>,
+}
+
+/// A Thompson NFA with epsilon transitions and transitions that assert the start or end of input.
+#[derive(Debug, Default)]
+struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(usize, usize), Error> {
+        if self.states.len() > MAX_STATES {
+            return Err(Error::InvalidPattern);
+        }
+        let start = self.state();
+        let end = match node {
+            Node::Empty => start,
+            Node::Bytes(set) => {
+                let end = self.state();
+                self.states[start].bytes.push((*set, end));
+                end
+            }
+            Node::Start => {
+                let end = self.state();
+                self.states[start].start.push(end);
+                end
+            }
+            Node::End => {
+                let end = self.state();
+                self.states[start].end.push(end);
+                end
+            }
+            Node::Concat(nodes) => {
+                let mut end = start;
+                for node in nodes {
+                    let (s, e) = self.compile(node)?;
+                    self.states[end].eps.push(s);
+                    end = e;
+                }
+                end
+            }
+            Node::Alt(nodes) => {
+                let end = self.state();
+                for node in nodes {
+                    let (s, e) = self.compile(node)?;
+                    self.states[start].eps.push(s);
+                    self.states[e].eps.push(end);
+                }
+                end
+            }
+            Node::Repeat(node, min, max) => {
+                let mut end = start;
+                for _ in 0..*min {
+                    let (s, e) = self.compile(node)?;
+                    self.states[end].eps.push(s);
+                    end = e;
+                }
+                match max {
+                    None => {
+                        let (s, e) = self.compile(node)?;
+                        self.states[end].eps.push(s);
+                        self.states[e].eps.push(end);
+                    }
+                    Some(max) => {
+                        let optional_end = self.state();
+                        for _ in *min..*max {
+                            let (s, e) = self.compile(node)?;
+                            self.states[end].eps.push(s);
+                            self.states[end].eps.push(optional_end);
+                            end = e;
+                        }
+                        self.states[end].eps.push(optional_end);
+                        end = optional_end;
+                    }
+                }
+                end
+            }
+        };
+        Ok((start, end))
+    }
+
+    /// The sorted set of states reachable without consuming input.
+    fn closure(&self, states: &[usize], at_start: bool, at_end: bool) -> Vec<usize> {
+        let mut reached = vec![false; self.states.len()];
+        let mut stack = states.to_vec();
+        while let Some(q) = stack.pop() {
+            if reached[q] {
+                continue;
+            }
+            reached[q] = true;
+            let state = &self.states[q];
+            stack.extend(&state.eps);
+            if at_start {
+                stack.extend(&state.start);
+            }
+            if at_end {
+                stack.extend(&state.end);
+            }
+        }
+        (0..self.states.len()).filter(|&q| reached[q]).collect()
+    }
+
+    fn to_dfa(&self, start: usize, accept: usize) -> Result<Dfa, Error> {
+        // the initial state is the only one where start assertions hold:
+        let initial = (self.closure(&[start], true, false), true);
+        let mut ids = HashMap::from([(initial.clone(), 0)]);
+        let mut sets = vec![initial];
+        let mut transitions = vec![];
+        let mut accepting = vec![];
+        while transitions.len() < sets.len() {
+            let (set, is_initial) = sets[transitions.len()].clone();
+            let accepts = self.closure(&set, is_initial, true).contains(&accept);
+            accepting.push(accepts);
+            let mut row = [0; 256];
+            for (byte, next) in row.iter_mut().enumerate() {
+                let targets: Vec<usize> = set
+                    .iter()
+                    .flat_map(|&q| self.states[q].bytes.iter())
+                    .filter(|(bytes, _)| bytes.contains(byte))
+                    .map(|&(_, target)| target)
+                    .collect();
+                let key = (self.closure(&targets, false, false), false);
+                *next = match ids.get(&key) {
+                    Some(&id) => id,
+                    None => {
+                        if sets.len() >= MAX_STATES {
+                            return Err(Error::InvalidPattern);
+                        }
+                        ids.insert(key.clone(), sets.len());
+                        sets.push(key);
+                        sets.len() - 1
+                    }
+                };
+            }
+            transitions.push(row);
+        }
+        Dfa::new(transitions, accepting)
+    }
+}
+
+#[test]
+fn test_regex_to_dfa() {
+    let dfa = Dfa::from_regex("^a(b|c)*d$").unwrap();
+    assert!(dfa.matches(b"ad"));
+    assert!(dfa.matches(b"abcbd"));
+    assert!(!dfa.matches(b"xad"));
+    assert!(!dfa.matches(b"abce"));
+    // start, after 'a' and accepting state, plus a single rejecting sink state:
+    assert_eq!(dfa.states(), 4);
+
+    let dfa = Dfa::from_regex("foo").unwrap();
+    assert!(dfa.matches(b"xxfooxx"));
+    assert!(!dfa.matches(b"fo"));
+
+    for invalid in ["(", "a)", "*", "[a", "a{3,2}", "\\b", "(?i)a"] {
+        assert_eq!(Dfa::from_regex(invalid), Err(Error::InvalidPattern));
+    }
+}
@@ -0,0 +1,200 @@
+//! Hashing inside of circuits.
+//!
+//! The circuits compute the same hash as the `blake3` crate, which allows a party to prove inside
+//! of the secure computation that its input matches a hash that was published in advance. Words
+//! of the message and of the hash are in little-endian bit order, like all other words, and bytes
+//! are sequences of 8 bits in little-endian bit order, so that a message of `n` bits is hashed as
+//! the `n / 8` bytes that these bits encode.
+//!
+//! Every compression of a 64-byte block adds 336 additions of 32-bit words and thus needs about
+//! 10,000 AND gates, with 1 compression per started block and 1 additional compression per
+//! additional 1024-byte chunk.
+
+use super::{int, CircuitBuilder, Word};
+use crate::GateIndex;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+const KEYED_HASH: u32 = 1 << 4;
+
+fn rotate_right(x: &[GateIndex], n: usize) -> Word {
+    (0..32).map(|i| x[(i + n) % 32]).collect()
+}
+
+/// Mixes the words `a`, `b`, `c` and `d` of the state with the message words `mx` and `my`.
+fn g(
+    b: &mut CircuitBuilder,
+    state: &mut [Word],
+    [a, bb, c, d]: [usize; 4],
+    mx: &[GateIndex],
+    my: &[GateIndex],
+) {
+    state[a] = int::add(b, &state[a], &state[bb]);
+    state[a] = int::add(b, &state[a], mx);
+    state[d] = rotate_right(&b.xor_word(&state[d], &state[a]), 16);
+    state[c] = int::add(b, &state[c], &state[d]);
+    state[bb] = rotate_right(&b.xor_word(&state[bb], &state[c]), 12);
+    state[a] = int::add(b, &state[a], &state[bb]);
+    state[a] = int::add(b, &state[a], my);
+    state[d] = rotate_right(&b.xor_word(&state[d], &state[a]), 8);
+    state[c] = int::add(b, &state[c], &state[d]);
+    state[bb] = rotate_right(&b.xor_word(&state[bb], &state[c]), 7);
+}
+
+/// Compresses a block of 16 words, returning the 8 words of the chaining value.
+fn compress(
+    b: &mut CircuitBuilder,
+    cv: &[Word],
+    block: &[Word],
+    counter: u64,
+    block_len: usize,
+    flags: u32,
+) -> Vec<Word> {
+    let mut state: Vec<Word> = cv.to_vec();
+    for iv in &IV[..4] {
+        state.push(b.constant_word(*iv as u64, 32));
+    }
+    state.push(b.constant_word(counter & 0xFFFF_FFFF, 32));
+    state.push(b.constant_word(counter >> 32, 32));
+    state.push(b.constant_word(block_len as u64, 32));
+    state.push(b.constant_word(flags as u64, 32));
+    let mut m = block.to_vec();
+    for round in 0..7 {
+        // the columns, then the diagonals of the state:
+        g(b, &mut state, [0, 4, 8, 12], &m[0], &m[1]);
+        g(b, &mut state, [1, 5, 9, 13], &m[2], &m[3]);
+        g(b, &mut state, [2, 6, 10, 14], &m[4], &m[5]);
+        g(b, &mut state, [3, 7, 11, 15], &m[6], &m[7]);
+        g(b, &mut state, [0, 5, 10, 15], &m[8], &m[9]);
+        g(b, &mut state, [1, 6, 11, 12], &m[10], &m[11]);
+        g(b, &mut state, [2, 7, 8, 13], &m[12], &m[13]);
+        g(b, &mut state, [3, 4, 9, 14], &m[14], &m[15]);
+        if round < 6 {
+            m = MSG_PERMUTATION.iter().map(|&i| m[i].clone()).collect();
+        }
+    }
+    (0..8)
+        .map(|i| b.xor_word(&state[i], &state[i + 8]))
+        .collect()
+}
+
+/// Hashes a single chunk of at most 1024 bytes, given as bits.
+fn chunk(
+    b: &mut CircuitBuilder,
+    key: &[Word],
+    bits: &[GateIndex],
+    counter: u64,
+    flags: u32,
+    root: bool,
+) -> Vec<Word> {
+    let zero = b.zero();
+    let blocks = ((bits.len() + BLOCK_LEN * 8 - 1) / (BLOCK_LEN * 8)).max(1);
+    let mut cv = key.to_vec();
+    for i in 0..blocks {
+        let start = (i * BLOCK_LEN * 8).min(bits.len());
+        let end = ((i + 1) * BLOCK_LEN * 8).min(bits.len());
+        let block = &bits[start..end];
+        let mut padded = block.to_vec();
+        padded.resize(BLOCK_LEN * 8, zero);
+        let words: Vec<Word> = padded.chunks(32).map(|w| w.to_vec()).collect();
+        let mut block_flags = flags;
+        if i == 0 {
+            block_flags |= CHUNK_START;
+        }
+        if i + 1 == blocks {
+            block_flags |= CHUNK_END;
+            if root {
+                block_flags |= ROOT;
+            }
+        }
+        cv = compress(b, &cv, &words, counter, block.len() / 8, block_flags);
+    }
+    cv
+}
+
+/// Hashes the chunks starting at chunk `counter` as a subtree, following the tree layout of
+/// blake3, in which the left subtree contains the largest power of 2 of chunks that leaves at
+/// least 1 chunk for the right subtree.
+fn subtree(
+    b: &mut CircuitBuilder,
+    key: &[Word],
+    bits: &[GateIndex],
+    counter: u64,
+    flags: u32,
+    root: bool,
+) -> Vec<Word> {
+    let chunks = ((bits.len() + CHUNK_LEN * 8 - 1) / (CHUNK_LEN * 8)).max(1);
+    if chunks == 1 {
+        return chunk(b, key, bits, counter, flags, root);
+    }
+    let left_chunks = 1 << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+    let (left, right) = bits.split_at(left_chunks * CHUNK_LEN * 8);
+    let mut block = subtree(b, key, left, counter, flags, false);
+    block.extend(subtree(
+        b,
+        key,
+        right,
+        counter + left_chunks as u64,
+        flags,
+        false,
+    ));
+    let parent_flags = flags | PARENT | if root { ROOT } else { 0 };
+    compress(b, key, &block, 0, BLOCK_LEN, parent_flags)
+}
+
+/// Computes the 256-bit keyed blake3 hash of a message of `8 * n` bits, which is the same as
+/// `blake3::keyed_hash(key, message)` for the `n` bytes of the message.
+///
+/// # Panics
+///
+/// Panics if the number of message bits is not a multiple of 8.
+pub fn blake3_keyed(b: &mut CircuitBuilder, key: &[u8; 32], message: &[GateIndex]) -> Word {
+    assert_eq!(message.len() % 8, 0, "blake3 hashes whole bytes");
+    let key: Vec<Word> = key
+        .chunks(4)
+        .map(|w| {
+            let w = u32::from_le_bytes([w[0], w[1], w[2], w[3]]);
+            b.constant_word(w as u64, 32)
+        })
+        .collect();
+    subtree(b, &key, message, 0, KEYED_HASH, true).concat()
+}
+
+#[test]
+fn test_blake3_keyed() {
+    use super::{to_bits, Party};
+
+    let key = *blake3::hash(b"test key").as_bytes();
+    // empty, partial block, whole blocks, multiple chunks:
+    for len in [0, 1, 63, 64, 65, 200, 1024, 1025, 2100] {
+        let message: Vec<u8> = (0..len).map(|i| (i * 7 + 3) as u8).collect();
+        let mut b = CircuitBuilder::new();
+        // constants are derived from the first gate, which is not part of the empty message:
+        b.input(Party::Contributor);
+        let bits = b.input_word(Party::Evaluator, 8 * len);
+        let hash = blake3_keyed(&mut b, &key, &bits);
+        let circuit = b.build(hash);
+        let input: Vec<bool> = message
+            .iter()
+            .flat_map(|&byte| to_bits(byte as u64, 8))
+            .collect();
+        let output = circuit.eval(&[false], &input).unwrap();
+        let expected: Vec<bool> = blake3::keyed_hash(&key, &message)
+            .as_bytes()
+            .iter()
+            .flat_map(|&byte| to_bits(byte as u64, 8))
+            .collect();
+        assert_eq!(output, expected, "message of {len} bytes");
+    }
+}
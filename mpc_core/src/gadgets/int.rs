@@ -0,0 +1,190 @@
+//! Integer arithmetic on words, interpreted as unsigned or two's complement signed integers.
+//!
+//! All operations wrap around on overflow, like the `wrapping_*` methods of Rust's integer types.
+//! Additions, subtractions and comparisons need one AND gate per bit, a multiplication of two
+//! `n`-bit words needs roughly `n * n` AND gates.
+
+use super::{CircuitBuilder, Word};
+use crate::GateIndex;
+
+/// Computes the majority of 3 bits using a single AND gate.
+fn majority(b: &mut CircuitBuilder, x: GateIndex, y: GateIndex, z: GateIndex) -> GateIndex {
+    let xy = b.xor(x, y);
+    let xz = b.xor(x, z);
+    let both = b.and(xy, xz);
+    b.xor(x, both)
+}
+
+fn add_with_carry(
+    b: &mut CircuitBuilder,
+    x: &[GateIndex],
+    y: &[GateIndex],
+    carry: GateIndex,
+) -> Word {
+    assert_eq!(x.len(), y.len());
+    let n = x.len();
+    let mut carry = carry;
+    let mut sum = Vec::with_capacity(n);
+    for (i, (&x, &y)) in x.iter().zip(y.iter()).enumerate() {
+        let xy = b.xor(x, y);
+        sum.push(b.xor(xy, carry));
+        // the carry out of the most significant bit is never needed:
+        if i + 1 < n {
+            carry = majority(b, x, y, carry);
+        }
+    }
+    sum
+}
+
+/// Computes `x + y`.
+pub fn add(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> Word {
+    let zero = b.zero();
+    add_with_carry(b, x, y, zero)
+}
+
+/// Computes `x - y`.
+pub fn sub(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> Word {
+    let not_y: Word = y.iter().map(|&y| b.not(y)).collect();
+    let one = b.one();
+    add_with_carry(b, x, &not_y, one)
+}
+
+/// Computes `-x`.
+pub fn neg(b: &mut CircuitBuilder, x: &[GateIndex]) -> Word {
+    let zero = b.constant_word(0, x.len());
+    sub(b, &zero, x)
+}
+
+/// Computes `x * y`, truncated to the width of the inputs.
+///
+/// The result is the same for signed and unsigned integers.
+pub fn mul(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> Word {
+    assert_eq!(x.len(), y.len());
+    let n = x.len();
+    let mut product = b.and_word(y[0], x);
+    for i in 1..n {
+        let partial = b.and_word(y[i], &x[..n - i]);
+        let high = add(b, &product[i..], &partial);
+        product.truncate(i);
+        product.extend(high);
+    }
+    product
+}
+
+/// Checks whether `x < y` for unsigned integers.
+pub fn lt(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> GateIndex {
+    assert_eq!(x.len(), y.len());
+    // `x < y` if and only if computing `x - y` results in a borrow:
+    let mut borrow = b.zero();
+    for (&x, &y) in x.iter().zip(y.iter()) {
+        let not_x = b.not(x);
+        borrow = majority(b, not_x, y, borrow);
+    }
+    borrow
+}
+
+/// Checks whether `x < y` for signed integers.
+pub fn lt_signed(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> GateIndex {
+    assert!(!x.is_empty());
+    // flipping the sign bits maps signed integers to unsigned integers with the same order:
+    let mut x = x.to_vec();
+    let mut y = y.to_vec();
+    let msb = x.len() - 1;
+    x[msb] = b.not(x[msb]);
+    y[msb] = b.not(y[msb]);
+    lt(b, &x, &y)
+}
+
+/// Checks whether `x >= value` for an unsigned integer and a public constant.
+///
+/// Needs at most one AND gate per bit, fewer if the constant has trailing zeros.
+pub fn ge_const(b: &mut CircuitBuilder, x: &[GateIndex], value: u64) -> GateIndex {
+    if x.len() < 64 && value >> x.len() != 0 {
+        return b.zero();
+    }
+    // compares the bits from least to most significant, `None` means "known to be true":
+    let mut ge = None;
+    for (i, &x) in x.iter().enumerate() {
+        let bit = i < 64 && (value >> i) & 1 == 1;
+        ge = match (bit, ge) {
+            (true, None) => Some(x),
+            (true, Some(ge)) => Some(b.and(x, ge)),
+            (false, None) => None,
+            (false, Some(ge)) => Some(b.or(x, ge)),
+        };
+    }
+    ge.unwrap_or_else(|| b.one())
+}
+
+/// Returns the sign bit of a signed integer, which is set if (and only if) it is negative.
+pub fn is_negative(x: &[GateIndex]) -> GateIndex {
+    *x.last().expect("words must not be empty")
+}
+
+/// Extends a signed integer to a larger width (or truncates it to a smaller one).
+pub fn sign_extend(x: &[GateIndex], width: usize) -> Word {
+    let sign = is_negative(x);
+    let mut extended = x.to_vec();
+    extended.resize(width, sign);
+    extended.truncate(width);
+    extended
+}
+
+/// Shifts a signed integer to the right by a public amount, rounding towards negative infinity.
+pub fn shr_signed(x: &[GateIndex], shift: usize) -> Word {
+    let sign = is_negative(x);
+    let mut shifted: Word = x.iter().skip(shift).copied().collect();
+    shifted.resize(x.len(), sign);
+    shifted
+}
+
+/// Selects the larger of two signed integers.
+pub fn max_signed(b: &mut CircuitBuilder, x: &[GateIndex], y: &[GateIndex]) -> Word {
+    let x_lt_y = lt_signed(b, x, y);
+    b.mux_word(x_lt_y, x, y)
+}
+
+#[test]
+fn test_integer_arithmetic() {
+    use super::{from_bits, to_bits, Party};
+
+    let width = 8;
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, width);
+    let y = b.input_word(Party::Evaluator, width);
+    let mut outputs = vec![];
+    outputs.extend(add(&mut b, &x, &y));
+    outputs.extend(sub(&mut b, &x, &y));
+    outputs.extend(neg(&mut b, &x));
+    outputs.extend(mul(&mut b, &x, &y));
+    outputs.extend(shr_signed(&x, 3));
+    outputs.extend(max_signed(&mut b, &x, &y));
+    outputs.push(lt(&mut b, &x, &y));
+    outputs.push(lt_signed(&mut b, &x, &y));
+    for value in [0, 1, 100, 128, 255, 256] {
+        outputs.push(ge_const(&mut b, &x, value));
+    }
+    let circuit = b.build(outputs);
+
+    let values = [0u8, 1, 2, 7, 100, 127, 128, 200, 255];
+    for &x in values.iter() {
+        for &y in values.iter() {
+            let output = circuit
+                .eval(&to_bits(x as u64, width), &to_bits(y as u64, width))
+                .unwrap();
+            let words: Vec<u8> = output.chunks(width).map(|w| from_bits(w) as u8).collect();
+            let (sx, sy) = (x as i8, y as i8);
+            assert_eq!(words[0], x.wrapping_add(y));
+            assert_eq!(words[1], x.wrapping_sub(y));
+            assert_eq!(words[2], x.wrapping_neg());
+            assert_eq!(words[3], x.wrapping_mul(y));
+            assert_eq!(words[4] as i8, sx >> 3);
+            assert_eq!(words[5] as i8, sx.max(sy));
+            assert_eq!(output[6 * width], x < y);
+            assert_eq!(output[6 * width + 1], sx < sy);
+            for (i, value) in [0, 1, 100, 128, 255, 256].into_iter().enumerate() {
+                assert_eq!(output[6 * width + 2 + i], x as u64 >= value);
+            }
+        }
+    }
+}
@@ -0,0 +1,599 @@
+//! Privacy-preserving inference of machine learning models.
+//!
+//! The contributor provides a private [`Model`] and the evaluator the private features to classify.
+//! Only the [`ModelShape`] is public, since it determines the circuit: the kind of model, the
+//! number of features and the number and sizes of trees or layers. All weights, thresholds, leaf
+//! values and (up to the public depth) the structure of decision trees remain private.
+//!
+//! Models can be imported from JSON, using one of the following formats:
+//!
+//! ```json
+//! { "type": "tree_ensemble", "features": 2, "depth": 2, "trees": [
+//!     { "feature": 0, "threshold": 0.5,
+//!       "left": { "value": -1.0 },
+//!       "right": { "feature": 1, "threshold": 2.0,
+//!                  "left": { "value": 0.5 }, "right": { "value": 1.0 } } }
+//! ] }
+//! { "type": "linear", "weights": [0.5, -1.25], "bias": 0.1 }
+//! { "type": "logistic", "weights": [0.5, -1.25], "bias": 0.1 }
+//! { "type": "network", "layers": [
+//!     { "weights": [[0.5, -1.0], [1.0, 0.25], [-0.5, 2.0]], "bias": [0.0, 0.1, -0.1] },
+//!     { "weights": [[1.0, -1.0, 0.5], [-1.0, 1.0, 0.5]], "bias": [0.0, 0.0] }
+//! ] }
+//! ```
+//!
+//! A decision tree goes to the left child if the feature is less than or equal to the threshold
+//! and an ensemble outputs the sum of the leaf values of all its trees. Logistic models approximate
+//! the sigmoid function as `clamp(x / 4 + 1/2, 0, 1)`. Networks apply ReLU to all hidden layers
+//! and output the index of the largest value of the last layer (preferring the lowest index).
+//!
+//! All computations use the two's complement [`FixedPoint`] format and wrap around on overflow.
+//! [`Model::predict`] performs exactly the same computations in plaintext, so that its results are
+//! identical to the results of the circuit.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    array::{ObliviousArray, ReadStrategy},
+    from_bits, int, to_bits, CircuitBuilder, Party, Word,
+};
+use crate::{Circuit, Error};
+
+/// A signed fixed-point number format with `bits` bits in total, of which `frac` are fractional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedPoint {
+    bits: usize,
+    frac: usize,
+}
+
+impl Default for FixedPoint {
+    /// 32 bits, of which 16 are fractional.
+    fn default() -> Self {
+        Self::new(32, 16)
+    }
+}
+
+impl FixedPoint {
+    /// Creates a format with `bits` bits in total, of which `frac` are fractional.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is larger than 64 or if the format cannot represent the number 1.
+    pub fn new(bits: usize, frac: usize) -> Self {
+        assert!(
+            bits <= 64,
+            "fixed-point numbers can be at most 64 bits wide"
+        );
+        assert!(
+            frac + 2 <= bits,
+            "fixed-point numbers must be able to represent 1"
+        );
+        Self { bits, frac }
+    }
+
+    /// The total number of bits.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// The number of fractional bits.
+    pub fn frac(&self) -> usize {
+        self.frac
+    }
+
+    /// Converts a number to the fixed-point representation, rounding to the nearest value.
+    pub fn encode(&self, x: f64) -> i64 {
+        self.wrap((x * (1u64 << self.frac) as f64).round() as i128)
+    }
+
+    /// Converts a fixed-point representation back to a number.
+    pub fn decode(&self, x: i64) -> f64 {
+        x as f64 / (1u64 << self.frac) as f64
+    }
+
+    /// Encodes a number as input bits.
+    pub fn to_bits(&self, x: f64) -> Vec<bool> {
+        to_bits(self.encode(x) as u64, self.bits)
+    }
+
+    /// Decodes a number from output bits.
+    pub fn from_bits(&self, bits: &[bool]) -> f64 {
+        self.decode(self.wrap(from_bits(bits) as i128))
+    }
+
+    fn wrap(&self, x: i128) -> i64 {
+        let unused = 128 - self.bits as u32;
+        ((x << unused) >> unused) as i64
+    }
+
+    fn add(&self, x: i64, y: i64) -> i64 {
+        self.wrap(x as i128 + y as i128)
+    }
+
+    fn mul(&self, x: i64, y: i64) -> i64 {
+        self.wrap((x as i128 * y as i128) >> self.frac)
+    }
+
+    fn constant(&self, b: &mut CircuitBuilder, x: i64) -> Word {
+        b.constant_word(x as u64, self.bits)
+    }
+
+    fn mul_word(&self, b: &mut CircuitBuilder, x: &[u32], y: &[u32]) -> Word {
+        let x = int::sign_extend(x, 2 * self.bits);
+        let y = int::sign_extend(y, 2 * self.bits);
+        let product = int::mul(b, &x, &y);
+        product[self.frac..self.frac + self.bits].to_vec()
+    }
+}
+
+/// A node of a decision tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TreeNode {
+    /// A leaf with the value that the tree outputs.
+    Leaf {
+        /// The output value.
+        value: f64,
+    },
+    /// An inner node comparing a feature to a threshold.
+    Split {
+        /// The index of the compared feature.
+        feature: usize,
+        /// Features less than or equal to the threshold go to the left.
+        threshold: f64,
+        /// The subtree for features less than or equal to the threshold.
+        left: Box<TreeNode>,
+        /// The subtree for features greater than the threshold.
+        right: Box<TreeNode>,
+    },
+}
+
+impl TreeNode {
+    fn depth(&self) -> usize {
+        match self {
+            TreeNode::Leaf { .. } => 0,
+            TreeNode::Split { left, right, .. } => 1 + left.depth().max(right.depth()),
+        }
+    }
+
+    fn max_feature(&self) -> usize {
+        match self {
+            TreeNode::Leaf { .. } => 0,
+            TreeNode::Split {
+                feature,
+                left,
+                right,
+                ..
+            } => (*feature).max(left.max_feature()).max(right.max_feature()),
+        }
+    }
+
+    /// Stores the tree as a complete tree of the specified depth, in breadth-first order.
+    fn flatten(
+        &self,
+        level: usize,
+        pos: usize,
+        depth: usize,
+        splits: &mut [(usize, f64)],
+        leaves: &mut [f64],
+    ) {
+        if level == depth {
+            if let TreeNode::Leaf { value } = self {
+                leaves[pos] = *value;
+            }
+            return;
+        }
+        let node = (1 << level) - 1 + pos;
+        match self {
+            TreeNode::Leaf { .. } => {
+                // a dummy split with the same leaf on both sides:
+                splits[node] = (0, 0.0);
+                self.flatten(level + 1, 2 * pos, depth, splits, leaves);
+                self.flatten(level + 1, 2 * pos + 1, depth, splits, leaves);
+            }
+            TreeNode::Split {
+                feature,
+                threshold,
+                left,
+                right,
+            } => {
+                splits[node] = (*feature, *threshold);
+                left.flatten(level + 1, 2 * pos, depth, splits, leaves);
+                right.flatten(level + 1, 2 * pos + 1, depth, splits, leaves);
+            }
+        }
+    }
+
+    fn predict(&self, features: &[i64], fp: FixedPoint) -> i64 {
+        match self {
+            TreeNode::Leaf { value } => fp.encode(*value),
+            TreeNode::Split {
+                feature,
+                threshold,
+                left,
+                right,
+            } => {
+                if features[*feature] <= fp.encode(*threshold) {
+                    left.predict(features, fp)
+                } else {
+                    right.predict(features, fp)
+                }
+            }
+        }
+    }
+}
+
+/// A fully connected layer of a neural network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    /// The weights, one row of inputs for every output of the layer.
+    pub weights: Vec<Vec<f64>>,
+    /// The bias of every output of the layer.
+    pub bias: Vec<f64>,
+}
+
+/// A machine learning model that is private to the contributor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Model {
+    /// An ensemble of decision trees, whose outputs are summed up.
+    TreeEnsemble {
+        /// The number of features.
+        features: usize,
+        /// The maximum depth of the trees, which is public.
+        depth: usize,
+        /// The trees of the ensemble.
+        trees: Vec<TreeNode>,
+    },
+    /// A linear regression model.
+    Linear {
+        /// The weight of every feature.
+        weights: Vec<f64>,
+        /// The bias that is added to the weighted sum.
+        bias: f64,
+    },
+    /// A logistic regression model, with an approximated sigmoid function.
+    Logistic {
+        /// The weight of every feature.
+        weights: Vec<f64>,
+        /// The bias that is added to the weighted sum.
+        bias: f64,
+    },
+    /// A feed-forward neural network with ReLU activations, classifying using argmax.
+    Network {
+        /// The layers of the network, starting with the one that receives the features.
+        layers: Vec<Layer>,
+    },
+}
+
+/// The public parts of a [`Model`], which determine the circuit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModelShape {
+    /// An ensemble of decision trees.
+    TreeEnsemble {
+        /// The number of features.
+        features: usize,
+        /// The depth of the (complete) trees.
+        depth: usize,
+        /// The number of trees.
+        trees: usize,
+    },
+    /// A linear regression model.
+    Linear {
+        /// The number of features.
+        features: usize,
+    },
+    /// A logistic regression model.
+    Logistic {
+        /// The number of features.
+        features: usize,
+    },
+    /// A feed-forward neural network.
+    Network {
+        /// The number of features, followed by the number of outputs of every layer.
+        sizes: Vec<usize>,
+    },
+}
+
+/// The result of an inference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prediction {
+    /// The value computed by a tree ensemble, linear or logistic model.
+    Value(f64),
+    /// The class chosen by a neural network.
+    Class(usize),
+}
+
+impl Model {
+    /// Parses a model from its JSON description, returning [`Error::InvalidModel`] if the
+    /// description is malformed or inconsistent.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let model: Model = serde_json::from_str(json).map_err(|_| Error::InvalidModel)?;
+        model.shape()?;
+        Ok(model)
+    }
+
+    /// The public shape of the model, or [`Error::InvalidModel`] if the model is inconsistent.
+    pub fn shape(&self) -> Result<ModelShape, Error> {
+        match self {
+            Model::TreeEnsemble {
+                features,
+                depth,
+                trees,
+            } => {
+                let valid = trees
+                    .iter()
+                    .all(|t| t.depth() <= *depth && t.max_feature() < *features);
+                if *features == 0 || *depth > 20 || !valid {
+                    return Err(Error::InvalidModel);
+                }
+                Ok(ModelShape::TreeEnsemble {
+                    features: *features,
+                    depth: *depth,
+                    trees: trees.len(),
+                })
+            }
+            Model::Linear { weights, .. } | Model::Logistic { weights, .. } => {
+                if weights.is_empty() {
+                    return Err(Error::InvalidModel);
+                }
+                let features = weights.len();
+                Ok(match self {
+                    Model::Linear { .. } => ModelShape::Linear { features },
+                    _ => ModelShape::Logistic { features },
+                })
+            }
+            Model::Network { layers } => {
+                let mut sizes = vec![layers
+                    .first()
+                    .and_then(|l| l.weights.first())
+                    .map_or(0, |row| row.len())];
+                for layer in layers {
+                    let inputs = *sizes.last().unwrap();
+                    if inputs == 0
+                        || layer.weights.len() != layer.bias.len()
+                        || layer.weights.iter().any(|row| row.len() != inputs)
+                    {
+                        return Err(Error::InvalidModel);
+                    }
+                    sizes.push(layer.bias.len());
+                }
+                if layers.is_empty() || *sizes.last().unwrap() == 0 {
+                    return Err(Error::InvalidModel);
+                }
+                Ok(ModelShape::Network { sizes })
+            }
+        }
+    }
+
+    /// Encodes the private parameters of the model as the contributor's input to the circuit.
+    pub fn contributor_input(&self, fp: FixedPoint) -> Result<Vec<bool>, Error> {
+        let shape = self.shape()?;
+        let mut bits = vec![];
+        match self {
+            Model::TreeEnsemble { depth, trees, .. } => {
+                let feature_width = index_width(shape.features());
+                for tree in trees {
+                    let mut splits = vec![(0, 0.0); (1 << depth) - 1];
+                    let mut leaves = vec![0.0; 1 << depth];
+                    tree.flatten(0, 0, *depth, &mut splits, &mut leaves);
+                    for (feature, threshold) in splits {
+                        bits.extend(to_bits(feature as u64, feature_width));
+                        bits.extend(fp.to_bits(threshold));
+                    }
+                    for value in leaves {
+                        bits.extend(fp.to_bits(value));
+                    }
+                }
+            }
+            Model::Linear { weights, bias } | Model::Logistic { weights, bias } => {
+                for &w in weights {
+                    bits.extend(fp.to_bits(w));
+                }
+                bits.extend(fp.to_bits(*bias));
+            }
+            Model::Network { layers } => {
+                for layer in layers {
+                    for (row, &bias) in layer.weights.iter().zip(layer.bias.iter()) {
+                        for &w in row {
+                            bits.extend(fp.to_bits(w));
+                        }
+                        bits.extend(fp.to_bits(bias));
+                    }
+                }
+            }
+        }
+        Ok(bits)
+    }
+
+    /// Runs the inference in plaintext, with exactly the same fixed-point computations as the
+    /// circuit.
+    pub fn predict(&self, features: &[f64], fp: FixedPoint) -> Result<Prediction, Error> {
+        let shape = self.shape()?;
+        if features.len() != shape.features() {
+            return Err(Error::InsufficientInput);
+        }
+        let x: Vec<i64> = features.iter().map(|&f| fp.encode(f)).collect();
+        let dot = |weights: &[f64], bias: f64, x: &[i64]| {
+            weights
+                .iter()
+                .zip(x.iter())
+                .fold(fp.encode(bias), |acc, (&w, &x)| {
+                    fp.add(acc, fp.mul(fp.encode(w), x))
+                })
+        };
+        Ok(match self {
+            Model::TreeEnsemble { trees, .. } => Prediction::Value(
+                fp.decode(
+                    trees
+                        .iter()
+                        .fold(0, |acc, t| fp.add(acc, t.predict(&x, fp))),
+                ),
+            ),
+            Model::Linear { weights, bias } => {
+                Prediction::Value(fp.decode(dot(weights, *bias, &x)))
+            }
+            Model::Logistic { weights, bias } => {
+                let one = fp.encode(1.0);
+                let t = fp.add(dot(weights, *bias, &x) >> 2, fp.encode(0.5));
+                Prediction::Value(fp.decode(t.clamp(0, one)))
+            }
+            Model::Network { layers } => {
+                let mut values = x;
+                for (i, layer) in layers.iter().enumerate() {
+                    values = layer
+                        .weights
+                        .iter()
+                        .zip(layer.bias.iter())
+                        .map(|(row, &bias)| dot(row, bias, &values))
+                        .map(|v| if i + 1 < layers.len() { v.max(0) } else { v })
+                        .collect();
+                }
+                let mut best = 0;
+                for (i, &v) in values.iter().enumerate() {
+                    if v > values[best] {
+                        best = i;
+                    }
+                }
+                Prediction::Class(best)
+            }
+        })
+    }
+}
+
+impl ModelShape {
+    /// The number of features expected by the model.
+    pub fn features(&self) -> usize {
+        match self {
+            ModelShape::TreeEnsemble { features, .. }
+            | ModelShape::Linear { features }
+            | ModelShape::Logistic { features } => *features,
+            ModelShape::Network { sizes } => sizes[0],
+        }
+    }
+
+    /// Encodes the features as the evaluator's input to the circuit.
+    pub fn evaluator_input(&self, features: &[f64], fp: FixedPoint) -> Result<Vec<bool>, Error> {
+        if features.len() != self.features() {
+            return Err(Error::InsufficientInput);
+        }
+        Ok(features.iter().flat_map(|&f| fp.to_bits(f)).collect())
+    }
+
+    /// Decodes the output of the circuit.
+    pub fn decode_output(&self, output: &[bool], fp: FixedPoint) -> Prediction {
+        match self {
+            ModelShape::Network { .. } => Prediction::Class(from_bits(output) as usize),
+            _ => Prediction::Value(fp.from_bits(output)),
+        }
+    }
+
+    /// Generates the inference circuit for all models of this shape.
+    ///
+    /// The contributor's input is obtained using [`Model::contributor_input`] and the evaluator's
+    /// input using [`ModelShape::evaluator_input`].
+    pub fn circuit(&self, fp: FixedPoint) -> Circuit {
+        let mut b = CircuitBuilder::new();
+        let features: Vec<Word> = (0..self.features())
+            .map(|_| b.input_word(Party::Evaluator, fp.bits))
+            .collect();
+        let output = match self {
+            ModelShape::TreeEnsemble { depth, trees, .. } => {
+                let features = ObliviousArray::new(features, fp.bits);
+                let mut sum = fp.constant(&mut b, 0);
+                for _ in 0..*trees {
+                    let value = tree(&mut b, &features, *depth, fp);
+                    sum = int::add(&mut b, &sum, &value);
+                }
+                sum
+            }
+            ModelShape::Linear { .. } => dot(&mut b, &features, fp),
+            ModelShape::Logistic { .. } => {
+                let z = dot(&mut b, &features, fp);
+                let half = fp.constant(&mut b, fp.encode(0.5));
+                let one = fp.constant(&mut b, fp.encode(1.0));
+                let t = int::add(&mut b, &int::shr_signed(&z, 2), &half);
+                let is_positive = b.not(int::is_negative(&t));
+                let above_one = int::lt_signed(&mut b, &one, &t);
+                let t = b.and_word(is_positive, &t);
+                b.mux_word(above_one, &t, &one)
+            }
+            ModelShape::Network { sizes } => {
+                let mut values = features;
+                for (i, &outputs) in sizes[1..].iter().enumerate() {
+                    values = (0..outputs).map(|_| dot(&mut b, &values, fp)).collect();
+                    if i + 2 < sizes.len() {
+                        values = values
+                            .iter()
+                            .map(|v| {
+                                let is_positive = b.not(int::is_negative(v));
+                                b.and_word(is_positive, v)
+                            })
+                            .collect();
+                    }
+                }
+                argmax(&mut b, &values)
+            }
+        };
+        b.build(output)
+    }
+}
+
+/// Computes the weighted sum of the inputs plus a bias, with weights and bias from the contributor.
+fn dot(b: &mut CircuitBuilder, inputs: &[Word], fp: FixedPoint) -> Word {
+    let weights: Vec<Word> = inputs
+        .iter()
+        .map(|_| b.input_word(Party::Contributor, fp.bits))
+        .collect();
+    let mut sum = b.input_word(Party::Contributor, fp.bits);
+    for (w, x) in weights.iter().zip(inputs.iter()) {
+        let product = fp.mul_word(b, w, x);
+        sum = int::add(b, &sum, &product);
+    }
+    sum
+}
+
+/// Evaluates a complete decision tree with private splits and leaves from the contributor.
+fn tree(b: &mut CircuitBuilder, features: &ObliviousArray, depth: usize, fp: FixedPoint) -> Word {
+    let feature_width = index_width(features.len());
+    let mut go_right = vec![];
+    for _ in 0..(1 << depth) - 1 {
+        let feature = b.input_word(Party::Contributor, feature_width);
+        let threshold = b.input_word(Party::Contributor, fp.bits);
+        let x = features.read(b, &feature, ReadStrategy::MuxTree);
+        go_right.push(int::lt_signed(b, &threshold, &x));
+    }
+    let mut values: Vec<Word> = (0..1 << depth)
+        .map(|_| b.input_word(Party::Contributor, fp.bits))
+        .collect();
+    // select the values bottom-up, one level of the tree at a time:
+    for level in (0..depth).rev() {
+        let first_node = (1 << level) - 1;
+        values = values
+            .chunks(2)
+            .enumerate()
+            .map(|(pos, pair)| b.mux_word(go_right[first_node + pos], &pair[0], &pair[1]))
+            .collect();
+    }
+    values.pop().unwrap()
+}
+
+/// Returns the index of the largest signed value, preferring lower indices.
+fn argmax(b: &mut CircuitBuilder, values: &[Word]) -> Word {
+    let width = index_width(values.len()).max(1);
+    let mut best = values[0].clone();
+    let mut best_index = b.constant_word(0, width);
+    for (i, value) in values.iter().enumerate().skip(1) {
+        let greater = int::lt_signed(b, &best, value);
+        best = b.mux_word(greater, &best, value);
+        let index = b.constant_word(i as u64, width);
+        best_index = b.mux_word(greater, &best_index, &index);
+    }
+    best_index
+}
+
+/// The number of bits needed to index `len` elements.
+fn index_width(len: usize) -> usize {
+    (usize::BITS - len.saturating_sub(1).leading_zeros()) as usize
+}
@@ -0,0 +1,357 @@
+//! Building blocks for generating circuits directly from Rust code.
+//!
+//! Circuits are usually compiled from a higher-level language, but some recurring patterns (such
+//! as looking up a secret index in an array) compile to poorly structured circuits. The gadgets in
+//! this module emit hand-optimized sub-circuits into a [`CircuitBuilder`], which can then be
+//! turned into a regular [`Circuit`].
+//!
+//! Multi-bit values are represented as a [`Word`], a vector of wires in little-endian bit order.
+
+use crate::{Circuit, Gate, GateIndex};
+
+pub mod array;
+pub mod dfa;
+pub mod hash;
+pub mod int;
+pub mod ml;
+pub mod noise;
+pub mod permutation;
+pub mod strings;
+
+/// A multi-bit value in the circuit, as a list of wires in little-endian bit order.
+pub type Word = Vec<GateIndex>;
+
+/// The party that provides an input to the circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    /// The party that contributes its input to the secure computation protocol.
+    Contributor,
+    /// The party that evaluates the circuit and the output.
+    Evaluator,
+}
+
+/// Incrementally builds a [`Circuit`] out of individual gates and gadgets.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBuilder {
+    gates: Vec<Gate>,
+    and_gates: usize,
+    zero: Option<GateIndex>,
+    one: Option<GateIndex>,
+}
+
+impl CircuitBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues building on the gates of an existing circuit, whose output gates are discarded.
+    pub fn from_circuit(circuit: &Circuit) -> Self {
+        let gates = circuit.gates().clone();
+        let and_gates = gates.iter().filter(|g| g.is_and()).count();
+        Self {
+            gates,
+            and_gates,
+            zero: None,
+            one: None,
+        }
+    }
+
+    /// The gates that have been added so far.
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// The number of AND gates that have been added so far.
+    ///
+    /// Comparing this number before and after adding a gadget yields the gadget's AND cost.
+    pub fn and_gates(&self) -> usize {
+        self.and_gates
+    }
+
+    fn push(&mut self, gate: Gate) -> GateIndex {
+        if gate.is_and() {
+            self.and_gates += 1;
+        }
+        self.gates.push(gate);
+        (self.gates.len() - 1) as GateIndex
+    }
+
+    /// Adds a single input bit of the specified party.
+    pub fn input(&mut self, party: Party) -> GateIndex {
+        match party {
+            Party::Contributor => self.push(Gate::InContrib),
+            Party::Evaluator => self.push(Gate::InEval),
+        }
+    }
+
+    /// Adds a `width`-bit input word of the specified party.
+    pub fn input_word(&mut self, party: Party, width: usize) -> Word {
+        (0..width).map(|_| self.input(party)).collect()
+    }
+
+    /// Adds a `width`-bit word that is XOR-shared between the parties, with each party providing
+    /// its share as input.
+    ///
+    /// This can be used to continue a computation on the
+    /// [`OutputShares`](crate::states::OutputShares) of a previous execution. The shares are input
+    /// without their MACs, so a malicious party can flip any bit of the previous output unnoticed.
+    /// Computations that need to stay secure against malicious parties must carry their values
+    /// from one execution to the next using
+    /// [`Contributor::reactive`](crate::states::Contributor::reactive) instead.
+    pub fn input_shared(&mut self, width: usize) -> Word {
+        let contrib = self.input_word(Party::Contributor, width);
+        let eval = self.input_word(Party::Evaluator, width);
+        self.xor_word(&contrib, &eval)
+    }
+
+    /// Adds a 64-bit word that is additively shared between the parties, with each party providing
+    /// its share as input.
+    ///
+    /// This converts an output of the [`arithmetic`](crate::arithmetic) engine into a word of the
+    /// circuit.
+    pub fn input_arith(&mut self) -> Word {
+        let contrib = self.input_word(Party::Contributor, crate::arithmetic::ARITH_BITS);
+        let eval = self.input_word(Party::Evaluator, crate::arithmetic::ARITH_BITS);
+        int::add(self, &contrib, &eval)
+    }
+
+    /// Masks a word of up to 64 bits with a random 64-bit mask that the contributor provides as
+    /// input, returning the wires of `x - mask` that can be used as outputs of the circuit.
+    ///
+    /// The evaluator learns only the masked value, which is its additive share of `x`, while the
+    /// mask is the share of the contributor. Both can then be used as
+    /// [`ArithGate::InShared`](crate::arithmetic::ArithGate::InShared) in the
+    /// [`arithmetic`](crate::arithmetic) engine.
+    pub fn output_arith(&mut self, x: &[GateIndex]) -> Word {
+        let mask = self.input_word(Party::Contributor, crate::arithmetic::ARITH_BITS);
+        let zero = self.zero();
+        let mut x = x.to_vec();
+        x.resize(crate::arithmetic::ARITH_BITS, zero);
+        int::sub(self, &x, &mask)
+    }
+
+    /// Adds a gate computing `x ^ y`.
+    pub fn xor(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
+        self.push(Gate::Xor(x, y))
+    }
+
+    /// Adds a gate computing `x & y`.
+    pub fn and(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
+        self.push(Gate::And(x, y))
+    }
+
+    /// Adds a gate computing `!x`.
+    pub fn not(&mut self, x: GateIndex) -> GateIndex {
+        self.push(Gate::Not(x))
+    }
+
+    /// Computes `x | y` using a single AND gate.
+    pub fn or(&mut self, x: GateIndex, y: GateIndex) -> GateIndex {
+        let sum = self.xor(x, y);
+        let carry = self.and(x, y);
+        self.xor(sum, carry)
+    }
+
+    /// Returns a wire that is always `false`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no gate has been added yet, since constants are derived from existing wires.
+    pub fn zero(&mut self) -> GateIndex {
+        if let Some(zero) = self.zero {
+            return zero;
+        }
+        assert!(
+            !self.gates.is_empty(),
+            "constants require at least one input gate"
+        );
+        let zero = self.xor(0, 0);
+        self.zero = Some(zero);
+        zero
+    }
+
+    /// Returns a wire that is always `true`.
+    pub fn one(&mut self) -> GateIndex {
+        if let Some(one) = self.one {
+            return one;
+        }
+        let zero = self.zero();
+        let one = self.not(zero);
+        self.one = Some(one);
+        one
+    }
+
+    /// Returns a wire with the specified constant value.
+    pub fn constant(&mut self, bit: bool) -> GateIndex {
+        if bit {
+            self.one()
+        } else {
+            self.zero()
+        }
+    }
+
+    /// Returns a `width`-bit word with the specified constant value.
+    pub fn constant_word(&mut self, value: u64, width: usize) -> Word {
+        to_bits(value, width)
+            .into_iter()
+            .map(|b| self.constant(b))
+            .collect()
+    }
+
+    /// Selects `if_true` if `sel` is set and `if_false` otherwise, using a single AND gate.
+    pub fn mux(&mut self, sel: GateIndex, if_false: GateIndex, if_true: GateIndex) -> GateIndex {
+        let diff = self.xor(if_false, if_true);
+        let diff = self.and(sel, diff);
+        self.xor(if_false, diff)
+    }
+
+    /// Selects between two words of equal width, using one AND gate per bit.
+    pub fn mux_word(
+        &mut self,
+        sel: GateIndex,
+        if_false: &[GateIndex],
+        if_true: &[GateIndex],
+    ) -> Word {
+        assert_eq!(if_false.len(), if_true.len());
+        if_false
+            .iter()
+            .zip(if_true.iter())
+            .map(|(&f, &t)| self.mux(sel, f, t))
+            .collect()
+    }
+
+    /// Swaps two words of equal width if `sel` is set, using one AND gate per bit.
+    pub fn cond_swap(&mut self, sel: GateIndex, x: &mut Word, y: &mut Word) {
+        assert_eq!(x.len(), y.len());
+        for (x, y) in x.iter_mut().zip(y.iter_mut()) {
+            let diff = self.xor(*x, *y);
+            let diff = self.and(sel, diff);
+            *x = self.xor(*x, diff);
+            *y = self.xor(*y, diff);
+        }
+    }
+
+    /// Computes the bitwise XOR of two words of equal width.
+    pub fn xor_word(&mut self, x: &[GateIndex], y: &[GateIndex]) -> Word {
+        assert_eq!(x.len(), y.len());
+        x.iter()
+            .zip(y.iter())
+            .map(|(&x, &y)| self.xor(x, y))
+            .collect()
+    }
+
+    /// Computes the AND of `bit` with every bit of `word`.
+    pub fn and_word(&mut self, bit: GateIndex, word: &[GateIndex]) -> Word {
+        word.iter().map(|&w| self.and(bit, w)).collect()
+    }
+
+    /// Computes the AND of all the specified bits, using `bits.len() - 1` AND gates.
+    ///
+    /// Returns a constant `true` wire if `bits` is empty.
+    pub fn and_all(&mut self, bits: &[GateIndex]) -> GateIndex {
+        match bits.len() {
+            0 => self.one(),
+            1 => bits[0],
+            n => {
+                let lhs = self.and_all(&bits[..n / 2]);
+                let rhs = self.and_all(&bits[n / 2..]);
+                self.and(lhs, rhs)
+            }
+        }
+    }
+
+    /// Computes the OR of all the specified bits, using `bits.len() - 1` AND gates.
+    ///
+    /// Returns a constant `false` wire if `bits` is empty.
+    pub fn or_all(&mut self, bits: &[GateIndex]) -> GateIndex {
+        match bits.len() {
+            0 => self.zero(),
+            1 => bits[0],
+            n => {
+                let lhs = self.or_all(&bits[..n / 2]);
+                let rhs = self.or_all(&bits[n / 2..]);
+                self.or(lhs, rhs)
+            }
+        }
+    }
+
+    /// Checks two words of equal width for equality, using `width - 1` AND gates.
+    pub fn eq(&mut self, x: &[GateIndex], y: &[GateIndex]) -> GateIndex {
+        assert_eq!(x.len(), y.len());
+        let equal_bits: Vec<GateIndex> = x
+            .iter()
+            .zip(y.iter())
+            .map(|(&x, &y)| {
+                let diff = self.xor(x, y);
+                self.not(diff)
+            })
+            .collect();
+        self.and_all(&equal_bits)
+    }
+
+    /// Checks a word for equality with a public constant, using `width - 1` AND gates.
+    pub fn eq_const(&mut self, x: &[GateIndex], value: u64) -> GateIndex {
+        let equal_bits: Vec<GateIndex> = x
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                if i < 64 && (value >> i) & 1 == 1 {
+                    x
+                } else {
+                    self.not(x)
+                }
+            })
+            .collect();
+        self.and_all(&equal_bits)
+    }
+
+    /// Finishes the circuit, exposing the specified wires as its outputs.
+    pub fn build(self, output_gates: Vec<GateIndex>) -> Circuit {
+        Circuit::new(self.gates, output_gates)
+    }
+}
+
+/// Encodes the lowest `width` bits of `value` in little-endian bit order.
+pub fn to_bits(value: u64, width: usize) -> Vec<bool> {
+    (0..width)
+        .map(|i| i < 64 && (value >> i) & 1 == 1)
+        .collect()
+}
+
+/// Decodes up to 64 bits in little-endian bit order.
+pub fn from_bits(bits: &[bool]) -> u64 {
+    bits.iter()
+        .take(64)
+        .enumerate()
+        .fold(0, |acc, (i, &b)| acc | (u64::from(b) << i))
+}
+
+#[test]
+fn test_bits_roundtrip() {
+    for value in [0, 1, 2, 0xdead_beef, u64::MAX] {
+        assert_eq!(from_bits(&to_bits(value, 64)), value);
+    }
+    assert_eq!(to_bits(5, 4), vec![true, false, true, false]);
+    assert_eq!(from_bits(&to_bits(0xff, 4)), 0xf);
+}
+
+#[test]
+fn test_builder_primitives() {
+    let mut b = CircuitBuilder::new();
+    let x = b.input(Party::Contributor);
+    let y = b.input(Party::Evaluator);
+    let or = b.or(x, y);
+    let mux = b.mux(x, x, y);
+    let one = b.one();
+    let zero = b.zero();
+    assert_eq!(b.and_gates(), 2);
+    let circuit = b.build(vec![or, mux, one, zero]);
+
+    for x in [false, true] {
+        for y in [false, true] {
+            let output = circuit.eval(&[x], &[y]).unwrap();
+            assert_eq!(output, vec![x | y, if x { y } else { x }, true, false]);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Sampling noise for differential privacy inside of circuits.
+//!
+//! Aggregate statistics computed in MPC can be released with differential privacy by adding
+//! calibrated noise before the result is revealed. Neither party must be able to influence (or
+//! know) the noise, which is why both parties provide random bits that are XORed in the circuit:
+//! as long as one of the parties chooses its bits uniformly at random, the noise is sampled from
+//! the intended distribution.
+//!
+//! Noise is sampled using a cumulative distribution table (CDT): a uniformly random number `u` of
+//! `precision` bits is compared to the (public) cumulative probabilities of the noise magnitudes,
+//! and a random sign is applied to the resulting magnitude. Probabilities are thus approximated
+//! to a multiple of `2^-precision` and magnitudes whose probability is smaller than that are never
+//! sampled. The sampler needs roughly `precision` AND gates for every magnitude in its table.
+
+use super::{int, CircuitBuilder, Word};
+use crate::GateIndex;
+
+/// The maximum supported precision, limited by the precision of `f64`.
+pub const MAX_PRECISION: usize = 48;
+
+/// A symmetric distribution over the integers, centered around 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// The discrete Laplace (or two-sided geometric) distribution, with `P(x) ~ exp(-|x| / scale)`.
+    Laplace {
+        /// The scale of the distribution, usually the sensitivity divided by epsilon.
+        scale: f64,
+    },
+    /// The discrete Gaussian distribution, with `P(x) ~ exp(-x^2 / (2 * sigma^2))`.
+    Gaussian {
+        /// The scale of the distribution (close to its standard deviation).
+        sigma: f64,
+    },
+}
+
+impl Distribution {
+    /// The unnormalized probability of a value with the specified magnitude.
+    fn weight(&self, magnitude: usize) -> f64 {
+        let x = magnitude as f64;
+        match *self {
+            Distribution::Laplace { scale } => (-x / scale).exp(),
+            Distribution::Gaussian { sigma } => (-x * x / (2.0 * sigma * sigma)).exp(),
+        }
+    }
+}
+
+/// Samples noise from a [`Distribution`], both in plaintext and in a circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoiseSampler {
+    precision: usize,
+    /// The magnitude of the noise is the number of thresholds that are `<=` the random number.
+    thresholds: Vec<u64>,
+}
+
+impl NoiseSampler {
+    /// Creates a sampler, approximating probabilities to a multiple of `2^-precision`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scale of the distribution is not positive and finite or if the precision is
+    /// not between 1 and [`MAX_PRECISION`].
+    pub fn new(distribution: Distribution, precision: usize) -> Self {
+        let scale = match distribution {
+            Distribution::Laplace { scale } => scale,
+            Distribution::Gaussian { sigma } => sigma,
+        };
+        assert!(
+            scale > 0.0 && scale.is_finite(),
+            "the scale of the noise must be positive"
+        );
+        assert!(
+            (1..=MAX_PRECISION).contains(&precision),
+            "the precision must be between 1 and {MAX_PRECISION}"
+        );
+        // the weights of all magnitudes that could still affect the rounded probabilities:
+        let negligible = distribution.weight(0) * (-(precision as f64) - 16.0).exp2();
+        let mut weights = vec![distribution.weight(0)];
+        while *weights.last().unwrap() > negligible {
+            weights.push(2.0 * distribution.weight(weights.len()));
+        }
+        let total: f64 = weights.iter().sum();
+        let one = 1u64 << precision;
+        let mut thresholds = vec![];
+        let mut cumulative = 0.0;
+        for w in weights {
+            cumulative += w;
+            let threshold = ((cumulative / total) * one as f64).round() as u64;
+            if threshold >= one {
+                break;
+            }
+            thresholds.push(threshold);
+        }
+        Self {
+            precision,
+            thresholds,
+        }
+    }
+
+    /// The number of random bits needed to sample a single value.
+    pub fn random_bits(&self) -> usize {
+        self.precision + 1
+    }
+
+    /// The largest magnitude that can be sampled.
+    pub fn max_magnitude(&self) -> usize {
+        self.thresholds.len()
+    }
+
+    /// The exact probability with which the sampler outputs the specified value.
+    pub fn probability(&self, value: i64) -> f64 {
+        let magnitude = value.unsigned_abs() as usize;
+        if magnitude > self.max_magnitude() {
+            return 0.0;
+        }
+        let lower = if magnitude == 0 {
+            0
+        } else {
+            self.thresholds[magnitude - 1]
+        };
+        let upper = match self.thresholds.get(magnitude) {
+            Some(&upper) => upper,
+            None => 1 << self.precision,
+        };
+        let p = (upper - lower) as f64 / (1u64 << self.precision) as f64;
+        // the magnitude 0 is sampled for both signs, all others are split between the signs:
+        if magnitude == 0 {
+            p
+        } else {
+            p / 2.0
+        }
+    }
+
+    /// Samples a value in plaintext, exactly like [`NoiseSampler::sample_word`] does in a circuit.
+    pub fn sample(&self, random: &[bool]) -> i64 {
+        assert_eq!(random.len(), self.random_bits());
+        let u = super::from_bits(&random[..self.precision]);
+        let magnitude = self.thresholds.iter().filter(|&&t| u >= t).count() as i64;
+        if random[self.precision] {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Samples a signed value of the specified width (wrapping around if it does not fit) from
+    /// [`NoiseSampler::random_bits`] uniformly random bits.
+    pub fn sample_word(&self, b: &mut CircuitBuilder, random: &[GateIndex], width: usize) -> Word {
+        assert_eq!(random.len(), self.random_bits());
+        let (u, sign) = (&random[..self.precision], random[self.precision]);
+        // `geq[i]` is set if `u >= thresholds[i]`, all set bits are at the start of the list:
+        let geq: Vec<GateIndex> = self
+            .thresholds
+            .iter()
+            .map(|&t| int::ge_const(b, u, t))
+            .collect();
+        // the magnitude is `m` if and only if `geq[m - 1]` is the last set bit:
+        let mut is_magnitude: Vec<GateIndex> = geq.windows(2).map(|w| b.xor(w[0], w[1])).collect();
+        is_magnitude.extend(geq.last());
+        let magnitude_width = (usize::BITS - self.max_magnitude().leading_zeros()) as usize + 1;
+        let mut magnitude = vec![];
+        for bit in 0..magnitude_width {
+            let set: Vec<GateIndex> = is_magnitude
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (i + 1) >> bit & 1 == 1)
+                .map(|(_, &gate)| gate)
+                .collect();
+            magnitude.push(xor_all(b, &set));
+        }
+        // conditionally negates the magnitude as `(magnitude ^ sign) + sign`:
+        let flipped: Word = magnitude.iter().map(|&m| b.xor(m, sign)).collect();
+        let mut carry = b.constant_word(0, magnitude_width);
+        carry[0] = sign;
+        let noise = int::add(b, &flipped, &carry);
+        int::sign_extend(&noise, width)
+    }
+}
+
+fn xor_all(b: &mut CircuitBuilder, bits: &[GateIndex]) -> GateIndex {
+    match bits.split_first() {
+        Some((&first, rest)) => rest.iter().fold(first, |acc, &bit| b.xor(acc, bit)),
+        None => b.zero(),
+    }
+}
+
+/// Adds `len` random bits, which are the XOR of input bits provided by both parties.
+///
+/// The bits are uniformly random as long as at least one of the parties is honest.
+pub fn random_input(b: &mut CircuitBuilder, len: usize) -> Vec<GateIndex> {
+    b.input_shared(len)
+}
+
+/// Adds noise to a signed integer (wrapping around on overflow), using random bits from
+/// [`random_input`].
+///
+/// Each party must provide [`NoiseSampler::random_bits`] uniformly random bits, after all inputs
+/// that were added to the circuit before.
+pub fn add_noise(b: &mut CircuitBuilder, sampler: &NoiseSampler, value: &[GateIndex]) -> Word {
+    let random = random_input(b, sampler.random_bits());
+    let noise = sampler.sample_word(b, &random, value.len());
+    int::add(b, value, &noise)
+}
+
+#[test]
+fn test_noise_sampler() {
+    use super::Party;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    let width = 8;
+    for distribution in [
+        Distribution::Laplace { scale: 0.5 },
+        Distribution::Laplace { scale: 3.0 },
+        Distribution::Gaussian { sigma: 2.0 },
+    ] {
+        for precision in [1, 5, 20] {
+            let sampler = NoiseSampler::new(distribution, precision);
+            let total: f64 = (-(sampler.max_magnitude() as i64)..=sampler.max_magnitude() as i64)
+                .map(|x| sampler.probability(x))
+                .sum();
+            assert!((total - 1.0).abs() < 1e-9);
+
+            let mut b = CircuitBuilder::new();
+            let random = b.input_word(Party::Evaluator, sampler.random_bits());
+            let noise = sampler.sample_word(&mut b, &random, width);
+            let circuit = b.build(noise);
+            for _ in 0..100 {
+                let random: Vec<bool> = (0..sampler.random_bits()).map(|_| rng.gen()).collect();
+                let output = circuit.eval(&[], &random).unwrap();
+                let expected = sampler.sample(&random);
+                assert_eq!(super::from_bits(&output) as i8 as i64, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Beneš permutation networks, for applying a secret permutation inside a circuit.
+//!
+//! A network for `n = 2^k` elements consists of `n * k - n / 2` switches, each of which either
+//! passes its 2 inputs through or swaps them. The switch settings for a permutation are computed
+//! in plaintext by the party that chooses the permutation (using [`benes_switches`]) and then
+//! provided as that party's private input to the circuit, where they are applied using
+//! [`apply_benes`]. Each switch costs one AND gate per bit of the permuted elements.
+
+use super::{CircuitBuilder, Word};
+use crate::GateIndex;
+
+/// The number of switches in a Beneš network for `n` elements.
+///
+/// # Panics
+///
+/// Panics if `n` is not a power of 2.
+pub fn benes_switch_count(n: usize) -> usize {
+    assert!(n.is_power_of_two(), "Beneš networks require a power of 2");
+    if n == 1 {
+        0
+    } else {
+        n * n.trailing_zeros() as usize - n / 2
+    }
+}
+
+/// Computes the switch settings that make a Beneš network move the element at position `i` to
+/// position `permutation[i]`.
+///
+/// # Panics
+///
+/// Panics if the length of the permutation is not a power of 2 or if it is not a permutation.
+pub fn benes_switches(permutation: &[usize]) -> Vec<bool> {
+    let n = permutation.len();
+    assert!(n.is_power_of_two(), "Beneš networks require a power of 2");
+    let mut inverse = vec![usize::MAX; n];
+    for (i, &p) in permutation.iter().enumerate() {
+        assert!(p < n && inverse[p] == usize::MAX, "not a permutation");
+        inverse[p] = i;
+    }
+    let mut switches = Vec::with_capacity(benes_switch_count(n));
+    program(permutation, &inverse, &mut switches);
+    switches
+}
+
+fn program(permutation: &[usize], inverse: &[usize], switches: &mut Vec<bool>) {
+    let n = permutation.len();
+    if n == 1 {
+        return;
+    }
+    if n == 2 {
+        switches.push(permutation[0] == 1);
+        return;
+    }
+
+    // 2-color the inputs (upper or lower sub-network) so that the 2 inputs of each input switch
+    // and the 2 sources of each output switch always end up in different sub-networks:
+    let mut lower: Vec<Option<bool>> = vec![None; n];
+    for start in (0..n).step_by(2) {
+        if lower[start].is_some() {
+            continue;
+        }
+        let mut input = start;
+        loop {
+            lower[input] = Some(false);
+            lower[input ^ 1] = Some(true);
+            // the output paired with `input ^ 1`'s output must come from the upper network:
+            let next = inverse[permutation[input ^ 1] ^ 1];
+            if lower[next].is_some() {
+                break;
+            }
+            input = next;
+        }
+    }
+
+    let half = n / 2;
+    let mut upper_perm = vec![0; half];
+    let mut lower_perm = vec![0; half];
+    let mut input_switches = Vec::with_capacity(half);
+    for i in 0..half {
+        let swapped = lower[2 * i] == Some(true);
+        input_switches.push(swapped);
+        let (u, l) = if swapped {
+            (2 * i + 1, 2 * i)
+        } else {
+            (2 * i, 2 * i + 1)
+        };
+        upper_perm[i] = permutation[u] / 2;
+        lower_perm[i] = permutation[l] / 2;
+    }
+    let mut output_switches = vec![false; half];
+    for (i, &swapped) in input_switches.iter().enumerate() {
+        let u = if swapped { 2 * i + 1 } else { 2 * i };
+        output_switches[permutation[u] / 2] = permutation[u] % 2 == 1;
+    }
+
+    switches.extend(input_switches);
+    for perm in [upper_perm, lower_perm] {
+        let mut inverse = vec![0; half];
+        for (i, &p) in perm.iter().enumerate() {
+            inverse[p] = i;
+        }
+        program(&perm, &inverse, switches);
+    }
+    switches.extend(output_switches);
+}
+
+/// Applies a Beneš network to the specified words, using the switch wires (in the order returned
+/// by [`benes_switches`]).
+///
+/// If `inverse` is set, the inverse permutation is applied instead, moving the element at position
+/// `permutation[i]` back to position `i`.
+pub fn apply_benes(
+    b: &mut CircuitBuilder,
+    words: &[Word],
+    switches: &[GateIndex],
+    inverse: bool,
+) -> Vec<Word> {
+    assert_eq!(switches.len(), benes_switch_count(words.len()));
+    let mut words = words.to_vec();
+    apply(b, &mut words, switches, inverse);
+    words
+}
+
+fn apply(b: &mut CircuitBuilder, words: &mut [Word], switches: &[GateIndex], inverse: bool) {
+    let n = words.len();
+    if n == 1 {
+        return;
+    }
+    if n == 2 {
+        let (lhs, rhs) = words.split_at_mut(1);
+        b.cond_swap(switches[0], &mut lhs[0], &mut rhs[0]);
+        return;
+    }
+    let half = n / 2;
+    let sub_switches = benes_switch_count(half);
+    let (input_switches, rest) = switches.split_at(half);
+    let (upper_switches, rest) = rest.split_at(sub_switches);
+    let (lower_switches, output_switches) = rest.split_at(sub_switches);
+    let (first, last) = if inverse {
+        (output_switches, input_switches)
+    } else {
+        (input_switches, output_switches)
+    };
+
+    // the first layer of switches distributes each pair to the upper and lower sub-network:
+    let mut upper = Vec::with_capacity(half);
+    let mut lower = Vec::with_capacity(half);
+    for (i, &switch) in first.iter().enumerate() {
+        let (lhs, rhs) = words.split_at_mut(2 * i + 1);
+        b.cond_swap(switch, &mut lhs[2 * i], &mut rhs[0]);
+        upper.push(lhs[2 * i].clone());
+        lower.push(rhs[0].clone());
+    }
+    apply(b, &mut upper, upper_switches, inverse);
+    apply(b, &mut lower, lower_switches, inverse);
+
+    // the last layer of switches recombines the outputs of the sub-networks:
+    for (i, &switch) in last.iter().enumerate() {
+        let (mut u, mut l) = (upper[i].clone(), lower[i].clone());
+        b.cond_swap(switch, &mut u, &mut l);
+        words[2 * i] = u;
+        words[2 * i + 1] = l;
+    }
+}
+
+#[test]
+fn test_benes_network() {
+    use super::{from_bits, to_bits, Party};
+    use rand::{seq::SliceRandom, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    for n in [1, 2, 4, 8, 32] {
+        let width = 6;
+        let mut permutation: Vec<usize> = (0..n).collect();
+        permutation.shuffle(&mut rng);
+        let switches = benes_switches(&permutation);
+        assert_eq!(switches.len(), benes_switch_count(n));
+
+        for inverse in [false, true] {
+            let mut b = CircuitBuilder::new();
+            let words: Vec<Word> = (0..n)
+                .map(|_| b.input_word(Party::Evaluator, width))
+                .collect();
+            let switch_wires = b.input_word(Party::Contributor, switches.len());
+            let permuted = apply_benes(&mut b, &words, &switch_wires, inverse);
+            assert_eq!(b.and_gates(), switches.len() * width);
+            let circuit = b.build(permuted.concat());
+
+            let values: Vec<bool> = (0..n as u64).flat_map(|i| to_bits(i, width)).collect();
+            let output = circuit.eval(&switches, &values).unwrap();
+            for (pos, value) in output.chunks(width).enumerate() {
+                let value = from_bits(value) as usize;
+                if inverse {
+                    assert_eq!(permutation[pos], value);
+                } else {
+                    assert_eq!(permutation[value], pos);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Matching secret byte strings, for example against blocklists or prefixes of identifiers.
+//!
+//! Strings are represented as a slice of 8-bit [`Word`]s, one per byte. Their lengths are part of
+//! the circuit and thus public. Strings of secret length can be padded to a public maximum length
+//! (for example using zero bytes), as long as the padding cannot occur in the strings themselves.
+//!
+//! For matching against more complex patterns, see [`super::dfa`].
+
+use super::{CircuitBuilder, Party, Word};
+use crate::GateIndex;
+
+/// The width of a single byte of a string.
+pub const BYTE: usize = 8;
+
+/// Adds an input string of `len` bytes provided by the specified party.
+pub fn input_string(b: &mut CircuitBuilder, party: Party, len: usize) -> Vec<Word> {
+    (0..len).map(|_| b.input_word(party, BYTE)).collect()
+}
+
+/// Adds a publicly known string.
+pub fn constant_string(b: &mut CircuitBuilder, bytes: &[u8]) -> Vec<Word> {
+    bytes
+        .iter()
+        .map(|&byte| b.constant_word(byte as u64, BYTE))
+        .collect()
+}
+
+/// Encodes a string as input bits, in the order expected by [`input_string`].
+pub fn string_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|&byte| super::to_bits(byte as u64, BYTE))
+        .collect()
+}
+
+/// Checks two strings for equality, using `8 * len - 1` AND gates.
+///
+/// Strings of different lengths are never equal.
+pub fn eq(b: &mut CircuitBuilder, x: &[Word], y: &[Word]) -> GateIndex {
+    if x.len() != y.len() {
+        return b.zero();
+    }
+    b.eq(&x.concat(), &y.concat())
+}
+
+/// Checks whether the string starts with the specified prefix.
+pub fn starts_with(b: &mut CircuitBuilder, s: &[Word], prefix: &[Word]) -> GateIndex {
+    if prefix.len() > s.len() {
+        return b.zero();
+    }
+    eq(b, &s[..prefix.len()], prefix)
+}
+
+/// Checks whether the string ends with the specified suffix.
+pub fn ends_with(b: &mut CircuitBuilder, s: &[Word], suffix: &[Word]) -> GateIndex {
+    if suffix.len() > s.len() {
+        return b.zero();
+    }
+    eq(b, &s[s.len() - suffix.len()..], suffix)
+}
+
+/// Returns a bit for every position in the string, which is set if the needle occurs there.
+///
+/// Needs `(s.len() - needle.len() + 1) * 8 * needle.len()` AND gates (roughly).
+pub fn find_all(b: &mut CircuitBuilder, s: &[Word], needle: &[Word]) -> Vec<GateIndex> {
+    if needle.len() > s.len() {
+        return vec![];
+    }
+    (0..=s.len() - needle.len())
+        .map(|i| eq(b, &s[i..i + needle.len()], needle))
+        .collect()
+}
+
+/// Checks whether the needle occurs anywhere in the string.
+///
+/// The empty needle occurs in every string.
+pub fn contains(b: &mut CircuitBuilder, s: &[Word], needle: &[Word]) -> GateIndex {
+    if needle.is_empty() {
+        return b.one();
+    }
+    let found = find_all(b, s, needle);
+    b.or_all(&found)
+}
+
+/// Checks whether the string is equal to any of the strings in a list, such as a blocklist.
+pub fn eq_any(b: &mut CircuitBuilder, s: &[Word], list: &[Vec<Word>]) -> GateIndex {
+    let found: Vec<GateIndex> = list.iter().map(|entry| eq(b, s, entry)).collect();
+    b.or_all(&found)
+}
+
+#[test]
+fn test_string_matching() {
+    let s = b"hello world";
+    for (needle, expected) in [
+        (&b"hello"[..], [true, false, true]),
+        (b"world", [false, true, true]),
+        (b"o w", [false, false, true]),
+        (b"hello world!", [false, false, false]),
+        (b"", [true, true, true]),
+    ] {
+        let mut b = CircuitBuilder::new();
+        let x = input_string(&mut b, Party::Evaluator, s.len());
+        let y = input_string(&mut b, Party::Contributor, needle.len());
+        let prefix = starts_with(&mut b, &x, &y);
+        let suffix = ends_with(&mut b, &x, &y);
+        let substring = contains(&mut b, &x, &y);
+        let circuit = b.build(vec![prefix, suffix, substring]);
+        let output = circuit
+            .eval(&string_to_bits(needle), &string_to_bits(s))
+            .unwrap();
+        assert_eq!(output, expected);
+    }
+}
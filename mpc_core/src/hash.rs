@@ -1,11 +1,115 @@
 //! Non-optimized hashing based on [`blake3::Hasher`].
+//!
+//! Every hash of the protocol is keyed with a [`DomainKey`], which is derived from the
+//! [`SessionId`] of the execution and a domain-separation tag for the purpose of the hash, so that
+//! hashes (and thus messages) of one session or sub-protocol are meaningless in any other.
 use std::io::Read;
 
+use serde::{Deserialize, Serialize};
 
-use crate::types::{KeyType, MacType};
+use crate::{
+    protocol::cointossing::CoinResult,
+    types::{KeyType, MacType},
+    PartyIndex,
+};
+
+/// Domain-separation tags, used as blake3 key derivation contexts.
+pub(crate) mod domain {
+    /// Derives the session identifier from the circuit and the coin tossed by both parties.
+    pub(crate) const SESSION: &str = "mpc_core 2024-06 session id";
+    /// Derives the session identifier of an execution that consumes a part of a pool.
+    pub(crate) const POOLED_SESSION: &str = "mpc_core 2024-06 pooled session id";
+    /// Binds the preprocessing of a pool to its size instead of a circuit.
+    pub(crate) const POOL: &str = "mpc_core 2024-06 pool";
+    /// Commits to the coin share of a party.
+    pub(crate) const COIN_COMMITMENT: &str = "mpc_core 2024-06 coin commitment";
+    /// Hashes the keys and MACs of leaky AND triples in `Π_LaAND`.
+    pub(crate) const LEAKY_AND: &str = "mpc_core 2024-06 leaky and";
+    /// Commits to the values of the equality check of `Π_LaAND`.
+    pub(crate) const LEAKY_AND_CHECK: &str = "mpc_core 2024-06 leaky and check";
+    /// Garbles the AND gates of the authenticated garbling.
+    pub(crate) const GARBLING: &str = "mpc_core 2024-06 garbling";
+    /// Garbles the AND gates of the multi-party authenticated garbling.
+    pub(crate) const MULTIPARTY_GARBLING: &str = "mpc_core 2024-06 multiparty garbling";
+    /// Binds a multi-party execution to its circuit and number of parties.
+    pub(crate) const MULTIPARTY_CONTEXT: &str = "mpc_core 2024-06 multiparty context";
+    /// Digests the values that the parties of a multi-party execution broadcast without MACs.
+    pub(crate) const BROADCAST: &str = "mpc_core 2024-06 multiparty broadcast";
+    /// Hashes the keys and MACs of the cross terms of the AND triples of two parties in `Π_HaAND`.
+    pub(crate) const HALF_AND: &str = "mpc_core 2024-06 half and";
+    /// Garbles the AND gates of a zero-knowledge proof.
+    pub(crate) const ZK_GARBLING: &str = "mpc_core 2024-06 zk garbling";
+    /// Commits to the output labels of the prover of a zero-knowledge proof.
+    pub(crate) const ZK_COMMITMENT: &str = "mpc_core 2024-06 zk commitment";
+    /// Digests the output labels of both executions of the dual-execution protocol.
+    pub(crate) const DUAL_EXECUTION: &str = "mpc_core 2024-06 dual execution";
+    /// Commits to the digest of the output labels of the dual-execution protocol.
+    pub(crate) const DUAL_EXECUTION_COMMITMENT: &str = "mpc_core 2024-06 dual execution commitment";
+    /// Keys the hash that commits a party to its input across executions.
+    pub(crate) const INPUT_COMMITMENT: &str = "mpc_core 2024-06 input commitment";
+    /// Derives the correlated OTs of the multiplication triples of the arithmetic engine.
+    pub(crate) const ARITHMETIC: &str = "mpc_core 2024-06 arithmetic triples";
+    /// Garbles the AND gates of the semi-honest half-gates protocol.
+    pub(crate) const HALF_GATES: &str = "mpc_core 2024-06 half gates";
+    /// Encrypts a saved state of a party with the key provided by the caller.
+    pub(crate) const SAVED_STATE_ENCRYPTION: &str = "mpc_core 2024-06 saved state encryption";
+}
+
+/// Identifies a single execution of the protocol between two parties.
+///
+/// Both parties derive the same identifier from the hash of the circuit and the coin tossed
+/// together at the start of the protocol, which contains a random contribution of each party.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SessionId([u8; 32]);
+
+impl SessionId {
+    /// Derives the session identifier from the (circuit) context and the tossed coin.
+    pub(crate) fn new(context: &[u8; 32], coin: &CoinResult) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key(domain::SESSION);
+        hasher.update(context);
+        hasher.update(coin);
+        Self(*hasher.finalize().as_bytes())
+    }
+
+    /// Derives the identifier of a session that is part of this session, such as an execution that
+    /// consumes a part of a pool.
+    pub(crate) fn derive(&self, context: &[u8; 32], position: &[u8]) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key(domain::POOLED_SESSION);
+        hasher.update(&self.0);
+        hasher.update(context);
+        hasher.update(position);
+        Self(*hasher.finalize().as_bytes())
+    }
+
+    /// The bytes of the identifier, as included in the header of every message.
+    pub(crate) fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Derives the key for all hashes of the specified domain in this session.
+    pub(crate) fn key(&self, domain: &str) -> DomainKey {
+        DomainKey(blake3::derive_key(domain, &self.0))
+    }
+}
+
+/// The key of a hash, specific to a session and a domain.
+#[derive(Clone)]
+pub(crate) struct DomainKey([u8; 32]);
+
+impl DomainKey {
+    /// Derives a key from a domain and a context directly, for hashes without a session.
+    pub(crate) fn new(domain: &str, context: &[u8; 32]) -> Self {
+        Self(blake3::derive_key(domain, context))
+    }
+
+    fn hasher(&self) -> blake3::Hasher {
+        blake3::Hasher::new_keyed(&self.0)
+    }
+}
 
 /// Hashing for building garbled tables.
 pub(crate) mod garbling_hash {
+    use super::DomainKey;
     use crate::{
         types::{BitShare, KeyType, MacType, WireLabel},
         GateIndex,
@@ -14,12 +118,13 @@ pub(crate) mod garbling_hash {
 
     /// Computes a garbled table share.
     pub(crate) fn new(
+        domain: &DomainKey,
         label_x: &WireLabel,
         label_y: &WireLabel,
         gate: GateIndex,
         row: u8,
     ) -> BitShare {
-        let mut hasher = blake3::Hasher::new();
+        let mut hasher = domain.hasher();
         hasher.update(&label_x.0.to_le_bytes());
         hasher.update(&label_y.0.to_le_bytes());
         hasher.update(&gate.to_le_bytes());
@@ -47,24 +152,73 @@ pub(crate) mod garbling_hash {
         }
     }
 
+    /// Computes the pad of a garbled table row of one of several garbling parties, which hides one
+    /// value for each of the `parties` and a bit.
+    pub(crate) fn row(
+        domain: &DomainKey,
+        label_x: &WireLabel,
+        label_y: &WireLabel,
+        gate: GateIndex,
+        row: u8,
+        parties: usize,
+    ) -> (Vec<MacType>, bool) {
+        let mut hasher = domain.hasher();
+        hasher.update(&label_x.0.to_le_bytes());
+        hasher.update(&label_y.0.to_le_bytes());
+        hasher.update(&gate.to_le_bytes());
+        hasher.update(&[row]);
+        let mut output_reader = hasher.finalize_xof();
+
+        let mut values = Vec::with_capacity(parties);
+        let mut buffer: [u8; 16] = [0; 16];
+        for _ in 0..parties {
+            let r = output_reader.read(&mut buffer);
+            assert!(r.is_ok());
+            values.push(MacType(u128::from_le_bytes(buffer)));
+        }
+        let mut bit_buffer: [u8; 1] = [0];
+        let r = output_reader.read(&mut bit_buffer);
+        assert!(r.is_ok());
+
+        (values, (bit_buffer[0] & 1) == 1)
+    }
+
     #[test]
     fn test_new() {
-        let h0 = new(&WireLabel(0), &WireLabel(1), 0, 0);
-        let h1 = new(&WireLabel(0), &WireLabel(1), 0, 1);
+        let domain = DomainKey::new(super::domain::GARBLING, &[0; 32]);
+        let h0 = new(&domain, &WireLabel(0), &WireLabel(1), 0, 0);
+        let h1 = new(&domain, &WireLabel(0), &WireLabel(1), 0, 1);
         assert_ne!(h0, h1);
     }
 }
 
-pub(crate) fn hash(mac: MacType) -> MacType {
-    hash_u128(mac.0)
+/// Commits to a sequence of values of a party, which are hidden by a random nonce until it is
+/// opened.
+pub(crate) fn commitment(
+    domain: &DomainKey,
+    party: PartyIndex,
+    nonce: &[u8; 32],
+    values: &[MacType],
+) -> [u8; 32] {
+    let mut hasher = domain.hasher();
+    hasher.update(&[party]);
+    hasher.update(nonce);
+    for value in values {
+        hasher.update(&value.0.to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+pub(crate) fn hash(domain: &DomainKey, mac: MacType) -> MacType {
+    hash_u128(domain, mac.0)
 }
 
-pub(crate) fn hash_key(key: KeyType) -> MacType {
-    hash_u128(key.0)
+pub(crate) fn hash_key(domain: &DomainKey, key: KeyType) -> MacType {
+    hash_u128(domain, key.0)
 }
 
-pub(crate) fn hash_keys(key1: KeyType, key2: KeyType) -> MacType {
-    let mut hasher = blake3::Hasher::new();
+pub(crate) fn hash_keys(domain: &DomainKey, key1: KeyType, key2: KeyType) -> MacType {
+    let mut hasher = domain.hasher();
     hasher.update(&key1.0.to_le_bytes());
     hasher.update(&key2.0.to_le_bytes());
     let mut output_reader = hasher.finalize_xof();
@@ -74,8 +228,8 @@ pub(crate) fn hash_keys(key1: KeyType, key2: KeyType) -> MacType {
     MacType(u128::from_le_bytes(buffer))
 }
 
-fn hash_u128(value: u128) -> MacType {
-    let mut hasher = blake3::Hasher::new();
+fn hash_u128(domain: &DomainKey, value: u128) -> MacType {
+    let mut hasher = domain.hasher();
     hasher.update(&value.to_le_bytes());
     let mut output_reader = hasher.finalize_xof();
     let mut buffer = [0u8; 16];
@@ -86,29 +240,40 @@ fn hash_u128(value: u128) -> MacType {
 
 #[test]
 fn test_hash_keys() {
-    let h0 = hash_keys(KeyType(0), KeyType(1));
-    let h1 = hash_keys(KeyType(0), KeyType(1));
+    let domain = SessionId::new(&[0; 32], &[0; 32]).key(domain::LEAKY_AND);
+    let h0 = hash_keys(&domain, KeyType(0), KeyType(1));
+    let h1 = hash_keys(&domain, KeyType(0), KeyType(1));
     assert_eq!(h0, h1);
-    let h2 = hash_keys(KeyType(1), KeyType(0));
+    let h2 = hash_keys(&domain, KeyType(1), KeyType(0));
     assert_ne!(h0, h2);
 }
 
 #[test]
-fn test_hash_values() {
-    let r0 = 164479851121213158701332959497568687214_u128;
-    let r1 = 32869993993155099816536977414117934351_u128;
+fn test_domain_separation() {
+    let session = SessionId::new(&[0; 32], &[0; 32]);
+    let other_circuit = SessionId::new(&[1; 32], &[0; 32]);
+    let other_coin = SessionId::new(&[0; 32], &[1; 32]);
+    assert_ne!(session, other_circuit);
+    assert_ne!(session, other_coin);
+    assert_ne!(session, session.derive(&[0; 32], &[]));
 
-    assert_eq!(252301825721988224801639279640745335827, hash(MacType(r0)).0);
-    assert_eq!(19881579897213927600698344798095172587, hash(MacType(r1)).0);
-    assert_eq!(
-        265242760764573362325515364989468422452,
-        hash_keys(KeyType(r0), KeyType(r1)).0
-    );
+    let r: u128 = rand::random();
+    let h = hash(&session.key(domain::LEAKY_AND), MacType(r));
+    assert_ne!(h, hash(&other_circuit.key(domain::LEAKY_AND), MacType(r)));
+    assert_ne!(h, hash(&other_coin.key(domain::LEAKY_AND), MacType(r)));
+    assert_ne!(h, hash(&session.key(domain::LEAKY_AND_CHECK), MacType(r)));
 }
 
 #[test]
 fn test_random_hash() {
     let r: u128 = rand::random();
-    let ref_0 = blake3::hash(&r.to_le_bytes());
-    assert_eq!(&ref_0.as_bytes()[..16], hash(MacType(r)).0.to_ne_bytes());
+    let session = SessionId::new(&[0; 32], &[0; 32]);
+    let key = blake3::derive_key(domain::LEAKY_AND, &session.0);
+    let ref_0 = blake3::keyed_hash(&key, &r.to_le_bytes());
+    assert_eq!(
+        &ref_0.as_bytes()[..16],
+        hash(&session.key(domain::LEAKY_AND), MacType(r))
+            .0
+            .to_le_bytes()
+    );
 }
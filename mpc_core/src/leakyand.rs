@@ -1,6 +1,6 @@
 //! Implementation of secure AND computation sub-protocols.
 use crate::{
-    hash::hash,
+    hash::{hash, DomainKey},
     types::{Delta, KeyType, MacType, K},
 };
 
@@ -12,8 +12,10 @@ pub(crate) type AndHashes = [[MacType; 2]];
 /// - `keys[i]` is the key for authenticated bit at index `i`
 /// - `random_bits` are the random values used in the protocol
 /// - `delta` is the local delta value
+/// - `domain` is the key of the leaky AND hashes in the current session
 pub(crate) fn compute_leaky_and_hashes(
     out: &mut AndHashes,
+    domain: &DomainKey,
     delta: &Delta,
     random_bits: u128,
     authenticated_bits_y: u128,
@@ -25,8 +27,8 @@ pub(crate) fn compute_leaky_and_hashes(
     for i in 0..K {
         let random_bit = u128::from(random_bits & (1 << i) != 0);
         let y_bit = u128::from((authenticated_bits_y & (1 << i)) != 0);
-        out[i][0] = MacType(hash(MacType(keys[i].0)).0 ^ random_bit);
-        out[i][1] = MacType(hash(delta.xor(MacType(keys[i].0))).0 ^ random_bit ^ y_bit);
+        out[i][0] = MacType(hash(domain, MacType(keys[i].0)).0 ^ random_bit);
+        out[i][1] = MacType(hash(domain, delta.xor(MacType(keys[i].0))).0 ^ random_bit ^ y_bit);
     }
 }
 
@@ -35,6 +37,7 @@ pub(crate) fn compute_leaky_and_hashes(
 /// Takes K-many `and_hashes` from the other party which were computed through
 /// [compute_leaky_and_hashes] and outputs K-many shares.
 pub(crate) fn derive_and_shares(
+    domain: &DomainKey,
     random_bits: u128,
     authenticated_bits: u128,
     macs: &[MacType],
@@ -47,13 +50,44 @@ pub(crate) fn derive_and_shares(
 
     for i in 0..K {
         let idx = usize::from((authenticated_bits & (1 << i)) != 0);
-        let is_set = (and_hashes[i][idx].0 ^ hash(macs[i]).0) != 0;
+        let is_set = (and_hashes[i][idx].0 ^ hash(domain, macs[i]).0) != 0;
         result |= (u128::from(is_set)) << i;
     }
 
     MacType(result ^ random_bits)
 }
 
+/// Computes the hashes of `Π_HaAND` for a single authenticated bit `x` of another party, which
+/// allow the other party to learn `random_bit ^ (x & y)` and nothing else.
+///
+/// Parameters:
+/// - `key` is the key for the other party's authenticated bit `x`
+/// - `delta` is the local delta value
+/// - `domain` is the key of the half AND hashes in the current session
+pub(crate) fn half_and_hashes(
+    domain: &DomainKey,
+    delta: &Delta,
+    key: KeyType,
+    random_bit: bool,
+    y: bool,
+) -> [MacType; 2] {
+    [
+        hash(domain, MacType(key.0)) ^ u128::from(random_bit),
+        hash(domain, delta.xor(MacType(key.0))) ^ u128::from(random_bit ^ y),
+    ]
+}
+
+/// Derives the share of `x & y` from the hashes computed by the other party through
+/// [half_and_hashes], using the own authenticated bit `x` and its `mac`.
+pub(crate) fn derive_half_and_share(
+    domain: &DomainKey,
+    x: bool,
+    mac: MacType,
+    hashes: &[MacType; 2],
+) -> bool {
+    (hashes[usize::from(x)] ^ hash(domain, mac)).0 != 0
+}
+
 // #[test]
 // fn test_leaky_and() {
 //     use rand_core::SeedableRng;
@@ -80,6 +114,7 @@ pub(crate) fn derive_and_shares(
 
 /// Generates K-many authenticated bits for testing.
 #[cfg(test)]
+#[allow(dead_code, clippy::type_complexity)]
 fn gen_abits() -> (
     Delta,
     KeyType,
@@ -5,6 +5,7 @@ use crate::{
 };
 use rand_core::{RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 
 pub(crate) const BLOCK_SIZE: usize = K;
 
@@ -70,30 +71,61 @@ pub(crate) mod message {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct ReceiverInitializer {
+    #[serde(with = "boxed_array")]
     senders: Box<[BaseSender; K]>,
+    #[serde(with = "boxed_array")]
     ot_messages: Box<[[OtMessage; 2]; K]>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct SenderInitializer {
     delta: Delta,
+    #[serde(with = "boxed_array")]
     receivers: Box<[BaseReceiver; K]>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct LeakyOtReceiver {
+    #[serde(with = "boxed_array")]
     otg0: Box<[ChaCha20Rng; K]>,
+    #[serde(with = "boxed_array")]
     otg1: Box<[ChaCha20Rng; K]>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct LeakyOtSender {
     delta: Delta,
+    #[serde(with = "boxed_array")]
     otg: Box<[ChaCha20Rng; K]>,
 }
 
+/// Serializes the boxed arrays of one element per OT, which are too large for serde's array impls.
+mod boxed_array {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::K;
+
+    pub(super) fn serialize<T: Serialize, S: Serializer>(
+        array: &[T; K],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(array.iter())
+    }
+
+    pub(super) fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<[T; K]>, D::Error> {
+        let elements = Vec::<T>::deserialize(deserializer)?;
+        let len = elements.len();
+        elements
+            .into_boxed_slice()
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"one element per OT"))
+    }
+}
+
 impl ReceiverInitializer {
     pub(crate) fn init(rng: &mut ChaCha20Rng) -> (Self, message::OtInit) {
         let senders = Box::new([(); K].map(|_| BaseSender::new(rng)));
@@ -101,8 +133,6 @@ impl ReceiverInitializer {
         for (i, idx) in idxs.iter_mut().enumerate().take(K) {
             *idx = i;
         }
-        let msgs = Box::new(idxs.map(|i| BaseSender::init_message(&senders[i])));
-
         let ot_messages = Box::new({
             idxs.map(|_| {
                 let mut ot_messages = [OtMessage::default(); 2];
@@ -116,8 +146,18 @@ impl ReceiverInitializer {
             senders,
             ot_messages,
         };
+        let msg = s.init_message();
+
+        (s, msg)
+    }
 
-        (s, message::OtInit(msgs))
+    /// Returns the same message as [`ReceiverInitializer::init`], for example to send it again.
+    pub(crate) fn init_message(&self) -> message::OtInit {
+        let mut idxs = [0; K];
+        for (i, idx) in idxs.iter_mut().enumerate() {
+            *idx = i;
+        }
+        message::OtInit(Box::new(idxs.map(|i| BaseSender::init_message(&self.senders[i]))))
     }
 
     pub(crate) fn recv(&self, m: &message::OtInit) -> (LeakyOtReceiver, message::OtInitReply) {
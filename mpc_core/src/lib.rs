@@ -2,35 +2,51 @@
 //!
 //! This crate implements secure two-party computation based on Yao's Garbled Circuits protocol (1986).
 //! It allows two parties to jointly compute functions over their private inputs without revealing
-//! the inputs to each other. The implementation follows the semi-honest security model.
+//! the inputs to each other. The implementation is secure against malicious parties by default,
+//! based on authenticated garbling, with classic semi-honest garbling as a faster alternative.
+//! Computations between three or more parties are supported by the [`multiparty`] protocol.
 //!
 //! # Architecture
 //!
 //! The implementation is structured into several key components:
 //! 
 //! - Circuit representation and manipulation (`circuit.rs`)
-//! - Cryptographic primitives and hashing (`hash.rs`)
+//! - Arithmetic secret sharing over `Z_2^64` with conversions to and from circuits
+//!   (`arithmetic.rs`)
+//! - Batched execution of many instances of a circuit (`batch.rs`)
+//! - Channels and drivers that run the whole protocol over a connection (`channel.rs`)
+//! - Commitments that bind a party to the same input across executions (`commitment.rs`)
+//! - Gadgets for generating optimized circuits (`gadgets/`)
+//! - Cryptographic primitives and session-bound, domain-separated hashing (`hash.rs`)
 //! - Oblivious Transfer protocols (`ot_base.rs`, `leakydelta_ot.rs`)
+//! - Square-root ORAM for private arrays spanning many executions (`oram.rs`)
+//! - Authenticated garbling for more than two parties (`multiparty.rs`)
+//! - Function-independent preprocessing of AND triples (`preprocessing.rs`)
+//! - Reactive computations keeping secret state across circuits (`reactive.rs`)
+//! - Semi-honest garbling with half-gates as a faster protocol option (`semihonest.rs`)
+//! - Dual execution of the half-gates protocol with at most one bit of leakage (`dualex.rs`)
+//! - Zero-knowledge proofs using garbled circuits (`zk.rs`)
 //! - Core protocol implementation (`protocol.rs`)
+//! - Versioned message envelope with typed payloads for every step (`message.rs`)
+//! - Saving and restoring the state of a party, optionally encrypted (`persistence.rs`)
 //! - State machine for protocol execution (`states.rs`)
 //!
-//! Communication channels are deliberately _not_ part of this crate. The sending and receiving of
-//! messages needs to be handled by the user of this crate, which allows the protocol to be used
-//! both in sync and async environments.
+//! The protocol itself is deliberately communication-agnostic: each party is a state machine that
+//! consumes the message of the other party and returns its reply. The [`channel`] module runs
+//! this loop over any blocking or async connection to the other party, with ready-made channels
+//! for in-memory pipes, `std::sync::mpsc` and byte streams such as TCP connections.
 //!
 //! # Examples
 //!
 //! ```
 //! use mpc_core::{
-//!     states::{Contributor, Evaluator, Msg},
+//!     channel::{run_contributor, run_evaluator, MpscChannel},
+//!     states::{Contributor, Evaluator},
 //!     Circuit, Error, Gate,
 //! };
 //! use rand::SeedableRng;
 //! use rand_chacha::ChaCha20Rng;
-//! use std::{
-//!     sync::{mpsc::channel, Arc},
-//!     thread,
-//! };
+//! use std::thread;
 //!
 //! fn main() -> Result<(), Error> {
 //!     // Create a simple circuit with 2 input bits, calculate their AND and output it:
@@ -39,58 +55,38 @@
 //!         vec![2],
 //!     );
 //!
-//!     let (to_eval, from_contrib) = channel::<Msg>();
-//!     let (to_contrib, from_eval) = channel::<Msg>();
+//!     let (mut channel_for_contrib, mut channel_for_eval) = MpscChannel::pair();
 //!
 //!     // Spawn a contributor as a new thread, with the evaluator remaining on the main thread:
 //!     let circuit_for_contrib_thread = simple_circuit.clone();
-//!     thread::spawn(move || -> Result<(), Error> {
+//!     let contributor = thread::spawn(move || -> Result<(), Error> {
 //!         let contributor_input = vec![true];
 //!
 //!         // Create a contributor and the initial message for the evaluator:
-//!         let (mut contributor, msg) = Contributor::new(
+//!         let (contributor, msg) = Contributor::new(
 //!             &circuit_for_contrib_thread,
 //!             contributor_input,
 //!             ChaCha20Rng::from_entropy(),
 //!         )?;
 //!
-//!         // Send initial message to start protocol:
-//!         to_eval.send(msg).unwrap();
-//!
-//!         for _ in 0..contributor.steps() {
-//!             let msg = from_eval.recv().expect("failed to get msg");
-//!
-//!             // Based on the message from the evaluator, the contributor transitions to a new state:
-//!             let (next_state, reply) = contributor.run(&msg)?;
-//!             to_eval.send(reply).expect("failed to send reply");
-//!             contributor = next_state;
-//!         }
+//!         // Send the initial message and reply to every message of the evaluator:
+//!         run_contributor(contributor, msg, &mut channel_for_contrib)?;
 //!         Ok(())
 //!     });
 //!
 //!     let evaluator_input = vec![false];
 //!
-//!     let mut evaluator = Evaluator::new(
+//!     let evaluator = Evaluator::new(
 //!         &simple_circuit,
 //!         evaluator_input,
 //!         ChaCha20Rng::from_entropy(),
 //!     )?;
 //!
-//!     for _ in 0..evaluator.steps() {
-//!         let msg = from_contrib.recv().expect("failed to get msg");
-//!
-//!         // Based on the message from the contributor, the evaluator transitions to a new state:
-//!         let (next_state, reply) = evaluator.run(&msg)?;
-//!         to_contrib.send(reply).expect("failed to send reply");
-//!         evaluator = next_state
-//!     }
-//!
 //!     // The final message from the contributor allows the evaluator to decrypt the output:
-//!     let final_msg = from_contrib.recv().expect("failed to get final msg");
-//!     let output = evaluator.output(&final_msg)?;
+//!     let output = run_evaluator(evaluator, &mut channel_for_eval)?;
 //!     assert_eq!(output, vec![false]);
 //!
-//!     Ok(())
+//!     contributor.join().expect("contributor thread panicked")
 //! }
 //! ```
 
@@ -98,15 +94,29 @@
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+pub mod arithmetic;
+pub mod batch;
+pub mod channel;
 mod circuit;
+pub mod commitment;
+mod dualex;
+pub mod gadgets;
 mod hash;
 mod leakyand;
 mod leakydelta_ot;
+pub mod message;
+pub mod multiparty;
+pub mod oram;
 mod ot_base;
+mod persistence;
+pub mod preprocessing;
 mod protocol;
+pub mod reactive;
+mod semihonest;
 mod simulator;
 pub mod states;
 mod types;
+pub mod zk;
 
 pub use circuit::*;
 pub use simulator::*;
@@ -114,8 +124,13 @@ pub use simulator::*;
 /// Errors occurring during the validation or the execution of the protocol.
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
-    /// A different message was expected from the other party at this point in the protocol.
-    UnexpectedMessageType,
+    /// The content of a message from the other party does not fit into the protocol at this point.
+    UnexpectedMessageType {
+        /// The step of the message, if known.
+        step: Option<message::Step>,
+        /// What exactly does not fit.
+        fault: MessageFault,
+    },
     /// The AND shares received did not match the number of gates.
     InsufficientAndShares,
     /// The garbled table share does not belong to an AND gate.
@@ -123,23 +138,102 @@ pub enum Error {
     /// Not enough input bits were provided as user input.
     InsufficientInput,
     /// A MAC checking error occurred, due to an accidental or deliberate data corruption.
-    MacError,
+    MacError {
+        /// The step of the message that failed the check, if known.
+        step: Option<message::Step>,
+        /// The check that failed.
+        check: MacCheck,
+        /// The number of values that failed the check.
+        failed: usize,
+        /// The position of the first value that failed, such as the index of a gate or an output,
+        /// or `None` if the check covers a single value.
+        index: Option<usize>,
+    },
     /// The authenticated AND triples did not pass the equality check.
-    LeakyAndNotEqual,
+    LeakyAndNotEqual {
+        /// The step of the message that failed the check, if known.
+        step: Option<message::Step>,
+        /// The number of values that failed the equality check.
+        failed: usize,
+        /// The position of the first value that failed the equality check.
+        index: usize,
+    },
     /// The provided circuit contains invalid gate connections.
     InvalidCircuit,
     /// The provided circuit has too many gates to be processed.
     MaxCircuitSizeExceeded,
+    /// A batch must contain at least one instance of the circuit.
+    EmptyBatch,
     /// The provided byte buffer could not be deserialized into an OT init message.
     OtInitDeserializationError,
     /// The provided byte buffer could not be deserialized into an OT block message.
     OtBlockDeserializationError,
     /// The provided byte buffer could not be deserialized into the expected type.
-    BincodeError,
+    BincodeError {
+        /// The step of the message that could not be deserialized, if it was a message.
+        step: Option<message::Step>,
+    },
     /// The protocol has already ended, no further messages can be processed.
     ProtocolEnded,
     /// The protocol is still in progress and does not yet have any output.
     ProtocolStillInProgress,
+    /// The pattern or automaton for matching strings is malformed or unsupported.
+    InvalidPattern,
+    /// The machine learning model is malformed or inconsistent.
+    InvalidModel,
+    /// The preprocessing pool does not contain enough authenticated bits or AND triples.
+    PoolExhausted,
+    /// The parties did not consume the same parts of their preprocessing pools.
+    PoolMismatch,
+    /// The next step of the protocol requires an input, which has not been provided yet.
+    MissingInput,
+    /// The input of the party has already been provided.
+    InputAlreadyProvided,
+    /// The output was requested in a different output mode than the one used by the parties.
+    OutputModeMismatch,
+    /// The state handle does not exist in the reactive state of the party.
+    UnknownStateHandle,
+    /// The loaded or stored state handles do not fit into the inputs or outputs of the circuit.
+    InvalidStateBindings,
+    /// The requested feature is not supported at the security level used by the parties.
+    UnsupportedSecurityLevel,
+    /// The number of bits of statistical security is not supported.
+    InvalidStatisticalSecurity,
+    /// The parties did not use the same statistical security.
+    StatisticalSecurityMismatch,
+    /// The evaluator can only open the protocol before its first step and without a pool.
+    CannotInitiate,
+    /// The other party uses a different version of the protocol, `0` for unframed messages.
+    IncompatibleProtocolVersion {
+        /// The protocol version of this party.
+        local: u16,
+        /// The protocol version of the other party.
+        remote: u16,
+    },
+    /// The message belongs to a different step of the protocol than the expected one.
+    UnexpectedStep {
+        /// The step that was expected at this point in the protocol.
+        expected: message::Step,
+        /// The step that the message belongs to.
+        received: message::Step,
+    },
+    /// The message belongs to a different session than the one of the party.
+    SessionMismatch,
+    /// The length of the message does not match the length announced by its header.
+    InvalidMessageLength,
+    /// The saved state is malformed or was saved by the other party or for a different circuit.
+    InvalidSavedState,
+    /// The saved state was encrypted with a different key, was not encrypted at all or has been
+    /// modified.
+    InvalidStateKey,
+    /// The number of parties or the index of a party does not fit the multi-party protocol.
+    InvalidParties,
+    /// The parties of the multi-party protocol did not receive the same broadcast values.
+    InconsistentBroadcast,
+    /// The channel to the other party was closed before the protocol was completed.
+    ChannelClosed,
+    /// The channel to the other party failed with an I/O error of the specified kind.
+    ChannelError(std::io::ErrorKind),
 }
 
 impl std::error::Error for Error {}
@@ -147,7 +241,9 @@ impl std::error::Error for Error {}
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::UnexpectedMessageType => f.write_str("Unexpected message kind"),
+            Error::UnexpectedMessageType { step, fault } => {
+                write!(f, "Unexpected message content{}: {fault}", AtStep(step))
+            }
             Error::InsufficientAndShares => {
                 f.write_str("Insufficient number of AND shares received from upstream")
             }
@@ -155,37 +251,319 @@ impl std::fmt::Display for Error {
                 f.write_str("Received a table share for an unsupported gate")
             }
             Error::InsufficientInput => f.write_str("Not enough or too many input bits provided"),
-            Error::MacError => f.write_str("At least 1 MAC check failed"),
-            Error::LeakyAndNotEqual => {
-                f.write_str("The equality check of the authenticated AND triples failed")
+            Error::MacError {
+                step,
+                check,
+                failed,
+                index,
+            } => {
+                write!(f, "{failed} {check} check(s) failed{}", AtStep(step))?;
+                match index {
+                    Some(index) => write!(f, ", first at index {index}"),
+                    None => Ok(()),
+                }
             }
+            Error::LeakyAndNotEqual {
+                step,
+                failed,
+                index,
+            } => write!(
+                f,
+                "The equality check of the authenticated AND triples failed for {failed} \
+                value(s){}, first at index {index}",
+                AtStep(step)
+            ),
             Error::InvalidCircuit => {
                 f.write_str("The provided circuit is invalid and cannot be executed")
             }
             Error::MaxCircuitSizeExceeded => f.write_str(
                 "The number of gates in the circuit exceed the maximum that can be processed",
             ),
+            Error::EmptyBatch => f.write_str("A batch must contain at least one instance"),
             Error::OtInitDeserializationError => f.write_str(
                 "The message buffer could not be deserialized into a proper OT init message",
             ),
             Error::OtBlockDeserializationError => f.write_str(
                 "The message buffer could not be deserialized into a proper OT block message",
             ),
-            Error::BincodeError => {
-                f.write_str("The message could not be serialized to / deserialized from bincode")
-            }
+            Error::BincodeError { step } => write!(
+                f,
+                "The message could not be serialized to / deserialized from bincode{}",
+                AtStep(step)
+            ),
             Error::ProtocolEnded => {
                 f.write_str("The protocol has already ended, no further messages can be processed.")
             }
             Error::ProtocolStillInProgress => {
                 f.write_str("The protocol is still in progress and does not yet have any output.")
             }
+            Error::InvalidPattern => {
+                f.write_str("The pattern for matching strings is malformed or unsupported")
+            }
+            Error::InvalidModel => f.write_str("The model is malformed or inconsistent"),
+            Error::PoolExhausted => f.write_str(
+                "The preprocessing pool does not contain enough authenticated bits or AND triples",
+            ),
+            Error::PoolMismatch => f.write_str(
+                "The parties did not consume the same parts of their preprocessing pools",
+            ),
+            Error::MissingInput => {
+                f.write_str("The next step requires an input, which has not been provided yet")
+            }
+            Error::InputAlreadyProvided => f.write_str("The input has already been provided"),
+            Error::OutputModeMismatch => {
+                f.write_str("The output was requested in a different output mode")
+            }
+            Error::UnknownStateHandle => f.write_str("The state handle does not exist"),
+            Error::InvalidStateBindings => {
+                f.write_str("The state handles do not fit into the inputs or outputs of the circuit")
+            }
+            Error::UnsupportedSecurityLevel => {
+                f.write_str("The feature is not supported at the security level of the parties")
+            }
+            Error::InvalidStatisticalSecurity => {
+                f.write_str("The statistical security must be between 1 and 128 bits")
+            }
+            Error::StatisticalSecurityMismatch => {
+                f.write_str("The parties did not use the same statistical security")
+            }
+            Error::CannotInitiate => f.write_str(
+                "The evaluator can only open the protocol before its first step and without a pool",
+            ),
+            Error::IncompatibleProtocolVersion { local, remote: 0 } => write!(
+                f,
+                "The other party sent an unframed message of an earlier version, expected \
+                protocol version {local}"
+            ),
+            Error::IncompatibleProtocolVersion { local, remote } => write!(
+                f,
+                "The other party uses protocol version {remote}, expected version {local}"
+            ),
+            Error::UnexpectedStep { expected, received } => write!(
+                f,
+                "Received a message of step {received:?}, expected a message of step {expected:?}"
+            ),
+            Error::SessionMismatch => {
+                f.write_str("The message belongs to a different session than the one of the party")
+            }
+            Error::InvalidMessageLength => {
+                f.write_str("The length of the message does not match the length of its header")
+            }
+            Error::InvalidSavedState => f.write_str(
+                "The saved state is malformed or belongs to a different party or circuit",
+            ),
+            Error::InvalidStateKey => f.write_str(
+                "The saved state could not be decrypted and authenticated with the provided key",
+            ),
+            Error::InvalidParties => f.write_str(
+                "The number of parties or the index of the party is invalid for the protocol",
+            ),
+            Error::InconsistentBroadcast => {
+                f.write_str("The parties did not receive the same broadcast values")
+            }
+            Error::ChannelClosed => {
+                f.write_str("The channel to the other party was closed before the protocol ended")
+            }
+            Error::ChannelError(kind) => {
+                write!(f, "The channel to the other party failed with an I/O error: {kind}")
+            }
         }
     }
 }
 
 impl From<bincode::Error> for Error {
     fn from(_: bincode::Error) -> Self {
-        Self::BincodeError
+        Self::BincodeError { step: None }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted => Self::ChannelClosed,
+            kind => Self::ChannelError(kind),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the error for a message that contains `received` values instead of `expected`.
+    pub(crate) fn unexpected_length(expected: usize, received: usize) -> Self {
+        Self::UnexpectedMessageType {
+            step: None,
+            fault: MessageFault::Length { expected, received },
+        }
+    }
+
+    /// Returns the error for a message that refers to the gate at `index` in the wrong way.
+    pub(crate) fn unexpected_gate(index: usize) -> Self {
+        Self::UnexpectedMessageType {
+            step: None,
+            fault: MessageFault::Gate(index),
+        }
+    }
+
+    /// Attributes the error to the step of the message that caused it, unless it already is.
+    pub(crate) fn at_step(mut self, step: Option<message::Step>) -> Self {
+        match &mut self {
+            Error::UnexpectedMessageType { step: s, .. }
+            | Error::MacError { step: s, .. }
+            | Error::LeakyAndNotEqual { step: s, .. }
+            | Error::BincodeError { step: s }
+                if s.is_none() =>
+            {
+                *s = step;
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Formats the step of an error, if known.
+struct AtStep<'a>(&'a Option<message::Step>);
+
+impl std::fmt::Display for AtStep<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(step) => write!(f, " at step {step:?}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The check that detected corrupted data in a [`Error::MacError`].
+///
+/// The checks only identify which values failed, never the values, MACs or keys themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacCheck {
+    /// The MACs of the bits that combine the leaky AND triples of a bucket, indexed by bucket.
+    Bucketing,
+    /// The MACs of the shares of the input masks, indexed by input gate.
+    InputMask,
+    /// The MACs linking inputs to the shares of a reactive state, indexed by input gate.
+    LinkedInput,
+    /// The MACs of the rows of the garbled AND tables, indexed by AND gate.
+    AndTable,
+    /// The labels and MACs of the revealed outputs, indexed by output.
+    Output,
+    /// The MACs of the disclosed output shares, indexed by output.
+    OutputShare,
+    /// The commitment to the share of the coin of the other party.
+    CoinCommitment,
+    /// The MACs of the random combinations that check that a party of the multi-party protocol
+    /// authenticated the same bits to all other parties, indexed by combination.
+    ShareConsistency,
+    /// The MACs of the masked AND triple bits of the multi-party protocol, indexed by AND gate.
+    MaskedAndBits,
+    /// The `Delta` revealed by the verifier of a zero-knowledge proof, checked against the
+    /// additional OTs of the prover, indexed by OT.
+    ProofDelta,
+    /// The garbled tables of a zero-knowledge proof, checked against the revealed `Delta`,
+    /// indexed by AND gate.
+    ProofTable,
+    /// The commitment of the prover of a zero-knowledge proof to its output labels.
+    ProofCommitment,
+    /// The equality of the output labels of both executions of the
+    /// [`SecurityLevel::DualExecution`](crate::states::SecurityLevel::DualExecution).
+    OutputEquality,
+    /// The opening of the [`InputCommitment`](crate::commitment::InputCommitment) of a party,
+    /// checked inside of the circuit.
+    InputCommitment,
+}
+
+impl std::fmt::Display for MacCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MacCheck::Bucketing => "bucketing MAC",
+            MacCheck::InputMask => "input mask MAC",
+            MacCheck::LinkedInput => "linked input MAC",
+            MacCheck::AndTable => "AND table MAC",
+            MacCheck::Output => "output MAC",
+            MacCheck::OutputShare => "output share MAC",
+            MacCheck::CoinCommitment => "coin commitment",
+            MacCheck::ShareConsistency => "share consistency MAC",
+            MacCheck::MaskedAndBits => "masked AND bit MAC",
+            MacCheck::ProofDelta => "proof Delta",
+            MacCheck::ProofTable => "proof garbled table",
+            MacCheck::ProofCommitment => "proof commitment",
+            MacCheck::OutputEquality => "dual execution output equality",
+            MacCheck::InputCommitment => "input commitment",
+        })
+    }
+}
+
+/// What does not fit into the protocol in an [`Error::UnexpectedMessageType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFault {
+    /// The message contains a different number of values than expected.
+    Length {
+        /// The number of values that were expected.
+        expected: usize,
+        /// The number of values that the message contains.
+        received: usize,
+    },
+    /// The message refers to the gate at this index, which is of the wrong kind or has already
+    /// been handled.
+    Gate(usize),
+    /// The header of the message refers to this step, which does not exist.
+    UnknownStep(u8),
+    /// The message was sent by this party, which is not part of the protocol or has already sent
+    /// its message for the step.
+    Sender(PartyIndex),
+}
+
+impl std::fmt::Display for MessageFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageFault::Length { expected, received } => {
+                write!(f, "expected {expected} value(s), received {received}")
+            }
+            MessageFault::Gate(index) => write!(f, "unexpected reference to gate {index}"),
+            MessageFault::UnknownStep(step) => write!(f, "unknown step {step}"),
+            MessageFault::Sender(party) => write!(f, "unexpected message of party {party}"),
+        }
+    }
+}
+
+/// Counts the values that fail a [`MacCheck`], remembering the first one.
+pub(crate) struct MacChecks {
+    check: MacCheck,
+    failed: usize,
+    index: Option<usize>,
+}
+
+impl MacChecks {
+    pub(crate) fn new(check: MacCheck) -> Self {
+        Self {
+            check,
+            failed: 0,
+            index: None,
+        }
+    }
+
+    /// Records the result of checking the value at `index`.
+    pub(crate) fn verify(&mut self, index: usize, success: bool) {
+        if !success {
+            self.failed += 1;
+            self.index.get_or_insert(index);
+        }
+    }
+
+    /// Fails with [`Error::MacError`] if any value failed the check.
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        if self.failed == 0 {
+            Ok(())
+        } else {
+            Err(Error::MacError {
+                step: None,
+                check: self.check,
+                failed: self.failed,
+                index: self.index,
+            })
+        }
     }
 }
@@ -0,0 +1,600 @@
+//! The framing of the messages exchanged between the parties.
+//!
+//! Every message is sent as an envelope, which starts with a fixed header of [`HEADER_LEN`] bytes,
+//! followed by the payload of the step:
+//!
+//! | Bytes | Content                                                                    |
+//! |-------|----------------------------------------------------------------------------|
+//! | 4     | the magic bytes `MPCE`                                                     |
+//! | 2     | the [`PROTOCOL_VERSION`] of the sender (little endian)                     |
+//! | 1     | the [`Step`] of the protocol that the message belongs to                   |
+//! | 32    | the session of the sender, or zeros if no session has been established yet |
+//! | 8     | the length of the payload (little endian)                                  |
+//!
+//! Each party knows which step it expects next and rejects messages of a different step, protocol
+//! version or session with a precise error before looking at the payload. Peers that predate the
+//! envelope (protocol version 0) send messages without the magic bytes, which are rejected with
+//! [`Error::IncompatibleProtocolVersion`]. Such peers in turn fail to decode the header as the
+//! length prefix of their first payload field, which is far larger than any message.
+
+use bincode::{deserialize, serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    hash::SessionId,
+    leakydelta_ot::message::SerializedOtInit,
+    preprocessing::PoolPosition,
+    states::{Msg, StatisticalSecurity},
+    types::{InputMaskShare, KeyType, MacType, PartialBitShare, TableShare, WireLabel},
+    Error, GateIndex, MessageFault,
+};
+
+/// The version of the message format and protocol, which must be the same for both parties.
+///
+/// Version 0 refers to the unframed messages of earlier releases, version 1 to the protocol that
+/// needed 15 instead of 11 messages for the preprocessing and input processing.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// The length of the header that precedes the payload of every message.
+pub const HEADER_LEN: usize = 4 + 2 + 1 + 32 + 8;
+
+const MAGIC: [u8; 4] = *b"MPCE";
+
+/// The step of the protocol that a message belongs to, in the order in which the messages are sent.
+///
+/// The steps of the preprocessing are shared by the circuit execution and the generation of a
+/// [`Pool`](crate::preprocessing::Pool).
+///
+/// If the evaluator opens the protocol using
+/// [`Evaluator::initiate`](crate::states::Evaluator::initiate), the first 5 steps are replaced by
+/// the 6 steps from [`Step::EvaluatorOtInit`] on, after which the protocol continues with
+/// [`Step::LeakyAndCorrections`].
+///
+/// The steps from [`Step::MultipartyOtInit`] on belong to the [`multiparty`](crate::multiparty)
+/// protocol, in which every party sends a message of the same step to every other party.
+///
+/// The steps from [`Step::ArithOtInit`] on belong to the [`arithmetic`](crate::arithmetic)
+/// engine, the steps from [`Step::ZkOtInit`] on to the zero-knowledge proofs of the
+/// [`zk`](crate::zk) module and the steps from [`Step::DualCircuit`] on to the
+/// [`SecurityLevel::DualExecution`](crate::states::SecurityLevel::DualExecution).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Step {
+    /// The contributor starts the base OTs and commits to its share of the coin.
+    BaseOtInit = 1,
+    /// The evaluator starts its own base OTs, commits to its share of the coin and answers the
+    /// base OTs of the contributor.
+    BaseOtResponse = 2,
+    /// The contributor answers the base OTs of the evaluator, opens its share of the coin,
+    /// completes its own base OTs and sends its extended OTs.
+    BaseOtCompletion = 3,
+    /// The evaluator completes its base OTs, sends its extended OTs and starts `Π_LaAND`.
+    OtExtension = 4,
+    /// The contributor starts `Π_LaAND` and sends its shares of the leaky AND triples.
+    LeakyAndStart = 5,
+    /// The evaluator sends its corrections of the leaky AND triples and starts their check.
+    LeakyAndCorrections = 6,
+    /// The contributor continues the check and commits to its values for the equality check.
+    LeakyAndCommitments = 7,
+    /// The evaluator commits to its values for the equality check, opens them and starts the
+    /// bucketing.
+    LeakyAndOpenings = 8,
+    /// The contributor opens its values for the equality check and starts the bucketing of a pool.
+    Bucketing = 9,
+    /// The contributor opens its values for the equality check, starts the bucketing and sends
+    /// its masked AND triple bits and its shares of the masks of the evaluator's input.
+    BucketingAndMasks = 10,
+    /// The evaluator sends its masked AND triple bits, the masks of the contributor's input and
+    /// its own masked input.
+    InputMasks = 11,
+    /// The contributor sends its shares of the garbled tables, the labels of all inputs and the
+    /// masks of the revealed outputs.
+    EvaluationInputs = 12,
+    /// The evaluator reveals the output to the contributor.
+    RevealedOutput = 13,
+    /// A party discloses its shares of the output.
+    DisclosedShares = 14,
+    /// The contributor sends the consumed part of its pool, its masked AND triple bits and its
+    /// shares of the masks of the evaluator's input.
+    PooledMasks = 15,
+    /// The contributor sends the half-gates garbled circuit.
+    HalfGatesCircuit = 16,
+    /// The evaluator starts the base OTs.
+    HalfGatesOtInit = 17,
+    /// The contributor answers the base OTs.
+    HalfGatesOtResponse = 18,
+    /// The evaluator completes the base OTs and sends its extended OTs.
+    HalfGatesOtExtension = 19,
+    /// The contributor sends the labels of the evaluator's input and the decoding of the output.
+    HalfGatesInputLabels = 20,
+    /// The evaluator reveals the output labels to the contributor.
+    HalfGatesOutputLabels = 21,
+    /// The evaluator opens the protocol, starts the base OTs and commits to its share of the coin.
+    EvaluatorOtInit = 22,
+    /// The contributor starts its own base OTs, commits to its share of the coin and answers the
+    /// base OTs of the evaluator.
+    ContributorOtResponse = 23,
+    /// The evaluator answers the base OTs of the contributor, opens its share of the coin,
+    /// completes its own base OTs and sends its extended OTs.
+    EvaluatorOtCompletion = 24,
+    /// The contributor completes its base OTs, sends its extended OTs and starts `Π_LaAND`.
+    ContributorOtExtension = 25,
+    /// The evaluator starts `Π_LaAND`.
+    EvaluatorLeakyAndStart = 26,
+    /// The contributor sends its shares of the leaky AND triples.
+    ContributorLeakyAndShares = 27,
+    /// A party starts the base OTs with the other party and commits to its share of the coin.
+    MultipartyOtInit = 28,
+    /// A party answers the base OTs of the other party and opens its share of the coin.
+    MultipartyOtResponse = 29,
+    /// A party completes the base OTs, sends its extended OTs and commits to its share of the coin
+    /// for the consistency check of the authenticated bits.
+    MultipartyOtExtension = 30,
+    /// A party sends the hashes of `Π_HaAND` and opens its share of the second coin.
+    MultipartyHalfAnds = 31,
+    /// A party broadcasts its corrections of the leaky AND triples and sends its values for the
+    /// consistency check of the authenticated bits and for the check of `Π_LaAND`.
+    MultipartyLeakyAndCorrections = 32,
+    /// A party commits to its value for the check of `Π_LaAND` and starts the bucketing.
+    MultipartyLeakyAndCommitments = 33,
+    /// A party opens its value for the check of `Π_LaAND` and sends its masked AND triple bits and
+    /// its shares of the masks of the other party's input.
+    MultipartyLeakyAndOpenings = 34,
+    /// A party broadcasts its masked input, a garbling party sends its garbled tables to the
+    /// evaluator.
+    MultipartyMaskedInputs = 35,
+    /// A garbling party sends the labels of all inputs to the evaluator.
+    MultipartyInputLabels = 36,
+    /// The evaluator reveals the masked output and its labels to a garbling party.
+    MultipartyRevealedOutput = 37,
+    /// A party discloses its shares of the masks of the output.
+    MultipartyOutputShares = 38,
+    /// The contributor starts the base OTs for the multiplication triples.
+    ArithOtInit = 39,
+    /// The evaluator answers the base OTs.
+    ArithOtResponse = 40,
+    /// The contributor completes the base OTs and sends its extended OTs, which choose the bits of
+    /// its shares of the triples.
+    ArithOtExtension = 41,
+    /// The evaluator sends the corrections that complete the triples and its openings of the first
+    /// layer of multiplications.
+    ArithTriples = 42,
+    /// A party sends its openings of the next layers of multiplications.
+    ArithOpenings = 43,
+    /// The prover starts the base OTs for the labels of its witness.
+    ZkOtInit = 44,
+    /// The verifier answers the base OTs.
+    ZkOtResponse = 45,
+    /// The prover completes the base OTs and sends its extended OTs.
+    ZkOtExtension = 46,
+    /// The verifier sends the garbled tables of the circuit.
+    ZkGarbledCircuit = 47,
+    /// The prover commits to the output labels.
+    ZkCommitment = 48,
+    /// The verifier reveals its `Delta`.
+    ZkReveal = 49,
+    /// The prover opens its commitment to the output labels.
+    ZkOpening = 50,
+    /// The contributor sends its garbled circuit with the labels of its input.
+    DualCircuit = 51,
+    /// The evaluator starts the base OTs for the circuit of the contributor and sends its own
+    /// garbled circuit.
+    DualOtInit = 52,
+    /// The contributor answers the base OTs of the evaluator and starts its own.
+    DualOtResponse = 53,
+    /// The evaluator sends its extended OTs and answers the base OTs of the contributor.
+    DualOtExtension = 54,
+    /// The contributor sends the labels of the evaluator's input and its extended OTs.
+    DualInputLabels = 55,
+    /// The evaluator sends the labels of the contributor's input and commits to the digest of its
+    /// output labels.
+    DualCommitment = 56,
+    /// The contributor sends the digest of its output labels.
+    DualDigest = 57,
+    /// The evaluator opens the commitment to its digest.
+    DualOpening = 58,
+}
+
+impl Step {
+    fn from_u8(step: u8) -> Option<Self> {
+        use Step::*;
+
+        let steps = [
+            BaseOtInit,
+            BaseOtResponse,
+            BaseOtCompletion,
+            OtExtension,
+            LeakyAndStart,
+            LeakyAndCorrections,
+            LeakyAndCommitments,
+            LeakyAndOpenings,
+            Bucketing,
+            BucketingAndMasks,
+            InputMasks,
+            EvaluationInputs,
+            RevealedOutput,
+            DisclosedShares,
+            PooledMasks,
+            HalfGatesCircuit,
+            HalfGatesOtInit,
+            HalfGatesOtResponse,
+            HalfGatesOtExtension,
+            HalfGatesInputLabels,
+            HalfGatesOutputLabels,
+            EvaluatorOtInit,
+            ContributorOtResponse,
+            EvaluatorOtCompletion,
+            ContributorOtExtension,
+            EvaluatorLeakyAndStart,
+            ContributorLeakyAndShares,
+            MultipartyOtInit,
+            MultipartyOtResponse,
+            MultipartyOtExtension,
+            MultipartyHalfAnds,
+            MultipartyLeakyAndCorrections,
+            MultipartyLeakyAndCommitments,
+            MultipartyLeakyAndOpenings,
+            MultipartyMaskedInputs,
+            MultipartyInputLabels,
+            MultipartyRevealedOutput,
+            MultipartyOutputShares,
+            ArithOtInit,
+            ArithOtResponse,
+            ArithOtExtension,
+            ArithTriples,
+            ArithOpenings,
+            ZkOtInit,
+            ZkOtResponse,
+            ZkOtExtension,
+            ZkGarbledCircuit,
+            ZkCommitment,
+            ZkReveal,
+            ZkOpening,
+            DualCircuit,
+            DualOtInit,
+            DualOtResponse,
+            DualOtExtension,
+            DualInputLabels,
+            DualCommitment,
+            DualDigest,
+            DualOpening,
+        ];
+        steps.into_iter().find(|s| *s as u8 == step)
+    }
+}
+
+/// Frames the payload of a step as a message for the other party.
+pub(crate) fn seal<T: Serialize>(
+    step: Step,
+    session: Option<SessionId>,
+    payload: &T,
+) -> Result<Msg, Error> {
+    let payload = serialize(payload)?;
+    let mut msg = Vec::with_capacity(HEADER_LEN + payload.len());
+    msg.extend_from_slice(&MAGIC);
+    msg.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    msg.push(step as u8);
+    msg.extend_from_slice(&session.map_or([0; 32], |session| *session.as_bytes()));
+    msg.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    msg.extend_from_slice(&payload);
+    Ok(msg)
+}
+
+/// Returns the step that a message belongs to, without checking anything but its magic bytes.
+pub(crate) fn step(msg: &[u8]) -> Option<Step> {
+    if msg.len() < HEADER_LEN || msg[..4] != MAGIC {
+        return None;
+    }
+    Step::from_u8(msg[6])
+}
+
+/// Returns the payload of a message, which must belong to the `expected` step and, once the
+/// session has been established, to the same session.
+pub(crate) fn open<T: DeserializeOwned>(
+    msg: &[u8],
+    expected: Step,
+    session: Option<SessionId>,
+) -> Result<T, Error> {
+    if msg.get(..4) != Some(&MAGIC[..]) {
+        return Err(Error::IncompatibleProtocolVersion {
+            local: PROTOCOL_VERSION,
+            remote: 0,
+        });
+    }
+    if msg.len() < HEADER_LEN {
+        return Err(Error::InvalidMessageLength);
+    }
+    let (header, payload) = msg.split_at(HEADER_LEN);
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != PROTOCOL_VERSION {
+        return Err(Error::IncompatibleProtocolVersion {
+            local: PROTOCOL_VERSION,
+            remote: version,
+        });
+    }
+    let received = Step::from_u8(header[6]).ok_or(Error::UnexpectedMessageType {
+        step: None,
+        fault: MessageFault::UnknownStep(header[6]),
+    })?;
+    if received != expected {
+        return Err(Error::UnexpectedStep { expected, received });
+    }
+    if let Some(session) = session {
+        if header[7..39] != session.as_bytes()[..] {
+            return Err(Error::SessionMismatch);
+        }
+    }
+    let mut len = [0; 8];
+    len.copy_from_slice(&header[39..47]);
+    if u64::from_le_bytes(len) != payload.len() as u64 {
+        return Err(Error::InvalidMessageLength);
+    }
+    deserialize(payload).map_err(|_| Error::BincodeError {
+        step: Some(expected),
+    })
+}
+
+/// The start of the base OTs of a party, its commitment to its share of the coin and the
+/// statistical security that it uses.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OtInit {
+    pub(crate) ot_init: SerializedOtInit,
+    pub(crate) coin_commitment: Vec<u8>,
+    pub(crate) security: StatisticalSecurity,
+}
+
+/// The sender's answer to the base OTs of the other party and the opened share of the coin.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OtResponse {
+    pub(crate) ot_init: SerializedOtInit,
+    pub(crate) coin: Vec<u8>,
+}
+
+/// The receiver's reply that completes the base OTs.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OtCompletion {
+    pub(crate) reply: Vec<u8>,
+}
+
+/// The extended OTs of the receiver, one block of `BLOCK_SIZE` OTs at a time.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OtBlocks {
+    pub(crate) blocks: Vec<Vec<MacType>>,
+}
+
+/// Step 1 of `Π_LaAND`, the hashes for the leaky AND triples.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LeakyAndHashes {
+    pub(crate) hashes: Vec<[MacType; 2]>,
+}
+
+/// Steps 2 and 3 of `Π_LaAND`, the shares of (or corrections for) the leaky AND triples.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LeakyAndShares {
+    pub(crate) shares: Vec<MacType>,
+}
+
+/// Steps 4/5 (a + b) of `Π_LaAND`, the values for checking the leaky AND triples.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LeakyAndCheck {
+    pub(crate) u: Vec<MacType>,
+}
+
+/// Steps 4/5 (c + d) of `Π_LaAND`, the committed values for the equality check.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LeakyAndCommitments {
+    pub(crate) commitments: Vec<MacType>,
+    pub(crate) w: Vec<(MacType, MacType)>,
+}
+
+/// Step 4/5 (e) of `Π_LaAND`, the opened values for the equality check.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LeakyAndOpenings {
+    pub(crate) r_prime: Vec<MacType>,
+    pub(crate) openings: Vec<(MacType, KeyType)>,
+}
+
+/// The authenticated bits for combining the AND triples of each bucket.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BucketingBits {
+    pub(crate) bits: Vec<bool>,
+    pub(crate) macs: Vec<MacType>,
+}
+
+/// The masked bits of the AND triples that are assigned to the AND gates.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MaskedAndBits {
+    pub(crate) lhs: Vec<bool>,
+    pub(crate) rhs: Vec<bool>,
+}
+
+/// The contributor's masked AND triple bits and its shares of the masks of the evaluator's input.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ContribMasks {
+    pub(crate) masks: MaskedAndBits,
+    pub(crate) input_mask_shares: Vec<InputMaskShare>,
+}
+
+/// The contributor's shares of the garbled tables.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GarbledTables {
+    pub(crate) tables: Vec<TableShare>,
+}
+
+/// The evaluator's shares of the masks of the contributor's input, its own masked input and the
+/// MACs linking its loaded state handles.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct InputMasks {
+    pub(crate) mask_shares: Vec<InputMaskShare>,
+    pub(crate) masked_inputs: Vec<(GateIndex, bool)>,
+    pub(crate) linked_macs: Vec<MacType>,
+}
+
+/// The labelled inputs, the output mask shares and the MACs linking loaded state handles, as sent
+/// by the contributor for the circuit evaluation.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvaluationInputs {
+    pub(crate) inputs: Vec<(GateIndex, WireLabel, bool)>,
+    pub(crate) output_mask_shares: Vec<InputMaskShare>,
+    pub(crate) linked_macs: Vec<MacType>,
+}
+
+/// The labels and masked values of the revealed outputs, with the evaluator's shares of their
+/// masks.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RevealedOutput {
+    pub(crate) outputs: Vec<(WireLabel, bool, PartialBitShare)>,
+}
+
+/// The disclosed shares of the output of a party.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DisclosedShares {
+    pub(crate) shares: Vec<PartialBitShare>,
+}
+
+/// The part of its pool consumed by the contributor, its masked AND triple bits and its shares of
+/// the masks of the evaluator's input.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PooledMasks {
+    pub(crate) position: PoolPosition,
+    pub(crate) masks: ContribMasks,
+}
+
+/// The garbled tables of all AND gates, followed by the labels of the contributor's input.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GarbledCircuit {
+    pub(crate) tables: Vec<[WireLabel; 2]>,
+    pub(crate) contrib_labels: Vec<WireLabel>,
+}
+
+/// The offsets that turn the evaluator's OT outputs into its input labels and the decoding bits
+/// of the output.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct InputLabels {
+    pub(crate) offsets: Vec<WireLabel>,
+    pub(crate) decoding: Vec<bool>,
+}
+
+/// The output labels revealed by the evaluator.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OutputLabels {
+    pub(crate) labels: Vec<WireLabel>,
+}
+
+/// Authenticated bits opened to another party, each with its MAC under the other party's `Delta`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OpenedBits {
+    pub(crate) bits: Vec<bool>,
+    pub(crate) macs: Vec<MacType>,
+}
+
+/// The completion of the base OTs, the extended OTs and the commitment to the share of the coin
+/// for the consistency check.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyOtExtension {
+    pub(crate) reply: Vec<u8>,
+    pub(crate) blocks: Vec<Vec<MacType>>,
+    pub(crate) coin_commitment: Vec<u8>,
+}
+
+/// The hashes of `Π_HaAND` for the leaky AND triples and the opened share of the coin for the
+/// consistency check.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyHalfAnds {
+    pub(crate) hashes: Vec<[MacType; 2]>,
+    pub(crate) coin: Vec<u8>,
+}
+
+/// The broadcast corrections of the leaky AND triples, the random combinations of the consistency
+/// check and the values for the check of `Π_LaAND`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyCorrections {
+    pub(crate) corrections: Vec<bool>,
+    pub(crate) consistency: OpenedBits,
+    pub(crate) u: Vec<MacType>,
+}
+
+/// The commitment to the value of the check of `Π_LaAND` and the bits for combining the leaky AND
+/// triples of each bucket.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyCommitment {
+    pub(crate) commitment: [u8; 32],
+    pub(crate) bucketing: OpenedBits,
+}
+
+/// The opened value of the check of `Π_LaAND`, the masked AND triple bits (two for each AND gate)
+/// and the shares of the masks of the other party's input.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyOpenings {
+    pub(crate) nonce: [u8; 32],
+    pub(crate) values: Vec<MacType>,
+    pub(crate) and_bits: OpenedBits,
+    pub(crate) input_masks: Vec<PartialBitShare>,
+}
+
+/// A row of a garbled table of one of the garbling parties, with a value for every party and a
+/// bit.
+pub(crate) type MultipartyRow = (Vec<MacType>, bool);
+
+/// The broadcast masked input of a party and, if sent to the evaluator, the garbled tables.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyMaskedInputs {
+    pub(crate) masked_inputs: Vec<bool>,
+    pub(crate) tables: Vec<[MultipartyRow; 4]>,
+}
+
+/// The labels of all inputs, as sent by a garbling party to the evaluator.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyInputLabels {
+    pub(crate) labels: Vec<WireLabel>,
+}
+
+/// The labels and masked values of the output, as revealed by the evaluator to a garbling party.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyOutput {
+    pub(crate) outputs: Vec<(WireLabel, bool)>,
+}
+
+/// The disclosed shares of the masks of the output and the digest of all broadcast values.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MultipartyOutputShares {
+    pub(crate) shares: OpenedBits,
+    pub(crate) transcript: [u8; 32],
+}
+
+/// The completion of the base OTs and the extended OTs of the arithmetic engine, one block for
+/// each multiplication triple.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ArithOtExtension {
+    pub(crate) reply: Vec<u8>,
+    pub(crate) blocks: Vec<Vec<MacType>>,
+}
+
+/// The corrections that turn the random OTs into correlated ones, 128 for each triple, and the
+/// openings `(x - a, y - b)` of the first layer of multiplications.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ArithTriples {
+    pub(crate) corrections: Vec<u64>,
+    pub(crate) openings: Vec<Vec<(u64, u64)>>,
+}
+
+/// The openings `(x - a, y - b)` of the next layers of multiplications.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ArithOpenings {
+    pub(crate) openings: Vec<Vec<(u64, u64)>>,
+}
+
+/// The `Delta` of the verifier of a zero-knowledge proof, together with the keys of the additional
+/// block of OTs that lets the prover check it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ZkReveal {
+    pub(crate) delta: u128,
+    pub(crate) keys: Vec<KeyType>,
+}
+
+/// The opening of the commitment of the prover to its output labels.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ZkOpening {
+    pub(crate) nonce: [u8; 32],
+    pub(crate) labels: Vec<WireLabel>,
+}
@@ -0,0 +1,1735 @@
+//! Authenticated garbling for three or more parties.
+//!
+//! The protocol generalizes the two-party protocol of [`states`](crate::states) to `n` parties,
+//! following the multi-party authenticated garbling of WRK17b ("Global-Scale Secure Multiparty
+//! Computation", Wang, Ranellucci and Katz):
+//!
+//! - Every party holds an XOR share of every wire mask, authenticated by a MAC under the `Delta` of
+//!   every other party. The MACs are set up using the leaky delta OTs of the two-party protocol,
+//!   run between every pair of parties, and a random linear combination of the bits checks that
+//!   every party authenticated the same bits to all other parties.
+//! - The cross terms of the leaky AND triples are computed between every pair of parties
+//!   (`Π_HaAND`), the triples are checked jointly (`Π_LaAND`) and combined in buckets like in the
+//!   two-party protocol.
+//! - Party 0 evaluates the circuit, all other parties garble it together, with every garbled row
+//!   authenticated by the MACs of all parties. All parties learn the output.
+//!
+//! Circuits for `n` parties use [`Gate::InParty`] for the input of each party instead of
+//! [`Gate::InContrib`] and [`Gate::InEval`], see [`Circuit::validate_parties`].
+//!
+//! Like the two-party protocol, the protocol is secure with abort against malicious parties, even
+//! if all but one of the parties collude. The values that a party broadcasts without MACs (the
+//! corrections of its leaky AND triples and its masked input) are echoed as a digest together with
+//! the output shares, so that a party that sent different values to different parties is detected
+//! with [`Error::InconsistentBroadcast`] before any output is computed.
+//!
+//! Every [`Party`] is a state machine that sends one message to every other party in each step and
+//! expects one message from every other party before its next step. The routing of the messages is
+//! left to the user of this crate, or to [`run_party`](crate::channel::run_party), which runs a
+//! party over a channel to every other party, for example over a [`mesh`](crate::channel::mesh)
+//! of in-memory pipes.
+//!
+//! # Examples
+//!
+//! ```
+//! use mpc_core::{channel::{mesh, run_party}, multiparty::Party, Circuit, Error, Gate};
+//! use rand::SeedableRng;
+//! use rand_chacha::ChaCha20Rng;
+//! use std::thread;
+//!
+//! fn main() -> Result<(), Error> {
+//!     // The AND of one input bit of each of 3 parties:
+//!     let circuit = Circuit::new(
+//!         vec![
+//!             Gate::InParty(0),
+//!             Gate::InParty(1),
+//!             Gate::InParty(2),
+//!             Gate::And(0, 1),
+//!             Gate::And(2, 3),
+//!         ],
+//!         vec![4],
+//!     );
+//!
+//!     let parties: Vec<_> = mesh(3)
+//!         .into_iter()
+//!         .enumerate()
+//!         .map(|(index, mut channels)| {
+//!             let circuit = circuit.clone();
+//!             thread::spawn(move || -> Result<Vec<bool>, Error> {
+//!                 let rng = ChaCha20Rng::from_entropy();
+//!                 let (party, msgs) = Party::new(&circuit, 3, index as u8, &[true][..], rng)?;
+//!                 run_party(party, msgs, &mut channels)
+//!             })
+//!         })
+//!         .collect();
+//!
+//!     for party in parties {
+//!         assert_eq!(party.join().expect("party panicked")?, vec![true]);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{borrow::Borrow, collections::BTreeMap};
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    hash::{self, domain, garbling_hash, hash_key, DomainKey, SessionId},
+    leakyand::{derive_half_and_share, half_and_hashes},
+    leakydelta_ot::{
+        message::OtInitReply, LeakyOtSender, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+    },
+    message::{
+        self, MultipartyCommitment, MultipartyCorrections, MultipartyHalfAnds,
+        MultipartyInputLabels, MultipartyMaskedInputs, MultipartyOpenings, MultipartyOtExtension,
+        MultipartyOutput, MultipartyOutputShares, MultipartyRow, OpenedBits, OtInit, OtResponse,
+        Step,
+    },
+    protocol::cointossing::{self, CoinResult, CoinShare, COIN_LEN},
+    states::{new_permutation, Msg, StatisticalSecurity},
+    types::{Delta, KeyType, MacType, PartialBitShare, WireLabel},
+    Circuit, CircuitBlake3Hash, Error, Gate, GateIndex, MacCheck, MacChecks, MessageFault,
+    PartyIndex,
+};
+
+/// The messages of a step, each together with the index of the party that it is sent to or was
+/// received from.
+pub type Msgs = Vec<(PartyIndex, Msg)>;
+
+/// The index of the party that evaluates the circuit, all other parties garble it.
+const EVALUATOR: usize = 0;
+
+/// A party of the multi-party protocol.
+pub struct Party<C: Borrow<Circuit>, I: Borrow<[bool]>> {
+    state: Box<PartyState>,
+    circuit: C,
+    input: I,
+    setup: Setup,
+    session: Option<SessionId>,
+}
+
+/// What all parties agree on before the protocol starts.
+struct Setup {
+    index: usize,
+    parties: usize,
+    security: StatisticalSecurity,
+    layout: Layout,
+    context: CircuitBlake3Hash,
+}
+
+/// The number of authenticated bits of a party and their purpose, in the order of their generation.
+#[derive(Clone, Copy)]
+struct Layout {
+    /// Masks of the input and AND gate wires.
+    wires: usize,
+    /// Leaky AND triples, before combining them in buckets.
+    leaky_ands: usize,
+    /// The number of leaky AND triples combined into each AND triple.
+    bucket_size: usize,
+    /// Random bits that hide the combinations of the consistency check.
+    checks: usize,
+    /// Blocks of extended OTs between every pair of parties.
+    blocks: usize,
+}
+
+impl Layout {
+    fn of(circuit: &Circuit, security: StatisticalSecurity) -> Self {
+        let inputs = circuit
+            .gates()
+            .iter()
+            .filter(|g| matches!(g, Gate::InParty(_)))
+            .count();
+        let bucket_size = security.bucket_size(circuit.and_gates());
+        let wires = inputs + circuit.and_gates();
+        let leaky_ands = circuit.and_gates() * bucket_size;
+        let checks = security.bits() as usize;
+        let total = wires + 3 * leaky_ands + checks;
+        Self {
+            wires,
+            leaky_ands,
+            bucket_size,
+            checks,
+            blocks: (total + BLOCK_SIZE - 1) / BLOCK_SIZE,
+        }
+    }
+}
+
+/// An authenticated share of a bit held by one of the parties (`⟨x⟩` in WRK17b).
+///
+/// The share is authenticated by a MAC under the `Delta` of every other party, which holds the
+/// matching key. The entries of the party itself are unused and always zero.
+#[derive(Debug, Clone)]
+struct AuthBit {
+    bit: bool,
+    /// The MACs of `bit` under the `Delta` of every other party, indexed by party.
+    macs: Vec<MacType>,
+    /// The keys of the shares of every other party under the own `Delta`, indexed by party.
+    keys: Vec<KeyType>,
+}
+
+impl AuthBit {
+    fn new(parties: usize) -> Self {
+        Self {
+            bit: false,
+            macs: vec![MacType(0); parties],
+            keys: vec![KeyType(0); parties],
+        }
+    }
+
+    fn xor_assign(&mut self, rhs: &AuthBit) {
+        self.bit ^= rhs.bit;
+        for (mac, rhs) in self.macs.iter_mut().zip(rhs.macs.iter()) {
+            *mac = *mac ^ *rhs;
+        }
+        for (key, rhs) in self.keys.iter_mut().zip(rhs.keys.iter()) {
+            *key = *key ^ *rhs;
+        }
+    }
+
+    /// XOR homomorphism.
+    fn xor(&self, rhs: &AuthBit) -> AuthBit {
+        let mut result = self.clone();
+        result.xor_assign(rhs);
+        result
+    }
+
+    /// Adds `rhs` if the public bit `c` is set.
+    fn xor_if(&self, rhs: &AuthBit, c: bool) -> AuthBit {
+        if c {
+            self.xor(rhs)
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Adds the public bit `c` to the shared bit, which flips the share of party 0 and the keys of
+    /// all other parties for it.
+    fn xor_public(&mut self, c: bool, setup: &Setup, delta: &Delta) {
+        if c {
+            if setup.index == 0 {
+                self.bit = !self.bit;
+            } else {
+                self.keys[0] = self.keys[0] ^ delta.0;
+            }
+        }
+    }
+
+    /// The share together with its MAC for the specified party.
+    fn opened(&self, party: usize) -> PartialBitShare {
+        PartialBitShare {
+            mac: self.macs[party],
+            bit: self.bit,
+        }
+    }
+
+    /// Verifies the share that the specified party opened.
+    fn verify(&self, party: usize, opened: PartialBitShare, delta: &Delta) -> bool {
+        opened.verify(&self.keys[party], delta)
+    }
+}
+
+fn mac(bit: bool, delta: &Delta) -> u128 {
+    if bit {
+        delta.0
+    } else {
+        0
+    }
+}
+
+fn random_bit(rng: &mut ChaCha20Rng) -> bool {
+    rng.next_u32() & 1 == 1
+}
+
+fn random_label(rng: &mut ChaCha20Rng) -> WireLabel {
+    WireLabel(rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64))
+}
+
+fn new_coin(rng: &mut ChaCha20Rng, context: &[u8; 32]) -> Result<(CoinShare, Vec<u8>), Error> {
+    let mut coin = [0u8; COIN_LEN];
+    rng.fill_bytes(&mut coin);
+    cointossing::init(coin, context)
+}
+
+/// The context that an execution is bound to, derived from the circuit and the number of parties.
+fn multiparty_context(circuit: &Circuit, parties: usize) -> CircuitBlake3Hash {
+    let mut hasher = blake3::Hasher::new_derive_key(domain::MULTIPARTY_CONTEXT);
+    hasher.update(&circuit.blake3_hash());
+    hasher.update(&(parties as u64).to_le_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+enum PartyState {
+    BaseOts(BaseOts),
+    OtResponses(OtResponses),
+    OtExtensions(OtExtensions),
+    HalfAnds(HalfAnds),
+    Corrections(Corrections),
+    Commitments(Commitments),
+    Openings(Openings),
+    MaskedInputs(MaskedInputs),
+    InputLabels(InputLabels),
+    RevealedOutput(RevealedOutput),
+    OutputShares(OutputShares),
+}
+
+/// Has started the base OTs with every other party and waits for theirs.
+struct BaseOts {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    receivers: BTreeMap<usize, ReceiverInitializer>,
+    coin_share: CoinShare,
+}
+
+/// Has answered the base OTs of every other party and waits for the answers to its own.
+struct OtResponses {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    receivers: BTreeMap<usize, ReceiverInitializer>,
+    senders: BTreeMap<usize, SenderInitializer>,
+    coin_share: CoinShare,
+    coin_commitments: BTreeMap<usize, Vec<u8>>,
+}
+
+/// Has sent its extended OTs and waits for the extended OTs of every other party.
+struct OtExtensions {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    coin: CoinResult,
+    session: SessionId,
+    senders: BTreeMap<usize, SenderInitializer>,
+    /// The authenticated bits, still without the keys of the other parties.
+    abits: Vec<AuthBit>,
+    check_share: CoinShare,
+}
+
+/// The authenticated bits, split up by their purpose.
+struct AuthBits {
+    wires: Vec<AuthBit>,
+    x: Vec<AuthBit>,
+    y: Vec<AuthBit>,
+    r: Vec<AuthBit>,
+    checks: Vec<AuthBit>,
+}
+
+/// Has sent the hashes of `Π_HaAND` and waits for those of every other party.
+struct HalfAnds {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    coin: CoinResult,
+    session: SessionId,
+    abits: AuthBits,
+    /// The XOR of the random bits of `Π_HaAND` with every other party.
+    random_bits: Vec<bool>,
+    check_share: CoinShare,
+    check_commitments: BTreeMap<usize, Vec<u8>>,
+}
+
+/// Has broadcast its corrections of the leaky AND triples and waits for those of every other
+/// party, together with their values for the checks.
+struct Corrections {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    coin: CoinResult,
+    session: SessionId,
+    abits: AuthBits,
+    corrections: Vec<bool>,
+    /// The own combinations of the consistency check, whose keys verify those of the others.
+    combinations: Vec<AuthBit>,
+    /// `Φ_i` of the check of `Π_LaAND`, for every leaky AND triple.
+    phi: Vec<MacType>,
+    transcript: blake3::Hasher,
+}
+
+/// A leaky or combined AND triple.
+#[derive(Clone)]
+struct Triple {
+    x: AuthBit,
+    y: AuthBit,
+    z: AuthBit,
+}
+
+/// Has committed to its value of the check of `Π_LaAND` and waits for the commitments and
+/// bucketing bits of every other party.
+struct Commitments {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    session: SessionId,
+    wires: Vec<AuthBit>,
+    triples: Vec<Triple>,
+    nonce: [u8; 32],
+    values: Vec<MacType>,
+    permutation: Vec<u32>,
+    bucketing: Vec<AuthBit>,
+    transcript: blake3::Hasher,
+}
+
+/// Has opened its value of the check of `Π_LaAND` and waits for the openings of every other
+/// party, together with their masked AND triple bits and their shares of the own input masks.
+struct Openings {
+    delta: Delta,
+    session: SessionId,
+    values: Vec<MacType>,
+    commitments: BTreeMap<usize, [u8; 32]>,
+    triples: Vec<Triple>,
+    /// The masks of all wires, indexed by gate.
+    masks: Vec<AuthBit>,
+    /// The labels for `false` of all wires, only used by the garbling parties.
+    labels: Vec<WireLabel>,
+    /// The masked bits of the AND triples, two for each AND gate.
+    and_bits: Vec<AuthBit>,
+    transcript: blake3::Hasher,
+}
+
+/// Has broadcast its masked input and waits for the masked inputs of every other party.
+struct MaskedInputs {
+    delta: Delta,
+    session: SessionId,
+    masks: Vec<AuthBit>,
+    /// The shares of the masks of the AND gates, `λ_α ∧ λ_β`, one for each AND gate.
+    and_masks: Vec<AuthBit>,
+    labels: Vec<WireLabel>,
+    masked_inputs: Vec<bool>,
+    transcript: blake3::Hasher,
+}
+
+/// Has sent the labels of all inputs to the evaluator, which waits for them.
+struct InputLabels {
+    delta: Delta,
+    session: SessionId,
+    masks: Vec<AuthBit>,
+    and_masks: Vec<AuthBit>,
+    labels: Vec<WireLabel>,
+    /// The masked values of all input gates, indexed by gate.
+    masked_values: Vec<Option<bool>>,
+    /// The garbled tables of every garbling party, as received by the evaluator.
+    tables: BTreeMap<usize, Vec<[MultipartyRow; 4]>>,
+    transcript: [u8; 32],
+}
+
+/// Has revealed the output to the garbling parties, which wait for it.
+struct RevealedOutput {
+    delta: Delta,
+    masks: Vec<AuthBit>,
+    labels: Vec<WireLabel>,
+    /// The masked values of the outputs, only known to the evaluator at this point.
+    masked_outputs: Vec<bool>,
+    transcript: [u8; 32],
+}
+
+/// Has disclosed its shares of the output masks and waits for those of every other party.
+struct OutputShares {
+    delta: Delta,
+    /// The masks of the outputs.
+    masks: Vec<AuthBit>,
+    masked_outputs: Vec<bool>,
+    transcript: [u8; 32],
+}
+
+impl<C: Borrow<Circuit>, I: Borrow<[bool]>> Party<C, I> {
+    /// Initializes the party with the specified `index` out of `parties`, returning the party and
+    /// the initial messages for every other party.
+    ///
+    /// Party 0 evaluates the circuit, all other parties garble it. The circuit must be valid for
+    /// the number of parties (see [`Circuit::validate_parties`]) and the input must contain a bit
+    /// for every [`Gate::InParty`] of the party.
+    pub fn new(
+        circuit: C,
+        parties: usize,
+        index: PartyIndex,
+        input: I,
+        rng: ChaCha20Rng,
+    ) -> Result<(Self, Msgs), Error> {
+        Self::with_statistical_security(
+            circuit,
+            parties,
+            index,
+            input,
+            StatisticalSecurity::default(),
+            rng,
+        )
+    }
+
+    /// Initializes the party with the specified [`StatisticalSecurity`], which must be the same for
+    /// all parties.
+    pub fn with_statistical_security(
+        circuit: C,
+        parties: usize,
+        index: PartyIndex,
+        input: I,
+        security: StatisticalSecurity,
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, Msgs), Error> {
+        if parties < 2 || parties > PartyIndex::MAX as usize + 1 || index as usize >= parties {
+            return Err(Error::InvalidParties);
+        }
+        circuit.borrow().validate_parties(parties)?;
+        circuit
+            .borrow()
+            .validate_party_input(index, input.borrow())?;
+
+        let setup = Setup {
+            index: index as usize,
+            parties,
+            security,
+            layout: Layout::of(circuit.borrow(), security),
+            context: multiparty_context(circuit.borrow(), parties),
+        };
+        let delta = Delta::gen_random(&mut rng);
+        let (coin_share, coin_commitment) = new_coin(&mut rng, &setup.context)?;
+        let mut receivers = BTreeMap::new();
+        let mut msgs = BTreeMap::new();
+        for j in setup.peers() {
+            let (receiver, init) = ReceiverInitializer::init(&mut rng);
+            receivers.insert(j, receiver);
+            let msg = OtInit {
+                ot_init: init.serialize(),
+                coin_commitment: coin_commitment.clone(),
+                security,
+            };
+            msgs.insert(j, msg);
+        }
+        let msgs = seal_all(Step::MultipartyOtInit, None, msgs)?;
+        let state = BaseOts {
+            rng,
+            delta,
+            receivers,
+            coin_share,
+        };
+        let party = Self {
+            state: Box::new(PartyState::BaseOts(state)),
+            circuit,
+            input,
+            setup,
+            session: None,
+        };
+        Ok((party, msgs))
+    }
+
+    /// Returns the number of steps before the output can be computed.
+    ///
+    /// Every step expects one message from every other party and returns one message for every
+    /// other party. The messages of the last step are passed to [`Party::output`].
+    pub fn steps(&self) -> u32 {
+        10
+    }
+
+    /// Returns the index of the party.
+    pub fn index(&self) -> PartyIndex {
+        self.setup.index as PartyIndex
+    }
+
+    /// Executes a single step, based on the messages of all other parties, returning the next state
+    /// and the messages for all other parties.
+    pub fn run(self, msgs: Msgs) -> Result<(Self, Msgs), Error> {
+        let step = msgs.first().and_then(|(_, msg)| message::step(msg));
+        self.run_step(msgs).map_err(|e| e.at_step(step))
+    }
+
+    fn run_step(self, msgs: Msgs) -> Result<(Self, Msgs), Error> {
+        use PartyState::*;
+
+        let Self {
+            state,
+            circuit,
+            input,
+            setup,
+            mut session,
+        } = self;
+        let c = circuit.borrow();
+        let (state, msgs) = match *state {
+            BaseOts(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyOtInit, session)?;
+                let (state, reply) = s.run(&setup, msgs)?;
+                let reply = seal_all(Step::MultipartyOtResponse, session, reply)?;
+                (OtResponses(state), reply)
+            }
+            OtResponses(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyOtResponse, session)?;
+                let (state, reply) = s.run(&setup, msgs)?;
+                session = Some(state.session);
+                let reply = seal_all(Step::MultipartyOtExtension, session, reply)?;
+                (OtExtensions(state), reply)
+            }
+            OtExtensions(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyOtExtension, session)?;
+                let (state, reply) = s.run(&setup, msgs)?;
+                let reply = seal_all(Step::MultipartyHalfAnds, session, reply)?;
+                (HalfAnds(state), reply)
+            }
+            HalfAnds(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyHalfAnds, session)?;
+                let (state, reply) = s.run(&setup, msgs)?;
+                let reply = seal_all(Step::MultipartyLeakyAndCorrections, session, reply)?;
+                (Corrections(state), reply)
+            }
+            Corrections(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyLeakyAndCorrections, session)?;
+                let (state, reply) = s.run(&setup, msgs)?;
+                let reply = seal_all(Step::MultipartyLeakyAndCommitments, session, reply)?;
+                (Commitments(state), reply)
+            }
+            Commitments(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyLeakyAndCommitments, session)?;
+                let (state, reply) = s.run(&setup, c, msgs)?;
+                let reply = seal_all(Step::MultipartyLeakyAndOpenings, session, reply)?;
+                (Openings(state), reply)
+            }
+            Openings(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyLeakyAndOpenings, session)?;
+                let (state, reply) = s.run(&setup, c, input.borrow(), msgs)?;
+                let reply = seal_all(Step::MultipartyMaskedInputs, session, reply)?;
+                (MaskedInputs(state), reply)
+            }
+            MaskedInputs(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyMaskedInputs, session)?;
+                let (state, reply) = s.run(&setup, c, msgs)?;
+                let reply = seal_all(Step::MultipartyInputLabels, session, reply)?;
+                (InputLabels(state), reply)
+            }
+            InputLabels(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyInputLabels, session)?;
+                let (state, reply) = s.run(&setup, c, msgs)?;
+                let reply = seal_all(Step::MultipartyRevealedOutput, session, reply)?;
+                (RevealedOutput(state), reply)
+            }
+            RevealedOutput(s) => {
+                let msgs = setup.open_all(msgs, Step::MultipartyRevealedOutput, session)?;
+                let (state, reply) = s.run(&setup, c, msgs)?;
+                let reply = seal_all(Step::MultipartyOutputShares, session, reply)?;
+                (OutputShares(state), reply)
+            }
+            OutputShares(_) => return Err(Error::ProtocolEnded),
+        };
+        let party = Self {
+            state: Box::new(state),
+            circuit,
+            input,
+            setup,
+            session,
+        };
+        Ok((party, msgs))
+    }
+
+    /// Returns the output of the circuit, based on the messages of the last step of all other
+    /// parties.
+    pub fn output(self, msgs: Msgs) -> Result<Vec<bool>, Error> {
+        match *self.state {
+            PartyState::OutputShares(s) => {
+                let step = Some(Step::MultipartyOutputShares);
+                let msgs = self
+                    .setup
+                    .open_all(msgs, Step::MultipartyOutputShares, self.session)
+                    .map_err(|e| e.at_step(step))?;
+                s.output(msgs).map_err(|e| e.at_step(step))
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+}
+
+impl Setup {
+    /// The indices of all other parties.
+    fn peers(&self) -> impl Iterator<Item = usize> {
+        let index = self.index;
+        (0..self.parties).filter(move |j| *j != index)
+    }
+
+    fn is_evaluator(&self) -> bool {
+        self.index == EVALUATOR
+    }
+
+    /// Opens the messages of all other parties, which must contain exactly one message of every
+    /// other party.
+    fn open_all<T: DeserializeOwned>(
+        &self,
+        msgs: Msgs,
+        step: Step,
+        session: Option<SessionId>,
+    ) -> Result<BTreeMap<usize, T>, Error> {
+        let mut opened = BTreeMap::new();
+        for (from, msg) in msgs {
+            let party = from as usize;
+            if party >= self.parties || party == self.index || opened.contains_key(&party) {
+                return Err(Error::UnexpectedMessageType {
+                    step: None,
+                    fault: MessageFault::Sender(from),
+                });
+            }
+            opened.insert(party, message::open(&msg, step, session)?);
+        }
+        if opened.len() != self.parties - 1 {
+            return Err(Error::unexpected_length(self.parties - 1, opened.len()));
+        }
+        Ok(opened)
+    }
+}
+
+fn seal_all<T: Serialize>(
+    step: Step,
+    session: Option<SessionId>,
+    payloads: BTreeMap<usize, T>,
+) -> Result<Msgs, Error> {
+    payloads
+        .into_iter()
+        .map(|(to, payload)| Ok((to as PartyIndex, message::seal(step, session, &payload)?)))
+        .collect()
+}
+
+fn expect_length(expected: usize, received: usize) -> Result<(), Error> {
+    if expected == received {
+        Ok(())
+    } else {
+        Err(Error::unexpected_length(expected, received))
+    }
+}
+
+type StepResult<S, T> = Result<(S, BTreeMap<usize, T>), Error>;
+
+impl BaseOts {
+    /// Answers the base OTs of every other party and opens the own share of the coin.
+    fn run(
+        mut self,
+        setup: &Setup,
+        msgs: BTreeMap<usize, OtInit>,
+    ) -> StepResult<OtResponses, OtResponse> {
+        let coin = cointossing::serialize(&self.coin_share)?;
+        let mut senders = BTreeMap::new();
+        let mut coin_commitments = BTreeMap::new();
+        let mut replies = BTreeMap::new();
+        for (j, msg) in msgs {
+            if msg.security != setup.security {
+                return Err(Error::StatisticalSecurityMismatch);
+            }
+            let ot_init = msg.ot_init.deserialize()?;
+            let (sender, reply) =
+                SenderInitializer::init(&mut self.rng, self.delta.clone(), &ot_init);
+            senders.insert(j, sender);
+            coin_commitments.insert(j, msg.coin_commitment);
+            let reply = OtResponse {
+                ot_init: reply.serialize(),
+                coin: coin.clone(),
+            };
+            replies.insert(j, reply);
+        }
+        let state = OtResponses {
+            rng: self.rng,
+            delta: self.delta,
+            receivers: self.receivers,
+            senders,
+            coin_share: self.coin_share,
+            coin_commitments,
+        };
+        Ok((state, replies))
+    }
+}
+
+impl OtResponses {
+    /// Derives the session from the coin, completes the own base OTs and extends them, using the
+    /// same random bits with every other party.
+    fn run(
+        mut self,
+        setup: &Setup,
+        msgs: BTreeMap<usize, OtResponse>,
+    ) -> StepResult<OtExtensions, MultipartyOtExtension> {
+        let mut upstream = Vec::with_capacity(msgs.len());
+        let mut receivers = BTreeMap::new();
+        let mut replies = BTreeMap::new();
+        for (j, msg) in msgs {
+            let commitment = self.coin_commitments.remove(&j).unwrap_or_default();
+            upstream.push((commitment, msg.coin));
+            let receiver = &self.receivers[&j];
+            let (receiver, reply) = receiver.recv(&msg.ot_init.deserialize()?);
+            receivers.insert(j, receiver);
+            replies.insert(j, reply.serialize());
+        }
+        let coin = cointossing::finish_all(self.coin_share, &setup.context, upstream)?;
+        let session = SessionId::new(&setup.context, &coin);
+
+        let blocks = setup.layout.blocks;
+        let mut abits = vec![AuthBit::new(setup.parties); blocks * BLOCK_SIZE];
+        let mut ot_blocks: BTreeMap<usize, Vec<Vec<MacType>>> = BTreeMap::new();
+        for block_id in 0..blocks {
+            let bits: u128 = self.rng.next_u64() as u128 | ((self.rng.next_u64() as u128) << 64);
+            let abits = &mut abits[block_id * BLOCK_SIZE..(block_id + 1) * BLOCK_SIZE];
+            for (i, abit) in abits.iter_mut().enumerate() {
+                abit.bit = bits & (1 << i) != 0;
+            }
+            for (j, receiver) in receivers.iter_mut() {
+                let mut macs_out = [MacType(0); BLOCK_SIZE];
+                let mut ot_out = Box::new([MacType(0); BLOCK_SIZE]);
+                receiver.new_batch(bits, &mut macs_out, &mut ot_out[0..]);
+                for (abit, mac) in abits.iter_mut().zip(macs_out) {
+                    abit.macs[*j] = mac;
+                }
+                ot_blocks.entry(*j).or_default().push(ot_out.to_vec());
+            }
+        }
+
+        // the bits are fixed by the extended OTs, the coin of their consistency check is tossed
+        // only now:
+        let (check_share, coin_commitment) = new_coin(&mut self.rng, session.as_bytes())?;
+        let replies = replies
+            .into_iter()
+            .map(|(j, reply)| {
+                let msg = MultipartyOtExtension {
+                    reply,
+                    blocks: ot_blocks.remove(&j).unwrap_or_default(),
+                    coin_commitment: coin_commitment.clone(),
+                };
+                (j, msg)
+            })
+            .collect();
+        let state = OtExtensions {
+            rng: self.rng,
+            delta: self.delta,
+            coin,
+            session,
+            senders: self.senders,
+            abits,
+            check_share,
+        };
+        Ok((state, replies))
+    }
+}
+
+impl OtExtensions {
+    /// Derives the keys of the bits of every other party from their extended OTs and starts
+    /// `Π_HaAND` with every other party.
+    fn run(
+        mut self,
+        setup: &Setup,
+        msgs: BTreeMap<usize, MultipartyOtExtension>,
+    ) -> StepResult<HalfAnds, MultipartyHalfAnds> {
+        let mut check_commitments = BTreeMap::new();
+        for (j, msg) in msgs {
+            let sender = self
+                .senders
+                .remove(&j)
+                .ok_or(Error::unexpected_length(setup.parties - 1, j))?;
+            let mut sender: LeakyOtSender = sender.recv(&OtInitReply::deserialize(msg.reply)?);
+            expect_length(setup.layout.blocks, msg.blocks.len())?;
+            for (block_id, block) in msg.blocks.into_iter().enumerate() {
+                let ot_rx: [MacType; BLOCK_SIZE] = block
+                    .try_into()
+                    .map_err(|_| Error::OtBlockDeserializationError)?;
+                let mut keys_out = [MacType(0); BLOCK_SIZE];
+                sender.send(&ot_rx, &mut keys_out);
+                let abits = &mut self.abits[block_id * BLOCK_SIZE..];
+                for (abit, key) in abits.iter_mut().zip(keys_out) {
+                    abit.keys[j] = KeyType(key.0);
+                }
+            }
+            check_commitments.insert(j, msg.coin_commitment);
+        }
+
+        let layout = setup.layout;
+        let mut abits = self.abits.into_iter();
+        let mut take = |n: usize| abits.by_ref().take(n).collect::<Vec<_>>();
+        let abits = AuthBits {
+            wires: take(layout.wires),
+            x: take(layout.leaky_ands),
+            y: take(layout.leaky_ands),
+            r: take(layout.leaky_ands),
+            checks: take(layout.checks),
+        };
+
+        // `Π_HaAND`, as the party that knows the keys of the other party's `x`:
+        let half_and = self.session.key(domain::HALF_AND);
+        let coin = cointossing::serialize(&self.check_share)?;
+        let mut random_bits = vec![false; layout.leaky_ands];
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let mut hashes = Vec::with_capacity(layout.leaky_ands);
+            for (t, (x, y)) in abits.x.iter().zip(abits.y.iter()).enumerate() {
+                let s = random_bit(&mut self.rng);
+                random_bits[t] ^= s;
+                hashes.push(half_and_hashes(&half_and, &self.delta, x.keys[j], s, y.bit));
+            }
+            let msg = MultipartyHalfAnds {
+                hashes,
+                coin: coin.clone(),
+            };
+            replies.insert(j, msg);
+        }
+        let state = HalfAnds {
+            rng: self.rng,
+            delta: self.delta,
+            coin: self.coin,
+            session: self.session,
+            abits,
+            random_bits,
+            check_share: self.check_share,
+            check_commitments,
+        };
+        Ok((state, replies))
+    }
+}
+
+impl HalfAnds {
+    /// Completes `Π_HaAND`, broadcasts the corrections of the leaky AND triples and sends the
+    /// values of the consistency check and of the check of `Π_LaAND`.
+    fn run(
+        mut self,
+        setup: &Setup,
+        msgs: BTreeMap<usize, MultipartyHalfAnds>,
+    ) -> StepResult<Corrections, MultipartyCorrections> {
+        let half_and = self.session.key(domain::HALF_AND);
+        let mut upstream = Vec::with_capacity(msgs.len());
+        let mut cross_terms = self.random_bits;
+        for (j, msg) in msgs {
+            let commitment = self.check_commitments.remove(&j).unwrap_or_default();
+            upstream.push((commitment, msg.coin));
+            expect_length(cross_terms.len(), msg.hashes.len())?;
+            for ((v, x), hashes) in cross_terms
+                .iter_mut()
+                .zip(self.abits.x.iter())
+                .zip(msg.hashes.iter())
+            {
+                *v ^= derive_half_and_share(&half_and, x.bit, x.macs[j], hashes);
+            }
+        }
+        let check_coin =
+            cointossing::finish_all(self.check_share, self.session.as_bytes(), upstream)?;
+
+        let AuthBits { x, y, r, .. } = &self.abits;
+        let corrections: Vec<bool> = (0..cross_terms.len())
+            .map(|t| (x[t].bit & y[t].bit) ^ cross_terms[t] ^ r[t].bit)
+            .collect();
+
+        let combinations = consistency_combinations(check_coin, &self.abits);
+
+        // `Φ_i` of the check of `Π_LaAND` and the hashes that allow the other parties to learn
+        // `x^j · Φ_i` without learning `Φ_i`:
+        let leaky_and = self.session.key(domain::LEAKY_AND);
+        let phi: Vec<MacType> = y
+            .iter()
+            .map(|y| {
+                let mut phi = MacType(mac(y.bit, &self.delta));
+                for j in setup.peers() {
+                    phi = phi ^ y.macs[j] ^ y.keys[j].0;
+                }
+                phi
+            })
+            .collect();
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let u = x
+                .iter()
+                .zip(phi.iter())
+                .map(|(x, phi)| {
+                    let k = x.keys[j];
+                    hash_key(&leaky_and, k) ^ hash_key(&leaky_and, k ^ self.delta.0) ^ *phi
+                })
+                .collect();
+            let consistency = OpenedBits {
+                bits: combinations.iter().map(|c| c.bit).collect(),
+                macs: combinations.iter().map(|c| c.macs[j]).collect(),
+            };
+            let msg = MultipartyCorrections {
+                corrections: corrections.clone(),
+                consistency,
+                u,
+            };
+            replies.insert(j, msg);
+        }
+        let state = Corrections {
+            rng: self.rng,
+            delta: self.delta,
+            coin: self.coin,
+            session: self.session,
+            abits: self.abits,
+            corrections,
+            combinations,
+            phi,
+            transcript: blake3::Hasher::new_derive_key(domain::BROADCAST),
+        };
+        Ok((state, replies))
+    }
+}
+
+/// Computes the random combinations of all authenticated bits (except for the random bits that
+/// hide the combinations) that check that every party authenticated the same bits to all other
+/// parties.
+///
+/// A party that authenticated different bits to two parties passes each combination with a
+/// probability of 1/2, since the combinations are only known after all bits have been fixed.
+fn consistency_combinations(coin: CoinResult, abits: &AuthBits) -> Vec<AuthBit> {
+    let mut rng = ChaCha20Rng::from_seed(coin);
+    let mut combinations = Vec::with_capacity(abits.checks.len());
+    for check in abits.checks.iter() {
+        let mut combination = check.clone();
+        let all = abits
+            .wires
+            .iter()
+            .chain(abits.x.iter())
+            .chain(abits.y.iter())
+            .chain(abits.r.iter());
+        for abit in all {
+            if random_bit(&mut rng) {
+                combination.xor_assign(abit);
+            }
+        }
+        combinations.push(combination);
+    }
+    combinations
+}
+
+impl Corrections {
+    /// Verifies the consistency check, fixes the leaky AND triples, commits to the value of the
+    /// check of `Π_LaAND` and starts the bucketing.
+    fn run(
+        mut self,
+        setup: &Setup,
+        msgs: BTreeMap<usize, MultipartyCorrections>,
+    ) -> StepResult<Commitments, MultipartyCommitment> {
+        let layout = setup.layout;
+        let mut checks = MacChecks::new(MacCheck::ShareConsistency);
+        let mut corrections = self.corrections.clone();
+        for (j, msg) in msgs.iter() {
+            let consistency = &msg.consistency;
+            expect_length(self.combinations.len(), consistency.bits.len())?;
+            expect_length(self.combinations.len(), consistency.macs.len())?;
+            for (c, combination) in self.combinations.iter().enumerate() {
+                let opened = PartialBitShare {
+                    bit: consistency.bits[c],
+                    mac: consistency.macs[c],
+                };
+                checks.verify(c, combination.verify(*j, opened, &self.delta));
+            }
+            expect_length(layout.leaky_ands, msg.corrections.len())?;
+            expect_length(layout.leaky_ands, msg.u.len())?;
+            for (e, correction) in corrections.iter_mut().zip(msg.corrections.iter()) {
+                *e ^= *correction;
+            }
+        }
+        checks.finish()?;
+        for party in 0..setup.parties {
+            let own = &self.corrections;
+            let bits = msgs.get(&party).map_or(own, |msg| &msg.corrections);
+            self.transcript.update(&bits_to_bytes(bits));
+        }
+
+        // `⟨z⟩ = ⟨r⟩ ⊕ e`, with `e` the XOR of all corrections:
+        let AuthBits { x, y, r, .. } = &self.abits;
+        let z: Vec<AuthBit> = r
+            .iter()
+            .zip(corrections)
+            .map(|(r, e)| {
+                let mut z = r.clone();
+                z.xor_public(e, setup, &self.delta);
+                z
+            })
+            .collect();
+
+        // the check of `Π_LaAND`: the values of all parties XOR to `(x ∧ y ⊕ z) · Δ`, where
+        // `Δ` is the XOR of the deltas of all parties, and thus to 0 for correct triples
+        let leaky_and = self.session.key(domain::LEAKY_AND);
+        let values: Vec<MacType> = (0..layout.leaky_ands)
+            .map(|t| {
+                let (x, z) = (&x[t], &z[t]);
+                let mut value = MacType(if x.bit { self.phi[t].0 } else { 0 });
+                value = value ^ mac(z.bit, &self.delta);
+                for j in setup.peers() {
+                    value =
+                        value ^ hash_key(&leaky_and, x.keys[j]) ^ hash::hash(&leaky_and, x.macs[j]);
+                    if x.bit {
+                        value = value ^ msgs[&j].u[t];
+                    }
+                    value = value ^ z.keys[j].0 ^ z.macs[j];
+                }
+                value
+            })
+            .collect();
+        let mut nonce = [0u8; 32];
+        self.rng.fill_bytes(&mut nonce);
+        let commitment = hash::commitment(
+            &self.session.key(domain::LEAKY_AND_CHECK),
+            setup.index as PartyIndex,
+            &nonce,
+            &values,
+        );
+
+        let triples: Vec<Triple> = x
+            .iter()
+            .zip(y.iter())
+            .zip(z)
+            .map(|((x, y), z)| Triple {
+                x: x.clone(),
+                y: y.clone(),
+                z,
+            })
+            .collect();
+
+        // the bucketing, revealing the XOR of `y` of the first triple with every other triple:
+        let bucket_size = layout.bucket_size;
+        let ands = layout.leaky_ands / bucket_size;
+        let permutation = new_permutation(ChaCha20Rng::from_seed(self.coin), layout.leaky_ands);
+        let mut bucketing = Vec::with_capacity(ands * (bucket_size - 1));
+        for bucket in permutation.chunks(bucket_size) {
+            let first = &triples[bucket[0] as usize];
+            for other in &bucket[1..] {
+                bucketing.push(first.y.xor(&triples[*other as usize].y));
+            }
+        }
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let msg = MultipartyCommitment {
+                commitment,
+                bucketing: OpenedBits {
+                    bits: bucketing.iter().map(|d| d.bit).collect(),
+                    macs: bucketing.iter().map(|d| d.macs[j]).collect(),
+                },
+            };
+            replies.insert(j, msg);
+        }
+        let state = Commitments {
+            rng: self.rng,
+            delta: self.delta,
+            session: self.session,
+            wires: self.abits.wires,
+            triples,
+            nonce,
+            values,
+            permutation,
+            bucketing,
+            transcript: self.transcript,
+        };
+        Ok((state, replies))
+    }
+}
+
+fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.iter().map(|b| *b as u8).collect()
+}
+
+/// Opens the own shares of authenticated bits to a party.
+fn open_to(bits: &[AuthBit], party: usize) -> OpenedBits {
+    OpenedBits {
+        bits: bits.iter().map(|b| b.bit).collect(),
+        macs: bits.iter().map(|b| b.macs[party]).collect(),
+    }
+}
+
+/// Verifies the authenticated bits opened by all other parties, returning the opened values.
+fn verify_openings<'a>(
+    own: &[AuthBit],
+    openings: impl Iterator<Item = (usize, &'a OpenedBits)>,
+    check: MacCheck,
+    delta: &Delta,
+    index: impl Fn(usize) -> usize,
+) -> Result<Vec<bool>, Error> {
+    let mut checks = MacChecks::new(check);
+    let mut values: Vec<bool> = own.iter().map(|b| b.bit).collect();
+    for (j, opened) in openings {
+        expect_length(own.len(), opened.bits.len())?;
+        expect_length(own.len(), opened.macs.len())?;
+        for (i, own) in own.iter().enumerate() {
+            let share = PartialBitShare {
+                bit: opened.bits[i],
+                mac: opened.macs[i],
+            };
+            checks.verify(index(i), own.verify(j, share, delta));
+            values[i] ^= share.bit;
+        }
+    }
+    checks.finish()?;
+    Ok(values)
+}
+
+impl Commitments {
+    /// Combines the leaky AND triples of every bucket, assigns the masks and labels of the wires
+    /// and opens the own value of the check of `Π_LaAND`, the masked AND triple bits and the
+    /// shares of the masks of the other parties' inputs.
+    fn run(
+        mut self,
+        setup: &Setup,
+        circuit: &Circuit,
+        msgs: BTreeMap<usize, MultipartyCommitment>,
+    ) -> StepResult<Openings, MultipartyOpenings> {
+        let bucket_size = setup.layout.bucket_size;
+        let openings = msgs.iter().map(|(j, msg)| (*j, &msg.bucketing));
+        let per_bucket = bucket_size - 1;
+        let bucketing = verify_openings(
+            &self.bucketing,
+            openings,
+            MacCheck::Bucketing,
+            &self.delta,
+            |i| i / per_bucket,
+        )?;
+        let commitments = msgs
+            .into_iter()
+            .map(|(j, msg)| (j, msg.commitment))
+            .collect();
+
+        // `(x ⊕ x') ∧ y = z ⊕ z' ⊕ (y ⊕ y') ∧ x'` for the triples `(x, y, z)` and
+        // `(x', y', z')`:
+        let mut triples = Vec::with_capacity(circuit.and_gates());
+        for (b, bucket) in self.permutation.chunks(bucket_size).enumerate() {
+            let mut triple = self.triples[bucket[0] as usize].clone();
+            for (k, other) in bucket[1..].iter().enumerate() {
+                let other = &self.triples[*other as usize];
+                triple.x.xor_assign(&other.x);
+                triple.z.xor_assign(&other.z);
+                if bucketing[b * per_bucket + k] {
+                    triple.z.xor_assign(&other.x);
+                }
+            }
+            triples.push(triple);
+        }
+
+        // the masks of the input and AND gates are the preprocessed authenticated bits, the labels
+        // of the garbling parties are random, with free XOR and NOT gates:
+        let gates = circuit.gates();
+        let mut wires = self.wires.into_iter();
+        let mut masks: Vec<AuthBit> = Vec::with_capacity(gates.len());
+        let mut labels: Vec<WireLabel> = Vec::new();
+        for gate in gates.iter() {
+            let (mask, label) = match *gate {
+                Gate::InParty(_) | Gate::And(_, _) => {
+                    let mask = wires.next().ok_or(Error::InsufficientInput)?;
+                    (mask, random_label(&mut self.rng))
+                }
+                Gate::Xor(x, y) => {
+                    let mask = masks[x as usize].xor(&masks[y as usize]);
+                    let label = if setup.is_evaluator() {
+                        WireLabel::default()
+                    } else {
+                        labels[x as usize].xor(&labels[y as usize])
+                    };
+                    (mask, label)
+                }
+                Gate::Not(x) => {
+                    let label = if setup.is_evaluator() {
+                        WireLabel::default()
+                    } else {
+                        labels[x as usize].xor(&WireLabel(self.delta.0))
+                    };
+                    (masks[x as usize].clone(), label)
+                }
+                Gate::InContrib | Gate::InEval => return Err(Error::InvalidCircuit),
+            };
+            masks.push(mask);
+            if !setup.is_evaluator() {
+                labels.push(label);
+            }
+        }
+
+        // the masked bits of the triples, which turn them into the AND triples of the gates:
+        let mut and_bits = Vec::with_capacity(triples.len() * 2);
+        let and_gates = gates.iter().filter_map(|g| match *g {
+            Gate::And(x, y) => Some((x, y)),
+            _ => None,
+        });
+        for ((x, y), triple) in and_gates.zip(triples.iter()) {
+            and_bits.push(masks[x as usize].xor(&triple.x));
+            and_bits.push(masks[y as usize].xor(&triple.y));
+        }
+
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let input_masks = gates
+                .iter()
+                .zip(masks.iter())
+                .filter(|(g, _)| **g == Gate::InParty(j as PartyIndex))
+                .map(|(_, mask)| mask.opened(j))
+                .collect();
+            let msg = MultipartyOpenings {
+                nonce: self.nonce,
+                values: self.values.clone(),
+                and_bits: open_to(&and_bits, j),
+                input_masks,
+            };
+            replies.insert(j, msg);
+        }
+        let state = Openings {
+            delta: self.delta,
+            session: self.session,
+            values: self.values,
+            commitments,
+            triples,
+            masks,
+            labels,
+            and_bits,
+            transcript: self.transcript,
+        };
+        Ok((state, replies))
+    }
+}
+
+impl Openings {
+    /// Verifies the check of `Π_LaAND`, derives the masks of the AND gates, broadcasts the masked
+    /// input and, if garbling, sends the garbled tables to the evaluator.
+    fn run(
+        self,
+        setup: &Setup,
+        circuit: &Circuit,
+        input: &[bool],
+        msgs: BTreeMap<usize, MultipartyOpenings>,
+    ) -> StepResult<MaskedInputs, MultipartyMaskedInputs> {
+        let check = self.session.key(domain::LEAKY_AND_CHECK);
+        let mut sums = self.values;
+        for (j, msg) in msgs.iter() {
+            expect_length(sums.len(), msg.values.len())?;
+            let commitment = hash::commitment(&check, *j as PartyIndex, &msg.nonce, &msg.values);
+            if commitment != self.commitments[j] {
+                return Err(Error::LeakyAndNotEqual {
+                    step: None,
+                    failed: sums.len(),
+                    index: 0,
+                });
+            }
+            for (sum, value) in sums.iter_mut().zip(msg.values.iter()) {
+                *sum = *sum ^ *value;
+            }
+        }
+        let mut failed = sums.iter().enumerate().filter(|(_, sum)| sum.0 != 0);
+        if let Some((index, _)) = failed.next() {
+            return Err(Error::LeakyAndNotEqual {
+                step: None,
+                failed: failed.count() + 1,
+                index,
+            });
+        }
+
+        let gates = circuit.gates();
+        let and_gates: Vec<usize> = (0..gates.len()).filter(|i| gates[*i].is_and()).collect();
+        let openings = msgs.iter().map(|(j, msg)| (*j, &msg.and_bits));
+        let and_bits = verify_openings(
+            &self.and_bits,
+            openings,
+            MacCheck::MaskedAndBits,
+            &self.delta,
+            |i| and_gates[i / 2],
+        )?;
+
+        // `λ_α ∧ λ_β = z ⊕ d_α · y ⊕ d_β · x ⊕ d_α · d_β` for the masked bits
+        // `d_α = λ_α ⊕ x` and `d_β = λ_β ⊕ y`:
+        let mut and_masks = Vec::with_capacity(self.triples.len());
+        for (k, triple) in self.triples.iter().enumerate() {
+            let (d_x, d_y) = (and_bits[2 * k], and_bits[2 * k + 1]);
+            let mut mask = triple.z.xor_if(&triple.y, d_x).xor_if(&triple.x, d_y);
+            mask.xor_public(d_x & d_y, setup, &self.delta);
+            and_masks.push(mask);
+        }
+
+        // the own input, masked by the XOR of the mask shares of all parties:
+        let own_inputs: Vec<usize> = (0..gates.len())
+            .filter(|i| gates[*i] == Gate::InParty(setup.index as PartyIndex))
+            .collect();
+        let mut checks = MacChecks::new(MacCheck::InputMask);
+        let mut masked_inputs = Vec::with_capacity(own_inputs.len());
+        for (w, (gate, bit)) in own_inputs.iter().zip(input.iter()).enumerate() {
+            let mask = &self.masks[*gate];
+            let mut masked = mask.bit ^ bit;
+            for (j, msg) in msgs.iter() {
+                expect_length(own_inputs.len(), msg.input_masks.len())?;
+                let share = msg.input_masks[w];
+                checks.verify(*gate, mask.verify(*j, share, &self.delta));
+                masked ^= share.bit;
+            }
+            masked_inputs.push(masked);
+        }
+        checks.finish()?;
+
+        let tables = if setup.is_evaluator() {
+            vec![]
+        } else {
+            let garbling = self.session.key(domain::MULTIPARTY_GARBLING);
+            let shares = Shares {
+                setup,
+                delta: &self.delta,
+                masks: &self.masks,
+                gates,
+            };
+            let ands = and_gates.iter().zip(and_masks.iter());
+            ands.map(|(gate, and_mask)| shares.garble_and(&garbling, &self.labels, *gate, and_mask))
+                .collect()
+        };
+
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let msg = MultipartyMaskedInputs {
+                masked_inputs: masked_inputs.clone(),
+                tables: if j == EVALUATOR {
+                    tables.clone()
+                } else {
+                    vec![]
+                },
+            };
+            replies.insert(j, msg);
+        }
+        let state = MaskedInputs {
+            delta: self.delta,
+            session: self.session,
+            masks: self.masks,
+            and_masks,
+            labels: self.labels,
+            masked_inputs,
+            transcript: self.transcript,
+        };
+        Ok((state, replies))
+    }
+}
+
+/// The own shares of the wire masks, from which the rows of the AND gates are derived.
+struct Shares<'a> {
+    setup: &'a Setup,
+    delta: &'a Delta,
+    masks: &'a [AuthBit],
+    gates: &'a [Gate],
+}
+
+impl Shares<'_> {
+    /// The own share of `r = λ_γ ⊕ (λ_α ⊕ a) ∧ (λ_β ⊕ b)` of the row `(a, b)` of an
+    /// AND gate, which is the masked value of the gate if `a` and `b` are the masked values of
+    /// its inputs.
+    fn row(&self, gate: usize, and_mask: &AuthBit, a: bool, b: bool) -> AuthBit {
+        let (x, y) = match self.gates[gate] {
+            Gate::And(x, y) => (x as usize, y as usize),
+            _ => unreachable!("rows are only derived for AND gates"),
+        };
+        let mut r = self.masks[gate]
+            .xor(and_mask)
+            .xor_if(&self.masks[y], a)
+            .xor_if(&self.masks[x], b);
+        r.xor_public(a & b, self.setup, self.delta);
+        r
+    }
+
+    /// Garbles the own share of an AND gate.
+    ///
+    /// Every row encrypts the own share of `r`, authenticated to every other party, under the
+    /// own labels of the row. Instead of the own MAC, the row contains the own label of the
+    /// output, offset by the keys of the other shares of `r` and `r · Δ`, which the evaluator
+    /// can only remove with the MACs of these shares.
+    fn garble_and(
+        &self,
+        domain: &DomainKey,
+        labels: &[WireLabel],
+        gate: usize,
+        and_mask: &AuthBit,
+    ) -> [MultipartyRow; 4] {
+        let (x, y) = match self.gates[gate] {
+            Gate::And(x, y) => (x as usize, y as usize),
+            _ => unreachable!("only AND gates are garbled"),
+        };
+        let index = self.setup.index;
+        let label_of = |wire: usize, bit: bool| WireLabel(labels[wire].0 ^ mac(bit, self.delta));
+        let garble_row = |row: u8| {
+            let (a, b) = (row & 2 != 0, row & 1 != 0);
+            let r = self.row(gate, and_mask, a, b);
+            let mut values = r.macs.clone();
+            let mut label = labels[gate].0 ^ mac(r.bit, self.delta);
+            for j in self.setup.peers() {
+                label ^= r.keys[j].0;
+            }
+            values[index] = MacType(label);
+            let (pad, pad_bit) = garbling_hash::row(
+                domain,
+                &label_of(x, a),
+                &label_of(y, b),
+                gate as GateIndex,
+                row,
+                self.setup.parties,
+            );
+            let values = values.into_iter().zip(pad).map(|(v, p)| v ^ p).collect();
+            (values, r.bit ^ pad_bit)
+        };
+        [garble_row(0), garble_row(1), garble_row(2), garble_row(3)]
+    }
+}
+
+impl MaskedInputs {
+    /// Collects the masked inputs of all parties and, if garbling, sends the own labels of all
+    /// inputs to the evaluator.
+    fn run(
+        self,
+        setup: &Setup,
+        circuit: &Circuit,
+        mut msgs: BTreeMap<usize, MultipartyMaskedInputs>,
+    ) -> StepResult<InputLabels, MultipartyInputLabels> {
+        let mut inputs = BTreeMap::new();
+        let mut tables = BTreeMap::new();
+        for (j, msg) in msgs.iter_mut() {
+            expect_length(
+                circuit.party_inputs(*j as PartyIndex),
+                msg.masked_inputs.len(),
+            )?;
+            let expected = if setup.is_evaluator() {
+                circuit.and_gates()
+            } else {
+                0
+            };
+            expect_length(expected, msg.tables.len())?;
+            inputs.insert(*j, msg.masked_inputs.iter());
+            if setup.is_evaluator() {
+                tables.insert(*j, std::mem::take(&mut msg.tables));
+            }
+        }
+        inputs.insert(setup.index, self.masked_inputs.iter());
+
+        let mut masked_values = vec![None; circuit.gates().len()];
+        for (w, gate) in circuit.gates().iter().enumerate() {
+            if let Gate::InParty(p) = gate {
+                let masked = inputs.get_mut(&(*p as usize)).and_then(|i| i.next());
+                masked_values[w] = Some(*masked.ok_or(Error::InsufficientInput)?);
+            }
+        }
+
+        let mut transcript = self.transcript;
+        for party in 0..setup.parties {
+            let bits = match msgs.get(&party) {
+                Some(msg) => &msg.masked_inputs,
+                None => &self.masked_inputs,
+            };
+            transcript.update(&bits_to_bytes(bits));
+        }
+
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let labels = if j == EVALUATOR {
+                masked_values
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(w, masked)| {
+                        masked.map(|masked| WireLabel(self.labels[w].0 ^ mac(masked, &self.delta)))
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            replies.insert(j, MultipartyInputLabels { labels });
+        }
+        let state = InputLabels {
+            delta: self.delta,
+            session: self.session,
+            masks: self.masks,
+            and_masks: self.and_masks,
+            labels: self.labels,
+            masked_values,
+            tables,
+            transcript: *transcript.finalize().as_bytes(),
+        };
+        Ok((state, replies))
+    }
+}
+
+impl InputLabels {
+    /// Evaluates the garbled circuit, if evaluating, and reveals the masked output, together with
+    /// its label, to every garbling party.
+    fn run(
+        self,
+        setup: &Setup,
+        circuit: &Circuit,
+        msgs: BTreeMap<usize, MultipartyInputLabels>,
+    ) -> StepResult<RevealedOutput, MultipartyOutput> {
+        let output_masks = |masks: &[AuthBit]| -> Vec<AuthBit> {
+            let outputs = circuit.output_gates().iter();
+            outputs.map(|o| masks[*o as usize].clone()).collect()
+        };
+        if !setup.is_evaluator() {
+            let replies = setup
+                .peers()
+                .map(|j| (j, MultipartyOutput { outputs: vec![] }))
+                .collect();
+            let state = RevealedOutput {
+                delta: self.delta,
+                masks: output_masks(&self.masks),
+                labels: self.labels,
+                masked_outputs: vec![],
+                transcript: self.transcript,
+            };
+            return Ok((state, replies));
+        }
+
+        let (values, labels) = self.evaluate(setup, circuit, msgs)?;
+        let masked_outputs: Vec<bool> = circuit
+            .output_gates()
+            .iter()
+            .map(|o| values[*o as usize])
+            .collect();
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let outputs = circuit
+                .output_gates()
+                .iter()
+                .map(|o| (labels[*o as usize][j].clone(), values[*o as usize]))
+                .collect();
+            replies.insert(j, MultipartyOutput { outputs });
+        }
+        let state = RevealedOutput {
+            delta: self.delta,
+            masks: output_masks(&self.masks),
+            labels: vec![],
+            masked_outputs,
+            transcript: self.transcript,
+        };
+        Ok((state, replies))
+    }
+
+    /// Evaluates the garbled circuit, returning the masked value of every wire and its label of
+    /// every garbling party.
+    #[allow(clippy::type_complexity)]
+    fn evaluate(
+        &self,
+        setup: &Setup,
+        circuit: &Circuit,
+        msgs: BTreeMap<usize, MultipartyInputLabels>,
+    ) -> Result<(Vec<bool>, Vec<Vec<WireLabel>>), Error> {
+        let gates = circuit.gates();
+        let inputs = self.masked_values.iter().filter(|v| v.is_some()).count();
+        let mut input_labels = BTreeMap::new();
+        for (j, msg) in msgs {
+            expect_length(inputs, msg.labels.len())?;
+            input_labels.insert(j, msg.labels.into_iter());
+        }
+
+        let garbling = self.session.key(domain::MULTIPARTY_GARBLING);
+        let shares = Shares {
+            setup,
+            delta: &self.delta,
+            masks: &self.masks,
+            gates,
+        };
+        let mut checks = MacChecks::new(MacCheck::AndTable);
+        let mut values: Vec<bool> = Vec::with_capacity(gates.len());
+        let mut labels: Vec<Vec<WireLabel>> = Vec::with_capacity(gates.len());
+        let mut and_num = 0;
+        for (w, gate) in gates.iter().enumerate() {
+            let (value, label) = match *gate {
+                Gate::InParty(_) => {
+                    let mut label = vec![WireLabel::default(); setup.parties];
+                    for (j, input_labels) in input_labels.iter_mut() {
+                        label[*j] = input_labels.next().ok_or(Error::InsufficientInput)?;
+                    }
+                    (
+                        self.masked_values[w].ok_or(Error::InsufficientInput)?,
+                        label,
+                    )
+                }
+                Gate::Xor(x, y) => {
+                    let (x, y) = (x as usize, y as usize);
+                    let label = labels[x].iter().zip(labels[y].iter());
+                    (
+                        values[x] ^ values[y],
+                        label.map(|(x, y)| x.xor(y)).collect(),
+                    )
+                }
+                Gate::Not(x) => (!values[x as usize], labels[x as usize].clone()),
+                Gate::And(x, y) => {
+                    let (x, y) = (x as usize, y as usize);
+                    let (a, b) = (values[x], values[y]);
+                    let row = (a as u8) << 1 | b as u8;
+                    let r = shares.row(w, &self.and_masks[and_num], a, b);
+                    let mut value = r.bit;
+                    let mut rows = BTreeMap::new();
+                    for (j, tables) in self.tables.iter() {
+                        let (encrypted, encrypted_bit) = &tables[and_num][row as usize];
+                        expect_length(setup.parties, encrypted.len())?;
+                        let (pad, pad_bit) = garbling_hash::row(
+                            &garbling,
+                            &labels[x][*j],
+                            &labels[y][*j],
+                            w as GateIndex,
+                            row,
+                            setup.parties,
+                        );
+                        let decrypted: Vec<MacType> =
+                            encrypted.iter().zip(pad).map(|(e, p)| *e ^ p).collect();
+                        let bit = encrypted_bit ^ pad_bit;
+                        let expected = r.keys[*j] ^ mac(bit, &self.delta);
+                        checks.verify(w, decrypted[EVALUATOR].0 == expected.0);
+                        value ^= bit;
+                        rows.insert(*j, decrypted);
+                    }
+                    // the label of party `j` is offset by the MACs of the shares of all other
+                    // parties under its delta, which are either known to the evaluator or part
+                    // of the rows of the other garbling parties:
+                    let mut label = vec![WireLabel::default(); setup.parties];
+                    for j in setup.peers() {
+                        let mut l = rows[&j][j] ^ r.macs[j];
+                        for (k, row) in rows.iter() {
+                            if *k != j {
+                                l = l ^ row[j];
+                            }
+                        }
+                        label[j] = WireLabel(l.0);
+                    }
+                    and_num += 1;
+                    (value, label)
+                }
+                Gate::InContrib | Gate::InEval => return Err(Error::InvalidCircuit),
+            };
+            values.push(value);
+            labels.push(label);
+        }
+        checks.finish()?;
+        Ok((values, labels))
+    }
+}
+
+impl RevealedOutput {
+    /// Verifies the masked output revealed by the evaluator, if garbling, and discloses the own
+    /// shares of the output masks to all other parties.
+    fn run(
+        self,
+        setup: &Setup,
+        circuit: &Circuit,
+        mut msgs: BTreeMap<usize, MultipartyOutput>,
+    ) -> StepResult<OutputShares, MultipartyOutputShares> {
+        let mut masked_outputs = self.masked_outputs;
+        if !setup.is_evaluator() {
+            let outputs = msgs
+                .remove(&EVALUATOR)
+                .map(|msg| msg.outputs)
+                .unwrap_or_default();
+            expect_length(circuit.output_gates().len(), outputs.len())?;
+            let mut checks = MacChecks::new(MacCheck::Output);
+            for (i, (o, (label, masked))) in circuit.output_gates().iter().zip(outputs).enumerate()
+            {
+                let expected = self.labels[*o as usize].0 ^ mac(masked, &self.delta);
+                checks.verify(i, label.0 == expected);
+                masked_outputs.push(masked);
+            }
+            checks.finish()?;
+        }
+
+        let mut replies = BTreeMap::new();
+        for j in setup.peers() {
+            let msg = MultipartyOutputShares {
+                shares: open_to(&self.masks, j),
+                transcript: self.transcript,
+            };
+            replies.insert(j, msg);
+        }
+        let state = OutputShares {
+            delta: self.delta,
+            masks: self.masks,
+            masked_outputs,
+            transcript: self.transcript,
+        };
+        Ok((state, replies))
+    }
+}
+
+impl OutputShares {
+    /// Checks that all parties saw the same broadcast values and unmasks the output.
+    fn output(self, msgs: BTreeMap<usize, MultipartyOutputShares>) -> Result<Vec<bool>, Error> {
+        if msgs.values().any(|msg| msg.transcript != self.transcript) {
+            return Err(Error::InconsistentBroadcast);
+        }
+        let openings = msgs.iter().map(|(j, msg)| (*j, &msg.shares));
+        let masks = verify_openings(
+            &self.masks,
+            openings,
+            MacCheck::OutputShare,
+            &self.delta,
+            |i| i,
+        )?;
+        let output = self.masked_outputs.iter().zip(masks);
+        Ok(output.map(|(masked, mask)| masked ^ mask).collect())
+    }
+}
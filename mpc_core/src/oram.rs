@@ -0,0 +1,793 @@
+//! Square-root ORAM, for private arrays that are accessed across many protocol executions.
+//!
+//! Accessing an array at a secret index inside a single circuit (see [`crate::gadgets::array`])
+//! costs AND gates linear in the size of the array for every access. A square-root ORAM instead
+//! keeps the array XOR-shared between the contributor and the evaluator in between protocol
+//! executions and follows the construction of Zahur et al. ("Revisiting Square-Root ORAM", 2016):
+//!
+//!   - The blocks of the array (together with some dummy blocks) are stored in a physical order
+//!     that is a secret permutation, composed of a random permutation chosen by each party and
+//!     applied using [Beneš networks](crate::gadgets::permutation).
+//!   - Each access looks up the physical position of the block in a position map, reveals it and
+//!     moves the block into a small stash. Accesses to a block that is already in the stash look up
+//!     a fresh dummy block instead, so that no physical position is ever revealed twice.
+//!   - After `T` accesses, the stash is written back and the physical array is shuffled anew.
+//!   - Large position maps are themselves stored in a (smaller) square-root ORAM, recursively.
+//!
+//! The period `T`, the number of dummy blocks and whether to store the position map recursively
+//! are chosen by estimating the AND gates of all circuits involved, so that the amortized cost per
+//! access is minimized. With `T ≈ √(n log n)` and a recursive position map this yields
+//! `O(√n log n)` AND gates per access for a fixed element width, compared to `O(n)` for a linear
+//! scan, at the price of several protocol executions per access. Linear scans remain cheaper for
+//! small arrays, the break-even point is around a thousand elements of 64 bits.
+//!
+//! The array is split into two halves, an [`OramContributor`] and an [`OramEvaluator`], each of
+//! which only ever holds the XOR shares, the permutation and the randomness of its own party.
+//! Every read or write runs a few circuits as [`SecurityLevel::SemiHonest`] executions over a
+//! [`Channel`] to the other half, so both halves must issue the same sequence of reads and writes.
+//! Indices, written values and read values are XOR-shared as well: the party that issues an
+//! access provides the index (and value) as its share, while the other party provides zeros, and
+//! the shares of a read value can be combined by whichever party should learn it.
+//!
+//! All outputs of the circuits are revealed to both parties. Values that need to remain secret are
+//! re-shared by masking them with random bits of both parties, the revealed physical positions are
+//! uniformly random and independent of the accessed indices. The shares are not authenticated in
+//! between executions however, so that the ORAM as a whole only protects against semi-honest
+//! parties. Whether an access is a read or a write is not hidden.
+//!
+//! # Examples
+//!
+//! ```
+//! use mpc_core::{
+//!     channel::duplex,
+//!     oram::{OramContributor, OramEvaluator},
+//!     Error,
+//! };
+//! use std::thread;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let (mut channel_contrib, mut channel_eval) = duplex();
+//!
+//! // the contributor owns the array, the evaluator reads at a secret index:
+//! let contributor = thread::spawn(move || -> Result<u64, Error> {
+//!     let mut oram = OramContributor::new(&mut channel_contrib, &[10, 20, 30, 40], 8)?;
+//!     oram.read(&mut channel_contrib, 0)
+//! });
+//! let mut oram = OramEvaluator::new(&mut channel_eval, &[0, 0, 0, 0], 8)?;
+//! let share = oram.read(&mut channel_eval, 2)?;
+//! let share_contrib = contributor.join().expect("the contributor panicked")?;
+//! assert_eq!(share ^ share_contrib, 30);
+//! # Ok(())
+//! # }
+//! ```
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::ops::Range;
+
+use crate::{
+    channel::{run_contributor, Channel},
+    gadgets::{
+        array::{ObliviousArray, ReadStrategy},
+        from_bits, int,
+        permutation::{apply_benes, benes_switch_count, benes_switches},
+        to_bits, CircuitBuilder, Party, Word,
+    },
+    states::{Contributor, Evaluator, SecurityLevel},
+    Error, GateIndex,
+};
+
+/// The number of positions that are packed into a single block of a recursive position map.
+const POSITIONS_PER_BLOCK: usize = 8;
+
+/// The width of the index shares of [`OramContributor::read`] and [`OramEvaluator::read`].
+const INDEX_BITS: usize = 64;
+
+/// The contributor's half of a private array of fixed-width elements, supporting reads and writes
+/// at secret indices.
+///
+/// Must be used together with an [`OramEvaluator`] at the other end of the channel, which needs to
+/// be passed to every operation and must be connected to the same evaluator each time.
+pub struct OramContributor {
+    oram: Oram,
+}
+
+/// The evaluator's half of a private array of fixed-width elements, supporting reads and writes at
+/// secret indices.
+///
+/// Must be used together with an [`OramContributor`] at the other end of the channel, which needs
+/// to be passed to every operation and must be connected to the same contributor each time.
+pub struct OramEvaluator {
+    oram: Oram,
+}
+
+impl OramContributor {
+    /// Creates the contributor's half of a private array of `width`-bit elements, from the
+    /// contributor's XOR shares of the elements.
+    ///
+    /// The party that owns the array provides the elements as its shares, the other party
+    /// provides zeros. Both parties must provide the same number of shares.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0 or larger than 64.
+    pub fn new<Ch: Channel>(channel: &mut Ch, shares: &[u64], width: usize) -> Result<Self, Error> {
+        let oram = Oram::new(Party::Contributor, channel, shares, width)?;
+        Ok(Self { oram })
+    }
+
+    /// The number of elements in the array.
+    pub fn len(&self) -> usize {
+        self.oram.len
+    }
+
+    /// Returns `true` if the array does not contain any elements.
+    pub fn is_empty(&self) -> bool {
+        self.oram.len == 0
+    }
+
+    /// The width of each element in bits.
+    pub fn width(&self) -> usize {
+        self.oram.width
+    }
+
+    /// The number of circuits that have been executed so far, including the initialization.
+    pub fn executions(&self) -> usize {
+        self.oram.ctx.executions
+    }
+
+    /// The number of AND gates in all circuits that have been executed so far.
+    pub fn and_gates(&self) -> usize {
+        self.oram.ctx.and_gates
+    }
+
+    /// Reads the element at an index given as the contributor's XOR share, returning the
+    /// contributor's XOR share of the element.
+    ///
+    /// Reading at an index that is out of bounds yields (shares of) zero.
+    pub fn read<Ch: Channel>(&mut self, channel: &mut Ch, index: u64) -> Result<u64, Error> {
+        self.oram.read(channel, index)
+    }
+
+    /// Overwrites the element at an index with a value, both given as the contributor's XOR
+    /// shares.
+    ///
+    /// Writing at an index that is out of bounds leaves the array unchanged.
+    pub fn write<Ch: Channel>(
+        &mut self,
+        channel: &mut Ch,
+        index: u64,
+        value: u64,
+    ) -> Result<(), Error> {
+        self.oram.write(channel, index, value)
+    }
+}
+
+impl OramEvaluator {
+    /// Creates the evaluator's half of a private array of `width`-bit elements, from the
+    /// evaluator's XOR shares of the elements.
+    ///
+    /// The party that owns the array provides the elements as its shares, the other party
+    /// provides zeros. Both parties must provide the same number of shares.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0 or larger than 64.
+    pub fn new<Ch: Channel>(channel: &mut Ch, shares: &[u64], width: usize) -> Result<Self, Error> {
+        let oram = Oram::new(Party::Evaluator, channel, shares, width)?;
+        Ok(Self { oram })
+    }
+
+    /// The number of elements in the array.
+    pub fn len(&self) -> usize {
+        self.oram.len
+    }
+
+    /// Returns `true` if the array does not contain any elements.
+    pub fn is_empty(&self) -> bool {
+        self.oram.len == 0
+    }
+
+    /// The width of each element in bits.
+    pub fn width(&self) -> usize {
+        self.oram.width
+    }
+
+    /// The number of circuits that have been executed so far, including the initialization.
+    pub fn executions(&self) -> usize {
+        self.oram.ctx.executions
+    }
+
+    /// The number of AND gates in all circuits that have been executed so far.
+    pub fn and_gates(&self) -> usize {
+        self.oram.ctx.and_gates
+    }
+
+    /// Reads the element at an index given as the evaluator's XOR share, returning the evaluator's
+    /// XOR share of the element.
+    ///
+    /// Reading at an index that is out of bounds yields (shares of) zero.
+    pub fn read<Ch: Channel>(&mut self, channel: &mut Ch, index: u64) -> Result<u64, Error> {
+        self.oram.read(channel, index)
+    }
+
+    /// Overwrites the element at an index with a value, both given as the evaluator's XOR shares.
+    ///
+    /// Writing at an index that is out of bounds leaves the array unchanged.
+    pub fn write<Ch: Channel>(
+        &mut self,
+        channel: &mut Ch,
+        index: u64,
+        value: u64,
+    ) -> Result<(), Error> {
+        self.oram.write(channel, index, value)
+    }
+}
+
+/// The state of either half of a private array.
+struct Oram {
+    len: usize,
+    width: usize,
+    root: Level,
+    ctx: Context,
+}
+
+impl Oram {
+    fn new(
+        party: Party,
+        channel: &mut dyn Channel,
+        shares: &[u64],
+        width: usize,
+    ) -> Result<Self, Error> {
+        assert!(
+            (1..=64).contains(&width),
+            "the element width must be between 1 and 64 bits"
+        );
+        Self::with_plan(party, channel, shares, width, &plan(shares.len(), width))
+    }
+
+    fn with_plan(
+        party: Party,
+        channel: &mut dyn Channel,
+        shares: &[u64],
+        width: usize,
+        plan: &Plan,
+    ) -> Result<Self, Error> {
+        let mut ctx = Context {
+            party,
+            rng: ChaCha20Rng::from_entropy(),
+            executions: 0,
+            and_gates: 0,
+        };
+        let blocks = shares.iter().map(|&v| to_bits(v, width)).collect();
+        let root = Level::new(&mut ctx, channel, blocks, plan)?;
+        Ok(Self {
+            len: shares.len(),
+            width,
+            root,
+            ctx,
+        })
+    }
+
+    fn read(&mut self, channel: &mut dyn Channel, index: u64) -> Result<u64, Error> {
+        let index = to_bits(index, INDEX_BITS);
+        let value = self.root.access(&mut self.ctx, channel, &index, None)?;
+        Ok(from_bits(&value))
+    }
+
+    fn write(&mut self, channel: &mut dyn Channel, index: u64, value: u64) -> Result<(), Error> {
+        let index = to_bits(index, INDEX_BITS);
+        let value = to_bits(value, self.width);
+        self.root
+            .access(&mut self.ctx, channel, &index, Some(&value))?;
+        Ok(())
+    }
+}
+
+struct Context {
+    party: Party,
+    rng: ChaCha20Rng,
+    executions: usize,
+    and_gates: usize,
+}
+
+/// This party's XOR share of a value that is shared between the contributor and the evaluator.
+type Share = Vec<bool>;
+
+/// A shared circuit output whose share is not yet known.
+struct PendingShare {
+    party: Party,
+    start: usize,
+    mask: Vec<bool>,
+}
+
+impl PendingShare {
+    /// Computes this party's share from the revealed output `value ^ mask_contrib ^ mask_eval`,
+    /// which is `value ^ mask_eval` for the contributor and `mask_eval` for the evaluator.
+    fn resolve(self, output: &[bool]) -> Share {
+        match self.party {
+            Party::Contributor => output[self.start..self.start + self.mask.len()]
+                .iter()
+                .zip(self.mask)
+                .map(|(&bit, mask)| bit ^ mask)
+                .collect(),
+            Party::Evaluator => self.mask,
+        }
+    }
+}
+
+/// A circuit over shared values, together with the input of this party.
+struct Computation {
+    party: Party,
+    b: CircuitBuilder,
+    input: Vec<bool>,
+    outputs: Vec<GateIndex>,
+}
+
+impl Computation {
+    fn new(party: Party) -> Self {
+        Self {
+            party,
+            b: CircuitBuilder::new(),
+            input: vec![],
+            outputs: vec![],
+        }
+    }
+
+    /// Adds `len` input bits of `owner`, which are only computed if `owner` is this party.
+    fn input(&mut self, owner: Party, len: usize, bits: impl FnOnce() -> Vec<bool>) -> Word {
+        if owner == self.party {
+            let bits = bits();
+            debug_assert_eq!(bits.len(), len);
+            self.input.extend(bits);
+        }
+        self.b.input_word(owner, len)
+    }
+
+    fn shared(&mut self, share: &[bool]) -> Word {
+        let contrib = self.input(Party::Contributor, share.len(), || share.to_vec());
+        let eval = self.input(Party::Evaluator, share.len(), || share.to_vec());
+        self.b.xor_word(&contrib, &eval)
+    }
+
+    fn output_public(&mut self, word: &[GateIndex]) -> Range<usize> {
+        let start = self.outputs.len();
+        self.outputs.extend(word);
+        start..self.outputs.len()
+    }
+
+    /// Outputs a word masked by fresh random bits of both parties, so that it can be revealed to
+    /// both parties and still be re-shared between them.
+    fn output_shared(&mut self, word: &[GateIndex], rng: &mut ChaCha20Rng) -> PendingShare {
+        let mask: Vec<bool> = (0..word.len()).map(|_| rng.gen()).collect();
+        let mask_contrib = self.input(Party::Contributor, word.len(), || mask.clone());
+        let mask_eval = self.input(Party::Evaluator, word.len(), || mask.clone());
+        let masked = self.b.xor_word(word, &mask_contrib);
+        let masked = self.b.xor_word(&masked, &mask_eval);
+        let start = self.output_public(&masked).start;
+        PendingShare {
+            party: self.party,
+            start,
+            mask,
+        }
+    }
+
+    /// Executes the circuit together with the other party and reveals the output to both parties.
+    fn run(self, ctx: &mut Context, channel: &mut dyn Channel) -> Result<Vec<bool>, Error> {
+        let circuit = self.b.build(self.outputs);
+        ctx.executions += 1;
+        ctx.and_gates += circuit.and_gates();
+        let rng = ChaCha20Rng::from_seed(ctx.rng.gen());
+        let level = SecurityLevel::SemiHonest;
+        match ctx.party {
+            Party::Contributor => {
+                let (contrib, msg) =
+                    Contributor::with_security_level(&circuit, &self.input[..], level, rng)?;
+                let contrib = run_contributor(contrib, msg, channel)?;
+                contrib.output(&channel.recv()?)
+            }
+            Party::Evaluator => {
+                let mut eval =
+                    Evaluator::with_security_level(&circuit, &self.input[..], level, rng)?;
+                for _ in 0..eval.steps() {
+                    let (next_state, reply) = eval.run(&channel.recv()?)?;
+                    channel.send(reply)?;
+                    eval = next_state;
+                }
+                let (output, reply) = eval.reveal_output(&channel.recv()?)?;
+                channel.send(reply)?;
+                Ok(output)
+            }
+        }
+    }
+}
+
+struct StashEntry {
+    index: Share,
+    data: Share,
+    position: usize,
+}
+
+enum PositionMap {
+    Linear(Vec<Share>),
+    Recursive(Box<Level>),
+}
+
+/// A single square-root ORAM, which might store the position map of a larger one.
+struct Level {
+    /// The number of real blocks, followed by `size - len` dummy blocks.
+    len: usize,
+    width: usize,
+    size: usize,
+    index_width: usize,
+    period: usize,
+    physical: Vec<Share>,
+    stash: Vec<StashEntry>,
+    /// The permutation of this party, which is applied after the contributor's permutation in case
+    /// of the evaluator.
+    perm: Vec<usize>,
+    /// Whether the blocks have been shuffled at least once.
+    shuffled: bool,
+    positions: PositionMap,
+    positions_plan: Option<Plan>,
+}
+
+impl Level {
+    fn new(
+        ctx: &mut Context,
+        channel: &mut dyn Channel,
+        mut blocks: Vec<Share>,
+        plan: &Plan,
+    ) -> Result<Self, Error> {
+        debug_assert_eq!(blocks.len(), plan.len);
+        blocks.resize(plan.size, vec![false; plan.width]);
+        let mut level = Self {
+            len: plan.len,
+            width: plan.width,
+            size: plan.size,
+            index_width: plan.size.trailing_zeros() as usize,
+            period: plan.period,
+            physical: blocks,
+            stash: vec![],
+            perm: (0..plan.size).collect(),
+            shuffled: false,
+            positions: PositionMap::Linear(vec![]),
+            positions_plan: plan.positions.as_deref().cloned(),
+        };
+        level.shuffle(ctx, channel)?;
+        Ok(level)
+    }
+
+    /// Writes back the stash and moves all blocks to fresh random positions.
+    fn shuffle(&mut self, ctx: &mut Context, channel: &mut dyn Channel) -> Result<(), Error> {
+        for entry in self.stash.drain(..) {
+            self.physical[entry.position] = entry.data;
+        }
+
+        // block `i` is stored at `perm_eval[perm_contrib[i]]` and needs to be moved to
+        // `new_eval[new_contrib[i]]`, which is done by undoing the evaluator's old permutation,
+        // then applying `new_contrib ∘ perm_contrib⁻¹` and finally `new_eval`:
+        let mut new_perm: Vec<usize> = (0..self.size).collect();
+        new_perm.shuffle(&mut ctx.rng);
+        let switch_count = benes_switch_count(self.size);
+        let perm = &self.perm;
+
+        let mut c = Computation::new(ctx.party);
+        let mut blocks: Vec<Word> = self.physical.iter().map(|s| c.shared(s)).collect();
+        if self.shuffled {
+            let switches = c.input(Party::Evaluator, switch_count, || benes_switches(perm));
+            blocks = apply_benes(&mut c.b, &blocks, &switches, true);
+        }
+        let switches = c.input(Party::Contributor, switch_count, || {
+            let mut contrib_step = vec![0; perm.len()];
+            for (i, &p) in perm.iter().enumerate() {
+                contrib_step[p] = new_perm[i];
+            }
+            benes_switches(&contrib_step)
+        });
+        blocks = apply_benes(&mut c.b, &blocks, &switches, false);
+        let eval_switches = c.input(Party::Evaluator, switch_count, || benes_switches(&new_perm));
+        blocks = apply_benes(&mut c.b, &blocks, &eval_switches, false);
+
+        // the position of block `i` is obtained by moving the public physical position `j` back to
+        // the block that ends up at `j`:
+        let contrib_switches = c.input(Party::Contributor, switch_count, || {
+            benes_switches(&new_perm)
+        });
+        let physical_positions: Vec<Word> = (0..self.size)
+            .map(|j| c.b.constant_word(j as u64, self.index_width))
+            .collect();
+        let positions = apply_benes(&mut c.b, &physical_positions, &eval_switches, true);
+        let positions = apply_benes(&mut c.b, &positions, &contrib_switches, true);
+
+        let blocks: Vec<PendingShare> = blocks
+            .iter()
+            .map(|w| c.output_shared(w, &mut ctx.rng))
+            .collect();
+        let positions: Vec<PendingShare> = positions
+            .iter()
+            .map(|w| c.output_shared(w, &mut ctx.rng))
+            .collect();
+        let output = c.run(ctx, channel)?;
+
+        self.physical = blocks.into_iter().map(|s| s.resolve(&output)).collect();
+        self.perm = new_perm;
+        self.shuffled = true;
+        let positions: Vec<Share> = positions.into_iter().map(|s| s.resolve(&output)).collect();
+        self.positions = if let Some(plan) = &self.positions_plan {
+            let packed = positions
+                .chunks(POSITIONS_PER_BLOCK)
+                .map(|chunk| chunk.concat())
+                .collect();
+            PositionMap::Recursive(Box::new(Level::new(ctx, channel, packed, plan)?))
+        } else {
+            PositionMap::Linear(positions)
+        };
+        Ok(())
+    }
+
+    /// Adds a shared index to the computation, returning its lowest `index_width` bits and whether
+    /// it is within the bounds of the real blocks.
+    fn index(&self, c: &mut Computation, index: &[bool]) -> (Word, GateIndex) {
+        let index = c.shared(index);
+        let out_of_bounds = int::ge_const(&mut c.b, &index, self.len as u64);
+        let in_bounds = c.b.not(out_of_bounds);
+        (index[..self.index_width].to_vec(), in_bounds)
+    }
+
+    /// Reads (and optionally overwrites) the block at a shared index of at least `index_width`
+    /// bits, returning the shares of the block.
+    fn access(
+        &mut self,
+        ctx: &mut Context,
+        channel: &mut dyn Channel,
+        index: &[bool],
+        value: Option<&[bool]>,
+    ) -> Result<Share, Error> {
+        if self.stash.len() == self.period {
+            self.shuffle(ctx, channel)?;
+        }
+
+        // look up the block itself, unless it is already in the stash or out of bounds, then use a
+        // fresh dummy:
+        let mut c = Computation::new(ctx.party);
+        let (i, in_bounds) = self.index(&mut c, index);
+        let found: Vec<GateIndex> = self
+            .stash
+            .iter()
+            .map(|entry| {
+                let stashed = c.shared(&entry.index);
+                c.b.eq(&stashed, &i)
+            })
+            .collect();
+        let found = c.b.or_all(&found);
+        // the low bits of an out-of-bounds index may alias a real block, which must not be fetched:
+        let out_of_bounds = c.b.not(in_bounds);
+        let use_dummy = c.b.or(found, out_of_bounds);
+        let dummy = (self.len + self.stash.len()) as u64;
+        let dummy = c.b.constant_word(dummy, self.index_width);
+        let target = c.b.mux_word(use_dummy, &i, &dummy);
+        let target_share = c.output_shared(&target, &mut ctx.rng);
+        let position = match &self.positions {
+            PositionMap::Linear(positions) => {
+                let positions = positions.iter().map(|p| c.shared(p)).collect();
+                let positions = ObliviousArray::new(positions, self.index_width);
+                let position = positions.read(&mut c.b, &target, ReadStrategy::MuxTree);
+                Some(c.output_public(&position))
+            }
+            PositionMap::Recursive(_) => None,
+        };
+        let output = c.run(ctx, channel)?;
+        let target = target_share.resolve(&output);
+
+        let position = match (position, &mut self.positions) {
+            (Some(position), _) => from_bits(&output[position]) as usize,
+            (None, PositionMap::Recursive(positions)) => {
+                let offset_bits = POSITIONS_PER_BLOCK.trailing_zeros() as usize;
+                let mut block_index = target[offset_bits..self.index_width].to_vec();
+                block_index.resize(positions.index_width, false);
+                let block = positions.access(ctx, channel, &block_index, None)?;
+
+                let mut c = Computation::new(ctx.party);
+                let block = c.shared(&block);
+                let offset = c.shared(&target[..offset_bits]);
+                let block = block.chunks(self.index_width).map(|w| w.to_vec()).collect();
+                let block = ObliviousArray::new(block, self.index_width);
+                let position = block.read(&mut c.b, &offset, ReadStrategy::MuxTree);
+                let position = c.output_public(&position);
+                let output = c.run(ctx, channel)?;
+                from_bits(&output[position]) as usize
+            }
+            (None, PositionMap::Linear(_)) => unreachable!("linear position maps are scanned"),
+        };
+        debug_assert!(self.stash.iter().all(|entry| entry.position != position));
+        self.stash.push(StashEntry {
+            index: target,
+            data: self.physical[position].clone(),
+            position,
+        });
+
+        // the block is now guaranteed to be in the stash, unless the index is out of bounds:
+        let mut c = Computation::new(ctx.party);
+        let (i, in_bounds) = self.index(&mut c, index);
+        let value = value.map(|v| c.shared(v));
+        let zero = c.b.zero();
+        let mut result = vec![zero; self.width];
+        let mut updated = vec![];
+        for entry in self.stash.iter() {
+            let stashed = c.shared(&entry.index);
+            let data = c.shared(&entry.data);
+            let selected = c.b.eq(&stashed, &i);
+            let selected = c.b.and(selected, in_bounds);
+            let data_if_selected = c.b.and_word(selected, &data);
+            result = c.b.xor_word(&result, &data_if_selected);
+            if let Some(value) = &value {
+                let data = c.b.mux_word(selected, &data, value);
+                updated.push(c.output_shared(&data, &mut ctx.rng));
+            }
+        }
+        let result = c.output_shared(&result, &mut ctx.rng);
+        let output = c.run(ctx, channel)?;
+
+        for (entry, data) in self.stash.iter_mut().zip(updated) {
+            entry.data = data.resolve(&output);
+        }
+        Ok(result.resolve(&output))
+    }
+}
+
+/// The parameters of a [`Level`], chosen to minimize the estimated AND gates per access.
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    len: usize,
+    width: usize,
+    size: usize,
+    period: usize,
+    /// The plan of the recursive position map, or `None` if it is scanned linearly.
+    positions: Option<Box<Plan>>,
+    /// The estimated amortized number of AND gates per access.
+    cost: f64,
+    /// The estimated number of AND gates for shuffling the blocks and building the position map.
+    shuffle_cost: f64,
+}
+
+fn plan(len: usize, width: usize) -> Plan {
+    let min_size = (len + 1).next_power_of_two().max(2);
+    let mut best: Option<Plan> = None;
+    for size in [min_size, 2 * min_size] {
+        let index_width = size.trailing_zeros() as usize;
+        // every stashed block is compared to the index twice and its data selected once:
+        let per_stashed_block = (width + 2 * index_width) as f64;
+        let switches = benes_switch_count(size) as f64;
+
+        // (recursive plan, cost of a position lookup, cost of rebuilding the position map):
+        let mut options = vec![(None, (size * index_width) as f64, 0.0)];
+        let blocks = (size + POSITIONS_PER_BLOCK - 1) / POSITIONS_PER_BLOCK;
+        if blocks < len {
+            let positions = plan(blocks, POSITIONS_PER_BLOCK * index_width);
+            let lookup = positions.cost + (POSITIONS_PER_BLOCK * index_width) as f64;
+            let rebuild = positions.shuffle_cost;
+            options.push((Some(Box::new(positions)), lookup, rebuild));
+        }
+
+        for (positions, lookup, rebuild) in options {
+            let shuffle_cost = switches * (3 * width + 2 * index_width) as f64 + rebuild;
+            // the stash grows by one block per access, so it holds `period / 2` blocks on average:
+            let period = (2.0 * shuffle_cost / per_stashed_block).sqrt().round() as usize;
+            let period = period.clamp(1, size - len);
+            let cost =
+                period as f64 * per_stashed_block / 2.0 + shuffle_cost / period as f64 + lookup;
+            if best.as_ref().map_or(true, |best| cost < best.cost) {
+                best = Some(Plan {
+                    len,
+                    width,
+                    size,
+                    period,
+                    positions,
+                    cost,
+                    shuffle_cost,
+                });
+            }
+        }
+    }
+    best.expect("at least one plan is always considered")
+}
+
+#[test]
+fn test_plan_is_sublinear() {
+    let small = plan(10, 8);
+    assert!(small.positions.is_none());
+    assert!(small.period >= 1 && small.period <= small.size - small.len);
+
+    let medium = plan(10_000, 64);
+    let large = plan(1_000_000, 64);
+    assert!(medium.positions.is_some() && large.positions.is_some());
+    assert!(medium.cost < 10_000.0 * 64.0 / 2.0);
+    // a linear scan would be 100 times more expensive:
+    assert!(large.cost < 20.0 * medium.cost);
+}
+
+#[test]
+fn test_plan_beats_linear_scan() {
+    // the break-even point of the module documentation:
+    let mut b = CircuitBuilder::new();
+    let array = ObliviousArray::from_inputs(&mut b, Party::Contributor, 1000, 64);
+    let linear_scan = array.read_cost(ReadStrategy::MuxTree, 10);
+    let oram = plan(1000, 64);
+    assert!(oram.cost < linear_scan as f64, "{oram:?} vs {linear_scan}");
+}
+
+#[test]
+fn test_recursive_position_map() -> Result<(), Error> {
+    use crate::channel::duplex;
+    use std::thread;
+
+    let len = 100;
+    let width = 16;
+    // force a recursive position map, which the planner would only choose for larger arrays:
+    let positions = plan(16, POSITIONS_PER_BLOCK * 7);
+    let forced = Plan {
+        len,
+        width,
+        size: 128,
+        period: 5,
+        positions: Some(Box::new(positions)),
+        cost: 0.0,
+        shuffle_cost: 0.0,
+    };
+
+    // the contributor writes and the evaluator reads, each at its own secret indices:
+    let mut rng = ChaCha20Rng::from_entropy();
+    let mut expected: Vec<u64> = (0..len).map(|_| rng.gen_range(0..1 << width)).collect();
+    let initial = expected.clone();
+    let mut accesses = vec![];
+    let mut reads = vec![];
+    for _ in 0..16 {
+        let index = rng.gen_range(0..len);
+        if rng.gen() {
+            let value = rng.gen_range(0..1 << width);
+            accesses.push((index as u64, Some(value)));
+            expected[index] = value;
+        } else {
+            accesses.push((index as u64, None));
+            reads.push(expected[index]);
+        }
+    }
+
+    let (mut channel, mut channel_eval) = duplex();
+    let contrib_accesses = accesses.clone();
+    let contrib_plan = forced.clone();
+    let contributor = thread::spawn(move || -> Result<Vec<u64>, Error> {
+        let shares = vec![0; len];
+        let mut oram = Oram::with_plan(
+            Party::Contributor,
+            &mut channel,
+            &shares,
+            width,
+            &contrib_plan,
+        )?;
+        let mut read_shares = vec![];
+        for (index, value) in contrib_accesses {
+            match value {
+                Some(value) => oram.write(&mut channel, index, value)?,
+                None => read_shares.push(oram.read(&mut channel, 0)?),
+            }
+        }
+        Ok(read_shares)
+    });
+
+    let channel = &mut channel_eval;
+    let mut oram = Oram::with_plan(Party::Evaluator, channel, &initial, width, &forced)?;
+    let mut read_shares = vec![];
+    for (index, value) in accesses {
+        match value {
+            Some(_) => oram.write(channel, 0, 0)?,
+            None => read_shares.push(oram.read(channel, index)?),
+        }
+    }
+    let contrib_shares = contributor.join().expect("the contributor panicked")?;
+    for ((eval, contrib), expected) in read_shares.iter().zip(contrib_shares).zip(reads) {
+        assert_eq!(eval ^ contrib, expected);
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@ use curve25519_dalek_ng::constants::RISTRETTO_BASEPOINT_TABLE;
 use curve25519_dalek_ng::ristretto::RistrettoPoint;
 use curve25519_dalek_ng::scalar::Scalar;
 use rand_core::{RngCore, CryptoRng};
+use serde::{Deserialize, Serialize};
 
 pub(crate) const MSG_LEN: usize = 32;
 
@@ -9,7 +10,7 @@ pub(crate) const MSG_LEN: usize = 32;
 pub(crate) type OtMessage = [u8; MSG_LEN];
 
 /// The party sending data to a [`Receiver`].
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Sender {
     private_key: Scalar,
     pub_key: RistrettoPoint,
@@ -17,7 +18,7 @@ pub(crate) struct Sender {
 }
 
 /// The party choosing 1-out-of-2 pieces of data.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Receiver {
     private_key: Scalar,
     upstream_pub_key: RistrettoPoint,
@@ -0,0 +1,126 @@
+//! The format of the states saved by [`Contributor::save`](crate::states::Contributor::save) and
+//! [`Evaluator::save`](crate::states::Evaluator::save).
+//!
+//! A saved state starts with a fixed header of [`HEADER_LEN`] bytes, followed by the state itself:
+//!
+//! | Bytes | Content                                                   |
+//! |-------|-----------------------------------------------------------|
+//! | 4     | the magic bytes `MPCS`                                    |
+//! | 2     | the [`PROTOCOL_VERSION`] of the party (little endian)     |
+//! | 1     | the [`Party`] that saved the state                        |
+//! | 1     | `1` if the state is encrypted, `0` otherwise              |
+//! | 32    | the hash of the circuit that the party is executing       |
+//!
+//! Unencrypted states are the bincode serialization of the state. Encrypted states are a random
+//! 24 byte nonce followed by the serialization encrypted with XChaCha20-Poly1305, which also
+//! authenticates the header as associated data. The encryption key is derived from the key
+//! provided by the caller, and saving the same state twice results in different bytes.
+
+use bincode::{deserialize, serialize};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::RngCore;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{hash::domain, message::PROTOCOL_VERSION, CircuitBlake3Hash, Error};
+
+/// The length of the header that precedes every saved state.
+const HEADER_LEN: usize = 4 + 2 + 1 + 1 + 32;
+
+const MAGIC: [u8; 4] = *b"MPCS";
+
+const NONCE_LEN: usize = 24;
+
+/// The party whose state is saved, so that a saved contributor cannot be restored as an evaluator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Party {
+    Contributor = 1,
+    Evaluator = 2,
+}
+
+/// Serializes the state of a party, encrypting it if a `key` is provided.
+pub(crate) fn save<T: Serialize>(
+    party: Party,
+    circuit: CircuitBlake3Hash,
+    state: &T,
+    key: Option<&[u8; 32]>,
+) -> Result<Vec<u8>, Error> {
+    let plaintext = serialize(state)?;
+    let mut saved = Vec::with_capacity(HEADER_LEN + NONCE_LEN + plaintext.len());
+    saved.extend_from_slice(&MAGIC);
+    saved.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    saved.push(party as u8);
+    saved.push(u8::from(key.is_some()));
+    saved.extend_from_slice(&circuit);
+    match key {
+        None => saved.extend_from_slice(&plaintext),
+        Some(key) => {
+            let mut nonce = [0; NONCE_LEN];
+            ChaCha20Rng::from_entropy().fill_bytes(&mut nonce);
+            let payload = Payload {
+                msg: &plaintext,
+                aad: &saved,
+            };
+            let ciphertext = cipher(key)
+                .encrypt(XNonce::from_slice(&nonce), payload)
+                .map_err(|_| Error::InvalidSavedState)?;
+            saved.extend_from_slice(&nonce);
+            saved.extend_from_slice(&ciphertext);
+        }
+    }
+    Ok(saved)
+}
+
+/// Deserializes the state of a party, which must have been saved by the same kind of party for
+/// the same circuit and using the same `key`.
+pub(crate) fn restore<T: DeserializeOwned>(
+    party: Party,
+    circuit: CircuitBlake3Hash,
+    saved: &[u8],
+    key: Option<&[u8; 32]>,
+) -> Result<T, Error> {
+    if saved.len() < HEADER_LEN || saved[..4] != MAGIC {
+        return Err(Error::InvalidSavedState);
+    }
+    let (header, payload) = saved.split_at(HEADER_LEN);
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != PROTOCOL_VERSION {
+        return Err(Error::IncompatibleProtocolVersion {
+            local: PROTOCOL_VERSION,
+            remote: version,
+        });
+    }
+    if header[6] != party as u8 || header[8..] != circuit[..] {
+        return Err(Error::InvalidSavedState);
+    }
+    match (header[7], key) {
+        (0, None) => Ok(deserialize(payload)?),
+        (1, Some(key)) => {
+            if payload.len() < NONCE_LEN {
+                return Err(Error::InvalidSavedState);
+            }
+            let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+            let payload = Payload {
+                msg: ciphertext,
+                aad: header,
+            };
+            let plaintext = cipher(key)
+                .decrypt(XNonce::from_slice(nonce), payload)
+                .map_err(|_| Error::InvalidStateKey)?;
+            Ok(deserialize(&plaintext)?)
+        }
+        (0 | 1, _) => Err(Error::InvalidStateKey),
+        _ => Err(Error::InvalidSavedState),
+    }
+}
+
+/// The cipher that encrypts and authenticates saved states with a key derived from `key`.
+fn cipher(key: &[u8; 32]) -> XChaCha20Poly1305 {
+    let key = blake3::derive_key(domain::SAVED_STATE_ENCRYPTION, key);
+    XChaCha20Poly1305::new(&key.into())
+}
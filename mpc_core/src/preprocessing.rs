@@ -0,0 +1,351 @@
+//! Function-independent preprocessing of authenticated bits and AND triples.
+//!
+//! Most of the work of the protocol (the leaky delta OTs, `Π_LaAND` and the bucketing of the AND
+//! triples) does not depend on the circuit, only on its size. The [`PoolContributor`] and the
+//! [`PoolEvaluator`] run this offline phase ahead of time, before any circuit is known, and produce
+//! a [`Pool`] for each party. The online phase ([`Contributor::with_pool`] and
+//! [`Evaluator::with_pool`]) then consumes authenticated bits and AND triples from the pools for
+//! any circuit that fits into what is left of them.
+//!
+//! Authenticated bits and AND triples must never be used twice. Every execution removes the parts
+//! that it uses from the pool and both parties check that they consumed the same parts of their
+//! pools, so that a pool that was persisted before an execution cannot be used again once the
+//! other party has moved on. Every execution is bound to its own session, derived from the session
+//! of the pool, the circuit and the consumed part of the pool. Pools contain the secret `Delta` and
+//! MAC keys of a party and must be stored as securely as any other secret key.
+//!
+//! [`Contributor::with_pool`]: crate::states::Contributor::with_pool
+//! [`Evaluator::with_pool`]: crate::states::Evaluator::with_pool
+
+use bincode::{deserialize, serialize};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash::{domain, SessionId},
+    message::{self, Step},
+    protocol::cointossing::CoinResult,
+    states::{
+        ContribStep1, ContribStep2, ContribStep3, ContribStep4, EvalStep1, EvalStep2, EvalStep3,
+        EvalStep4, EvalStep5, Msg, Preprocessed, PreprocessingSize, StatisticalSecurity,
+    },
+    types::{BitShare, Delta},
+    Circuit, CircuitBlake3Hash, Error,
+};
+
+/// Authenticated bits and AND triples of one party, generated ahead of time.
+#[derive(Serialize, Deserialize)]
+pub struct Pool {
+    id: CoinResult,
+    session: SessionId,
+    delta: Delta,
+    abits: Vec<BitShare>,
+    and_triples: Vec<BitShare>,
+    used_abits: usize,
+    used_ands: usize,
+    security: StatisticalSecurity,
+}
+
+/// The part of a pool that is consumed by an execution, which must be the same for both parties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PoolPosition {
+    id: CoinResult,
+    abits: usize,
+    ands: usize,
+}
+
+impl Pool {
+    fn new(preprocessed: Preprocessed, security: StatisticalSecurity) -> Self {
+        Self {
+            id: preprocessed.coin,
+            session: preprocessed.session,
+            delta: preprocessed.delta,
+            abits: preprocessed.abits,
+            and_triples: preprocessed.and_triples,
+            used_abits: 0,
+            used_ands: 0,
+            security,
+        }
+    }
+
+    /// The statistical security of the AND triples, which the pool was generated with.
+    pub fn security(&self) -> StatisticalSecurity {
+        self.security
+    }
+
+    /// The number of authenticated bits left in the pool.
+    pub fn abits(&self) -> usize {
+        self.abits.len()
+    }
+
+    /// The number of AND triples left in the pool.
+    pub fn ands(&self) -> usize {
+        self.and_triples.len() / 3
+    }
+
+    /// Returns true if enough is left in the pool to execute the circuit.
+    pub fn fits(&self, circuit: &Circuit) -> bool {
+        let size = PreprocessingSize::of(circuit, self.security);
+        size.abits <= self.abits() && size.ands <= self.ands()
+    }
+
+    /// Serializes the pool, for example to persist it between executions.
+    ///
+    /// The pool must be persisted again after every execution, since restoring an older copy will
+    /// lead to a [`Error::PoolMismatch`] in the next execution.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(serialize(self)?)
+    }
+
+    /// Deserializes a pool that was serialized using [`Pool::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(deserialize(bytes)?)
+    }
+
+    /// Removes the authenticated bits and AND triples needed for a circuit from the pool, binding
+    /// them to a new session for the circuit with the specified hash.
+    pub(crate) fn take(
+        &mut self,
+        size: PreprocessingSize,
+        circuit: CircuitBlake3Hash,
+    ) -> Result<(PoolPosition, Preprocessed), Error> {
+        if size.abits > self.abits() || size.ands > self.ands() {
+            return Err(Error::PoolExhausted);
+        }
+        let position = PoolPosition {
+            id: self.id,
+            abits: self.used_abits,
+            ands: self.used_ands,
+        };
+        let preprocessed = Preprocessed {
+            coin: self.id,
+            session: self.session.derive(&circuit, &serialize(&position)?),
+            delta: self.delta.clone(),
+            abits: self.abits.drain(..size.abits).collect(),
+            and_triples: self.and_triples.drain(..size.ands * 3).collect(),
+        };
+        self.used_abits += size.abits;
+        self.used_ands += size.ands;
+        Ok((position, preprocessed))
+    }
+}
+
+/// The contributor's side of the offline phase, generating its [`Pool`].
+pub struct PoolContributor {
+    state: Box<ContribState>,
+    size: PreprocessingSize,
+    session: Option<SessionId>,
+}
+
+/// The evaluator's side of the offline phase, generating its [`Pool`].
+pub struct PoolEvaluator {
+    state: Box<EvalState>,
+    size: PreprocessingSize,
+    session: Option<SessionId>,
+}
+
+enum ContribState {
+    Step1(ContribStep1),
+    Step2(ContribStep2),
+    Step3(ContribStep3),
+    Step4(ContribStep4),
+    Done(Box<Pool>),
+}
+
+enum EvalState {
+    Step1(EvalStep1),
+    Step2(EvalStep2),
+    Step3(EvalStep3),
+    Step4(EvalStep4),
+    Step5(EvalStep5),
+}
+
+/// The context that the preprocessing of a pool is bound to, in place of the hash of a circuit.
+fn pool_context(size: PreprocessingSize) -> CircuitBlake3Hash {
+    let mut hasher = blake3::Hasher::new_derive_key(domain::POOL);
+    hasher.update(&(size.abits as u64).to_le_bytes());
+    hasher.update(&(size.ands as u64).to_le_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+impl PoolContributor {
+    /// Starts generating a pool with the specified number of authenticated bits and AND triples,
+    /// returning a state and an initial message for the [`PoolEvaluator`].
+    ///
+    /// A circuit needs one authenticated bit for every input and AND gate and one AND triple for
+    /// every AND gate. The [`StatisticalSecurity`] of the AND triples must be the same for both
+    /// parties.
+    pub fn new(
+        abits: usize,
+        ands: usize,
+        security: StatisticalSecurity,
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        let size = PreprocessingSize {
+            abits,
+            ands,
+            security,
+        };
+        let delta = Delta::gen_random(&mut rng);
+        let (state, msg) = ContribStep1::init_with_size(delta, rng, size, pool_context(size))?;
+        let msg = message::seal(Step::BaseOtInit, None, &msg)?;
+        let contrib = Self {
+            state: Box::new(ContribState::Step1(state)),
+            size,
+            session: None,
+        };
+        Ok((contrib, msg))
+    }
+
+    /// Returns the number of messages that need to be exchanged before reaching the end state.
+    ///
+    /// The last message of the contributor allows the [`PoolEvaluator`] to finish its pool.
+    pub fn steps(&self) -> u32 {
+        4
+    }
+
+    /// Executes a single step, based on the message received from the [`PoolEvaluator`].
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    fn run_step(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        use ContribState::*;
+
+        let mut session = self.session;
+        let (state, msg) = match *self.state {
+            Step1(s) => {
+                let (state, reply) = s.run(message::open(msg, Step::BaseOtResponse, session)?)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::BaseOtCompletion, session, &reply)?;
+                (Step2(state), reply)
+            }
+            Step2(s) => {
+                let msg = message::open(msg, Step::OtExtension, session)?;
+                let (state, reply) = s.run(msg, self.size)?;
+                let reply = message::seal(Step::LeakyAndStart, session, &reply)?;
+                (Step3(state), reply)
+            }
+            Step3(s) => {
+                let msg = message::open(msg, Step::LeakyAndCorrections, session)?;
+                let (state, reply) = s.run(msg)?;
+                let reply = message::seal(Step::LeakyAndCommitments, session, &reply)?;
+                (Step4(state), reply)
+            }
+            Step4(s) => {
+                let msg = message::open(msg, Step::LeakyAndOpenings, session)?;
+                let ((preprocessed, _), reply) = s.run(msg, self.size)?;
+                let reply = message::seal(Step::Bucketing, session, &reply)?;
+                (
+                    Done(Box::new(Pool::new(preprocessed, self.size.security))),
+                    reply,
+                )
+            }
+            Done(_) => return Err(Error::ProtocolEnded),
+        };
+        let next_state = Self {
+            state: Box::new(state),
+            size: self.size,
+            session,
+        };
+        Ok((next_state, msg))
+    }
+
+    /// Returns the pool or [`Error::ProtocolStillInProgress`] if the offline phase has not ended.
+    pub fn pool(self) -> Result<Pool, Error> {
+        match *self.state {
+            ContribState::Done(pool) => Ok(*pool),
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+}
+
+impl PoolEvaluator {
+    /// Starts generating a pool with the specified number of authenticated bits and AND triples,
+    /// which must match the numbers used by the [`PoolContributor`], just like the
+    /// [`StatisticalSecurity`] of the AND triples.
+    pub fn new(
+        abits: usize,
+        ands: usize,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            state: Box::new(EvalState::Step1(EvalStep1::new(rng))),
+            size: PreprocessingSize {
+                abits,
+                ands,
+                security,
+            },
+            session: None,
+        })
+    }
+
+    /// Returns the number of messages that need to be exchanged before reaching the end state.
+    ///
+    /// After the end state is reached, the evaluator expects one last message from the
+    /// [`PoolContributor`] to finish its pool.
+    pub fn steps(&self) -> u32 {
+        4
+    }
+
+    /// Executes a single step, based on the message received from the [`PoolContributor`].
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    fn run_step(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        use EvalState::*;
+
+        let mut session = self.session;
+        let (state, msg) = match *self.state {
+            Step1(s) => {
+                let msg = message::open(msg, Step::BaseOtInit, session)?;
+                let (state, reply) = s.run(msg, self.size, pool_context(self.size))?;
+                let reply = message::seal(Step::BaseOtResponse, session, &reply)?;
+                (Step2(state), reply)
+            }
+            Step2(s) => {
+                let msg = message::open(msg, Step::BaseOtCompletion, session)?;
+                let (state, reply) = s.run(msg, self.size)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::OtExtension, session, &reply)?;
+                (Step3(state), reply)
+            }
+            Step3(s) => {
+                let (state, reply) = s.run(message::open(msg, Step::LeakyAndStart, session)?)?;
+                let reply = message::seal(Step::LeakyAndCorrections, session, &reply)?;
+                (Step4(state), reply)
+            }
+            Step4(s) => {
+                let msg = message::open(msg, Step::LeakyAndCommitments, session)?;
+                let (state, reply) = s.run(msg, self.size)?;
+                let reply = message::seal(Step::LeakyAndOpenings, session, &reply)?;
+                (Step5(state), reply)
+            }
+            Step5(_) => return Err(Error::ProtocolEnded),
+        };
+        let next_state = Self {
+            state: Box::new(state),
+            size: self.size,
+            session,
+        };
+        Ok((next_state, msg))
+    }
+
+    /// Returns the pool, based on the last message received from the [`PoolContributor`].
+    pub fn pool(self, msg: &[u8]) -> Result<Pool, Error> {
+        match *self.state {
+            EvalState::Step5(s) => {
+                let msg = message::open(msg, Step::Bucketing, self.session)?;
+                let (preprocessed, _) = s
+                    .bucketing(msg)
+                    .map_err(|e| e.at_step(Some(Step::Bucketing)))?;
+                Ok(Pool::new(preprocessed, self.size.security))
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+}
@@ -6,9 +6,13 @@
 //! Protocol steps:
 //! 1. Initialize with [`init`] to get a commitment message
 //! 2. Use [`serialize`] on the coin share
-//! 3. Finish with [`finish`] using the other party's commitment and share messages
+//! 3. Finish with [`finish`] using the other party's commitment and share messages, or with
+//!    [`finish_all`] using the messages of all other parties of a multi-party protocol
+//!
+//! The commitments are bound to a context (the hash of the circuit) that both parties must agree
+//! on, so that a commitment of one computation cannot be opened in another.
 
-use crate::Error;
+use crate::{hash::domain, Error, MacCheck};
 use serde::{Serialize, Deserialize};
 
 /// Number of bits for a coin.
@@ -23,13 +27,21 @@ pub(crate) struct CoinShare([u8; COIN_LEN]);
 pub(crate) type CoinResult = [u8; COIN_LEN];
 
 /// Creates a new coinshare and a message to be shared with another party.
-pub(crate) fn init(coin: [u8; COIN_LEN]) -> Result<(CoinShare, Vec<u8>), Error> {
-    let hash = hash_coinshare(&coin);
-    let msg = bincode::serialize(&hash)?;
+pub(crate) fn init(
+    coin: [u8; COIN_LEN],
+    context: &[u8; 32],
+) -> Result<(CoinShare, Vec<u8>), Error> {
     let coin_share = CoinShare(coin);
+    let msg = commitment(&coin_share, context)?;
     Ok((coin_share, msg))
 }
 
+/// Returns the same commitment message as [`init`], for example to send it again.
+pub(crate) fn commitment(cs: &CoinShare, context: &[u8; 32]) -> Result<Vec<u8>, Error> {
+    let msg = bincode::serialize(&hash_coinshare(&cs.0, context))?;
+    Ok(msg)
+}
+
 /// Serializes a CoinShare to be disclosed to another party at the 2nd protocol step.
 pub(crate) fn serialize(cs: &CoinShare) -> Result<Vec<u8>, Error> {
     let msg = bincode::serialize(&cs.0)?;
@@ -39,21 +51,42 @@ pub(crate) fn serialize(cs: &CoinShare) -> Result<Vec<u8>, Error> {
 /// Verifies the upstream coinshare and returns the resulting coin.
 pub(crate) fn finish(
     coin_share: CoinShare,
+    context: &[u8; 32],
     upstream_hash_msg: Vec<u8>,
     upstream_coin: Vec<u8>,
 ) -> Result<CoinResult, Error> {
     let upstream_hash: [u8; HASH_LEN] = bincode::deserialize(&upstream_hash_msg)?;
     let upstream_coin: [u8; COIN_LEN] = bincode::deserialize(&upstream_coin)?;
 
-    if upstream_hash != hash_coinshare(&upstream_coin) {
-        return Err(Error::MacError);
+    if upstream_hash != hash_coinshare(&upstream_coin, context) {
+        return Err(Error::MacError {
+            step: None,
+            check: MacCheck::CoinCommitment,
+            failed: 1,
+            index: None,
+        });
     }
 
     Ok(xor(coin_share.0, upstream_coin))
 }
 
-fn hash_coinshare(s: &[u8; COIN_LEN]) -> [u8; HASH_LEN] {
-    let mut hasher = blake3::Hasher::new();
+/// Verifies the coinshares of all other parties of a multi-party protocol, given as pairs of their
+/// commitment and share messages, and returns the resulting coin.
+pub(crate) fn finish_all(
+    coin_share: CoinShare,
+    context: &[u8; 32],
+    upstream: Vec<(Vec<u8>, Vec<u8>)>,
+) -> Result<CoinResult, Error> {
+    let mut coin = coin_share.0;
+    for (upstream_hash_msg, upstream_coin) in upstream {
+        coin = finish(CoinShare(coin), context, upstream_hash_msg, upstream_coin)?;
+    }
+    Ok(coin)
+}
+
+fn hash_coinshare(s: &[u8; COIN_LEN], context: &[u8; 32]) -> [u8; HASH_LEN] {
+    let mut hasher = blake3::Hasher::new_derive_key(domain::COIN_COMMITMENT);
+    hasher.update(context);
     hasher.update(s);
     let mut output_reader = hasher.finalize_xof();
     let mut result = [0u8; HASH_LEN];
@@ -82,22 +115,30 @@ fn test_coinshare() {
     let coin2 = [!test_val; COIN_LEN];
     let expected = [255u8; COIN_LEN];
 
-    let (coin_share1, commitment_msg1) = init(coin1).unwrap();
+    let (coin_share1, commitment_msg1) = init(coin1, &[0; 32]).unwrap();
     let coin_msg1 = serialize(&coin_share1).unwrap();
 
-    let (coin_share2, commitment_msg2) = init(coin2).unwrap();
+    let (coin_share2, commitment_msg2) = init(coin2, &[0; 32]).unwrap();
     let coin_msg2 = serialize(&coin_share2).unwrap();
 
     assert_eq!(
         expected,
-        finish(coin_share1, commitment_msg2, coin_msg2).unwrap()
+        finish(coin_share1, &[0; 32], commitment_msg2, coin_msg2).unwrap()
     );
     assert_eq!(
         expected,
-        finish(coin_share2, commitment_msg1, coin_msg1).unwrap()
+        finish(coin_share2, &[0; 32], commitment_msg1, coin_msg1).unwrap()
     );
 }
 
+#[cfg(test)]
+const COMMITMENT_ERROR: Error = Error::MacError {
+    step: None,
+    check: MacCheck::CoinCommitment,
+    failed: 1,
+    index: None,
+};
+
 #[test]
 fn test_coinshare_fail() {
     use rand_core::RngCore;
@@ -113,31 +154,39 @@ fn test_coinshare_fail() {
 
     let corruption_index = (rng.next_u32() as usize) % (COIN_LEN * 8);
 
-    let (coin_share1, _) = init(coin1).unwrap();
-    let (coin_share2_ok, commitment_msg2_ok) = init(coin2.clone()).unwrap();
+    let (coin_share1, _) = init(coin1, &[0; 32]).unwrap();
+    let (coin_share2_ok, commitment_msg2_ok) = init(coin2, &[0; 32]).unwrap();
     let coin_msg2_ok = serialize(&coin_share2_ok).unwrap();
 
-    let mut coin2 = coin2.clone();
+    let mut coin2 = coin2;
     coin2[corruption_index / 8] ^= 1 << (corruption_index % 8);
 
-    let (coin_share2_nok, commitment_msg2_nok) = init(coin2).unwrap();
+    let (coin_share2_nok, commitment_msg2_nok) = init(coin2, &[0; 32]).unwrap();
     let coin_msg2_nok = serialize(&coin_share2_nok).unwrap();
 
     assert_eq!(
-        Err(Error::MacError),
+        Err(COMMITMENT_ERROR),
         finish(
             coin_share1.clone(),
+            &[0; 32],
             commitment_msg2_nok,
             coin_msg2_ok.clone()
         )
     );
 
     assert_eq!(
-        Err(Error::MacError),
+        Err(COMMITMENT_ERROR),
         finish(
             coin_share1.clone(),
-            commitment_msg2_ok,
+            &[0; 32],
+            commitment_msg2_ok.clone(),
             coin_msg2_nok.clone()
         )
     );
+
+    // a commitment for another context cannot be opened:
+    assert_eq!(
+        Err(COMMITMENT_ERROR),
+        finish(coin_share1, &[1; 32], commitment_msg2_ok, coin_msg2_ok)
+    );
 }
@@ -0,0 +1,166 @@
+//! Reactive computations, which keep secret state across successive circuits.
+//!
+//! Each party keeps a [`ReactiveState`] for the whole session, containing its `Delta` and its
+//! shares of named state handles. Every execution of the session uses the same `Delta`, which
+//! allows the outputs of one circuit to stay secret-shared and authenticated and to be used as the
+//! inputs of the next circuit, without re-encrypting or re-signing the state inside the circuit.
+//!
+//! The [`StateBindings`] of an execution, which must be the same for both parties, determine which
+//! handles are used as inputs and which outputs are stored as handles:
+//!
+//! - The loaded handles are the first input bits of _both_ parties, in the order in which they are
+//!   loaded. A circuit combines the shares of both parties using
+//!   [`CircuitBuilder::input_shared`](crate::gadgets::CircuitBuilder::input_shared), once for every
+//!   loaded handle and before any other input is added. The input provided by each party only
+//!   contains its remaining input bits.
+//! - The stored handles are the first output bits of the circuit, in the order in which they are
+//!   stored. These outputs stay secret, while all remaining outputs are revealed to the evaluator.
+//!
+//! Both parties prove that they use exactly the shares that were stored in a previous execution,
+//! failing with [`Error::MacError`] otherwise. Handles can be overwritten by storing a new value
+//! under the same name, after which the old shares can no longer be used.
+
+use std::collections::BTreeMap;
+
+use bincode::{deserialize, serialize};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    states::OutputShares,
+    types::{BitShare, Delta},
+    Circuit, Error,
+};
+
+/// The secret state of one of the parties across the executions of a reactive computation.
+///
+/// The state contains the secret `Delta` and MAC keys of the party and must be stored as securely
+/// as any other secret key.
+#[derive(Serialize, Deserialize)]
+pub struct ReactiveState {
+    delta: Delta,
+    handles: BTreeMap<String, Vec<BitShare>>,
+}
+
+/// The state handles that are loaded and stored by one execution of a reactive computation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateBindings {
+    loads: Vec<String>,
+    stores: Vec<(String, usize)>,
+}
+
+/// The loaded shares and the stored handles of a single execution.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ReactiveStep {
+    pub(crate) loaded: Vec<BitShare>,
+    stores: Vec<(String, usize)>,
+}
+
+impl ReactiveState {
+    /// Starts a new reactive computation without any state handles.
+    pub fn new(rng: &mut ChaCha20Rng) -> Self {
+        Self {
+            delta: Delta::gen_random(rng),
+            handles: BTreeMap::new(),
+        }
+    }
+
+    /// The names of all state handles, in alphabetical order.
+    pub fn handles(&self) -> impl Iterator<Item = &str> {
+        self.handles.keys().map(String::as_str)
+    }
+
+    /// The number of bits of the state handle or `None` if the handle does not exist.
+    pub fn handle_len(&self, name: &str) -> Option<usize> {
+        self.handles.get(name).map(Vec::len)
+    }
+
+    /// Removes a state handle, returning true if the handle existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.handles.remove(name).is_some()
+    }
+
+    /// Serializes the state, for example to persist it between executions.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(serialize(self)?)
+    }
+
+    /// Deserializes a state that was serialized using [`ReactiveState::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(deserialize(bytes)?)
+    }
+
+    pub(crate) fn delta(&self) -> &Delta {
+        &self.delta
+    }
+
+    /// Loads the handles of an execution, checking that they fit into the circuit.
+    pub(crate) fn step(
+        &self,
+        circuit: &Circuit,
+        bindings: &StateBindings,
+    ) -> Result<ReactiveStep, Error> {
+        let mut loaded = vec![];
+        for name in bindings.loads.iter() {
+            let shares = self.handles.get(name).ok_or(Error::UnknownStateHandle)?;
+            loaded.extend(shares.iter().copied());
+        }
+        let stored: usize = bindings.stores.iter().map(|(_, len)| len).sum();
+        if loaded.len() > circuit.contrib_inputs()
+            || loaded.len() > circuit.eval_inputs()
+            || stored > circuit.output_gates().len()
+        {
+            return Err(Error::InvalidStateBindings);
+        }
+        Ok(ReactiveStep {
+            loaded,
+            stores: bindings.stores.clone(),
+        })
+    }
+
+    /// Stores the shares of the first outputs of an execution under the names of the handles.
+    pub(crate) fn store(&mut self, step: &ReactiveStep, shares: OutputShares) -> Result<(), Error> {
+        if shares.delta != self.delta {
+            return Err(Error::InvalidStateBindings);
+        }
+        let mut shares = shares.shares.into_iter();
+        for (name, len) in step.stores.iter() {
+            let handle = shares.by_ref().take(*len).collect();
+            self.handles.insert(name.clone(), handle);
+        }
+        Ok(())
+    }
+}
+
+impl StateBindings {
+    /// Creates bindings that neither load nor store any state handles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the state handle with the specified name as the next input bits of both parties.
+    pub fn load(mut self, name: impl Into<String>) -> Self {
+        self.loads.push(name.into());
+        self
+    }
+
+    /// Stores the next `len` output bits under the specified name, instead of revealing them.
+    pub fn store(mut self, name: impl Into<String>, len: usize) -> Self {
+        self.stores.push((name.into(), len));
+        self
+    }
+}
+
+impl ReactiveStep {
+    /// The number of output bits that are stored instead of being revealed.
+    pub(crate) fn stored_len(&self) -> usize {
+        self.stores.iter().map(|(_, len)| len).sum()
+    }
+}
+
+/// Prepends the shares of the loaded state handles (if any) to the input of a party.
+pub(crate) fn full_input(step: Option<&ReactiveStep>, input: &[bool]) -> Vec<bool> {
+    let loaded = step.map_or(&[][..], |step| &step.loaded);
+    let loaded = loaded.iter().map(|share| share.bit);
+    loaded.chain(input.iter().copied()).collect()
+}
@@ -0,0 +1,468 @@
+//! Classic Yao garbling for semi-honest parties, using free-XOR, point-and-permute and half-gates.
+//!
+//! The contributor garbles the circuit under a global `Delta` whose lowest bit is set, so that the
+//! lowest bit of every wire label serves as its permute bit. XOR and NOT gates are free, AND gates
+//! are garbled as two half-gates (Zahur, Rosulek and Evans, "Two Halves Make a Whole", 2015) and
+//! cost two labels each. The evaluator obtains the labels of its input bits through the same base
+//! OTs and OT extension used by the maliciously secure protocol, with the garbling `Delta` as the
+//! correlation of the extended OTs.
+//!
+//! Without any interaction before the garbling, the hashes cannot be bound to a session that both
+//! parties contribute to and are only bound to the circuit, with a domain-separation tag of their
+//! own.
+//!
+//! None of the messages are authenticated and neither party checks the behavior of the other one,
+//! which is why this mode must only be used between parties that trust each other to follow the
+//! protocol.
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash::{domain, hash_keys, DomainKey},
+    leakydelta_ot::{
+        message::{OtInitReply, SerializedOtInit},
+        LeakyOtReceiver, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+    },
+    message::{GarbledCircuit, InputLabels, OtBlocks, OtCompletion, OutputLabels},
+    types::{Delta, KeyType, MacType, WireLabel},
+    Circuit,
+    Error::{self, *},
+    Gate, GateIndex, MacCheck, MacChecks,
+};
+
+/// The contributor after garbling the circuit, waiting for the evaluator's base OT messages.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GarblerInit {
+    rng: ChaCha20Rng,
+    delta: Delta,
+    /// The labels for `false` of the evaluator's input bits.
+    eval_labels: Vec<WireLabel>,
+    /// The labels for `false` of the output bits.
+    output_labels: Vec<WireLabel>,
+}
+
+/// The contributor waiting for the evaluator's extended OTs.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GarblerOt {
+    delta: Delta,
+    s: SenderInitializer,
+    eval_labels: Vec<WireLabel>,
+    output_labels: Vec<WireLabel>,
+}
+
+/// The contributor after the protocol has ended.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GarblerDone {
+    delta: Delta,
+    output_labels: Vec<WireLabel>,
+}
+
+/// The evaluator waiting for the garbled circuit.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvaluatorInit {
+    rng: ChaCha20Rng,
+}
+
+/// The evaluator waiting for the contributor's base OT messages.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvaluatorOt {
+    rng: ChaCha20Rng,
+    r_init: ReceiverInitializer,
+    garbled: GarbledCircuit,
+}
+
+/// The evaluator waiting for the labels of its input and the decoding bits of the output.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EvaluatorOutput {
+    garbled: GarbledCircuit,
+    /// The labels of the evaluator's input bits, as received through the extended OTs.
+    eval_labels: Vec<WireLabel>,
+}
+
+/// The hash used for garbling the half-gates, with the index of the half-gate as its tweak.
+fn hash(half_gates: &DomainKey, label: &WireLabel, tweak: GateIndex, half: u32) -> WireLabel {
+    let tweak = (u128::from(tweak) << 1) | u128::from(half);
+    WireLabel(hash_keys(half_gates, KeyType(label.0), KeyType(tweak)).0)
+}
+
+/// The key of the half-gate hashes, bound to the garbled circuit.
+fn half_gates_key(circuit: &Circuit) -> DomainKey {
+    DomainKey::new(domain::HALF_GATES, &circuit.blake3_hash())
+}
+
+fn permute_bit(label: &WireLabel) -> bool {
+    label.0 & 1 != 0
+}
+
+fn random_label(rng: &mut ChaCha20Rng) -> WireLabel {
+    WireLabel(rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64))
+}
+
+fn blocks(eval_inputs: usize) -> usize {
+    (eval_inputs + BLOCK_SIZE - 1) / BLOCK_SIZE
+}
+
+impl GarblerInit {
+    /// Garbles the circuit, returning the garbled tables and the contributor's input labels as
+    /// the initial message for the evaluator.
+    pub(crate) fn garble(
+        circuit: &Circuit,
+        input: &[bool],
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, GarbledCircuit), Error> {
+        circuit.validate_contributor_input(input)?;
+        circuit.validate()?;
+        let delta = Delta(Delta::gen_random(&mut rng).0 | 1);
+        let half_gates = half_gates_key(circuit);
+
+        let mut input = input.iter();
+        let mut labels: Vec<WireLabel> = Vec::with_capacity(circuit.gates().len());
+        let mut tables = Vec::with_capacity(circuit.and_gates());
+        let mut contrib_labels = Vec::with_capacity(circuit.contrib_inputs());
+        let mut eval_labels = Vec::with_capacity(circuit.eval_inputs());
+        for (index, gate) in circuit.gates().iter().enumerate() {
+            let label_0 = match *gate {
+                Gate::InContrib => {
+                    let label_0 = random_label(&mut rng);
+                    let bit = *input.next().ok_or(InsufficientInput)?;
+                    contrib_labels.push(label_0.xor(&WireLabel(if bit { delta.0 } else { 0 })));
+                    label_0
+                }
+                Gate::InEval => {
+                    let label_0 = random_label(&mut rng);
+                    eval_labels.push(label_0.clone());
+                    label_0
+                }
+                Gate::InParty(_) => return Err(InvalidCircuit),
+                Gate::Xor(x, y) => labels[x as usize].xor(&labels[y as usize]),
+                Gate::Not(x) => labels[x as usize].xor(&WireLabel(delta.0)),
+                Gate::And(x, y) => {
+                    let (table, label_0) = garble_and(
+                        &half_gates,
+                        &labels[x as usize],
+                        &labels[y as usize],
+                        index as GateIndex,
+                        &delta,
+                    );
+                    tables.push(table);
+                    label_0
+                }
+            };
+            labels.push(label_0);
+        }
+        let output_labels = circuit
+            .output_gates()
+            .iter()
+            .map(|index| labels[*index as usize].clone())
+            .collect();
+
+        let msg = GarbledCircuit {
+            tables,
+            contrib_labels,
+        };
+        let state = Self {
+            rng,
+            delta,
+            eval_labels,
+            output_labels,
+        };
+        Ok((state, msg))
+    }
+
+    /// Answers the evaluator's base OT messages, using `Delta` as the correlation of the OTs.
+    pub(crate) fn run(
+        mut self,
+        msg: SerializedOtInit,
+    ) -> Result<(GarblerOt, SerializedOtInit), Error> {
+        let ot_init = msg.deserialize()?;
+        let (s, reply) = SenderInitializer::init(&mut self.rng, self.delta.clone(), &ot_init);
+        let reply = reply.serialize();
+        let state = GarblerOt {
+            delta: self.delta,
+            s,
+            eval_labels: self.eval_labels,
+            output_labels: self.output_labels,
+        };
+        Ok((state, reply))
+    }
+}
+
+/// Garbles an AND gate as two half-gates, returning its table and its output label for `false`.
+pub(crate) fn garble_and(
+    half_gates: &DomainKey,
+    label_x: &WireLabel,
+    label_y: &WireLabel,
+    gate: GateIndex,
+    delta: &Delta,
+) -> ([WireLabel; 2], WireLabel) {
+    let delta_label = WireLabel(delta.0);
+    let zero = WireLabel(0);
+    let (p_x, p_y) = (permute_bit(label_x), permute_bit(label_y));
+
+    // the generator half-gate, for which the generator knows the permute bit of `y`
+    let hash_x0 = hash(half_gates, label_x, gate, 0);
+    let hash_x1 = hash(half_gates, &label_x.xor(&delta_label), gate, 0);
+    let table_g = hash_x0
+        .xor(&hash_x1)
+        .xor(if p_y { &delta_label } else { &zero });
+    let w_g = hash_x0.xor(if p_x { &table_g } else { &zero });
+
+    // the evaluator half-gate, for which the evaluator knows the value of `y ^ p_y`
+    let hash_y0 = hash(half_gates, label_y, gate, 1);
+    let hash_y1 = hash(half_gates, &label_y.xor(&delta_label), gate, 1);
+    let hash_diff = hash_y0.xor(&hash_y1);
+    let table_e = hash_diff.xor(label_x);
+    let w_e = hash_y0.xor(if p_y { &hash_diff } else { &zero });
+
+    ([table_g, table_e], w_g.xor(&w_e))
+}
+
+/// Evaluates an AND gate garbled by [`garble_and`].
+pub(crate) fn eval_and(
+    half_gates: &DomainKey,
+    label_x: &WireLabel,
+    label_y: &WireLabel,
+    gate: GateIndex,
+    [table_g, table_e]: &[WireLabel; 2],
+) -> WireLabel {
+    let zero = WireLabel(0);
+    let w_g =
+        hash(half_gates, label_x, gate, 0).xor(if permute_bit(label_x) { table_g } else { &zero });
+    let w_e = hash(half_gates, label_y, gate, 1).xor(&if permute_bit(label_y) {
+        table_e.xor(label_x)
+    } else {
+        zero
+    });
+    w_g.xor(&w_e)
+}
+
+impl GarblerOt {
+    /// Derives the labels of the evaluator's input from the extended OTs, returning the final
+    /// message, which completes the input labels of the evaluator and decodes the output.
+    pub(crate) fn run(
+        self,
+        (reply, ot_blocks): (OtCompletion, OtBlocks),
+    ) -> Result<(GarblerDone, InputLabels), Error> {
+        let ot_blocks = ot_blocks.blocks;
+        if ot_blocks.len() != blocks(self.eval_labels.len()) {
+            return Err(Error::unexpected_length(
+                blocks(self.eval_labels.len()),
+                ot_blocks.len(),
+            ));
+        }
+        let reply = OtInitReply::deserialize(reply.reply)?;
+        let mut s = self.s.recv(&reply);
+
+        // the evaluator received `key ^ bit * Delta`, which is turned into the label of its input
+        // bit by sending the difference between `key` and the label for `false`
+        let mut offsets = Vec::with_capacity(self.eval_labels.len());
+        for (block, labels) in ot_blocks
+            .into_iter()
+            .zip(self.eval_labels.chunks(BLOCK_SIZE))
+        {
+            let ot_rx: [MacType; BLOCK_SIZE] = block
+                .try_into()
+                .map_err(|_| Error::OtBlockDeserializationError)?;
+            let mut keys_out = [MacType(0); BLOCK_SIZE];
+            s.send(&ot_rx, &mut keys_out);
+            for (key, label_0) in keys_out.iter().zip(labels) {
+                offsets.push(label_0.xor(&WireLabel(key.0)));
+            }
+        }
+        let decoding: Vec<bool> = self.output_labels.iter().map(permute_bit).collect();
+
+        let msg = InputLabels { offsets, decoding };
+        let state = GarblerDone {
+            delta: self.delta,
+            output_labels: self.output_labels,
+        };
+        Ok((state, msg))
+    }
+}
+
+impl GarblerDone {
+    /// Returns the labels that encode the specified output, as an evaluator would obtain them.
+    pub(crate) fn output_labels(&self, output: &[bool]) -> Vec<WireLabel> {
+        let delta_label = WireLabel(self.delta.0);
+        self.output_labels
+            .iter()
+            .zip(output)
+            .map(|(label_0, bit)| {
+                if *bit {
+                    label_0.xor(&delta_label)
+                } else {
+                    label_0.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Decodes the output labels sent by the evaluator, which can only be valid labels for the
+    /// actual output.
+    pub(crate) fn run(self, msg: OutputLabels) -> Result<Vec<bool>, Error> {
+        let labels = msg.labels;
+        if labels.len() != self.output_labels.len() {
+            return Err(Error::unexpected_length(
+                self.output_labels.len(),
+                labels.len(),
+            ));
+        }
+        let delta_label = WireLabel(self.delta.0);
+        let mut checks = MacChecks::new(MacCheck::Output);
+        let mut output = Vec::with_capacity(labels.len());
+        for (i, (label, label_0)) in labels.iter().zip(self.output_labels.iter()).enumerate() {
+            let bit = *label != *label_0;
+            checks.verify(i, !bit || *label == label_0.xor(&delta_label));
+            output.push(bit);
+        }
+        checks.finish()?;
+        Ok(output)
+    }
+}
+
+impl EvaluatorInit {
+    pub(crate) fn new(rng: ChaCha20Rng) -> Self {
+        Self { rng }
+    }
+
+    /// Stores the garbled circuit and starts the base OTs.
+    pub(crate) fn run(
+        mut self,
+        msg: GarbledCircuit,
+        circuit: &Circuit,
+    ) -> Result<(EvaluatorOt, SerializedOtInit), Error> {
+        circuit.validate()?;
+        if msg.tables.len() != circuit.and_gates() {
+            return Err(Error::unexpected_length(
+                circuit.and_gates(),
+                msg.tables.len(),
+            ));
+        }
+        if msg.contrib_labels.len() != circuit.contrib_inputs() {
+            return Err(Error::unexpected_length(
+                circuit.contrib_inputs(),
+                msg.contrib_labels.len(),
+            ));
+        }
+        let (r_init, ot_msg) = ReceiverInitializer::init(&mut self.rng);
+        let reply = ot_msg.serialize();
+        let state = EvaluatorOt {
+            rng: self.rng,
+            r_init,
+            garbled: msg,
+        };
+        Ok((state, reply))
+    }
+}
+
+impl EvaluatorOt {
+    /// Finishes the base OTs and extends them to one OT for each input bit of the evaluator.
+    pub(crate) fn run(
+        mut self,
+        msg: SerializedOtInit,
+        input: &[bool],
+    ) -> Result<(EvaluatorOutput, (OtCompletion, OtBlocks)), Error> {
+        let ot_init = msg.deserialize()?;
+        let (r, reply): (LeakyOtReceiver, _) = self.r_init.recv(&ot_init);
+        let mut r = r;
+
+        let mut eval_labels = Vec::with_capacity(input.len());
+        let mut ot_blocks = Vec::with_capacity(blocks(input.len()));
+        for chunk in input.chunks(BLOCK_SIZE) {
+            // bits beyond the input are padded with random choices
+            let mut bits = self.rng.next_u64() as u128 | ((self.rng.next_u64() as u128) << 64);
+            for (i, bit) in chunk.iter().enumerate() {
+                bits = (bits & !(1 << i)) | (u128::from(*bit) << i);
+            }
+            let mut macs_out = [MacType(0); BLOCK_SIZE];
+            let mut ot_out = [MacType(0); BLOCK_SIZE];
+            r.new_batch(bits, &mut macs_out, &mut ot_out);
+            eval_labels.extend(macs_out[..chunk.len()].iter().map(|mac| WireLabel(mac.0)));
+            ot_blocks.push(ot_out.to_vec());
+        }
+
+        let reply = (
+            OtCompletion {
+                reply: reply.serialize(),
+            },
+            OtBlocks { blocks: ot_blocks },
+        );
+        let state = EvaluatorOutput {
+            garbled: self.garbled,
+            eval_labels,
+        };
+        Ok((state, reply))
+    }
+}
+
+impl EvaluatorOutput {
+    /// Evaluates the garbled circuit, returning the output together with a message that reveals
+    /// the output labels to the contributor.
+    pub(crate) fn run(
+        self,
+        msg: InputLabels,
+        circuit: &Circuit,
+    ) -> Result<(Vec<bool>, OutputLabels), Error> {
+        let InputLabels { offsets, decoding } = msg;
+        if offsets.len() != self.eval_labels.len() {
+            return Err(Error::unexpected_length(
+                self.eval_labels.len(),
+                offsets.len(),
+            ));
+        }
+        if decoding.len() != circuit.output_gates().len() {
+            return Err(Error::unexpected_length(
+                circuit.output_gates().len(),
+                decoding.len(),
+            ));
+        }
+        let GarbledCircuit {
+            tables,
+            contrib_labels,
+        } = self.garbled;
+        let mut contrib_labels = contrib_labels.into_iter();
+        let mut eval_labels = self
+            .eval_labels
+            .iter()
+            .zip(offsets.iter())
+            .map(|(label, offset)| label.xor(offset));
+        let mut tables = tables.iter();
+
+        let half_gates = half_gates_key(circuit);
+        let mut labels: Vec<WireLabel> = Vec::with_capacity(circuit.gates().len());
+        for (index, gate) in circuit.gates().iter().enumerate() {
+            let label = match *gate {
+                Gate::InContrib => contrib_labels.next().ok_or(Error::unexpected_gate(index))?,
+                Gate::InEval => eval_labels.next().ok_or(Error::unexpected_gate(index))?,
+                Gate::InParty(_) => return Err(InvalidCircuit),
+                Gate::Xor(x, y) => labels[x as usize].xor(&labels[y as usize]),
+                Gate::Not(x) => labels[x as usize].clone(),
+                Gate::And(x, y) => {
+                    let table = tables.next().ok_or(Error::unexpected_gate(index))?;
+                    eval_and(
+                        &half_gates,
+                        &labels[x as usize],
+                        &labels[y as usize],
+                        index as GateIndex,
+                        table,
+                    )
+                }
+            };
+            labels.push(label);
+        }
+
+        let mut output = Vec::with_capacity(decoding.len());
+        let mut output_labels = Vec::with_capacity(decoding.len());
+        for (index, decoding) in circuit.output_gates().iter().zip(decoding) {
+            let label = &labels[*index as usize];
+            output.push(permute_bit(label) ^ decoding);
+            output_labels.push(label.clone());
+        }
+        let reply = OutputLabels {
+            labels: output_labels,
+        };
+        Ok((output, reply))
+    }
+}
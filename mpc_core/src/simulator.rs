@@ -1,7 +1,7 @@
 //! Simulation environment for secure computation under ideal functionality
 
 use crate::{
-    states::{Contributor, Evaluator},
+    states::{Contributor, Evaluator, SecurityLevel},
     Circuit, Error,
 };
 use rand_core::SeedableRng;
@@ -17,13 +17,34 @@ pub fn simulate(
     input_contributor: &[bool],
     input_evaluator: &[bool],
 ) -> Result<Vec<bool>, Error> {
-    let mut eval = Evaluator::new(
-        circuit.clone(),
+    simulate_with_security_level(
+        circuit,
+        input_contributor,
         input_evaluator,
+        SecurityLevel::Malicious,
+    )
+}
+
+/// Simulates the local execution of the circuit using the protocol of the specified
+/// [`SecurityLevel`].
+pub fn simulate_with_security_level(
+    circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+    security_level: SecurityLevel,
+) -> Result<Vec<bool>, Error> {
+    let mut eval = Evaluator::with_security_level(
+        circuit,
+        input_evaluator,
+        security_level,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (mut contrib, mut msg_for_eval) = Contributor::with_security_level(
+        circuit,
+        input_contributor,
+        security_level,
         ChaCha20Rng::from_entropy(),
     )?;
-    let (mut contrib, mut msg_for_eval) =
-        Contributor::new(circuit, input_contributor, ChaCha20Rng::from_entropy())?;
 
     assert_eq!(contrib.steps(), eval.steps());
 
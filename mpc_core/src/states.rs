@@ -2,19 +2,36 @@
 //!
 //! At each step in the protocol, each party ([`Contributor`] and [`Evaluator`]) always expects a
 //! single message from the other party. Based on the message each party either computes the final
-//! output or transitions into a new state, returning a message for the other party.
+//! output or transitions into a new state, returning a message for the other party. Every message
+//! is framed in a versioned [envelope](crate::message) that identifies its step and session.
 //!
 //! The parties are deliberately communication-agnostic and expect the sending and receiving of
-//! messages to be handled by the user of this crate.
+//! messages to be handled by the user of this crate, for example using the drivers of the
+//! [`channel`](crate::channel) module.
 
 use std::borrow::Borrow;
 
 use crate::{
-    hash::{garbling_hash, hash, hash_key, hash_keys},
+    hash::{domain, garbling_hash, hash, hash_key, hash_keys, DomainKey, SessionId},
     leakyand::{compute_leaky_and_hashes, derive_and_shares},
     leakydelta_ot::{
-        message::{OtInitReply, SerializedOtInit},
-        LeakyOtReceiver, LeakyOtSender, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+        message::OtInitReply, LeakyOtReceiver, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+    },
+    message::{
+        self, BucketingBits, ContribMasks, DisclosedShares, EvaluationInputs, GarbledTables,
+        InputMasks, LeakyAndCheck, LeakyAndCommitments, LeakyAndHashes, LeakyAndOpenings,
+        LeakyAndShares, MaskedAndBits, OtBlocks, OtCompletion, OtInit, OtResponse, PooledMasks,
+        RevealedOutput, Step,
+    },
+    persistence::{self, Party},
+    preprocessing::{Pool, PoolPosition},
+    reactive::{full_input, ReactiveState, ReactiveStep, StateBindings},
+    semihonest::{
+        EvaluatorInit, EvaluatorOt, EvaluatorOutput, GarblerDone, GarblerInit, GarblerOt,
+    },
+    dualex::{
+        ContribDual1, ContribDual2, ContribDual3, ContribDualDone, EvalDual1, EvalDual2, EvalDual3,
+        EvalDualOutput,
     },
     protocol::{
         self,
@@ -22,255 +39,1490 @@ use crate::{
     },
     types::{
         AndTableShare, BitShare, Delta, InputMaskShare, KeyType, MacType, PartialBitShare,
-        TableShare, WireLabel, WireMask, WireState, K,
+        WireLabel, WireMask, WireState, K,
     },
-    Circuit,
+    Circuit, CircuitBlake3Hash,
     Error::{self, *},
-    Gate, GateIndex,
+    Gate, GateIndex, MacCheck, MacChecks,
 };
-use bincode::{deserialize, serialize};
 use rand_core::{RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 
 /// The type of messages exchanged between [`Contributor`] and [`Evaluator`].
 pub type Msg = Vec<u8>;
 
 const TRIPLES: usize = BLOCK_SIZE * 3;
 
+/// The number of authenticated bits and AND triples produced by the preprocessing phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PreprocessingSize {
+    /// Authenticated bits used as masks of the input and AND gate wires.
+    pub(crate) abits: usize,
+    /// Authenticated AND triples, one for each AND gate.
+    pub(crate) ands: usize,
+    /// The statistical security of the bucketing, which determines how many leaky AND triples are
+    /// combined into each of the AND triples.
+    pub(crate) security: StatisticalSecurity,
+}
+
+impl PreprocessingSize {
+    pub(crate) fn of(circuit: &Circuit, security: StatisticalSecurity) -> Self {
+        Self {
+            abits: circuit.and_gates() + circuit.eval_inputs() + circuit.contrib_inputs(),
+            ands: circuit.and_gates(),
+            security,
+        }
+    }
+
+    pub(crate) fn bucket_size(&self) -> usize {
+        self.security.bucket_size(self.ands)
+    }
+}
+
+/// The authenticated bits and AND triples of a party after a finished preprocessing phase.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Preprocessed {
+    /// The result of the coin toss, shared by both parties.
+    pub(crate) coin: CoinResult,
+    /// The session of the preprocessing, which all hashes of the execution are bound to.
+    pub(crate) session: SessionId,
+    pub(crate) delta: Delta,
+    pub(crate) abits: Vec<BitShare>,
+    /// The AND triples, as 3 consecutive authenticated bits each.
+    pub(crate) and_triples: Vec<BitShare>,
+}
+
 /// The party that contributes its input to the secure computation protocol.
 pub struct Contributor<C: Borrow<Circuit>, I: Borrow<[bool]>> {
     state: Box<ContribState>,
     circuit: C,
-    input: I,
+    input: Option<I>,
+    steps: u32,
+    output_mode: OutputMode,
+    reactive: Option<ReactiveStep>,
+    security: StatisticalSecurity,
+    session: Option<SessionId>,
 }
 
 /// The party that evaluates the circuit and the output.
 pub struct Evaluator<C: Borrow<Circuit>, I: Borrow<[bool]>> {
     state: Box<EvalState>,
     circuit: C,
-    input: I,
+    input: Option<I>,
+    steps: u32,
+    output_mode: OutputMode,
+    reactive: Option<ReactiveStep>,
+    security: StatisticalSecurity,
+    session: Option<SessionId>,
+}
+
+/// Determines which of the parties learn the output of the computation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OutputMode {
+    /// The [`Evaluator`] learns the output and can then reveal it to the [`Contributor`].
+    #[default]
+    Revealed,
+    /// Neither party learns the output, both parties end up with [`OutputShares`] instead.
+    Shared,
+}
+
+/// The error of [`Contributor::try_run`] and [`Evaluator::try_run`].
+pub enum StepError<P> {
+    /// The step needs the input of the party, which has not been provided yet.
+    ///
+    /// Contains the unchanged party, which can run the step once the input has been provided.
+    MissingInput(P),
+    /// The step failed and the party cannot continue.
+    Failed(Error),
+}
+
+impl<P> From<StepError<P>> for Error {
+    fn from(e: StepError<P>) -> Self {
+        match e {
+            StepError::MissingInput(_) => Error::MissingInput,
+            StepError::Failed(e) => e,
+        }
+    }
+}
+
+/// Determines against which kind of parties the protocol is secure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SecurityLevel {
+    /// Authenticated garbling, which is secure even if one of the parties deviates from the
+    /// protocol in order to learn the other party's input or to modify the output.
+    #[default]
+    Malicious,
+    /// Classic Yao garbling with free-XOR, point-and-permute and half-gates, which is much faster
+    /// but only secure if both parties follow the protocol, for example between mutually audited
+    /// parties.
+    ///
+    /// The semi-honest protocol supports neither [`OutputMode::Shared`], nor preprocessing pools or
+    /// reactive computations.
+    SemiHonest,
+    /// Dual execution of the half-gates protocol, in which both parties garble the circuit once
+    /// and evaluate the circuit of the other party, followed by an equality check of the output
+    /// labels of both executions. This costs about twice as much as the
+    /// [`SecurityLevel::SemiHonest`] protocol, which is still much less than the malicious
+    /// protocol.
+    ///
+    /// # Leakage
+    ///
+    /// The protocol is secure against malicious parties except for a leakage of a single bit: a
+    /// party that garbles a wrong circuit or sends wrong labels learns whether the equality check
+    /// succeeds, which can depend on the input of the other party in any way that the cheating
+    /// party chooses, for example on a single bit of the input. A failed check makes the protocol
+    /// abort with [`Error::MacError`], so a party that cheats repeatedly to learn more bits is
+    /// detected after its first failed guess. If the check succeeds, the output is correct and the
+    /// same for both parties.
+    ///
+    /// Dual execution supports neither [`OutputMode::Shared`], nor preprocessing pools or reactive
+    /// computations.
+    DualExecution,
+}
+
+/// The statistical security parameter `ρ` of the protocol, which must be the same for both
+/// parties.
+///
+/// An adversary can break the authenticity of the AND triples with a probability of at most
+/// `2^-ρ`. Higher values require larger buckets of leaky AND triples and thus more preprocessing,
+/// see [`StatisticalSecurity::bucket_size`]. The default is 40 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatisticalSecurity(u32);
+
+impl StatisticalSecurity {
+    /// Creates a statistical security parameter of the specified number of bits, which must be
+    /// between 1 and 128.
+    pub fn new(bits: u32) -> Result<Self, Error> {
+        if bits == 0 || bits as usize > K {
+            return Err(InvalidStatisticalSecurity);
+        }
+        Ok(Self(bits))
+    }
+
+    /// The number of bits of statistical security.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the number of leaky AND triples that are combined into a single AND triple, for a
+    /// circuit with the specified number of AND gates.
+    ///
+    /// The leaky AND triples are randomly assigned to buckets of size `B`, one bucket for each of
+    /// the `n` AND gates. A combined triple is only insecure if all triples of its bucket are bad,
+    /// and every bad triple escapes detection with a probability of at most 1/2. The probability
+    /// that an adversary succeeds is thus bounded by `2^-B · n / binomial(n·B, B)` (WRK17, based on
+    /// the bucketing analysis of Frederiksen et al.), and the bucket size is the smallest `B ≥ 2`
+    /// for which this bound is at most `2^-ρ`.
+    pub fn bucket_size(&self, and_gates: usize) -> usize {
+        let n = and_gates.max(1) as f64;
+        let mut bucket_size = 2;
+        loop {
+            let b = bucket_size as f64;
+            let log2_binomial: f64 = (0..bucket_size)
+                .map(|i| ((n * b - i as f64) / (b - i as f64)).log2())
+                .sum();
+            if -b + n.log2() - log2_binomial <= -(self.0 as f64) {
+                return bucket_size;
+            }
+            bucket_size += 1;
+        }
+    }
+}
+
+impl Default for StatisticalSecurity {
+    fn default() -> Self {
+        Self(40)
+    }
+}
+
+/// The XOR shares of the output bits held by one of the parties when using [`OutputMode::Shared`].
+///
+/// Every share is authenticated by a MAC under the other party's `Delta`, which allows the parties
+/// to open the output jointly at a later point without being able to modify their shares.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OutputShares {
+    pub(crate) delta: Delta,
+    pub(crate) shares: Vec<BitShare>,
+}
+
+impl OutputShares {
+    /// Returns the party's shares of the output bits.
+    ///
+    /// The shares can be used as input of another circuit, which combines the shares of both
+    /// parties using
+    /// [`CircuitBuilder::input_shared`](crate::gadgets::CircuitBuilder::input_shared). The bits
+    /// do not include the MACs, so the other party is not bound to its shares once they are input
+    /// again. Use [`Contributor::reactive`] and [`Evaluator::reactive`] to continue a computation
+    /// that must stay secure against malicious parties.
+    pub fn bits(&self) -> Vec<bool> {
+        self.shares.iter().map(|share| share.bit).collect()
+    }
+
+    /// Returns a message that discloses the shares to the other party, so that it can open the
+    /// output.
+    pub fn disclose(&self) -> Result<Msg, Error> {
+        let shares = self.shares.iter().map(PartialBitShare::from).collect();
+        message::seal(Step::DisclosedShares, None, &DisclosedShares { shares })
+    }
+
+    /// Opens the output, based on the shares disclosed by the other party.
+    ///
+    /// Fails with [`Error::MacError`] if the shares of the other party have been modified.
+    pub fn open(&self, msg: &[u8]) -> Result<Vec<bool>, Error> {
+        let DisclosedShares {
+            shares: other_shares,
+        } = message::open(msg, Step::DisclosedShares, None)?;
+        if other_shares.len() != self.shares.len() {
+            return Err(Error::unexpected_length(self.shares.len(), other_shares.len())
+                .at_step(Some(Step::DisclosedShares)));
+        }
+        let mut checks = MacChecks::new(MacCheck::OutputShare);
+        let mut output = Vec::with_capacity(self.shares.len());
+        for (i, (share, other_share)) in self.shares.iter().zip(other_shares.iter()).enumerate() {
+            checks.verify(i, other_share.verify(&share.key, &self.delta));
+            output.push(share.bit ^ other_share.bit);
+        }
+        checks
+            .finish()
+            .map_err(|e| e.at_step(Some(Step::DisclosedShares)))?;
+        Ok(output)
+    }
 }
 
 impl<C: Borrow<Circuit>, I: Borrow<[bool]>> Contributor<C, I> {
     /// Initializes the contributor, returning a state and an initial message for the [`Evaluator`].
     pub fn new(circuit: C, input: I, rng: ChaCha20Rng) -> Result<(Self, Msg), Error> {
-        let (state, msg) = ContribStep1::init(circuit.borrow(), input.borrow(), rng)?;
+        Self::with_statistical_security(circuit, input, StatisticalSecurity::default(), rng)
+    }
+
+    /// Initializes the contributor with the specified [`StatisticalSecurity`], which must be the
+    /// same for both parties, returning a state and an initial message for the [`Evaluator`].
+    ///
+    /// The evaluator fails with [`Error::StatisticalSecurityMismatch`] if it uses a different
+    /// statistical security.
+    pub fn with_statistical_security(
+        circuit: C,
+        input: I,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        let (state, msg) = ContribStep1::init(circuit.borrow(), input.borrow(), security, rng)?;
+        let msg = message::seal(Step::BaseOtInit, None, &msg)?;
+        let contrib = Self {
+            state: Box::new(ContribState::Step1(state)),
+            circuit,
+            input: Some(input),
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session: None,
+        };
+        Ok((contrib, msg))
+    }
+
+    /// Initializes the contributor at the specified [`SecurityLevel`], which must be the same for
+    /// both parties, returning a state and an initial message for the [`Evaluator`].
+    ///
+    /// At the [`SecurityLevel::SemiHonest`] the contributor garbles the circuit right away and
+    /// sends it as the initial message, after which only 2 steps remain, 3 steps at the
+    /// [`SecurityLevel::DualExecution`].
+    pub fn with_security_level(
+        circuit: C,
+        input: I,
+        security_level: SecurityLevel,
+        rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        match security_level {
+            SecurityLevel::Malicious => Self::new(circuit, input, rng),
+            SecurityLevel::SemiHonest => {
+                let (state, msg) = GarblerInit::garble(circuit.borrow(), input.borrow(), rng)?;
+                let msg = message::seal(Step::HalfGatesCircuit, None, &msg)?;
+                let contrib = Self {
+                    state: Box::new(ContribState::SemiHonest(state)),
+                    circuit,
+                    input: Some(input),
+                    steps: 2,
+                    output_mode: OutputMode::Revealed,
+                    reactive: None,
+                    security: StatisticalSecurity::default(),
+                    session: None,
+                };
+                Ok((contrib, msg))
+            }
+            SecurityLevel::DualExecution => {
+                let (state, msg) = ContribDual1::garble(circuit.borrow(), input.borrow(), rng)?;
+                let msg = message::seal(Step::DualCircuit, None, &msg)?;
+                let contrib = Self {
+                    state: Box::new(ContribState::Dual1(state)),
+                    circuit,
+                    input: Some(input),
+                    steps: 3,
+                    output_mode: OutputMode::Revealed,
+                    reactive: None,
+                    security: StatisticalSecurity::default(),
+                    session: None,
+                };
+                Ok((contrib, msg))
+            }
+        }
+    }
+
+    /// Initializes the contributor without an input, returning a state and an initial message for
+    /// the [`Evaluator`].
+    ///
+    /// All steps of the preprocessing phase can run before the input is known, which must then be
+    /// supplied using [`Contributor::provide_input`] before the input processing phase. The
+    /// [`StatisticalSecurity`] must be the same for both parties.
+    pub fn new_without_input(
+        circuit: C,
+        security: StatisticalSecurity,
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        circuit.borrow().validate()?;
+        let delta = Delta::gen_random(&mut rng);
+        let size = PreprocessingSize::of(circuit.borrow(), security);
+        let context = circuit.borrow().blake3_hash();
+        let (state, msg) = ContribStep1::init_with_size(delta, rng, size, context)?;
+        let msg = message::seal(Step::BaseOtInit, None, &msg)?;
         let contrib = Self {
             state: Box::new(ContribState::Step1(state)),
             circuit,
-            input,
+            input: None,
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session: None,
         };
         Ok((contrib, msg))
     }
 
+    /// Supplies the input of a contributor that was initialized without an input.
+    ///
+    /// Fails with [`Error::InputAlreadyProvided`] if the contributor already has an input.
+    pub fn provide_input(&mut self, input: I) -> Result<(), Error> {
+        if self.input.is_some() {
+            return Err(Error::InputAlreadyProvided);
+        }
+        let full_input = full_input(self.reactive.as_ref(), input.borrow());
+        self.circuit
+            .borrow()
+            .validate_contributor_input(&full_input)?;
+        self.input = Some(input);
+        Ok(())
+    }
+
+    /// Returns true if the next step cannot run until the input has been provided.
+    pub fn needs_input(&self) -> bool {
+        self.input.is_none()
+            && matches!(*self.state, ContribState::Step5(_) | ContribState::Dual2(_))
+    }
+
+    /// Sets the [`OutputMode`], which must be the same for both parties.
+    ///
+    /// The output mode must be set before the last step of the contributor. Only the
+    /// [`SecurityLevel::Malicious`] protocol can keep the output shared, all other levels fail
+    /// with [`Error::UnsupportedSecurityLevel`] for the [`OutputMode::Shared`].
+    pub fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Initializes the contributor for one execution of a reactive computation, returning a state
+    /// and an initial message for the [`Evaluator`].
+    ///
+    /// The contributor uses the `Delta` of its [`ReactiveState`] and the state handles loaded by
+    /// the `bindings` as its first input bits, followed by the `input`. Once the protocol has
+    /// ended, the stored handles are added to the state using [`Contributor::update_state`]. The
+    /// [`StatisticalSecurity`] must be the same for both parties.
+    pub fn reactive(
+        circuit: C,
+        input: I,
+        state: &ReactiveState,
+        bindings: &StateBindings,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        let step = state.step(circuit.borrow(), bindings)?;
+        let full_input = full_input(Some(&step), input.borrow());
+        circuit.borrow().validate_contributor_input(&full_input)?;
+        circuit.borrow().validate()?;
+        let size = PreprocessingSize::of(circuit.borrow(), security);
+        let context = circuit.borrow().blake3_hash();
+        let (s, msg) = ContribStep1::init_with_size(state.delta().clone(), rng, size, context)?;
+        let msg = message::seal(Step::BaseOtInit, None, &msg)?;
+        let contrib = Self {
+            state: Box::new(ContribState::Step1(s)),
+            circuit,
+            input: Some(input),
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: Some(step),
+            security,
+            session: None,
+        };
+        Ok((contrib, msg))
+    }
+
+    /// Initializes the contributor using authenticated bits and AND triples from a [`Pool`],
+    /// returning a state and an initial message for the [`Evaluator`].
+    ///
+    /// All the circuit-independent preprocessing has already happened when generating the pool,
+    /// which is why only 1 step remains. The evaluator must consume the same part of its own pool
+    /// using [`Evaluator::with_pool`]. The statistical security is the one of the pool, see
+    /// [`Pool::security`].
+    pub fn with_pool(
+        circuit: C,
+        input: I,
+        pool: &mut Pool,
+        mut rng: ChaCha20Rng,
+    ) -> Result<(Self, Msg), Error> {
+        circuit
+            .borrow()
+            .validate_contributor_input(input.borrow())?;
+        circuit.borrow().validate()?;
+        let security = pool.security();
+        let size = PreprocessingSize::of(circuit.borrow(), security);
+        let (position, preprocessed) = pool.take(size, circuit.borrow().blake3_hash())?;
+        let session = Some(preprocessed.session);
+        let (state, masks) = assign_contrib_masks(preprocessed, &mut rng, circuit.borrow())?;
+        let msg = message::seal(Step::PooledMasks, session, &PooledMasks { position, masks })?;
+        let contrib = Self {
+            state: Box::new(ContribState::Step5(state)),
+            circuit,
+            input: Some(input),
+            steps: 1,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session,
+        };
+        Ok((contrib, msg))
+    }
+
+    /// Serializes the state of the contributor, so that it can continue the protocol after being
+    /// restored using [`Contributor::restore`], for example in a new process.
+    ///
+    /// The saved state contains the secret `Delta`, MAC keys and RNG of the contributor, which is
+    /// why it is encrypted and authenticated if a `key` is provided. The circuit and the input are
+    /// not part of the saved state.
+    ///
+    /// A state must be saved again after every step: restoring an older copy and running it on a
+    /// different message reuses its randomness, which might reveal secrets to the evaluator.
+    pub fn save(&self, key: Option<&[u8; 32]>) -> Result<Vec<u8>, Error> {
+        let saved = SavedParty {
+            state: &self.state,
+            steps: self.steps,
+            output_mode: self.output_mode,
+            reactive: &self.reactive,
+            security: self.security,
+            session: self.session,
+        };
+        let circuit = self.circuit.borrow().blake3_hash();
+        persistence::save(Party::Contributor, circuit, &saved, key)
+    }
+
+    /// Restores a contributor that was saved using [`Contributor::save`] with the same `key`,
+    /// continuing with the same RNG position and `Delta`.
+    ///
+    /// The `input` can be omitted and provided later using [`Contributor::provide_input`] if the
+    /// contributor was initialized without an input. Fails with [`Error::InvalidSavedState`] if the
+    /// state was saved by an evaluator or for a different circuit and with
+    /// [`Error::InvalidStateKey`] if it cannot be decrypted with the `key`.
+    pub fn restore(
+        circuit: C,
+        input: Option<I>,
+        saved: &[u8],
+        key: Option<&[u8; 32]>,
+    ) -> Result<Self, Error> {
+        let context = circuit.borrow().blake3_hash();
+        let saved: SavedParty<Box<ContribState>, Option<ReactiveStep>> =
+            persistence::restore(Party::Contributor, context, saved, key)?;
+        let mut contrib = Self {
+            state: saved.state,
+            circuit,
+            input: None,
+            steps: saved.steps,
+            output_mode: saved.output_mode,
+            reactive: saved.reactive,
+            security: saved.security,
+            session: saved.session,
+        };
+        if let Some(input) = input {
+            contrib.provide_input(input)?;
+        }
+        Ok(contrib)
+    }
+
     /// Returns the number of messages that need to be exchanged before the protocol is completed.
     ///
     /// When the end state is reached, the contributor's last message will enable the [`Evaluator`]
-    /// to compute the final output.
+    /// to compute the final output. If the evaluator opened the protocol using
+    /// [`Evaluator::initiate`], the contributor needs 1 additional step, which is only included
+    /// once it has run on the opening message.
     pub fn steps(&self) -> u32 {
-        7
+        self.steps
     }
 
     /// Executes a single step in the protocol, based on the message received from the [`Evaluator`].
+    ///
+    /// Fails with [`Error::UnexpectedStep`] if the message belongs to a different step and with
+    /// [`Error::SessionMismatch`] if it belongs to a different execution of the protocol. Fails
+    /// with [`Error::MissingInput`] if the step needs an input that has not been provided yet,
+    /// which [`Contributor::try_run`] recovers from.
     pub fn run(self, msg: &[u8]) -> Result<(Contributor<C, I>, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    /// Executes a single step like [`Contributor::run`], but returns the contributor unchanged if
+    /// the step needs an input that has not been provided yet.
+    ///
+    /// Unlike [`Contributor::run`], which fails with [`Error::MissingInput`] after consuming the
+    /// contributor and its preprocessing, the step can then be retried after
+    /// [`Contributor::provide_input`].
+    pub fn try_run(self, msg: &[u8]) -> Result<(Contributor<C, I>, Msg), StepError<Self>> {
+        if self.needs_input() {
+            return Err(StepError::MissingInput(self));
+        }
+        self.run(msg).map_err(StepError::Failed)
+    }
+
+    fn run_step(self, msg: &[u8]) -> Result<(Contributor<C, I>, Msg), Error> {
         use ContribState::*;
 
+        if self.output_mode == OutputMode::Shared && !self.state.supports_shared_output() {
+            return Err(Error::UnsupportedSecurityLevel);
+        }
+        let shared_outputs = self.shared_outputs();
+        let size = self.preprocessing_size();
+        let mut session = self.session;
+        let mut steps = self.steps;
         let (state, msg) = match *self.state {
-            Step1(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step1a(state)), msg)
+            Step1(s) if message::step(msg) == Some(Step::EvaluatorOtInit) => {
+                // the initial message of the contributor is sent as part of its reply instead,
+                // which takes 1 additional step:
+                steps += 1;
+                let msg = message::open(msg, Step::EvaluatorOtInit, session)?;
+                let (state, reply) = s.respond(msg)?;
+                let reply = message::seal(Step::ContributorOtResponse, session, &reply)?;
+                (Box::new(Responder1(state)), reply)
+            }
+            Responder1(s) => {
+                let msg = message::open(msg, Step::EvaluatorOtCompletion, session)?;
+                let (state, reply) = s.run(msg, size)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::ContributorOtExtension, session, &reply)?;
+                (Box::new(Responder2(state)), reply)
+            }
+            Responder2(s) => {
+                let msg = message::open(msg, Step::EvaluatorLeakyAndStart, session)?;
+                let (state, reply) = s.run(msg)?;
+                let reply = message::seal(Step::ContributorLeakyAndShares, session, &reply)?;
+                (Box::new(Step3(state)), reply)
             }
-            Step1a(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow())?;
-                (Box::new(Step2(state)), msg)
+            Step1(s) => {
+                let (state, reply) = s.run(message::open(msg, Step::BaseOtResponse, session)?)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::BaseOtCompletion, session, &reply)?;
+                (Box::new(Step2(state)), reply)
             }
             Step2(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step3(state)), msg)
+                let msg = message::open(msg, Step::OtExtension, session)?;
+                let (state, reply) = s.run(msg, size)?;
+                let reply = message::seal(Step::LeakyAndStart, session, &reply)?;
+                (Box::new(Step3(state)), reply)
             }
             Step3(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step4(state)), msg)
+                let msg = message::open(msg, Step::LeakyAndCorrections, session)?;
+                let (state, reply) = s.run(msg)?;
+                let reply = message::seal(Step::LeakyAndCommitments, session, &reply)?;
+                (Box::new(Step4(state)), reply)
             }
             Step4(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow())?;
-                (Box::new(Step5(ContribBucketingStep(state))), msg)
+                let msg = message::open(msg, Step::LeakyAndOpenings, session)?;
+                let ((preprocessed, mut rng), (reply1, reply2)) = s.run(msg, size)?;
+                let circuit = self.circuit.borrow();
+                let (state, reply3) = assign_contrib_masks(preprocessed, &mut rng, circuit)?;
+                let reply = (reply1, reply2, reply3);
+                let reply = message::seal(Step::BucketingAndMasks, session, &reply)?;
+                (Box::new(Step5(state)), reply)
             }
             Step5(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow(), self.input.borrow())?;
-                (Box::new(Step6(state)), msg)
+                let input = required_input(&self.input)?;
+                let input = full_input(self.reactive.as_ref(), input);
+                let circuit = self.circuit.borrow();
+                let loaded = self.reactive.as_ref().map_or(&[][..], |r| &r.loaded);
+                let (msg1, msg2) = message::open(msg, Step::InputMasks, session)?;
+                // the garbled tables are sent together with the labels of the inputs, every row of
+                // the tables stays authenticated by the MACs of the evaluator:
+                let (state, reply1) = ot_ands8_contrib(s, msg1, circuit)?;
+                let (state, reply2) = state.run(msg2, circuit, &input, loaded, shared_outputs)?;
+                let reply = message::seal(Step::EvaluationInputs, session, &(reply1, reply2))?;
+                (Box::new(Done(state)), reply)
             }
-            Step6(s) => {
-                let ((), msg) = s.run(msg, self.circuit.borrow(), self.input.borrow())?;
-                (Box::new(Done), msg)
+            SemiHonest(s) => {
+                let (state, reply) = s.run(message::open(msg, Step::HalfGatesOtInit, session)?)?;
+                let reply = message::seal(Step::HalfGatesOtResponse, session, &reply)?;
+                (Box::new(SemiHonestOt(state)), reply)
+            }
+            SemiHonestOt(s) => {
+                let msg = message::open(msg, Step::HalfGatesOtExtension, session)?;
+                let (state, reply) = s.run(msg)?;
+                let reply = message::seal(Step::HalfGatesInputLabels, session, &reply)?;
+                (Box::new(SemiHonestDone(state)), reply)
+            }
+            Dual1(s) => {
+                let msg = message::open(msg, Step::DualOtInit, session)?;
+                let (state, reply) = s.run(msg, self.circuit.borrow())?;
+                let reply = message::seal(Step::DualOtResponse, session, &reply)?;
+                (Box::new(Dual2(state)), reply)
+            }
+            Dual2(s) => {
+                let input = required_input(&self.input)?;
+                let msg = message::open(msg, Step::DualOtExtension, session)?;
+                let (state, reply) = s.run(msg, input)?;
+                let reply = message::seal(Step::DualInputLabels, session, &reply)?;
+                (Box::new(Dual3(state)), reply)
             }
-            Done => return Err(Error::ProtocolEnded),
+            Dual3(s) => {
+                let msg = message::open(msg, Step::DualCommitment, session)?;
+                let (state, reply) = s.run(msg, self.circuit.borrow())?;
+                let reply = message::seal(Step::DualDigest, session, &reply)?;
+                (Box::new(DualDone(state)), reply)
+            }
+            Done(_) | SemiHonestDone(_) | DualDone(_) => return Err(Error::ProtocolEnded),
         };
         let next_state = Contributor {
             state,
             circuit: self.circuit,
             input: self.input,
+            steps,
+            output_mode: self.output_mode,
+            reactive: self.reactive,
+            security: self.security,
+            session,
         };
         Ok((next_state, msg))
     }
+
+    /// Returns the output of the computation, based on the message sent by
+    /// [`Evaluator::reveal_output`] after the protocol has ended.
+    ///
+    /// The output is authenticated using the contributor's MACs and fails with
+    /// [`Error::MacError`] if the evaluator tampered with it.
+    pub fn output(self, msg: &[u8]) -> Result<Vec<bool>, Error> {
+        match *self.state {
+            ContribState::Done(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::OutputModeMismatch)
+            }
+            ContribState::Done(s) => s
+                .run(message::open(msg, Step::RevealedOutput, self.session)?)
+                .map_err(|e| e.at_step(Some(Step::RevealedOutput))),
+            ContribState::SemiHonestDone(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            ContribState::SemiHonestDone(s) => s
+                .run(message::open(msg, Step::HalfGatesOutputLabels, self.session)?)
+                .map_err(|e| e.at_step(Some(Step::HalfGatesOutputLabels))),
+            ContribState::DualDone(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            ContribState::DualDone(s) => s
+                .run(message::open(msg, Step::DualOpening, self.session)?)
+                .map_err(|e| e.at_step(Some(Step::DualOpening))),
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+
+    /// Returns the contributor's shares of the output after the protocol has ended, if the
+    /// [`OutputMode::Shared`] is used.
+    pub fn output_shares(self) -> Result<OutputShares, Error> {
+        match *self.state {
+            ContribState::Done(_) if self.output_mode != OutputMode::Shared => {
+                Err(Error::OutputModeMismatch)
+            }
+            ContribState::Done(s) => Ok(s.shares()),
+            ContribState::SemiHonestDone(_) | ContribState::DualDone(_) => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+
+    /// Adds the handles stored by a reactive execution to the [`ReactiveState`] of the contributor
+    /// after the protocol has ended.
+    pub fn update_state(&self, state: &mut ReactiveState) -> Result<(), Error> {
+        match (&*self.state, &self.reactive) {
+            (ContribState::Done(_), _) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::OutputModeMismatch)
+            }
+            (ContribState::Done(s), Some(step)) => state.store(step, s.shares()),
+            (ContribState::Done(_), None) => Err(Error::OutputModeMismatch),
+            (ContribState::SemiHonestDone(_) | ContribState::DualDone(_), _) => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+
+    /// The number of authenticated bits and AND triples required for the circuit.
+    fn preprocessing_size(&self) -> PreprocessingSize {
+        PreprocessingSize::of(self.circuit.borrow(), self.security)
+    }
+
+    /// The number of outputs that stay secret-shared, all other outputs are revealed.
+    fn shared_outputs(&self) -> usize {
+        match (self.output_mode, &self.reactive) {
+            (OutputMode::Shared, _) => self.circuit.borrow().output_gates().len(),
+            (OutputMode::Revealed, Some(step)) => step.stored_len(),
+            (OutputMode::Revealed, None) => 0,
+        }
+    }
 }
 
 impl<C: Borrow<Circuit>, I: Borrow<[bool]>> Evaluator<C, I> {
     /// Initializes the evaluator, returning its initial state.
     pub fn new(circuit: C, input: I, rng: ChaCha20Rng) -> Result<Self, Error> {
+        Self::with_statistical_security(circuit, input, StatisticalSecurity::default(), rng)
+    }
+
+    /// Initializes the evaluator with the specified [`StatisticalSecurity`], which must be the
+    /// same for both parties, returning its initial state.
+    ///
+    /// The contributor fails with [`Error::StatisticalSecurityMismatch`] if it uses a different
+    /// statistical security.
+    pub fn with_statistical_security(
+        circuit: C,
+        input: I,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
         let state = EvalStep1::init(circuit.borrow(), input.borrow(), rng)?;
         Ok(Self {
             state: Box::new(EvalState::Step1(state)),
             circuit,
-            input,
+            input: Some(input),
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session: None,
         })
     }
 
+    /// Initializes the evaluator at the specified [`SecurityLevel`], which must be the same for
+    /// both parties, returning its initial state.
+    pub fn with_security_level(
+        circuit: C,
+        input: I,
+        security_level: SecurityLevel,
+        rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
+        match security_level {
+            SecurityLevel::Malicious => Self::new(circuit, input, rng),
+            SecurityLevel::SemiHonest => {
+                circuit
+                    .borrow()
+                    .validate_evaluator_input(input.borrow())?;
+                Ok(Self {
+                    state: Box::new(EvalState::SemiHonest(EvaluatorInit::new(rng))),
+                    circuit,
+                    input: Some(input),
+                    steps: 2,
+                    output_mode: OutputMode::Revealed,
+                    reactive: None,
+                    security: StatisticalSecurity::default(),
+                    session: None,
+                })
+            }
+            SecurityLevel::DualExecution => {
+                circuit
+                    .borrow()
+                    .validate_evaluator_input(input.borrow())?;
+                Ok(Self {
+                    state: Box::new(EvalState::Dual1(EvalDual1::new(rng))),
+                    circuit,
+                    input: Some(input),
+                    steps: 3,
+                    output_mode: OutputMode::Revealed,
+                    reactive: None,
+                    security: StatisticalSecurity::default(),
+                    session: None,
+                })
+            }
+        }
+    }
+
+    /// Initializes the evaluator without an input, returning its initial state.
+    ///
+    /// All steps of the preprocessing phase can run before the input is known, which must then be
+    /// supplied using [`Evaluator::provide_input`] before the input processing phase. The
+    /// [`StatisticalSecurity`] must be the same for both parties.
+    pub fn new_without_input(
+        circuit: C,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
+        circuit.borrow().validate()?;
+        Ok(Self {
+            state: Box::new(EvalState::Step1(EvalStep1::new(rng))),
+            circuit,
+            input: None,
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session: None,
+        })
+    }
+
+    /// Supplies the input of an evaluator that was initialized without an input.
+    ///
+    /// Fails with [`Error::InputAlreadyProvided`] if the evaluator already has an input.
+    pub fn provide_input(&mut self, input: I) -> Result<(), Error> {
+        if self.input.is_some() {
+            return Err(Error::InputAlreadyProvided);
+        }
+        let full_input = full_input(self.reactive.as_ref(), input.borrow());
+        self.circuit.borrow().validate_evaluator_input(&full_input)?;
+        self.input = Some(input);
+        Ok(())
+    }
+
+    /// Returns true if the next step cannot run until the input has been provided.
+    pub fn needs_input(&self) -> bool {
+        self.input.is_none()
+            && matches!(
+                *self.state,
+                EvalState::Step5(_)
+                    | EvalState::Pooled(_)
+                    | EvalState::SemiHonestOt(_)
+                    | EvalState::Dual1(_)
+                    | EvalState::Dual2(_)
+            )
+    }
+
+    /// Sets the [`OutputMode`], which must be the same for both parties.
+    ///
+    /// Only the [`SecurityLevel::Malicious`] protocol can keep the output shared, all other levels
+    /// fail with [`Error::UnsupportedSecurityLevel`] for the [`OutputMode::Shared`].
+    pub fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Initializes the evaluator for one execution of a reactive computation, returning its
+    /// initial state.
+    ///
+    /// The evaluator uses the `Delta` of its [`ReactiveState`] and the state handles loaded by the
+    /// `bindings` as its first input bits, followed by the `input`. The stored handles are added
+    /// to the state using [`Evaluator::update_state`]. The [`StatisticalSecurity`] must be the
+    /// same for both parties.
+    pub fn reactive(
+        circuit: C,
+        input: I,
+        state: &ReactiveState,
+        bindings: &StateBindings,
+        security: StatisticalSecurity,
+        rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
+        let step = state.step(circuit.borrow(), bindings)?;
+        let full_input = full_input(Some(&step), input.borrow());
+        circuit.borrow().validate_evaluator_input(&full_input)?;
+        Ok(Self {
+            state: Box::new(EvalState::Step1(EvalStep1::with_delta(
+                state.delta().clone(),
+                rng,
+            ))),
+            circuit,
+            input: Some(input),
+            steps: 5,
+            output_mode: OutputMode::Revealed,
+            reactive: Some(step),
+            security,
+            session: None,
+        })
+    }
+
+    /// Initializes the evaluator using authenticated bits and AND triples from a [`Pool`],
+    /// returning its initial state.
+    ///
+    /// The evaluator checks that the [`Contributor`] consumed the same part of its pool, failing
+    /// with [`Error::PoolMismatch`] otherwise. The statistical security is the one of the pool, see
+    /// [`Pool::security`].
+    pub fn with_pool(
+        circuit: C,
+        input: I,
+        pool: &mut Pool,
+        mut rng: ChaCha20Rng,
+    ) -> Result<Self, Error> {
+        circuit.borrow().validate_evaluator_input(input.borrow())?;
+        circuit.borrow().validate()?;
+        let security = pool.security();
+        let size = PreprocessingSize::of(circuit.borrow(), security);
+        let (position, preprocessed) = pool.take(size, circuit.borrow().blake3_hash())?;
+        let (state, masks) = assign_masks(preprocessed, &mut rng, circuit.borrow())?;
+        Ok(Self {
+            state: Box::new(EvalState::Pooled(EvalPooledStep {
+                position,
+                state,
+                masks,
+            })),
+            circuit,
+            input: Some(input),
+            steps: 1,
+            output_mode: OutputMode::Revealed,
+            reactive: None,
+            security,
+            session: None,
+        })
+    }
+
+    /// Opens the protocol instead of the [`Contributor`], returning a state and the opening
+    /// message, so that the evaluator can already send protocol data with its first message, for
+    /// example in the request that creates a session with a server.
+    ///
+    /// The contributor then does not send its initial message on its own, but runs its first
+    /// step on the opening message and includes its initial message in the reply. This takes 1
+    /// additional message, the number of steps of the evaluator stays the same.
+    ///
+    /// Fails with [`Error::UnsupportedSecurityLevel`] at the [`SecurityLevel::SemiHonest`] and the
+    /// [`SecurityLevel::DualExecution`] and with [`Error::CannotInitiate`] if the evaluator uses a
+    /// [`Pool`] or has already run a step.
+    pub fn initiate(self) -> Result<(Self, Msg), Error> {
+        let size = self.preprocessing_size();
+        let context = self.circuit.borrow().blake3_hash();
+        let (state, msg) = match *self.state {
+            EvalState::Step1(s) => {
+                self.circuit.borrow().validate()?;
+                let (state, msg) = s.initiate(size, context)?;
+                let msg = message::seal(Step::EvaluatorOtInit, None, &msg)?;
+                (EvalState::Initiator1(state), msg)
+            }
+            EvalState::SemiHonest(_) | EvalState::Dual1(_) => {
+                return Err(Error::UnsupportedSecurityLevel)
+            }
+            _ => return Err(Error::CannotInitiate),
+        };
+        let eval = Self {
+            state: Box::new(state),
+            ..self
+        };
+        Ok((eval, msg))
+    }
+
     /// Returns the number of messages that need to be exchanged before reaching the end state.
     ///
     /// After the end state is reached, the evaluator expects one last message from the
     /// [`Contributor`] to compute the final output.
     pub fn steps(&self) -> u32 {
-        7
+        self.steps
+    }
+
+    /// Serializes the state of the evaluator, so that it can continue the protocol after being
+    /// restored using [`Evaluator::restore`], for example in a new process.
+    ///
+    /// The saved state contains the secret `Delta`, MAC keys and RNG of the evaluator, which is
+    /// why it is encrypted and authenticated if a `key` is provided. The circuit and the input are
+    /// not part of the saved state.
+    ///
+    /// A state must be saved again after every step: restoring an older copy and running it on a
+    /// different message reuses its randomness, which might reveal secrets to the contributor.
+    pub fn save(&self, key: Option<&[u8; 32]>) -> Result<Vec<u8>, Error> {
+        let saved = SavedParty {
+            state: &self.state,
+            steps: self.steps,
+            output_mode: self.output_mode,
+            reactive: &self.reactive,
+            security: self.security,
+            session: self.session,
+        };
+        let circuit = self.circuit.borrow().blake3_hash();
+        persistence::save(Party::Evaluator, circuit, &saved, key)
+    }
+
+    /// Restores an evaluator that was saved using [`Evaluator::save`] with the same `key`,
+    /// continuing with the same RNG position and `Delta`.
+    ///
+    /// The `input` can be omitted and provided later using [`Evaluator::provide_input`] if the
+    /// evaluator was initialized without an input. Fails with [`Error::InvalidSavedState`] if the
+    /// state was saved by a contributor or for a different circuit and with
+    /// [`Error::InvalidStateKey`] if it cannot be decrypted with the `key`.
+    pub fn restore(
+        circuit: C,
+        input: Option<I>,
+        saved: &[u8],
+        key: Option<&[u8; 32]>,
+    ) -> Result<Self, Error> {
+        let context = circuit.borrow().blake3_hash();
+        let saved: SavedParty<Box<EvalState>, Option<ReactiveStep>> =
+            persistence::restore(Party::Evaluator, context, saved, key)?;
+        let mut eval = Self {
+            state: saved.state,
+            circuit,
+            input: None,
+            steps: saved.steps,
+            output_mode: saved.output_mode,
+            reactive: saved.reactive,
+            security: saved.security,
+            session: saved.session,
+        };
+        if let Some(input) = input {
+            eval.provide_input(input)?;
+        }
+        Ok(eval)
     }
 
     /// Executes a single step in the protocol, based on the message received from the [`Contributor`].
+    ///
+    /// Fails with [`Error::UnexpectedStep`] if the message belongs to a different step and with
+    /// [`Error::SessionMismatch`] if it belongs to a different execution of the protocol. Fails
+    /// with [`Error::MissingInput`] if the step needs an input that has not been provided yet,
+    /// which [`Evaluator::try_run`] recovers from.
     pub fn run(self, msg: &[u8]) -> Result<(Evaluator<C, I>, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    /// Executes a single step like [`Evaluator::run`], but returns the evaluator unchanged if the
+    /// step needs an input that has not been provided yet.
+    ///
+    /// Unlike [`Evaluator::run`], which fails with [`Error::MissingInput`] after consuming the
+    /// evaluator and its preprocessing, the step can then be retried after
+    /// [`Evaluator::provide_input`].
+    pub fn try_run(self, msg: &[u8]) -> Result<(Evaluator<C, I>, Msg), StepError<Self>> {
+        if self.needs_input() {
+            return Err(StepError::MissingInput(self));
+        }
+        self.run(msg).map_err(StepError::Failed)
+    }
+
+    fn run_step(self, msg: &[u8]) -> Result<(Evaluator<C, I>, Msg), Error> {
         use EvalState::*;
 
+        if self.output_mode == OutputMode::Shared && !self.state.supports_shared_output() {
+            return Err(Error::UnsupportedSecurityLevel);
+        }
+        let shared_outputs = self.shared_outputs();
+        let size = self.preprocessing_size();
+        let loaded = self.reactive.as_ref().map_or(&[][..], |r| &r.loaded);
+        let mut session = self.session;
         let (state, msg) = match *self.state {
             Step1(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow())?;
-                (Box::new(Step2(state)), msg)
+                self.circuit.borrow().validate()?;
+                let context = self.circuit.borrow().blake3_hash();
+                let msg = message::open(msg, Step::BaseOtInit, session)?;
+                let (state, reply) = s.run(msg, size, context)?;
+                let reply = message::seal(Step::BaseOtResponse, session, &reply)?;
+                (Box::new(Step2(state)), reply)
             }
             Step2(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step2a(state)), msg)
+                let msg = message::open(msg, Step::BaseOtCompletion, session)?;
+                let (state, reply) = s.run(msg, size)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::OtExtension, session, &reply)?;
+                (Box::new(Step3(state)), reply)
+            }
+            Initiator1(s) => {
+                let msg = message::open(msg, Step::ContributorOtResponse, session)?;
+                let (state, reply) = s.run(msg)?;
+                session = Some(state.session());
+                let reply = message::seal(Step::EvaluatorOtCompletion, session, &reply)?;
+                (Box::new(Initiator2(state)), reply)
             }
-            Step2a(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow())?;
-                (Box::new(Step3(state)), msg)
+            Initiator2(s) => {
+                let msg = message::open(msg, Step::ContributorOtExtension, session)?;
+                let (state, reply) = s.run(msg, size)?;
+                let reply = message::seal(Step::EvaluatorLeakyAndStart, session, &reply)?;
+                (Box::new(Initiator3(state)), reply)
+            }
+            Initiator3(s) => {
+                let msg = message::open(msg, Step::ContributorLeakyAndShares, session)?;
+                let (state, reply) = s.run(msg)?;
+                let reply = message::seal(Step::LeakyAndCorrections, session, &reply)?;
+                (Box::new(Step4(state)), reply)
             }
             Step3(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step4(state)), msg)
+                let (state, reply) = s.run(message::open(msg, Step::LeakyAndStart, session)?)?;
+                let reply = message::seal(Step::LeakyAndCorrections, session, &reply)?;
+                (Box::new(Step4(state)), reply)
             }
             Step4(s) => {
-                let (state, msg) = s.run(msg)?;
-                (Box::new(Step5(state)), msg)
+                let msg = message::open(msg, Step::LeakyAndCommitments, session)?;
+                let (state, reply) = s.run(msg, size)?;
+                let reply = message::seal(Step::LeakyAndOpenings, session, &reply)?;
+                (Box::new(Step5(state)), reply)
             }
             Step5(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow())?;
-                (Box::new(Step6(state)), msg)
+                let circuit = self.circuit.borrow();
+                let input = full_input(self.reactive.as_ref(), required_input(&self.input)?);
+                let (msg1, msg2, msg3) = message::open(msg, Step::BucketingAndMasks, session)?;
+                let (preprocessed, mut rng) = s.bucketing((msg1, msg2))?;
+                let (state, masks) = assign_masks(preprocessed, &mut rng, circuit)?;
+                let (state, reply) = ot_ands8_eval(state, msg3, circuit, &input, loaded)?;
+                let reply = message::seal(Step::InputMasks, session, &(masks, reply))?;
+                (Box::new(Step6(state)), reply)
             }
             Step6(s) => {
-                let (state, msg) = s.run(msg, self.circuit.borrow(), self.input.borrow())?;
-                (Box::new(Step8(state)), msg)
-            }
-            Step8(s) => {
-                let (_, _) = s.run(msg, self.circuit.borrow())?;
+                let msg = message::open(msg, Step::EvaluationInputs, session)?;
+                s.run(msg, self.circuit.borrow(), shared_outputs)?;
                 (Box::new(Done()), vec![])
             }
-            Done() => return Err(Error::ProtocolEnded),
+            Pooled(s) => {
+                // the session of the contributor is only known to match once it is known to have
+                // consumed the same part of its pool
+                let other: PooledMasks = message::open(msg, Step::PooledMasks, None)?;
+                if other.position != s.position {
+                    return Err(Error::PoolMismatch);
+                }
+                session = Some(s.state.session);
+                let circuit = self.circuit.borrow();
+                let input = full_input(self.reactive.as_ref(), required_input(&self.input)?);
+                let (state, reply) = ot_ands8_eval(s.state, other.masks, circuit, &input, loaded)?;
+                let reply = message::seal(Step::InputMasks, session, &(s.masks, reply))?;
+                (Box::new(Step6(state)), reply)
+            }
+            SemiHonest(s) => {
+                let msg = message::open(msg, Step::HalfGatesCircuit, session)?;
+                let (state, reply) = s.run(msg, self.circuit.borrow())?;
+                let reply = message::seal(Step::HalfGatesOtInit, session, &reply)?;
+                (Box::new(SemiHonestOt(state)), reply)
+            }
+            SemiHonestOt(s) => {
+                let input = required_input(&self.input)?;
+                let msg = message::open(msg, Step::HalfGatesOtResponse, session)?;
+                let (state, reply) = s.run(msg, input)?;
+                let reply = message::seal(Step::HalfGatesOtExtension, session, &reply)?;
+                (Box::new(SemiHonestOutput(state)), reply)
+            }
+            Dual1(s) => {
+                let input = required_input(&self.input)?;
+                let msg = message::open(msg, Step::DualCircuit, session)?;
+                let (state, reply) = s.run(msg, self.circuit.borrow(), input)?;
+                let reply = message::seal(Step::DualOtInit, session, &reply)?;
+                (Box::new(Dual2(state)), reply)
+            }
+            Dual2(s) => {
+                let input = required_input(&self.input)?;
+                let msg = message::open(msg, Step::DualOtResponse, session)?;
+                let (state, reply) = s.run(msg, input)?;
+                let reply = message::seal(Step::DualOtExtension, session, &reply)?;
+                (Box::new(Dual3(state)), reply)
+            }
+            Dual3(s) => {
+                let msg = message::open(msg, Step::DualInputLabels, session)?;
+                let (state, reply) = s.run(msg, self.circuit.borrow())?;
+                let reply = message::seal(Step::DualCommitment, session, &reply)?;
+                (Box::new(DualOutput(state)), reply)
+            }
+            Done() | SemiHonestOutput(_) | DualOutput(_) => return Err(Error::ProtocolEnded),
         };
         let next_state = Evaluator {
             state,
             circuit: self.circuit,
             input: self.input,
+            steps: self.steps,
+            output_mode: self.output_mode,
+            reactive: self.reactive,
+            security: self.security,
+            session,
         };
         Ok((next_state, msg))
     }
 
     /// Returns the output of the computation or `None` if the protocol has not ended.
     pub fn output(self, msg: &[u8]) -> Result<Vec<bool>, Error> {
+        let (output, _) = self.reveal_output(msg)?;
+        Ok(output)
+    }
+
+    /// Returns the output of the computation together with a message for the [`Contributor`],
+    /// which allows it to learn the output using [`Contributor::output`].
+    ///
+    /// The message contains the output wire labels and the evaluator's authenticated shares of the
+    /// output masks, so that the contributor can verify that the output was not modified.
+    pub fn reveal_output(self, msg: &[u8]) -> Result<(Vec<bool>, Msg), Error> {
+        let shared_outputs = self.shared_outputs();
+        match *self.state {
+            EvalState::Step6(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::OutputModeMismatch)
+            }
+            EvalState::Step6(s) => {
+                let msg = message::open(msg, Step::EvaluationInputs, self.session)?;
+                let (output, reply, _) = s
+                    .run(msg, self.circuit.borrow(), shared_outputs)
+                    .map_err(|e| e.at_step(Some(Step::EvaluationInputs)))?;
+                let reply = message::seal(Step::RevealedOutput, self.session, &reply)?;
+                Ok((output, reply))
+            }
+            EvalState::SemiHonestOutput(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            EvalState::SemiHonestOutput(s) => {
+                let msg = message::open(msg, Step::HalfGatesInputLabels, self.session)?;
+                let (output, reply) = s
+                    .run(msg, self.circuit.borrow())
+                    .map_err(|e| e.at_step(Some(Step::HalfGatesInputLabels)))?;
+                let reply = message::seal(Step::HalfGatesOutputLabels, self.session, &reply)?;
+                Ok((output, reply))
+            }
+            EvalState::DualOutput(_) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            EvalState::DualOutput(s) => {
+                let msg = message::open(msg, Step::DualDigest, self.session)?;
+                let (output, reply) = s
+                    .run(msg)
+                    .map_err(|e| e.at_step(Some(Step::DualDigest)))?;
+                let reply = message::seal(Step::DualOpening, self.session, &reply)?;
+                Ok((output, reply))
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+
+    /// Returns the evaluator's shares of the output, based on the last message of the
+    /// [`Contributor`], if the [`OutputMode::Shared`] is used.
+    pub fn output_shares(self, msg: &[u8]) -> Result<OutputShares, Error> {
+        let shared_outputs = self.shared_outputs();
         match *self.state {
-            EvalState::Step8(s) => {
-                let (output, _) = s.run(msg, self.circuit.borrow())?;
-                Ok(output)
+            EvalState::Step6(_) if self.output_mode != OutputMode::Shared => {
+                Err(Error::OutputModeMismatch)
+            }
+            EvalState::Step6(s) => {
+                let msg = message::open(msg, Step::EvaluationInputs, self.session)?;
+                let (_, _, shares) = s
+                    .run(msg, self.circuit.borrow(), shared_outputs)
+                    .map_err(|e| e.at_step(Some(Step::EvaluationInputs)))?;
+                Ok(shares)
+            }
+            EvalState::SemiHonestOutput(_) | EvalState::DualOutput(_) => {
+                Err(Error::UnsupportedSecurityLevel)
+            }
+            _ => Err(Error::ProtocolStillInProgress),
+        }
+    }
+
+    /// Adds the handles stored by a reactive execution to the [`ReactiveState`] of the evaluator,
+    /// based on the last message of the [`Contributor`].
+    ///
+    /// Returns the outputs that are not stored, together with a message that reveals them to the
+    /// contributor, just like [`Evaluator::reveal_output`].
+    pub fn update_state(
+        self,
+        msg: &[u8],
+        state: &mut ReactiveState,
+    ) -> Result<(Vec<bool>, Msg), Error> {
+        let shared_outputs = self.shared_outputs();
+        match (*self.state, self.reactive) {
+            (EvalState::Step6(_), _) if self.output_mode != OutputMode::Revealed => {
+                Err(Error::OutputModeMismatch)
+            }
+            (EvalState::Step6(s), Some(step)) => {
+                let msg = message::open(msg, Step::EvaluationInputs, self.session)?;
+                let (output, reply, shares) = s
+                    .run(msg, self.circuit.borrow(), shared_outputs)
+                    .map_err(|e| e.at_step(Some(Step::EvaluationInputs)))?;
+                state.store(&step, shares)?;
+                Ok((output, message::seal(Step::RevealedOutput, self.session, &reply)?))
+            }
+            (EvalState::Step6(_), None) => Err(Error::OutputModeMismatch),
+            (EvalState::SemiHonestOutput(_) | EvalState::DualOutput(_), _) => {
+                Err(Error::UnsupportedSecurityLevel)
             }
             _ => Err(Error::ProtocolStillInProgress),
         }
     }
+
+    /// The number of authenticated bits and AND triples required for the circuit.
+    fn preprocessing_size(&self) -> PreprocessingSize {
+        PreprocessingSize::of(self.circuit.borrow(), self.security)
+    }
+
+    /// The number of outputs that stay secret-shared, all other outputs are revealed.
+    fn shared_outputs(&self) -> usize {
+        match (self.output_mode, &self.reactive) {
+            (OutputMode::Shared, _) => self.circuit.borrow().output_gates().len(),
+            (OutputMode::Revealed, Some(step)) => step.stored_len(),
+            (OutputMode::Revealed, None) => 0,
+        }
+    }
+}
+
+/// The next state and the payload of the message for the other party.
+type MpcResult<S, P> = Result<(S, P), Error>;
+
+/// Everything that is saved of a party except for its circuit and input, with the state and the
+/// reactive step as references when saving and owned when restoring.
+#[derive(Serialize, Deserialize)]
+struct SavedParty<S, R> {
+    state: S,
+    steps: u32,
+    output_mode: OutputMode,
+    reactive: R,
+    security: StatisticalSecurity,
+    session: Option<SessionId>,
 }
 
-type MpcResult<S> = Result<(S, Msg), Error>;
+/// Returns the input of a party or [`Error::MissingInput`] if it has not been provided yet.
+fn required_input<I: Borrow<[bool]>>(input: &Option<I>) -> Result<&[bool], Error> {
+    input.as_ref().map(|i| i.borrow()).ok_or(MissingInput)
+}
 
+#[derive(Serialize, Deserialize)]
 enum ContribState {
     Step1(ContribStep1),
-    Step1a(ContribStep1a),
     Step2(ContribStep2),
     Step3(ContribStep3),
     Step4(ContribStep4),
-    Step5(ContribBucketingStep),
-    Step6(InputProcContrib),
-    Done,
-}
-
+    Step5(OtAndsState6),
+    Done(OutputContrib),
+    SemiHonest(GarblerInit),
+    SemiHonestOt(GarblerOt),
+    SemiHonestDone(GarblerDone),
+    Dual1(ContribDual1),
+    Dual2(ContribDual2),
+    Dual3(ContribDual3),
+    DualDone(ContribDualDone),
+    /// The steps that replace [`ContribState::Step1`] if the evaluator opened the protocol.
+    Responder1(ContribResponderStep1),
+    Responder2(ContribResponderStep2),
+}
+
+impl ContribState {
+    /// Returns false for the states of the protocols that always reveal the output.
+    fn supports_shared_output(&self) -> bool {
+        !matches!(
+            self,
+            ContribState::SemiHonest(_)
+                | ContribState::SemiHonestOt(_)
+                | ContribState::SemiHonestDone(_)
+                | ContribState::Dual1(_)
+                | ContribState::Dual2(_)
+                | ContribState::Dual3(_)
+                | ContribState::DualDone(_)
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 enum EvalState {
     Step1(EvalStep1),
     Step2(EvalStep2),
-    Step2a(EvalStep2a),
     Step3(EvalStep3),
     Step4(EvalStep4),
     Step5(EvalStep5),
-    Step6(EvalStep6),
-    Step8(InputProcEval),
+    Step6(InputProcEval),
+    Pooled(EvalPooledStep),
     Done(),
+    SemiHonest(EvaluatorInit),
+    SemiHonestOt(EvaluatorOt),
+    SemiHonestOutput(EvaluatorOutput),
+    Dual1(EvalDual1),
+    Dual2(EvalDual2),
+    Dual3(EvalDual3),
+    DualOutput(EvalDualOutput),
+    /// The steps that replace [`EvalState::Step1`] and [`EvalState::Step2`] if the evaluator
+    /// opened the protocol.
+    Initiator1(EvalInitiatorStep1),
+    Initiator2(EvalInitiatorStep2),
+    Initiator3(EvalInitiatorStep3),
+}
+
+impl EvalState {
+    /// Returns false for the states of the protocols that always reveal the output.
+    fn supports_shared_output(&self) -> bool {
+        !matches!(
+            self,
+            EvalState::SemiHonest(_)
+                | EvalState::SemiHonestOt(_)
+                | EvalState::SemiHonestOutput(_)
+                | EvalState::Dual1(_)
+                | EvalState::Dual2(_)
+                | EvalState::Dual3(_)
+                | EvalState::DualOutput(_)
+        )
+    }
+}
+
+/// Waits for the masked AND triple bits of the contributor, which must come from the same part of
+/// its pool.
+#[derive(Serialize, Deserialize)]
+struct EvalPooledStep {
+    position: PoolPosition,
+    state: OtAndsState6,
+    masks: MaskedAndBits,
 }
 
-#[derive(Clone)]
-struct EvalStep1(OtPreInitState);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalStep1(OtPreInitState);
 
-#[derive(Clone)]
-struct ContribStep1(OtInitState1);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribStep1(OtInitState1);
 
-#[derive(Clone)]
-struct ContribStep1a(OtInitState3);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalStep2(OtInitState2);
 
-#[derive(Clone)]
-struct EvalStep2(OtInitState2);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribStep2(OtInitState4);
 
-struct EvalStep2a(OtInitState4);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalStep3(OtAndsState1);
 
-#[derive(Clone)]
-struct ContribStep2(OtAndsState1);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribStep3(OtAndsState2);
 
-#[derive(Clone)]
-struct EvalStep3(OtAndsState2);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalStep4(OtAndsState3);
 
-#[derive(Clone)]
-struct ContribStep3(OtAndsState2);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribStep4(OtAndsState4);
 
-#[derive(Clone)]
-struct EvalStep4(OtAndsState3);
+/// The evaluator after opening the protocol, which replaces its first 2 steps with the following 3.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalInitiatorStep1(OtInitState1);
 
-#[derive(Clone)]
-struct ContribStep4(OtAndsState4);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribResponderStep1(OtInitState2);
 
-#[derive(Clone)]
-struct ContribBucketingStep(AndsBucketingState);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalInitiatorStep2(OtInitState4);
 
-#[derive(Clone)]
-struct EvalStep5(OtAndsState5);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContribResponderStep2(OtAndsState1);
 
-#[derive(Clone)]
-struct EvalStep6(OtAndsState6);
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalInitiatorStep3(OtAndsState2);
 
-#[derive(Clone)]
+/// Has sent its bucketing bits and waits for the contributor's, which are only used once the
+/// equality check of `Π_LaAND` has succeeded.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EvalStep5 {
+    bucketing: AndsBucketingState,
+    equality: LeakyAndEquality,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct OtPreInitState {
     rng: ChaCha20Rng,
+    delta: Delta,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtInitState1 {
     rng: ChaCha20Rng,
     delta: Delta,
     r_init: ReceiverInitializer,
     coin_share: CoinShare,
     blocks: usize,
+    security: StatisticalSecurity,
+    context: CircuitBlake3Hash,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtInitState2 {
     rng: ChaCha20Rng,
     delta: Delta,
@@ -279,33 +1531,37 @@ struct OtInitState2 {
     coin_share: CoinShare,
     coin_commitment: Vec<u8>, //< upstream coin commitment message
     blocks: usize,
+    context: CircuitBlake3Hash,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtInitState3 {
     rng: ChaCha20Rng,
     delta: Delta,
     s: SenderInitializer,
     r: LeakyOtReceiver,
     coin: CoinResult,
+    session: SessionId,
     blocks: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtInitState4 {
     rng: ChaCha20Rng,
     delta: Delta,
-    s: LeakyOtSender,
+    s: SenderInitializer,
     coin: CoinResult,
+    session: SessionId,
     blocks: usize,
     abits: Vec<BitShare>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtAndsState1 {
     rng: ChaCha20Rng,
     delta: Delta,
     coin: CoinResult,
+    session: SessionId,
     random_bits: Vec<MacType>,
     and_triples: Vec<BitShare>,
     wire_abits: Vec<BitShare>,
@@ -315,11 +1571,12 @@ struct OtAndsState1 {
     r_prime: Vec<MacType>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtAndsState2 {
     rng: ChaCha20Rng,
     delta: Delta,
     coin: CoinResult,
+    session: SessionId,
     and_triples: Vec<BitShare>,
     wire_abits: Vec<BitShare>,
     and_shares: Vec<MacType>,
@@ -328,11 +1585,12 @@ struct OtAndsState2 {
     r_prime: Vec<MacType>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtAndsState3 {
     rng: ChaCha20Rng,
     delta: Delta,
     coin: CoinResult,
+    session: SessionId,
     and_triples: Vec<BitShare>,
     wire_abits: Vec<BitShare>,
     r_and_rand_key: Vec<(MacType, KeyType)>,
@@ -340,11 +1598,12 @@ struct OtAndsState3 {
     r_prime: Vec<MacType>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtAndsState4 {
     rng: ChaCha20Rng,
     delta: Delta,
     coin: CoinResult,
+    session: SessionId,
     and_triples: Vec<BitShare>,
     wire_abits: Vec<BitShare>,
     r_and_rand_key: Vec<(MacType, KeyType)>,
@@ -352,11 +1611,12 @@ struct OtAndsState4 {
     r_prime: Vec<MacType>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct OtAndsState5 {
     rng: ChaCha20Rng,
     delta: Delta,
     coin: CoinResult,
+    session: SessionId,
     and_triples: Vec<BitShare>,
     wire_abits: Vec<BitShare>,
     r_and_rand_key: Vec<(MacType, KeyType)>,
@@ -364,9 +1624,11 @@ struct OtAndsState5 {
     r_prime: Vec<MacType>,
 }
 
-#[derive(Clone)]
-struct AndsBucketingState {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct AndsBucketingState {
     rng: ChaCha20Rng,
+    coin: CoinResult,
+    session: SessionId,
     delta: Delta,
     bucketing_bits: Vec<bool>,
     wire_abits: Vec<BitShare>,
@@ -376,9 +1638,20 @@ struct AndsBucketingState {
     bucket_size: usize,
 }
 
-#[derive(Clone)]
-struct OtAndsState6 {
+/// The own values of the equality check of `Π_LaAND`, which are kept until the other party has
+/// opened its values.
+#[derive(Clone, Serialize, Deserialize)]
+struct LeakyAndEquality {
+    session: SessionId,
+    r_and_rand_key: Vec<(MacType, KeyType)>,
+    r_and_rand_hash: Vec<MacType>,
+    r_prime: Vec<MacType>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct OtAndsState6 {
     delta: Delta,
+    session: SessionId,
     and_triples: Vec<BitShare>,
     masks: Vec<WireMask>,
     lhs_and_bits: Vec<bool>,
@@ -386,27 +1659,48 @@ struct OtAndsState6 {
 }
 
 /// WRK17 "input processing phase".
-#[derive(Clone)]
-struct InputProcContrib {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct InputProcContrib {
     delta: Delta,
     pending_from_b: usize,
-    mac_checks_success: bool,
     masks: Vec<WireMask>,
 }
 
+/// Verifies the output revealed by the evaluator at the end of the protocol.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct OutputContrib {
+    delta: Delta,
+    /// The masks of the output gates, in the order of the outputs.
+    masks: Vec<WireMask>,
+    /// The number of outputs at the start that stay secret-shared.
+    shared: usize,
+}
+
 /// WRK17 "input processing phase" / "circuit evaluation phase".
-struct InputProcEval {
+#[derive(Serialize, Deserialize)]
+pub(crate) struct InputProcEval {
     delta: Delta,
+    session: SessionId,
     pending_input: usize,
     masks: Vec<WireMask>,
     wires: Vec<WireState>,
+    /// The shares of the loaded state handles, used as the first inputs of both parties.
+    loaded: Vec<BitShare>,
 }
 
 impl EvalStep1 {
     pub(crate) fn init(circuit: &Circuit, input: &[bool], rng: ChaCha20Rng) -> Result<Self, Error> {
         circuit.validate_evaluator_input(input)?;
-        let state = OtPreInitState { rng };
-        Ok(Self(state))
+        Ok(Self::new(rng))
+    }
+
+    pub(crate) fn new(mut rng: ChaCha20Rng) -> Self {
+        let delta = Delta::gen_random(&mut rng);
+        Self::with_delta(delta, rng)
+    }
+
+    pub(crate) fn with_delta(delta: Delta, rng: ChaCha20Rng) -> Self {
+        Self(OtPreInitState { rng, delta })
     }
 }
 
@@ -414,175 +1708,237 @@ impl ContribStep1 {
     pub(crate) fn init(
         circuit: &Circuit,
         input: &[bool],
+        security: StatisticalSecurity,
         mut rng: ChaCha20Rng,
-    ) -> Result<(Self, Msg), Error> {
+    ) -> Result<(Self, OtInit), Error> {
         circuit.validate_contributor_input(input)?;
-        let (state, msg) = init_ot1(Delta::gen_random(&mut rng), rng, circuit)?;
+        circuit.validate()?;
+        let delta = Delta::gen_random(&mut rng);
+        let size = PreprocessingSize::of(circuit, security);
+        Self::init_with_size(delta, rng, size, circuit.blake3_hash())
+    }
+
+    /// Starts the preprocessing, bound to the `context` (usually the hash of the circuit), which
+    /// must be the same for both parties.
+    pub(crate) fn init_with_size(
+        delta: Delta,
+        rng: ChaCha20Rng,
+        size: PreprocessingSize,
+        context: CircuitBlake3Hash,
+    ) -> Result<(Self, OtInit), Error> {
+        let (state, msg) = init_ot1(delta, rng, size, context)?;
         Ok((Self(state), msg))
     }
 }
 
 impl EvalStep1 {
-    fn run(mut self, msg: &[u8], circuit: &Circuit) -> MpcResult<EvalStep2> {
-        let (state, reply1) = init_ot1(Delta::gen_random(&mut self.0.rng), self.0.rng, circuit)?;
+    pub(crate) fn run(
+        self,
+        msg: OtInit,
+        size: PreprocessingSize,
+        context: CircuitBlake3Hash,
+    ) -> MpcResult<EvalStep2, (OtInit, OtResponse)> {
+        let (state, reply1) = init_ot1(self.0.delta, self.0.rng, size, context)?;
         let (state, reply2) = init_ot2(state, msg)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((EvalStep2(state), reply))
+        Ok((EvalStep2(state), (reply1, reply2)))
     }
 }
 
 impl ContribStep1 {
-    fn run(self, msg: &[u8]) -> MpcResult<ContribStep1a> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = init_ot2(self.0, &msg1)?;
-        let (state, reply2) = init_ot3(state, &msg2)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((ContribStep1a(state), reply))
+    pub(crate) fn run(
+        self,
+        msg: (OtInit, OtResponse),
+    ) -> MpcResult<ContribStep2, (OtResponse, OtCompletion, OtBlocks)> {
+        let (state, reply) = complete_base_ots(self.0, msg)?;
+        Ok((ContribStep2(state), reply))
+    }
+
+    /// Answers the base OTs of an evaluator that opened the protocol, sending its own initial
+    /// message in the same reply.
+    pub(crate) fn respond(
+        self,
+        msg: OtInit,
+    ) -> MpcResult<ContribResponderStep1, (OtInit, OtResponse)> {
+        let reply1 = self.0.init_msg()?;
+        let (state, reply2) = init_ot2(self.0, msg)?;
+        Ok((ContribResponderStep1(state), (reply1, reply2)))
     }
 }
 
 impl EvalStep2 {
-    fn run(self, msg: &[u8]) -> MpcResult<EvalStep2a> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = init_ot3(self.0, &msg1)?;
-        let (state, reply2) = init_ot4(state, msg2)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((EvalStep2a(state), reply))
+    pub(crate) fn run(
+        self,
+        msg: (OtResponse, OtCompletion, OtBlocks),
+        size: PreprocessingSize,
+    ) -> MpcResult<EvalStep3, (OtCompletion, OtBlocks, LeakyAndHashes)> {
+        let (state, reply) = extend_ots(self.0, msg, size)?;
+        Ok((EvalStep3(state), reply))
     }
 }
 
-impl ContribStep1a {
-    fn run(self, msg: &[u8], circuit: &Circuit) -> MpcResult<ContribStep2> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = init_ot4(self.0, msg1)?;
-        let (state, reply2) = ot_ands1(state, &msg2, circuit)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((ContribStep2(state), reply))
+impl EvalStep1 {
+    /// Opens the protocol instead of the contributor.
+    pub(crate) fn initiate(
+        self,
+        size: PreprocessingSize,
+        context: CircuitBlake3Hash,
+    ) -> MpcResult<EvalInitiatorStep1, OtInit> {
+        let (state, msg) = init_ot1(self.0.delta, self.0.rng, size, context)?;
+        Ok((EvalInitiatorStep1(state), msg))
     }
 }
 
-impl EvalStep2a {
-    fn run(self, msg: &[u8], circuit: &Circuit) -> MpcResult<EvalStep3> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply) = ot_ands1(self.0, &msg1, circuit)?;
+impl EvalInitiatorStep1 {
+    pub(crate) fn run(
+        self,
+        msg: (OtInit, OtResponse),
+    ) -> MpcResult<EvalInitiatorStep2, (OtResponse, OtCompletion, OtBlocks)> {
+        let (state, reply) = complete_base_ots(self.0, msg)?;
+        Ok((EvalInitiatorStep2(state), reply))
+    }
+}
 
-        // Step 2 of `Π_{LaAND}`
-        let and_hashes: Vec<[MacType; 2]> = deserialize(&msg2)?;
-        let and_shares = state.compute_and_shares(&and_hashes, Role::Evaluator)?;
-        let state = OtAndsState2 {
-            rng: state.rng,
-            delta: state.delta,
-            coin: state.coin,
-            and_triples: state.and_triples,
-            wire_abits: state.wire_abits,
-            r_and_rand_key: state.r_and_rand_key,
-            r_and_rand_hash: state.r_and_rand_hash,
-            r_prime: state.r_prime,
-            and_shares,
-        };
-        Ok((EvalStep3(state), reply))
+impl ContribResponderStep1 {
+    pub(crate) fn run(
+        self,
+        msg: (OtResponse, OtCompletion, OtBlocks),
+        size: PreprocessingSize,
+    ) -> MpcResult<ContribResponderStep2, (OtCompletion, OtBlocks, LeakyAndHashes)> {
+        let (state, reply) = extend_ots(self.0, msg, size)?;
+        Ok((ContribResponderStep2(state), reply))
+    }
+}
+
+impl EvalInitiatorStep2 {
+    /// The session established by the coin toss.
+    pub(crate) fn session(&self) -> SessionId {
+        self.0.session
+    }
+
+    // Implements Step 1 + 2 of `Π_{LaAND}` of WRK17a
+    pub(crate) fn run(
+        self,
+        (msg1, msg2, msg3): (OtCompletion, OtBlocks, LeakyAndHashes),
+        size: PreprocessingSize,
+    ) -> MpcResult<EvalInitiatorStep3, LeakyAndHashes> {
+        let (state, reply) = ot_ands1(self.0, msg1, msg2, size)?;
+        let and_shares = state.compute_and_shares(&msg3.hashes, Role::Evaluator)?;
+        Ok((EvalInitiatorStep3(state.with_and_shares(and_shares)), reply))
     }
 }
 
-impl ContribStep2 {
+impl ContribResponderStep2 {
+    /// The session established by the coin toss.
+    pub(crate) fn session(&self) -> SessionId {
+        self.0.session
+    }
+
     // Implements Step 2 of `Π_{LaAND}` of WRK17a
-    fn run(self, msg: &[u8]) -> MpcResult<ContribStep3> {
-        let and_hashes: Vec<[MacType; 2]> = deserialize(msg)?;
-        let state = self.0;
-        let and_shares = state.compute_and_shares(&and_hashes, Role::Contributor)?;
-        let reply = serialize(&and_shares)?;
-        let state = OtAndsState2 {
-            rng: state.rng,
-            delta: state.delta,
-            coin: state.coin,
-            and_triples: state.and_triples,
-            wire_abits: state.wire_abits,
-            r_and_rand_key: state.r_and_rand_key,
-            r_and_rand_hash: state.r_and_rand_hash,
-            r_prime: state.r_prime,
-            and_shares: state.and_shares,
-        };
-        Ok((ContribStep3(state), reply))
+    pub(crate) fn run(self, msg: LeakyAndHashes) -> MpcResult<ContribStep3, LeakyAndShares> {
+        let shares = self.0.compute_and_shares(&msg.hashes, Role::Contributor)?;
+        let state = self.0.with_and_shares(vec![]);
+        Ok((ContribStep3(state), LeakyAndShares { shares }))
     }
 }
 
-/// Receives its message from [`ContribStep2`] which is a (large) vector of `AND` shares.
-impl EvalStep3 {
-    fn run(self, msg: &[u8]) -> MpcResult<EvalStep4> {
+impl EvalInitiatorStep3 {
+    // Implements Step 3 of `Π_{LaAND}` of WRK17a
+    pub(crate) fn run(
+        self,
+        msg: LeakyAndShares,
+    ) -> MpcResult<EvalStep4, (LeakyAndShares, LeakyAndCheck)> {
         let (state, replies) = ot_ands3_update_z2_eval(self.0, msg)?;
-        let reply = serialize(&replies)?;
-        Ok((EvalStep4(state), reply))
+        Ok((EvalStep4(state), replies))
     }
 }
 
-impl ContribStep3 {
-    fn run(self, msg: &[u8]) -> MpcResult<ContribStep4> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = ot_ands3_update_z2_contrib(self.0, &msg1)?;
-        let (state, reply2) = ot_ands4(state, &msg2)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((ContribStep4(state), reply))
+impl ContribStep2 {
+    /// The session established by the coin toss.
+    pub(crate) fn session(&self) -> SessionId {
+        self.0.session
     }
-}
 
-impl EvalStep4 {
-    fn run(self, msg: &[u8]) -> MpcResult<EvalStep5> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = ot_ands4(self.0, &msg1)?;
-        let (state, reply2) = ot_ands5(state, &msg2)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((EvalStep5(state), reply))
+    // Implements Step 1 + 2 of `Π_{LaAND}` of WRK17a
+    pub(crate) fn run(
+        self,
+        (msg1, msg2, msg3): (OtCompletion, OtBlocks, LeakyAndHashes),
+        size: PreprocessingSize,
+    ) -> MpcResult<ContribStep3, (LeakyAndHashes, LeakyAndShares)> {
+        let (state, reply) = ot_ands1(self.0, msg1, msg2, size)?;
+        let shares = state.compute_and_shares(&msg3.hashes, Role::Contributor)?;
+        let state = state.with_and_shares(vec![]);
+        Ok((ContribStep3(state), (reply, LeakyAndShares { shares })))
     }
 }
 
-impl ContribStep4 {
-    fn run(self, msg: &[u8], circuit: &Circuit) -> MpcResult<AndsBucketingState> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = ot_ands5(self.0, &msg1)?;
-        let (state, reply2) = ot_ands6(state, &msg2, circuit)?;
-        let reply = serialize(&(reply1, reply2))?;
-        Ok((state, reply))
+impl EvalStep3 {
+    /// The session established by the coin toss.
+    pub(crate) fn session(&self) -> SessionId {
+        self.0.session
     }
-}
-
-impl EvalStep5 {
-    fn run(self, msg: &[u8], circuit: &Circuit) -> MpcResult<EvalStep6> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = ot_ands6(self.0, &msg1, circuit)?;
-        let (state, reply2) = state.finish(&msg2, circuit)?;
 
-        let msg = serialize(&(reply1, reply2))?;
-        Ok((EvalStep6(state), msg))
+    // Implements Step 2 + 3 of `Π_{LaAND}` of WRK17a
+    pub(crate) fn run(
+        self,
+        (msg1, msg2): (LeakyAndHashes, LeakyAndShares),
+    ) -> MpcResult<EvalStep4, (LeakyAndShares, LeakyAndCheck)> {
+        let and_shares = self.0.compute_and_shares(&msg1.hashes, Role::Evaluator)?;
+        let state = self.0.with_and_shares(and_shares);
+        let (state, replies) = ot_ands3_update_z2_eval(state, msg2)?;
+        Ok((EvalStep4(state), replies))
     }
 }
 
-impl ContribBucketingStep {
-    fn run(self, msg: &[u8], circuit: &Circuit, input: &[bool]) -> MpcResult<InputProcContrib> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply1) = self.0.finish(&msg1, circuit)?;
-        let (state, reply2) = ot_ands8_contrib(state, &msg2, circuit, input)?;
-
-        let msg = serialize(&(reply1, reply2))?;
-        Ok((state, msg))
+impl ContribStep3 {
+    pub(crate) fn run(
+        self,
+        (msg1, msg2): (LeakyAndShares, LeakyAndCheck),
+    ) -> MpcResult<ContribStep4, (LeakyAndCheck, LeakyAndCommitments)> {
+        let (state, reply1) = ot_ands3_update_z2_contrib(self.0, msg1)?;
+        let (state, reply2) = ot_ands4(state, msg2)?;
+        Ok((ContribStep4(state), (reply1, reply2)))
     }
 }
 
-impl EvalStep6 {
-    fn run(self, msg: &[u8], circuit: &Circuit, input: &[bool]) -> MpcResult<InputProcEval> {
-        let (msg1, msg2): (Msg, Msg) = deserialize(msg)?;
-        let (state, reply) = ot_ands8_eval(self.0, &msg1, &msg2, circuit, input)?;
-        Ok((state, reply))
+impl EvalStep4 {
+    /// Opens the values for the equality check and sends the bucketing bits in the same message,
+    /// since they only depend on the triples and the coin.
+    pub(crate) fn run(
+        self,
+        (msg1, msg2): (LeakyAndCheck, LeakyAndCommitments),
+        size: PreprocessingSize,
+    ) -> MpcResult<EvalStep5, (LeakyAndCommitments, LeakyAndOpenings, BucketingBits)> {
+        let (state, reply1) = ot_ands4(self.0, msg1)?;
+        let (state, reply2) = ot_ands5(state, msg2)?;
+        let ((bucketing, equality), reply3) = ot_ands6(state, size)?;
+        Ok((EvalStep5 { bucketing, equality }, (reply1, reply2, reply3)))
     }
 }
 
-type StateResult<S> = Result<(S, Msg), Error>;
+impl ContribStep4 {
+    /// Finishes the preprocessing phase, returning the message for the evaluator's bucketing.
+    pub(crate) fn run(
+        self,
+        (msg1, msg2, msg3): (LeakyAndCommitments, LeakyAndOpenings, BucketingBits),
+        size: PreprocessingSize,
+    ) -> MpcResult<(Preprocessed, ChaCha20Rng), (LeakyAndOpenings, BucketingBits)> {
+        let (state, reply1) = ot_ands5(self.0, msg1)?;
+        let ((bucketing, equality), reply2) = ot_ands6(state, size)?;
+        // 2nd part of Step 4e/5e of `Π_{LaAND}`, before any triple is used:
+        check_hash(&equality, &msg2.r_prime, &msg2.openings)?;
+        Ok((bucketing.finish(msg3)?, (reply1, reply2)))
+    }
+}
 
-/// Calculates the bucket size for statistical security.
-fn bucket_size(circuit: &Circuit) -> usize {
-    match circuit.and_gates() {
-        n if n >= 280_000 => 3,
-        n if n >= 3_100 => 4,
-        _ => 5,
+impl EvalStep5 {
+    /// Finishes the preprocessing phase.
+    pub(crate) fn bucketing(
+        self,
+        (msg1, msg2): (LeakyAndOpenings, BucketingBits),
+    ) -> Result<(Preprocessed, ChaCha20Rng), Error> {
+        // 2nd part of Step 4e/5e of `Π_{LaAND}`, before any triple is used:
+        check_hash(&self.equality, &msg1.r_prime, &msg1.openings)?;
+        self.bucketing.finish(msg2)
     }
 }
 
@@ -591,41 +1947,67 @@ enum Role {
     Evaluator,
 }
 
-fn init_ot1(delta: Delta, mut rng: ChaCha20Rng, p: &Circuit) -> StateResult<OtInitState1> {
-    p.validate()?;
-
+fn init_ot1(
+    delta: Delta,
+    mut rng: ChaCha20Rng,
+    size: PreprocessingSize,
+    context: CircuitBlake3Hash,
+) -> MpcResult<OtInitState1, OtInit> {
     // the number of authenticated bits we need for wires
-    let wire_abits = p.and_gates() + p.eval_inputs() + p.contrib_inputs();
+    let wire_abits = size.abits;
 
     // the number of authenticated bits need for AND triples
-    let triples_bits = p.and_gates() * 3 * bucket_size(p);
+    let triples_bits = size.ands * 3 * size.bucket_size();
     let triples_bits_aligned = (triples_bits + TRIPLES - 1) / TRIPLES * TRIPLES;
     let total_abits = wire_abits + triples_bits_aligned;
     let num_abits_aligned = (total_abits + BLOCK_SIZE - 1) / BLOCK_SIZE * BLOCK_SIZE;
-    let (r_init, ot_msg) = ReceiverInitializer::init(&mut rng);
-    let (coin_share, coin_msg) = {
+    let (r_init, _) = ReceiverInitializer::init(&mut rng);
+    let (coin_share, _) = {
         let mut coin = [0u8; protocol::cointossing::COIN_LEN];
         rng.fill_bytes(&mut coin);
-        protocol::cointossing::init(coin)?
+        protocol::cointossing::init(coin, &context)?
     };
 
-    let msg = serialize(&(&ot_msg.serialize(), &coin_msg))?;
     let state = OtInitState1 {
         rng,
         delta,
         r_init,
         coin_share,
         blocks: num_abits_aligned / BLOCK_SIZE,
+        security: size.security,
+        context,
     };
+    let msg = state.init_msg()?;
     Ok((state, msg))
 }
 
-fn init_ot2(mut state: OtInitState1, msg: &[u8]) -> StateResult<OtInitState2> {
-    let (serialized_ot_init, coin_commitment): (SerializedOtInit, Vec<u8>) = deserialize(msg)?;
-    let ot_init = serialized_ot_init.deserialize()?;
+impl OtInitState1 {
+    /// The message that starts the base OTs, which can be sent again until the other party has
+    /// answered, since it only depends on the state.
+    fn init_msg(&self) -> Result<OtInit, Error> {
+        Ok(OtInit {
+            ot_init: self.r_init.init_message().serialize(),
+            coin_commitment: protocol::cointossing::commitment(&self.coin_share, &self.context)?,
+            security: self.security,
+        })
+    }
+}
+
+fn init_ot2(mut state: OtInitState1, msg: OtInit) -> MpcResult<OtInitState2, OtResponse> {
+    let OtInit {
+        ot_init,
+        coin_commitment,
+        security,
+    } = msg;
+    if security != state.security {
+        return Err(StatisticalSecurityMismatch);
+    }
+    let ot_init = ot_init.deserialize()?;
     let sender = SenderInitializer::init(&mut state.rng, state.delta.clone(), &ot_init);
-    let coin_msg = protocol::cointossing::serialize(&state.coin_share)?;
-    let msg = serialize(&(sender.1.serialize(), coin_msg))?;
+    let msg = OtResponse {
+        ot_init: sender.1.serialize(),
+        coin: protocol::cointossing::serialize(&state.coin_share)?,
+    };
     let state = OtInitState2 {
         rng: state.rng,
         delta: state.delta,
@@ -634,32 +2016,63 @@ fn init_ot2(mut state: OtInitState1, msg: &[u8]) -> StateResult<OtInitState2> {
         coin_share: state.coin_share,
         coin_commitment,
         blocks: state.blocks,
+        context: state.context,
     };
     Ok((state, msg))
 }
 
-fn init_ot3(state: OtInitState2, msg: &[u8]) -> StateResult<OtInitState3> {
-    let (serialized_ot_init, upstream_coin): (SerializedOtInit, Vec<u8>) = deserialize(msg)?;
-    let coin =
-        protocol::cointossing::finish(state.coin_share, state.coin_commitment, upstream_coin)?;
-    let ot_init = serialized_ot_init.deserialize()?;
+fn init_ot3(state: OtInitState2, msg: OtResponse) -> MpcResult<OtInitState3, OtCompletion> {
+    let coin = protocol::cointossing::finish(
+        state.coin_share,
+        &state.context,
+        state.coin_commitment,
+        msg.coin,
+    )?;
+    let session = SessionId::new(&state.context, &coin);
+    let ot_init = msg.ot_init.deserialize()?;
     let (r, reply) = state.r_init.recv(&ot_init);
-    let reply = reply.serialize();
+    let reply = OtCompletion {
+        reply: reply.serialize(),
+    };
     let state = OtInitState3 {
         rng: state.rng,
         delta: state.delta,
         s: state.s,
         r,
         coin,
+        session,
         blocks: state.blocks,
     };
     Ok((state, reply))
 }
 
-fn init_ot4(mut state: OtInitState3, msg: Vec<u8>) -> StateResult<OtInitState4> {
-    let init_msg = OtInitReply::deserialize(msg)?;
-    let s = state.s.recv(&init_msg);
-
+/// Completes the base OTs of the other party and its own, sending its extended OTs right away.
+fn complete_base_ots(
+    state: OtInitState1,
+    (msg1, msg2): (OtInit, OtResponse),
+) -> MpcResult<OtInitState4, (OtResponse, OtCompletion, OtBlocks)> {
+    let (state, reply1) = init_ot2(state, msg1)?;
+    let (state, reply2) = init_ot3(state, msg2)?;
+    let (state, reply3) = init_ot4(state)?;
+    Ok((state, (reply1, reply2, reply3)))
+}
+
+/// Completes the base OTs and sends its extended OTs, together with Step 1 of `Π_{LaAND}`, which
+/// only needs the extended OTs that the other party has already sent.
+fn extend_ots(
+    state: OtInitState2,
+    (msg1, msg2, msg3): (OtResponse, OtCompletion, OtBlocks),
+    size: PreprocessingSize,
+) -> MpcResult<OtAndsState1, (OtCompletion, OtBlocks, LeakyAndHashes)> {
+    let (state, reply1) = init_ot3(state, msg1)?;
+    let (state, reply2) = init_ot4(state)?;
+    let (state, reply3) = ot_ands1(state, msg2, msg3, size)?;
+    Ok((state, (reply1, reply2, reply3)))
+}
+
+/// Extends the completed base OTs of the receiver, which does not need to wait for its own sender
+/// to complete.
+fn init_ot4(mut state: OtInitState3) -> MpcResult<OtInitState4, OtBlocks> {
     let mut r = state.r;
     let mut blocks = Vec::new();
     let mut abits = vec![BitShare::default(); state.blocks * BLOCK_SIZE];
@@ -676,28 +2089,35 @@ fn init_ot4(mut state: OtInitState3, msg: Vec<u8>) -> StateResult<OtInitState4>
         }
         blocks.push(ot_out.to_vec());
     }
-    let reply = serialize(&blocks)?;
+    let reply = OtBlocks { blocks };
 
     let state = OtInitState4 {
         rng: state.rng,
         delta: state.delta,
         blocks: state.blocks,
         coin: state.coin,
+        session: state.session,
         abits,
-        s,
+        s: state.s,
     };
     Ok((state, reply))
 }
 
-fn ot_ands1(mut state: OtInitState4, msg: &[u8], circuit: &Circuit) -> StateResult<OtAndsState1> {
-    let blocks: Vec<Vec<MacType>> = deserialize(msg)?;
-    for (block_id, block) in blocks.into_iter().enumerate() {
+fn ot_ands1(
+    mut state: OtInitState4,
+    msg1: OtCompletion,
+    msg2: OtBlocks,
+    size: PreprocessingSize,
+) -> MpcResult<OtAndsState1, LeakyAndHashes> {
+    let init_msg = OtInitReply::deserialize(msg1.reply)?;
+    let mut s = state.s.recv(&init_msg);
+    for (block_id, block) in msg2.blocks.into_iter().enumerate() {
         let ot_rx: [MacType; BLOCK_SIZE] = block
             .try_into()
             .map_err(|_| Error::OtBlockDeserializationError)?;
         let ot_rx = Box::new(ot_rx);
         let mut keys_out = [MacType(0); BLOCK_SIZE];
-        state.s.send(ot_rx.as_ref(), &mut keys_out);
+        s.send(ot_rx.as_ref(), &mut keys_out);
 
         let abits = &mut state.abits[block_id * BLOCK_SIZE..];
         for i in 0..BLOCK_SIZE {
@@ -706,8 +2126,8 @@ fn ot_ands1(mut state: OtInitState4, msg: &[u8], circuit: &Circuit) -> StateResu
     }
 
     // the number of authenticated bits we need for wires
-    let n_and_gates = circuit.and_gates();
-    let n_wire_abits = circuit.and_gates() + circuit.eval_inputs() + circuit.contrib_inputs();
+    let n_and_gates = size.ands;
+    let n_wire_abits = size.abits;
     let abits_blocks = (n_wire_abits + BLOCK_SIZE - 1) / BLOCK_SIZE;
     assert_eq!(0, (state.blocks - abits_blocks) % 3);
 
@@ -730,6 +2150,7 @@ fn ot_ands1(mut state: OtInitState4, msg: &[u8], circuit: &Circuit) -> StateResu
         rng: state.rng,
         delta: state.delta,
         coin: state.coin,
+        session: state.session,
         and_triples: triples,
         wire_abits: wire_abits.to_vec(),
         and_shares: Default::default(),
@@ -740,18 +2161,33 @@ fn ot_ands1(mut state: OtInitState4, msg: &[u8], circuit: &Circuit) -> StateResu
     };
 
     // Step 1 of `Π_{LaAND}`
-    let and_hashes = state.compute_and_ot_data();
-    let msg = serialize(&and_hashes)?;
+    let hashes = state.compute_and_ot_data();
 
-    Ok((state, msg))
+    Ok((state, LeakyAndHashes { hashes }))
 }
 
 impl OtAndsState1 {
+    fn with_and_shares(self, and_shares: Vec<MacType>) -> OtAndsState2 {
+        OtAndsState2 {
+            rng: self.rng,
+            delta: self.delta,
+            coin: self.coin,
+            session: self.session,
+            and_triples: self.and_triples,
+            wire_abits: self.wire_abits,
+            r_and_rand_key: self.r_and_rand_key,
+            r_and_rand_hash: self.r_and_rand_hash,
+            r_prime: self.r_prime,
+            and_shares,
+        }
+    }
+
     fn compute_and_ot_data(&mut self) -> Vec<[MacType; 2]> {
         let and_bits = &self.and_triples[0..];
         let num_blocks = and_bits.len() / BLOCK_SIZE / 3;
 
         self.random_bits.clear();
+        let leaky_and = self.session.key(domain::LEAKY_AND);
 
         let mut result: Vec<[MacType; 2]> = Vec::with_capacity(num_blocks * BLOCK_SIZE);
         result.resize(num_blocks * BLOCK_SIZE, [MacType(0), MacType(0)]);
@@ -764,6 +2200,7 @@ impl OtAndsState1 {
             let y = collect_y_bits(bits);
             compute_leaky_and_hashes(
                 &mut result[(i * BLOCK_SIZE)..],
+                &leaky_and,
                 &self.delta,
                 r,
                 y.0,
@@ -786,11 +2223,13 @@ impl OtAndsState1 {
             return Err(InsufficientAndShares);
         }
 
+        let leaky_and = self.session.key(domain::LEAKY_AND);
         let mut result: Vec<MacType> = Vec::with_capacity(num_blocks);
         for i in 0..num_blocks {
             let bits = &and_bits[(i * BLOCK_SIZE * 3)..];
             let (x, y, z) = collect_authenticated_bits(bits);
             let and_bits = derive_and_shares(
+                &leaky_and,
                 self.random_bits[i].0,
                 x.0,
                 &macs(bits),
@@ -810,7 +2249,8 @@ impl OtAndsState1 {
 }
 
 /// Implements sub-protoocol Π_{LaAND} steps 4a+4b (resp. 5a+5b) of WRK17a.
-fn compute_u(delta: &Delta, and_bits: &[BitShare]) -> Vec<MacType> {
+fn compute_u(delta: &Delta, session: &SessionId, and_bits: &[BitShare]) -> Vec<MacType> {
+    let leaky_and = session.key(domain::LEAKY_AND);
     let mut msgs = Vec::with_capacity(and_bits.len() / 3);
     for i in (0..and_bits.len()).step_by(3) {
         let BitShare {
@@ -828,22 +2268,35 @@ fn compute_u(delta: &Delta, and_bits: &[BitShare]) -> Vec<MacType> {
             bit: b_z2,
             ..
         } = and_bits[i + 2];
-        let t0 = hash_keys(k_x1, k_z1 ^ (if b_z2 { delta.0 } else { 0 }));
+        let t0 = hash_keys(&leaky_and, k_x1, k_z1 ^ (if b_z2 { delta.0 } else { 0 }));
         let u0 = t0
             ^ hash_keys(
+                &leaky_and,
                 k_x1 ^ delta.0,
                 k_y1 ^ k_z1 ^ (if b_y2 ^ b_z2 { delta.0 } else { 0 }),
             );
-        let t1 = hash_keys(k_x1, k_y1 ^ k_z1 ^ (if b_y2 ^ b_z2 { delta.0 } else { 0 }));
-        let u1 = t1 ^ hash_keys(k_x1 ^ delta.0, k_z1 ^ (if b_z2 { delta.0 } else { 0 }));
+        let t1 = hash_keys(
+            &leaky_and,
+            k_x1,
+            k_y1 ^ k_z1 ^ (if b_y2 ^ b_z2 { delta.0 } else { 0 }),
+        );
+        let u1 = t1
+            ^ hash_keys(
+                &leaky_and,
+                k_x1 ^ delta.0,
+                k_z1 ^ (if b_z2 { delta.0 } else { 0 }),
+            );
         let u_for_other_party = if b_x2 { u1 } else { u0 };
         msgs.push(u_for_other_party);
     }
     msgs
 }
 
-fn ot_ands3_update_z2_contrib(mut state: OtAndsState2, msg: &[u8]) -> StateResult<OtAndsState3> {
-    let and_shares: Vec<MacType> = deserialize(msg)?;
+fn ot_ands3_update_z2_contrib(
+    mut state: OtAndsState2,
+    msg: LeakyAndShares,
+) -> MpcResult<OtAndsState3, LeakyAndCheck> {
+    let and_shares = msg.shares;
     // Step 3 of `Π_{LaAND}`
     let and_bits = &mut state.and_triples[0..];
     let num_blocks = and_bits.len() / BLOCK_SIZE / 3;
@@ -864,12 +2317,13 @@ fn ot_ands3_update_z2_contrib(mut state: OtAndsState2, msg: &[u8]) -> StateResul
     }
 
     // Step 4/5 (a + b) of `Π_{LaAND}`
-    let u = compute_u(&state.delta, &state.and_triples);
-    let msg = serialize(&u)?;
+    let u = compute_u(&state.delta, &state.session, &state.and_triples);
+    let msg = LeakyAndCheck { u };
     let state = OtAndsState3 {
         rng: state.rng,
         delta: state.delta,
         coin: state.coin,
+        session: state.session,
         and_triples: state.and_triples,
         wire_abits: state.wire_abits,
         r_and_rand_key: state.r_and_rand_key,
@@ -881,9 +2335,9 @@ fn ot_ands3_update_z2_contrib(mut state: OtAndsState2, msg: &[u8]) -> StateResul
 
 fn ot_ands3_update_z2_eval(
     mut state: OtAndsState2,
-    msg: &[u8],
-) -> Result<(OtAndsState3, (Msg, Msg)), Error> {
-    let upstream_ands: Vec<MacType> = deserialize(msg)?;
+    msg: LeakyAndShares,
+) -> MpcResult<OtAndsState3, (LeakyAndShares, LeakyAndCheck)> {
+    let upstream_ands = msg.shares;
     // Step 3 of `Π_{LaAND}`
     let and_bits = &mut state.and_triples[0..];
     let num_blocks = and_bits.len() / BLOCK_SIZE / 3;
@@ -906,16 +2360,17 @@ fn ot_ands3_update_z2_eval(
             bits[bit_idx * 3 + 2].bit ^= (d.0 & (1 << bit_idx)) != 0
         }
     }
-    let msg1 = serialize(&result)?;
+    let msg1 = LeakyAndShares { shares: result };
 
     // Step 4/5 (a + b) of `Π_{LaAND}`
-    let u = compute_u(&state.delta, &state.and_triples);
-    let msg2 = serialize(&u)?;
+    let u = compute_u(&state.delta, &state.session, &state.and_triples);
+    let msg2 = LeakyAndCheck { u };
 
     let state = OtAndsState3 {
         rng: state.rng,
         delta: state.delta,
         coin: state.coin,
+        session: state.session,
         and_triples: state.and_triples,
         wire_abits: state.wire_abits,
         r_and_rand_key: state.r_and_rand_key,
@@ -925,14 +2380,19 @@ fn ot_ands3_update_z2_eval(
     Ok((state, (msg1, msg2)))
 }
 
-fn ot_ands4(mut state: OtAndsState3, msg: &[u8]) -> StateResult<OtAndsState4> {
-    let u_from_other_party: Vec<MacType> = deserialize(msg)?;
+fn ot_ands4(
+    mut state: OtAndsState3,
+    msg: LeakyAndCheck,
+) -> MpcResult<OtAndsState4, LeakyAndCommitments> {
+    let u_from_other_party = msg.u;
     // Step 4/5 (c + d) of `Π_{LaAND}`
     // implementation of Protoocol Π_{LaAND} steps 4c+4d (resp. 5c+5d) of WRK17a
     let and_bits = &state.and_triples[0..];
     let mut r_and_rand = Vec::with_capacity(and_bits.len() / 3);
     let mut r_and_rand_hashed = Vec::with_capacity(and_bits.len() / 3);
     let mut w = Vec::with_capacity(and_bits.len() / 3);
+    let leaky_and = state.session.key(domain::LEAKY_AND);
+    let leaky_and_check = state.session.key(domain::LEAKY_AND_CHECK);
     for i in (0..and_bits.len()).step_by(3) {
         let BitShare {
             key: k_x1,
@@ -944,25 +2404,28 @@ fn ot_ands4(mut state: OtAndsState3, msg: &[u8]) -> StateResult<OtAndsState4> {
 
         let r: u128 = state.rng.next_u64() as u128 | ((state.rng.next_u64() as u128) << 64);
         let u = u_from_other_party[i / 3];
-        let v0 = hash_keys(m_x2.into(), m_z2.into());
-        let v1 = hash_keys(m_x2.into(), KeyType(m_z2.0) ^ m_y2.0);
+        let v0 = hash_keys(&leaky_and, m_x2.into(), m_z2.into());
+        let v1 = hash_keys(&leaky_and, m_x2.into(), KeyType(m_z2.0) ^ m_y2.0);
         let (w_x2_0, w_x2_1) = if b_x2 {
-            let w_1_0 = hash_key(k_x1) ^ v1 ^ u.0 ^ r;
-            let w_1_1 = hash_key(k_x1 ^ state.delta.0) ^ v0 ^ u.0 ^ r;
+            let w_1_0 = hash_key(&leaky_and, k_x1) ^ v1 ^ u.0 ^ r;
+            let w_1_1 = hash_key(&leaky_and, k_x1 ^ state.delta.0) ^ v0 ^ u.0 ^ r;
             (w_1_0, w_1_1)
         } else {
-            let w_0_0 = hash_key(k_x1) ^ v0 ^ r;
-            let w_0_1 = hash_key(k_x1 ^ state.delta.0) ^ v1 ^ r;
+            let w_0_0 = hash_key(&leaky_and, k_x1) ^ v0 ^ r;
+            let w_0_1 = hash_key(&leaky_and, k_x1 ^ state.delta.0) ^ v1 ^ r;
             (w_0_0, w_0_1)
         };
         // hash r + random key for 'commit & open' eq check
         let rand_for_eq_box_hash = KeyType(state.rng.next_u64() as u128 | ((state.rng.next_u64() as u128) << 64));
-        let hash_for_commit = hash_keys(KeyType(r), rand_for_eq_box_hash);
+        let hash_for_commit = hash_keys(&leaky_and_check, KeyType(r), rand_for_eq_box_hash);
         r_and_rand.push((MacType(r), rand_for_eq_box_hash));
         r_and_rand_hashed.push(hash_for_commit);
         w.push((w_x2_0, w_x2_1));
     }
-    let msg = serialize(&(r_and_rand_hashed, w))?;
+    let msg = LeakyAndCommitments {
+        commitments: r_and_rand_hashed,
+        w,
+    };
 
     state.r_and_rand_key = r_and_rand;
 
@@ -970,6 +2433,7 @@ fn ot_ands4(mut state: OtAndsState3, msg: &[u8]) -> StateResult<OtAndsState4> {
         rng: state.rng,
         delta: state.delta,
         coin: state.coin,
+        session: state.session,
         and_triples: state.and_triples,
         wire_abits: state.wire_abits,
         r_and_rand_key: state.r_and_rand_key,
@@ -979,13 +2443,19 @@ fn ot_ands4(mut state: OtAndsState3, msg: &[u8]) -> StateResult<OtAndsState4> {
     Ok((state, msg))
 }
 
-fn ot_ands5(mut state: OtAndsState4, msg: &[u8]) -> StateResult<OtAndsState5> {
-    let (r_and_rand_hashed, w_from_other_party): (Vec<MacType>, Vec<(MacType, MacType)>) =
-        deserialize(msg)?;
+fn ot_ands5(
+    mut state: OtAndsState4,
+    msg: LeakyAndCommitments,
+) -> MpcResult<OtAndsState5, LeakyAndOpenings> {
+    let LeakyAndCommitments {
+        commitments: r_and_rand_hashed,
+        w: w_from_other_party,
+    } = msg;
 
     // Step 4/5 (e) of `Π_{LaAND}`
     let and_bits = &state.and_triples[0..];
     let mut r_prime = Vec::with_capacity(and_bits.len() / 3);
+    let leaky_and = state.session.key(domain::LEAKY_AND);
     for i in (0..and_bits.len()).step_by(3) {
         let BitShare {
             key: k_x1,
@@ -1003,27 +2473,36 @@ fn ot_ands5(mut state: OtAndsState4, msg: &[u8]) -> StateResult<OtAndsState5> {
             ..
         } = and_bits[i + 2];
 
-        let t0 = hash_keys(k_x1, k_z1 ^ (if b_z2 { state.delta.0 } else { 0 }));
+        let t0 = hash_keys(
+            &leaky_and,
+            k_x1,
+            k_z1 ^ (if b_z2 { state.delta.0 } else { 0 }),
+        );
         let t1 = hash_keys(
+            &leaky_and,
             k_x1,
             k_y1 ^ k_z1 ^ (if b_y2 ^ b_z2 { state.delta.0 } else { 0 }),
         );
         let t_x2 = if b_x2 { t1 } else { t0 };
         let (w_x1_0, w_x1_1) = w_from_other_party[i / 3];
         let w_x1_x2 = if b_x2 { w_x1_1 } else { w_x1_0 };
-        r_prime.push(hash(m_x2) ^ w_x1_x2 ^ t_x2);
+        r_prime.push(hash(&leaky_and, m_x2) ^ w_x1_x2 ^ t_x2);
     }
 
     // 'commit' step of F_EQ check
     state.r_and_rand_hash = r_and_rand_hashed;
     state.r_prime = r_prime.clone();
 
-    let msg = serialize(&(r_prime, state.r_and_rand_key.clone()))?;
+    let msg = LeakyAndOpenings {
+        r_prime,
+        openings: state.r_and_rand_key.clone(),
+    };
 
     let state = OtAndsState5 {
         rng: state.rng,
         delta: state.delta,
         coin: state.coin,
+        session: state.session,
         and_triples: state.and_triples,
         wire_abits: state.wire_abits,
         r_and_rand_key: state.r_and_rand_key,
@@ -1034,41 +2513,51 @@ fn ot_ands5(mut state: OtAndsState4, msg: &[u8]) -> StateResult<OtAndsState5> {
 }
 
 fn check_hash(
-    state: &OtAndsState5,
+    state: &LeakyAndEquality,
     r_prime: &[MacType],
     r_and_rand: &[(MacType, KeyType)],
 ) -> Result<(), Error> {
-    if r_prime.len() != r_and_rand.len() || r_prime.len() != state.r_and_rand_key.len() {
-        return Err(UnexpectedMessageType);
+    let expected = state.r_and_rand_key.len();
+    if r_prime.len() != expected {
+        return Err(Error::unexpected_length(expected, r_prime.len()));
+    }
+    if r_and_rand.len() != expected {
+        return Err(Error::unexpected_length(expected, r_and_rand.len()));
     }
 
-    let mut success = true;
+    let mut failed = 0;
+    let mut first_failed = None;
 
     // 'open' step of F_EQ check
-    for (i, (r, rand_key)) in r_and_rand.iter().enumerate() {
-        let hashed = hash_keys(KeyType(r.0), KeyType(rand_key.0));
+    let leaky_and_check = state.session.key(domain::LEAKY_AND_CHECK);
+    for (i, ((r, rand_key), r_prime)) in r_and_rand.iter().zip(r_prime).enumerate() {
+        let hashed = hash_keys(&leaky_and_check, KeyType(r.0), KeyType(rand_key.0));
         // check that the hash received previously matches the r + rand received now:
         let hash_ok = state.r_and_rand_hash[i] == hashed;
         // check that the r received now matches own r':
         let r_equal = *r == state.r_prime[i];
-        success &= hash_ok & r_equal;
-    }
-    for (i, r_prime) in r_prime.iter().enumerate() {
         // check that the r' received now from the other party matches own r:
         let r_prime_check = state.r_and_rand_key[i].0 == *r_prime;
 
-        success &= r_prime_check;
+        if !(hash_ok & r_equal & r_prime_check) {
+            failed += 1;
+            first_failed.get_or_insert(i);
+        }
     }
 
-    if !success {
-        Err(LeakyAndNotEqual)
-    } else {
-        Ok(())
+    match first_failed {
+        Some(index) => Err(LeakyAndNotEqual {
+            step: None,
+            failed,
+            index,
+        }),
+        None => Ok(()),
     }
 }
 
 fn compute_hashes_contrib(
     state: &OtAndsState6,
+    garbling: &DomainKey,
     gate_index: usize,
     output_mask: &WireMask,
     lhs: &WireMask,
@@ -1094,10 +2583,10 @@ fn compute_hashes_contrib(
 
     let gi_u32 = gate_index as u32;
     [
-        h0.xor(&garbling_hash::new(l0, r0, gi_u32, 0)),
-        h1.xor(&garbling_hash::new(l0, &r1, gi_u32, 1)),
-        h2.xor(&garbling_hash::new(&l1, r0, gi_u32, 2)),
-        h3.xor(&garbling_hash::new(&l1, &r1, gi_u32, 3)),
+        h0.xor(&garbling_hash::new(garbling, l0, r0, gi_u32, 0)),
+        h1.xor(&garbling_hash::new(garbling, l0, &r1, gi_u32, 1)),
+        h2.xor(&garbling_hash::new(garbling, &l1, r0, gi_u32, 2)),
+        h3.xor(&garbling_hash::new(garbling, &l1, &r1, gi_u32, 3)),
     ]
 }
 
@@ -1173,6 +2662,29 @@ fn preprocessing_and_gate_bits(
     (lhs_bits, rhs_bits)
 }
 
+/// Returns a random permutation of `total_abits` indices, derived from the tossed coin.
+pub(crate) fn new_permutation(mut rng: ChaCha20Rng, total_abits: usize) -> Vec<u32> {
+    let mut permutation = vec![0; total_abits];
+    for (i, item) in permutation.iter_mut().enumerate().take(total_abits) {
+        *item = i as u32;
+    }
+
+    let mut idx: Vec<i32> = vec![0; total_abits];
+    for item in idx.iter_mut() {
+        *item = rng.next_u32() as i32;
+    }
+    let idx = idx;
+
+    for i in (0..total_abits as i32).rev() {
+        let idx = idx[i as usize] % (i + 1);
+        let idx = if idx < 0 { -idx } else { idx };
+
+        permutation.swap(i as usize, idx as usize);
+    }
+
+    permutation
+}
+
 /// Implements the preprocessing phase for AND gates.
 ///
 /// The protocol processes authenticated AND triples from previous steps and returns a new vector
@@ -1182,31 +2694,12 @@ fn preprocessing_and_gate_bits(
 ///   - Function `finish`: Upon receiving upstream bits, computes the final authenticated AND
 ///     triples.
 impl AndsBucketingState {
-    fn init(state: OtAndsState5, circuit: &Circuit) -> StateResult<AndsBucketingState> {
-        fn new_permutation(mut rng: ChaCha20Rng, total_abits: usize) -> Vec<u32> {
-            let mut permutation = vec![0; total_abits];
-            for (i, item) in permutation.iter_mut().enumerate().take(total_abits) {
-                *item = i as u32;
-            }
-
-            let mut idx: Vec<i32> = vec![0; total_abits];
-            for i in 0..total_abits {
-                idx[i] = rng.next_u32() as i32;
-            }
-            let idx = idx;
-
-            for i in (0..total_abits as i32).rev() {
-                let idx = idx[i as usize] % (i + 1);
-                let idx = if idx < 0 { -idx } else { idx };
-
-                permutation.swap(i as usize, idx as usize);
-            }
-
-            permutation
-        }
-
-        let bucket_size = bucket_size(circuit);
-        let length = circuit.and_gates();
+    fn init(
+        state: OtAndsState5,
+        size: PreprocessingSize,
+    ) -> MpcResult<AndsBucketingState, BucketingBits> {
+        let bucket_size = size.bucket_size();
+        let length = size.ands;
 
         assert!(state.and_triples.len() >= length * bucket_size);
 
@@ -1230,10 +2723,15 @@ impl AndsBucketingState {
             }
         }
 
-        let msg = serialize(&(&bits, macs))?;
+        let msg = BucketingBits {
+            bits: bits.clone(),
+            macs,
+        };
 
         let state = AndsBucketingState {
             rng: state.rng,
+            coin: state.coin,
+            session: state.session,
             delta: state.delta,
             bucketing_bits: bits,
             wire_abits: state.wire_abits,
@@ -1246,42 +2744,42 @@ impl AndsBucketingState {
         Ok((state, msg))
     }
 
-    fn finish(self, msg: &[u8], circuit: &Circuit) -> StateResult<OtAndsState6> {
+    pub(crate) fn finish(self, msg: BucketingBits) -> Result<(Preprocessed, ChaCha20Rng), Error> {
         let mut state = self.update_triples(msg)?;
 
-        let wire_abits = state.wire_abits;
-        let masks = preprocessing_assign_masks(wire_abits, &mut state.rng, &state.delta, circuit);
-        let (lhs_and_bits, rhs_and_bits) =
-            preprocessing_and_gate_bits(circuit, &masks, &state.and_triples);
-        let msg = serialize(&(&lhs_and_bits, &rhs_and_bits))?;
-
-        let state = OtAndsState6 {
+        // only the first triple of each bucket is used for the AND gates:
+        state.and_triples.truncate(state.length * 3);
+        let preprocessed = Preprocessed {
+            coin: state.coin,
+            session: state.session,
             delta: state.delta,
+            abits: state.wire_abits,
             and_triples: state.and_triples,
-            masks,
-            lhs_and_bits,
-            rhs_and_bits,
         };
-
-        Ok((state, msg))
+        Ok((preprocessed, state.rng))
     }
 
     /// Updates the triples and performs verification checks.
-    fn update_triples(self, msg: &[u8]) -> Result<AndsBucketingState, Error> {
+    fn update_triples(self, msg: BucketingBits) -> Result<AndsBucketingState, Error> {
         assert!(self.bucketing_bits.len() == self.length * self.bucket_size);
 
-        let (upstream_bits, upstream_macs): (Vec<bool>, Vec<MacType>) = deserialize(msg)?;
-        if upstream_bits.len() != self.bucketing_bits.len()
-            || upstream_macs.len() != self.bucketing_bits.len()
-        {
-            return Err(Error::InsufficientInput);
+        let BucketingBits {
+            bits: upstream_bits,
+            macs: upstream_macs,
+        } = msg;
+        let expected = self.bucketing_bits.len();
+        if upstream_bits.len() != expected {
+            return Err(Error::unexpected_length(expected, upstream_bits.len()));
+        }
+        if upstream_macs.len() != expected {
+            return Err(Error::unexpected_length(expected, upstream_macs.len()));
         }
 
         let perm = &self.permutation;
 
         // Verify the authenticated bits
         {
-            let mut checks_succeeded = 0;
+            let mut checks = MacChecks::new(MacCheck::Bucketing);
             for i in 0..self.length {
                 let lhs = perm[i * self.bucket_size] as usize;
 
@@ -1293,12 +2791,10 @@ impl AndsBucketingState {
                         mac: upstream_macs[i * self.bucket_size + j],
                     };
 
-                    checks_succeeded += upstream_bs.verify(&d.key, &self.delta) as usize;
+                    checks.verify(i, upstream_bs.verify(&d.key, &self.delta));
                 }
             }
-            if checks_succeeded != self.length * (self.bucket_size - 1) {
-                return Err(Error::MacError);
-            }
+            checks.finish()?;
         }
 
         // Update the authenticated bits
@@ -1309,6 +2805,8 @@ impl AndsBucketingState {
 
         Ok(AndsBucketingState {
             rng: self.rng,
+            coin: self.coin,
+            session: self.session,
             delta: self.delta,
             bucketing_bits,
             wire_abits: self.wire_abits,
@@ -1320,21 +2818,98 @@ impl AndsBucketingState {
     }
 }
 
-fn ot_ands6(state: OtAndsState5, msg: &[u8], circuit: &Circuit) -> StateResult<AndsBucketingState> {
-    // 2nd part of Step 4e/5e of `Π_{LaAND}`
-    let (r_prime, r_and_rand): (Vec<MacType>, Vec<(MacType, KeyType)>) = deserialize(msg)?;
-    check_hash(&state, &r_prime, &r_and_rand)?;
+/// Starts the bucketing, keeping the own values of the equality check of `Π_{LaAND}` until the
+/// other party has opened its values.
+fn ot_ands6(
+    mut state: OtAndsState5,
+    size: PreprocessingSize,
+) -> MpcResult<(AndsBucketingState, LeakyAndEquality), BucketingBits> {
+    let equality = LeakyAndEquality {
+        session: state.session,
+        r_and_rand_key: std::mem::take(&mut state.r_and_rand_key),
+        r_and_rand_hash: std::mem::take(&mut state.r_and_rand_hash),
+        r_prime: std::mem::take(&mut state.r_prime),
+    };
+    let (bucketing, msg) = AndsBucketingState::init(state, size)?;
+    Ok(((bucketing, equality), msg))
+}
+
+/// Assigns the preprocessed authenticated bits to the wires of the circuit, returning the masked
+/// AND triple bits for the other party.
+pub(crate) fn assign_masks(
+    preprocessed: Preprocessed,
+    rng: &mut ChaCha20Rng,
+    circuit: &Circuit,
+) -> MpcResult<OtAndsState6, MaskedAndBits> {
+    let Preprocessed {
+        delta,
+        session,
+        abits,
+        and_triples,
+        ..
+    } = preprocessed;
+    let masks = preprocessing_assign_masks(abits, rng, &delta, circuit);
+    let (lhs_and_bits, rhs_and_bits) = preprocessing_and_gate_bits(circuit, &masks, &and_triples);
+    let msg = MaskedAndBits {
+        lhs: lhs_and_bits.clone(),
+        rhs: rhs_and_bits.clone(),
+    };
+
+    let state = OtAndsState6 {
+        delta,
+        session,
+        and_triples,
+        masks,
+        lhs_and_bits,
+        rhs_and_bits,
+    };
+    Ok((state, msg))
+}
+
+/// Returns the own shares of the masks of the other party's input gates, which need no
+/// interaction and can be sent together with the masked AND triple bits.
+pub(crate) fn input_mask_shares(
+    circuit: &Circuit,
+    masks: &[WireMask],
+    input_gate: Gate,
+) -> Vec<InputMaskShare> {
+    let mut mask_shares = Vec::new();
+    for (index, gate) in circuit.gates().iter().enumerate() {
+        if *gate == input_gate {
+            mask_shares.push((
+                index as GateIndex,
+                PartialBitShare {
+                    mac: masks[index].bit.mac,
+                    bit: masks[index].bit.bit,
+                },
+            ))
+        }
+    }
+    mask_shares
+}
 
-    AndsBucketingState::init(state, circuit)
+/// Assigns the contributor's masks like [`assign_masks`], adding its shares of the masks of the
+/// evaluator's input to the message.
+pub(crate) fn assign_contrib_masks(
+    preprocessed: Preprocessed,
+    rng: &mut ChaCha20Rng,
+    circuit: &Circuit,
+) -> MpcResult<OtAndsState6, ContribMasks> {
+    let (state, masks) = assign_masks(preprocessed, rng, circuit)?;
+    let input_mask_shares = input_mask_shares(circuit, &state.masks, Gate::InEval);
+    let msg = ContribMasks {
+        masks,
+        input_mask_shares,
+    };
+    Ok((state, msg))
 }
 
-fn ot_ands8_contrib(
+pub(crate) fn ot_ands8_contrib(
     mut state: OtAndsState6,
-    msg1: &[u8],
+    msg1: MaskedAndBits,
     circuit: &Circuit,
-    input: &[bool],
-) -> StateResult<InputProcContrib> {
-    let (x2, y2): (Vec<bool>, Vec<bool>) = deserialize(msg1)?;
+) -> MpcResult<InputProcContrib, GarbledTables> {
+    let MaskedAndBits { lhs: x2, rhs: y2 } = msg1;
     if state.lhs_and_bits.len() != x2.len()
         || state.rhs_and_bits.len() != y2.len()
         || state.lhs_and_bits.len() != state.rhs_and_bits.len()
@@ -1350,6 +2925,7 @@ fn ot_ands8_contrib(
     let masks = &state.masks;
     let mut ands = 0_usize;
     let mut garbled_table_shares = Vec::new();
+    let garbling = state.session.key(domain::GARBLING);
 
     for (index, gate) in circuit.gates().iter().enumerate() {
         if let Gate::And(input_lhs, input_rhs) = gate {
@@ -1358,6 +2934,7 @@ fn ot_ands8_contrib(
 
             let values = compute_hashes_contrib(
                 &state,
+                &garbling,
                 index,
                 &masks[index],
                 &masks[*input_lhs as usize],
@@ -1371,32 +2948,16 @@ fn ot_ands8_contrib(
     let pending_from_a = circuit.contrib_inputs();
     let pending_from_b = circuit.eval_inputs();
 
-    if pending_from_a > input.len() {
-        return Err(InsufficientInput);
-    }
     if pending_from_a + pending_from_b == 0 {
         return Err(InvalidCircuit);
     }
-
-    // generate message for each input bit and continue
-    let mut input_mask_shares = Vec::with_capacity(pending_from_b);
-    for (index, gate) in circuit.gates().iter().enumerate() {
-        if gate == &Gate::InEval {
-            input_mask_shares.push((
-                index as GateIndex,
-                PartialBitShare {
-                    bit: masks[index].bit.bit,
-                    mac: masks[index].bit.mac,
-                },
-            ))
-        }
-    }
-    let msg = serialize(&(garbled_table_shares, input_mask_shares))?;
+    let msg = GarbledTables {
+        tables: garbled_table_shares,
+    };
 
     let state = InputProcContrib {
         delta: state.delta,
         pending_from_b,
-        mac_checks_success: true,
         masks: state.masks,
     };
     Ok((state, msg))
@@ -1424,15 +2985,21 @@ impl OtAndsState6 {
     }
 }
 
-fn ot_ands8_eval(
+pub(crate) fn ot_ands8_eval(
     mut state: OtAndsState6,
-    msg1: &[u8],
-    msg2: &[u8],
+    msg: ContribMasks,
     circuit: &Circuit,
     input: &[bool],
-) -> StateResult<InputProcEval> {
-    let (upstream_lhs_bits, upstream_rhs_bits): (Vec<bool>, Vec<bool>) = deserialize(msg1)?;
-
+    loaded: &[BitShare],
+) -> MpcResult<InputProcEval, InputMasks> {
+    let ContribMasks {
+        masks:
+            MaskedAndBits {
+                lhs: upstream_lhs_bits,
+                rhs: upstream_rhs_bits,
+            },
+        input_mask_shares: upstream_mask_shares,
+    } = msg;
     for i in 0..state.lhs_and_bits.len() {
         state.lhs_and_bits[i] ^= upstream_lhs_bits[i];
         state.rhs_and_bits[i] ^= upstream_rhs_bits[i];
@@ -1455,61 +3022,6 @@ fn ot_ands8_eval(
     }
 
     // input processing:
-    let (garbled_table_shares, input_mask_shares): (Vec<TableShare>, Vec<InputMaskShare>) =
-        deserialize(msg2)?;
-    if ands != garbled_table_shares.len() {
-        return Err(UnexpectedGarbledTableShare);
-    }
-    for (gate, and_share) in garbled_table_shares {
-        if !circuit.gates()[gate as usize].is_and() {
-            return Err(UnexpectedGarbledTableShare);
-        }
-        wires[gate as usize].other_and_table = and_share;
-    }
-
-    if circuit.eval_inputs() > input.len() {
-        return Err(InsufficientInput);
-    }
-
-    // generate message for each input bit and continue
-    let mut mask_shares = Vec::new();
-    for (index, gate) in circuit.gates().iter().enumerate() {
-        if gate == &Gate::InContrib {
-            mask_shares.push((
-                index as GateIndex,
-                PartialBitShare {
-                    mac: state.masks[index].bit.mac,
-                    bit: state.masks[index].bit.bit,
-                },
-            ))
-        }
-    }
-
-    let mut masked_inputs = Vec::with_capacity(input_mask_shares.len());
-    for ((index, bit_share), input) in input_mask_shares.iter().zip(input.iter()) {
-        if circuit.gates()[*index as usize] != Gate::InEval {
-            return Err(UnexpectedMessageType);
-        }
-
-        let mask = &state.masks[*index as usize];
-        assert!(bit_share.verify(&mask.bit.key, &state.delta));
-
-        let masked_input = mask.bit.bit ^ bit_share.bit ^ input;
-        masked_inputs.push((*index, masked_input));
-    }
-
-    let (garbled_table_shares, input_mask_shares): (Vec<TableShare>, Vec<InputMaskShare>) =
-        deserialize(msg2)?;
-    if ands != garbled_table_shares.len() {
-        return Err(UnexpectedGarbledTableShare);
-    }
-    for (gate, and_share) in garbled_table_shares {
-        if !circuit.gates()[gate as usize].is_and() {
-            return Err(UnexpectedGarbledTableShare);
-        }
-        wires[gate as usize].other_and_table = and_share;
-    }
-
     let input_gates = circuit
         .gates()
         .iter()
@@ -1520,37 +3032,40 @@ fn ot_ands8_eval(
     }
 
     // generate message for each input bit and continue
-    let mut mask_shares = Vec::new();
-    for (index, gate) in circuit.gates().iter().enumerate() {
-        if gate == &Gate::InContrib {
-            mask_shares.push((
-                index as GateIndex,
-                PartialBitShare {
-                    mac: state.masks[index].bit.mac,
-                    bit: state.masks[index].bit.bit,
-                },
-            ))
-        }
-    }
-
-    let mut masked_inputs = Vec::with_capacity(input_mask_shares.len());
-    for ((index, bit_share), input) in input_mask_shares.iter().zip(input.iter()) {
+    let mask_shares = input_mask_shares(circuit, &state.masks, Gate::InContrib);
+    let mut masked_inputs = Vec::with_capacity(upstream_mask_shares.len());
+    let mut linked_macs = Vec::with_capacity(loaded.len());
+    let mut checks = MacChecks::new(MacCheck::InputMask);
+    for (i, ((index, bit_share), input)) in upstream_mask_shares.iter().zip(input).enumerate() {
         if circuit.gates()[*index as usize] != Gate::InEval {
-            return Err(UnexpectedMessageType);
+            return Err(Error::unexpected_gate(*index as usize));
         }
 
         let mask = &state.masks[*index as usize];
-        assert!(bit_share.verify(&mask.bit.key, &state.delta));
+        checks.verify(*index as usize, bit_share.verify(&mask.bit.key, &state.delta));
 
         let masked_input = mask.bit.bit ^ bit_share.bit ^ input;
         masked_inputs.push((*index, masked_input));
+
+        // inputs from loaded state handles are linked to the stored shares by disclosing the MAC
+        // of `input ^ mask`
+        if let Some(state_share) = loaded.get(i) {
+            linked_macs.push(MacType(state_share.mac.0 ^ mask.bit.mac.0));
+        }
     }
-    let reply = serialize(&(mask_shares, masked_inputs))?;
+    checks.finish()?;
+    let reply = InputMasks {
+        mask_shares,
+        masked_inputs,
+        linked_macs,
+    };
     let state = InputProcEval {
         delta: state.delta,
+        session: state.session,
         pending_input: circuit.eval_inputs() + circuit.contrib_inputs(),
         masks: state.masks,
         wires,
+        loaded: loaded.to_vec(),
     };
 
     Ok((state, reply))
@@ -1628,69 +3143,247 @@ fn compute_hashes(
 }
 
 impl InputProcContrib {
-    fn run(mut self, msg: &[u8], circuit: &Circuit, input: &[bool]) -> MpcResult<()> {
+    fn run(
+        self,
+        msg: InputMasks,
+        circuit: &Circuit,
+        input: &[bool],
+        loaded: &[BitShare],
+        shared_outputs: usize,
+    ) -> MpcResult<OutputContrib, EvaluationInputs> {
         // P_B sends its mask to P_A which then returns masked input plus label to P_B for final
         // circuit evaluation
-        let (shares, inputs): (Vec<InputMaskShare>, Vec<(u32, bool)>) = deserialize(msg)?;
+        let InputMasks {
+            mask_shares: shares,
+            masked_inputs: inputs,
+            linked_macs,
+        } = msg;
         let mut evaluation_inputs = Vec::with_capacity(shares.len());
-        for ((index, bit_share), input) in shares.iter().zip(input.iter()) {
+        let mut my_linked_macs = Vec::with_capacity(loaded.len());
+        let mut mask_checks = MacChecks::new(MacCheck::InputMask);
+        for (i, ((index, bit_share), input)) in shares.iter().zip(input.iter()).enumerate() {
             if circuit.gates()[*index as usize] != Gate::InContrib {
-                return Err(UnexpectedMessageType);
+                return Err(Error::unexpected_gate(*index as usize));
             }
             let mask = &self.masks[*index as usize];
 
-            self.mac_checks_success &= bit_share.verify(&mask.bit.key, &self.delta);
+            mask_checks.verify(*index as usize, bit_share.verify(&mask.bit.key, &self.delta));
             let my_input_masked = input ^ bit_share.bit ^ mask.bit.bit;
             let label = mask.label(my_input_masked, &self.delta);
             evaluation_inputs.push((*index, label, my_input_masked));
+
+            // inputs from loaded state handles are linked to the stored shares by disclosing the
+            // MAC of `input ^ mask`
+            if let Some(state_share) = loaded.get(i) {
+                my_linked_macs.push(MacType(state_share.mac.0 ^ mask.bit.mac.0));
+            }
         }
 
         // P_B sends masked bit to P_A so P_A can return its label
+        if linked_macs.len() != loaded.len() {
+            return Err(Error::unexpected_length(loaded.len(), linked_macs.len()));
+        }
+        let linked_gates = linked_gates(circuit, Gate::InEval, loaded.len());
+        let mut linked = 0;
+        let mut linked_checks = MacChecks::new(MacCheck::LinkedInput);
         for (index, bit) in inputs {
-            if circuit.gates()[index as usize] != Gate::InEval {
-                return Err(UnexpectedMessageType);
-            }
-            if self.pending_from_b == 0 {
-                return Err(UnexpectedMessageType);
+            if circuit.gates()[index as usize] != Gate::InEval || self.pending_from_b == 0 {
+                return Err(Error::unexpected_gate(index as usize));
             }
 
             let mask = &self.masks[index as usize];
+            if linked < loaded.len() && index as usize == linked_gates[linked] {
+                let key = KeyType(loaded[linked].key.0 ^ mask.bit.key.0);
+                let linked_share = PartialBitShare {
+                    mac: linked_macs[linked],
+                    bit: bit ^ mask.bit.bit,
+                };
+                linked_checks.verify(index as usize, linked_share.verify(&key, &self.delta));
+                linked += 1;
+            }
             let label = mask.label(bit, &self.delta);
             evaluation_inputs.push((index, label, bit));
         }
+        if linked != loaded.len() {
+            return Err(Error::unexpected_length(loaded.len(), linked));
+        }
+        mask_checks.finish()?;
+        linked_checks.finish()?;
 
-        if self.mac_checks_success {
-            // disclose masks of output gates to other party
-            let mut mask_shares = Vec::new();
-            for index in circuit.output_gates() {
-                mask_shares.push((
-                    *index,
-                    PartialBitShare {
-                        mac: self.masks[*index as usize].bit.mac,
-                        bit: self.masks[*index as usize].bit.bit,
-                    },
-                ));
-            }
-            let reply = serialize(&(evaluation_inputs, mask_shares))?;
-            Ok(((), reply))
-        } else {
-            Err(MacError)
+        // disclose masks of output gates to other party, unless the output stays shared
+        let mut mask_shares = Vec::new();
+        for index in &circuit.output_gates()[shared_outputs..] {
+            mask_shares.push((
+                *index,
+                PartialBitShare {
+                    mac: self.masks[*index as usize].bit.mac,
+                    bit: self.masks[*index as usize].bit.bit,
+                },
+            ));
+        }
+        let reply = EvaluationInputs {
+            inputs: evaluation_inputs,
+            output_mask_shares: mask_shares,
+            linked_macs: my_linked_macs,
+        };
+        let masks = circuit
+            .output_gates()
+            .iter()
+            .map(|index| self.masks[*index as usize].clone())
+            .collect();
+        let state = OutputContrib {
+            delta: self.delta,
+            masks,
+            shared: shared_outputs,
+        };
+        Ok((state, reply))
+    }
+}
+
+/// Returns the indices of the first `n` input gates of a party.
+fn linked_gates(circuit: &Circuit, input_gate: Gate, n: usize) -> Vec<usize> {
+    let gates = circuit.gates().iter().enumerate();
+    let inputs = gates.filter(|(_, gate)| **gate == input_gate);
+    inputs.map(|(index, _)| index).take(n).collect()
+}
+
+impl OutputContrib {
+    fn run(self, msg: RevealedOutput) -> Result<Vec<bool>, Error> {
+        // P_B reveals the labels of the output wires, which P_A can only accept for the correct
+        // masked values, together with its authenticated shares of the output masks
+        let revealed = msg.outputs;
+        let masks = &self.masks[self.shared..];
+        if revealed.len() != masks.len() {
+            return Err(Error::unexpected_length(masks.len(), revealed.len()));
+        }
+        let mut checks = MacChecks::new(MacCheck::Output);
+        let mut output = Vec::with_capacity(revealed.len());
+        for (i, ((label, masked_value, bit_share), mask)) in
+            revealed.iter().zip(masks).enumerate()
+        {
+            let label_ok = *label == mask.label(*masked_value, &self.delta);
+            checks.verify(i, label_ok & bit_share.verify(&mask.bit.key, &self.delta));
+            output.push(masked_value ^ bit_share.bit ^ mask.bit.bit);
+        }
+        checks.finish()?;
+        Ok(output)
+    }
+
+    fn shares(&self) -> OutputShares {
+        // P_A's share is its output mask, P_B's share `masked_value ^ mask` is authenticated by the
+        // label of the masked value XORed with the MAC of P_B's mask, i.e. under `label_0 ^ key`
+        let shares = self.masks[..self.shared]
+            .iter()
+            .map(|mask| BitShare {
+                key: KeyType(mask.label_0.0 ^ mask.bit.key.0),
+                mac: mask.bit.mac,
+                bit: mask.bit.bit,
+            })
+            .collect();
+        OutputShares {
+            delta: self.delta.clone(),
+            shares,
         }
     }
 }
 
 impl InputProcEval {
-    fn run(mut self, msg: &[u8], circuit: &Circuit) -> MpcResult<Vec<bool>> {
-        let (inputs, shares): (Vec<(u32, WireLabel, bool)>, Vec<InputMaskShare>) =
-            deserialize(msg)?;
+    /// Evaluates the circuit, returning the revealed outputs, a message that reveals them to P_A
+    /// and the shares of the first `shared_outputs` outputs.
+    fn run(
+        mut self,
+        (msg1, msg2): (GarbledTables, EvaluationInputs),
+        circuit: &Circuit,
+        shared_outputs: usize,
+    ) -> Result<(Vec<bool>, RevealedOutput, OutputShares), Error> {
+        self.receive_tables(msg1, circuit)?;
+        let (wires, shares) = self.evaluate(msg2, circuit)?;
+        let (shared, revealed) = circuit.output_gates().split_at(shared_outputs);
+        if revealed.len() != shares.len() {
+            return Err(OutputModeMismatch);
+        }
+        let mut checks = MacChecks::new(MacCheck::Output);
+        let mut output = Vec::with_capacity(revealed.len());
+        let mut revealed_to_contrib = Vec::with_capacity(revealed.len());
+        for (i, (output_index, (index, bit_share))) in revealed.iter().zip(shares).enumerate() {
+            if *output_index != index {
+                return Err(Error::unexpected_gate(index as usize));
+            }
+            checks.verify(
+                i,
+                bit_share.verify(&self.masks[index as usize].bit.key, &self.delta),
+            );
+
+            let wire = &wires[index as usize];
+            let mask = &self.masks[index as usize];
+            output.push(wire.masked_value ^ bit_share.bit ^ mask.bit.bit);
+
+            let my_share = PartialBitShare {
+                mac: mask.bit.mac,
+                bit: mask.bit.bit,
+            };
+            revealed_to_contrib.push((wire.label.clone(), wire.masked_value, my_share));
+        }
+        checks.finish()?;
+
+        // the label of the masked value XORed with the MAC of the mask authenticates the share
+        // `masked_value ^ mask` under the Delta of P_A
+        let shares = shared
+            .iter()
+            .map(|index| {
+                let wire = &wires[*index as usize];
+                let mask = &self.masks[*index as usize];
+                BitShare {
+                    key: mask.bit.key,
+                    mac: MacType(wire.label.0 ^ mask.bit.mac.0),
+                    bit: wire.masked_value ^ mask.bit.bit,
+                }
+            })
+            .collect();
+        let shares = OutputShares {
+            delta: self.delta,
+            shares,
+        };
+        let reply = RevealedOutput {
+            outputs: revealed_to_contrib,
+        };
+        Ok((output, reply, shares))
+    }
+
+    /// Stores the shares of the garbled tables of P_A, which are sent together with the inputs.
+    fn receive_tables(&mut self, msg: GarbledTables, circuit: &Circuit) -> Result<(), Error> {
+        let GarbledTables {
+            tables: garbled_table_shares,
+        } = msg;
+        if circuit.and_gates() != garbled_table_shares.len() {
+            return Err(UnexpectedGarbledTableShare);
+        }
+        for (gate, and_share) in garbled_table_shares {
+            if !circuit.gates()[gate as usize].is_and() {
+                return Err(UnexpectedGarbledTableShare);
+            }
+            self.wires[gate as usize].other_and_table = and_share;
+        }
+        Ok(())
+    }
+
+    /// Evaluates the circuit, returning all wires and the output mask shares of P_A.
+    fn evaluate(
+        &mut self,
+        msg: EvaluationInputs,
+        circuit: &Circuit,
+    ) -> Result<(Vec<WireState>, Vec<InputMaskShare>), Error> {
+        let EvaluationInputs {
+            inputs,
+            output_mask_shares: shares,
+            linked_macs,
+        } = msg;
         for (index, label, masked_value) in inputs {
             if circuit.gates()[index as usize] != Gate::InEval
                 && circuit.gates()[index as usize] != Gate::InContrib
+                || self.pending_input == 0
             {
-                return Err(UnexpectedMessageType);
-            }
-            if self.pending_input == 0 {
-                return Err(UnexpectedMessageType);
+                return Err(Error::unexpected_gate(index as usize));
             }
 
             self.wires[index as usize].label = label.clone();
@@ -1700,8 +3393,25 @@ impl InputProcEval {
         }
 
         assert_eq!(self.pending_input, 0);
-        let mut wires = self.wires;
-        let mut mac_checks_success = true;
+        let mut wires = std::mem::take(&mut self.wires);
+        let garbling = self.session.key(domain::GARBLING);
+        let mut linked_checks = MacChecks::new(MacCheck::LinkedInput);
+        let mut and_checks = MacChecks::new(MacCheck::AndTable);
+
+        // P_A proves that its inputs from loaded state handles match the stored shares
+        if linked_macs.len() != self.loaded.len() {
+            return Err(Error::unexpected_length(self.loaded.len(), linked_macs.len()));
+        }
+        let linked_gates = linked_gates(circuit, Gate::InContrib, self.loaded.len());
+        for ((index, state_share), mac) in linked_gates.iter().zip(&self.loaded).zip(linked_macs) {
+            let mask = &self.masks[*index];
+            let key = KeyType(state_share.key.0 ^ mask.bit.key.0);
+            let linked_share = PartialBitShare {
+                mac,
+                bit: wires[*index].masked_value ^ mask.bit.bit,
+            };
+            linked_checks.verify(*index, linked_share.verify(&key, &self.delta));
+        }
         for (index, gate) in circuit.gates().iter().enumerate() {
             if let Gate::Xor(input_lhs, input_rhs) = gate {
                 wires[index].masked_value = wires[*input_lhs as usize].masked_value
@@ -1718,14 +3428,15 @@ impl InputProcEval {
 
                 let row: u8 = 2 * u8::from(lhs.masked_value) + u8::from(rhs.masked_value);
                 let result = wires[index].other_and_table[row as usize].xor(&garbling_hash::new(
+                    &garbling,
                     &lhs.label,
                     &rhs.label,
                     index as u32,
                     row,
                 ));
 
-                mac_checks_success &= PartialBitShare::from(&result)
-                    .verify(&wires[index].my_and_table[row as usize].key, &self.delta);
+                let key = &wires[index].my_and_table[row as usize].key;
+                and_checks.verify(index, PartialBitShare::from(&result).verify(key, &self.delta));
 
                 wires[index].masked_value =
                     wires[index].my_and_table[row as usize].bit ^ result.bit;
@@ -1733,29 +3444,8 @@ impl InputProcEval {
                     WireLabel(result.key.0 ^ wires[index].my_and_table[row as usize].mac.0);
             }
         }
-        if !mac_checks_success {
-            return Err(MacError);
-        }
-
-        let mut output = Vec::with_capacity(circuit.output_gates().len());
-        if circuit.output_gates().len() != shares.len() {
-            return Err(UnexpectedMessageType);
-        }
-        for (index, bit_share) in shares {
-            mac_checks_success &=
-                bit_share.verify(&self.masks[index as usize].bit.key, &self.delta);
-
-            let result = wires[index as usize].masked_value
-                ^ bit_share.bit
-                ^ self.masks[index as usize].bit.bit;
-
-            output.push(result);
-        }
-        if mac_checks_success {
-            let empty_reply = vec![];
-            Ok((output, empty_reply))
-        } else {
-            Err(MacError)
-        }
+        linked_checks.finish()?;
+        and_checks.finish()?;
+        Ok((wires, shares))
     }
 }
@@ -0,0 +1,590 @@
+//! Zero-knowledge proofs that a circuit outputs `true` on a private witness, using garbled circuits
+//! (Jawurek, Kerschbaum and Orlandi, "Zero-Knowledge Using Garbled Circuits", 2013).
+//!
+//! The [`Prover`] knows a witness, which provides the bits of all input gates of the circuit, and
+//! convinces the [`Verifier`], who has no input, that every output of the circuit is `true` for
+//! this witness, without revealing anything else about it. Since the verifier knows no secret, it
+//! can garble the circuit itself and later reveal the complete garbling:
+//!
+//! 1. The prover obtains the labels of its witness bits through the same base OTs and OT
+//!    extension that the other protocols use, with the `Delta` of the verifier as the correlation,
+//!    together with an additional block of OTs for random bits.
+//! 2. The verifier garbles the circuit with half-gates, using the keys of the OTs as the labels
+//!    for `false` of the input gates, and sends the garbled tables.
+//! 3. The prover evaluates the circuit and commits to the labels of the outputs.
+//! 4. The verifier reveals its `Delta` and the keys of the additional OTs. The prover checks these
+//!    keys against its own random bits, which binds `Delta` to the OTs, then derives all input
+//!    labels, garbles the circuit again and checks that the tables are the ones it received.
+//! 5. Only if the garbling was correct does the prover open its commitment. The verifier accepts
+//!    the proof if the committed labels are the labels for `true`.
+//!
+//! The prover cannot compute the label for `true` of an output that is `false` without knowing
+//! `Delta`, which is only revealed after the prover has committed to its labels. The verifier, on
+//! the other hand, learns nothing about the witness from the garbling check: the labels that the
+//! prover derives from the revealed `Delta` are the same for every witness, so that a wrong
+//! garbling makes the prover abort regardless of the witness.
+//!
+//! # Examples
+//!
+//! ```
+//! use mpc_core::{zk::{Prover, Verifier}, Circuit, Gate};
+//! use rand::SeedableRng;
+//! use rand_chacha::ChaCha20Rng;
+//!
+//! // A statement about a secret 2-bit value: it is 1 or 2.
+//! let circuit = Circuit::new(
+//!     vec![Gate::InEval, Gate::InEval, Gate::Xor(0, 1)],
+//!     vec![2],
+//! );
+//!
+//! let rng = ChaCha20Rng::from_entropy();
+//! let (prover, msg) = Prover::new(&circuit, &[false, true], rng).unwrap();
+//! let rng = ChaCha20Rng::from_entropy();
+//! let verifier = Verifier::new(&circuit, rng).unwrap();
+//!
+//! let (verifier, msg) = verifier.run(&msg).unwrap();
+//! let (prover, msg) = prover.run(&msg).unwrap();
+//! let (verifier, msg) = verifier.run(&msg).unwrap();
+//! let (prover, msg) = prover.run(&msg).unwrap();
+//! let (verifier, msg) = verifier.run(&msg).unwrap();
+//! let msg = prover.open(&msg).unwrap();
+//! assert!(verifier.output(&msg).unwrap());
+//! ```
+
+use std::borrow::Borrow;
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::RngCore;
+
+use crate::{
+    hash::{commitment, domain, DomainKey},
+    leakydelta_ot::{
+        message::{OtInitReply, SerializedOtInit},
+        LeakyOtReceiver, ReceiverInitializer, SenderInitializer, BLOCK_SIZE,
+    },
+    message::{self, OtBlocks, OtCompletion, Step, ZkOpening, ZkReveal},
+    semihonest::{eval_and, garble_and},
+    states::Msg,
+    types::{Delta, KeyType, MacType, WireLabel},
+    Circuit, Error, Gate, GateIndex, MacCheck, MacChecks,
+};
+
+/// The prover of a zero-knowledge proof, which knows the witness and starts the protocol.
+pub struct Prover<C: Borrow<Circuit>> {
+    circuit: C,
+    state: ProverState,
+}
+
+/// The verifier of a zero-knowledge proof, which garbles the circuit.
+pub struct Verifier<C: Borrow<Circuit>> {
+    circuit: C,
+    rng: ChaCha20Rng,
+    state: VerifierState,
+}
+
+enum ProverState {
+    /// The prover has started the base OTs.
+    Init {
+        rng: ChaCha20Rng,
+        witness: Vec<bool>,
+        r_init: ReceiverInitializer,
+    },
+    /// The prover has sent its extended OTs and waits for the garbled circuit.
+    Extended {
+        rng: ChaCha20Rng,
+        witness: Vec<bool>,
+        ots: ProverOts,
+    },
+    /// The prover has committed to its output labels and waits for `Delta`.
+    Committed {
+        witness: Vec<bool>,
+        ots: ProverOts,
+        tables: Vec<[WireLabel; 2]>,
+        nonce: [u8; 32],
+        output_labels: Vec<WireLabel>,
+    },
+}
+
+/// The OT outputs of the prover, `key ^ bit * Delta` for each of its bits.
+struct ProverOts {
+    labels: Vec<WireLabel>,
+    check_bits: u128,
+    check_macs: Vec<MacType>,
+}
+
+enum VerifierState {
+    /// The verifier waits for the base OTs of the prover.
+    Init,
+    /// The verifier has answered the base OTs and waits for the extended OTs.
+    OtResponse {
+        delta: Delta,
+        s_init: SenderInitializer,
+    },
+    /// The verifier has sent the garbled circuit and waits for the commitment of the prover.
+    Garbled {
+        delta: Delta,
+        check_keys: Vec<KeyType>,
+        output_labels: Vec<WireLabel>,
+    },
+    /// The verifier has revealed `Delta` and waits for the opening of the commitment.
+    Revealed {
+        delta: Delta,
+        output_labels: Vec<WireLabel>,
+        commitment: [u8; 32],
+    },
+}
+
+/// Checks that the circuit only has inputs of the contributor and the evaluator.
+fn validate(circuit: &Circuit) -> Result<(), Error> {
+    circuit.validate()?;
+    if circuit
+        .gates()
+        .iter()
+        .any(|g| matches!(g, Gate::InParty(_)))
+    {
+        return Err(Error::InvalidCircuit);
+    }
+    Ok(())
+}
+
+fn witness_len(circuit: &Circuit) -> usize {
+    circuit.contrib_inputs() + circuit.eval_inputs()
+}
+
+/// The number of OT blocks, one more than needed for the witness for the check of `Delta`.
+fn blocks(circuit: &Circuit) -> usize {
+    (witness_len(circuit) + BLOCK_SIZE - 1) / BLOCK_SIZE + 1
+}
+
+/// The key of the half-gate hashes, bound to the circuit of the proof.
+fn garbling_key(circuit: &Circuit) -> DomainKey {
+    DomainKey::new(domain::ZK_GARBLING, &circuit.blake3_hash())
+}
+
+/// The key of the commitment of the prover to its output labels.
+fn commitment_key(circuit: &Circuit) -> DomainKey {
+    DomainKey::new(domain::ZK_COMMITMENT, &circuit.blake3_hash())
+}
+
+fn commit(circuit: &Circuit, nonce: &[u8; 32], labels: &[WireLabel]) -> [u8; 32] {
+    let labels: Vec<MacType> = labels.iter().map(|l| MacType(l.0)).collect();
+    commitment(&commitment_key(circuit), 0, nonce, &labels)
+}
+
+/// Garbles the circuit using the specified labels for `false` of its input gates, returning the
+/// garbled tables and the labels for `false` of the outputs.
+fn garble(
+    circuit: &Circuit,
+    input_labels: &[WireLabel],
+    delta: &Delta,
+) -> (Vec<[WireLabel; 2]>, Vec<WireLabel>) {
+    let key = garbling_key(circuit);
+    let mut input_labels = input_labels.iter();
+    let mut labels: Vec<WireLabel> = Vec::with_capacity(circuit.gates().len());
+    let mut tables = Vec::with_capacity(circuit.and_gates());
+    for (index, gate) in circuit.gates().iter().enumerate() {
+        let label_0 = match *gate {
+            Gate::InContrib | Gate::InEval | Gate::InParty(_) => {
+                input_labels.next().cloned().unwrap_or_default()
+            }
+            Gate::Xor(x, y) => labels[x as usize].xor(&labels[y as usize]),
+            Gate::Not(x) => labels[x as usize].xor(&WireLabel(delta.0)),
+            Gate::And(x, y) => {
+                let (table, label_0) = garble_and(
+                    &key,
+                    &labels[x as usize],
+                    &labels[y as usize],
+                    index as GateIndex,
+                    delta,
+                );
+                tables.push(table);
+                label_0
+            }
+        };
+        labels.push(label_0);
+    }
+    let output_labels = circuit
+        .output_gates()
+        .iter()
+        .map(|index| labels[*index as usize].clone())
+        .collect();
+    (tables, output_labels)
+}
+
+/// Evaluates the garbled circuit on the labels of the witness, returning the output labels.
+fn evaluate(
+    circuit: &Circuit,
+    input_labels: &[WireLabel],
+    tables: &[[WireLabel; 2]],
+) -> Result<Vec<WireLabel>, Error> {
+    let key = garbling_key(circuit);
+    let mut input_labels = input_labels.iter();
+    let mut tables = tables.iter();
+    let mut labels: Vec<WireLabel> = Vec::with_capacity(circuit.gates().len());
+    for (index, gate) in circuit.gates().iter().enumerate() {
+        let label = match *gate {
+            Gate::InContrib | Gate::InEval | Gate::InParty(_) => input_labels
+                .next()
+                .cloned()
+                .ok_or(Error::unexpected_gate(index))?,
+            Gate::Xor(x, y) => labels[x as usize].xor(&labels[y as usize]),
+            Gate::Not(x) => labels[x as usize].clone(),
+            Gate::And(x, y) => {
+                let table = tables.next().ok_or(Error::unexpected_gate(index))?;
+                eval_and(
+                    &key,
+                    &labels[x as usize],
+                    &labels[y as usize],
+                    index as GateIndex,
+                    table,
+                )
+            }
+        };
+        labels.push(label);
+    }
+    Ok(circuit
+        .output_gates()
+        .iter()
+        .map(|index| labels[*index as usize].clone())
+        .collect())
+}
+
+impl<C: Borrow<Circuit>> Prover<C> {
+    /// Initializes the prover with the witness, which provides the bits of all input gates of the
+    /// circuit in the order of the gates, returning the prover and the initial message for the
+    /// verifier.
+    pub fn new(circuit: C, witness: &[bool], mut rng: ChaCha20Rng) -> Result<(Self, Msg), Error> {
+        validate(circuit.borrow())?;
+        if witness.len() != witness_len(circuit.borrow()) {
+            return Err(Error::InsufficientInput);
+        }
+        let (r_init, ot_msg) = ReceiverInitializer::init(&mut rng);
+        let msg = message::seal(Step::ZkOtInit, None, &ot_msg.serialize())?;
+        let state = ProverState::Init {
+            rng,
+            witness: witness.to_vec(),
+            r_init,
+        };
+        Ok((Self { circuit, state }, msg))
+    }
+
+    /// Executes the next step of the protocol, returning the next state and the reply for the
+    /// verifier.
+    ///
+    /// The prover expects two messages here, the answer to its base OTs and the garbled circuit,
+    /// followed by the revealed `Delta` that is handled by [`Prover::open`].
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    /// The number of messages that the prover expects in [`Prover::run`], before the message for
+    /// [`Prover::open`].
+    pub fn steps(&self) -> u32 {
+        2
+    }
+
+    fn run_step(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let circuit = self.circuit.borrow();
+        let (state, reply) = match self.state {
+            ProverState::Init {
+                mut rng,
+                witness,
+                r_init,
+            } => {
+                let ot_init: SerializedOtInit = message::open(msg, Step::ZkOtResponse, None)?;
+                let ot_init = ot_init.deserialize()?;
+                let (r, reply): (LeakyOtReceiver, _) = r_init.recv(&ot_init);
+                let mut r = r;
+
+                let mut labels = Vec::with_capacity(witness.len());
+                let mut ot_blocks = Vec::with_capacity(blocks(circuit));
+                for chunk in witness.chunks(BLOCK_SIZE) {
+                    // bits beyond the witness are padded with random choices
+                    let mut bits = random_bits(&mut rng);
+                    for (i, bit) in chunk.iter().enumerate() {
+                        bits = (bits & !(1 << i)) | (u128::from(*bit) << i);
+                    }
+                    let mut macs_out = [MacType(0); BLOCK_SIZE];
+                    let mut ot_out = [MacType(0); BLOCK_SIZE];
+                    r.new_batch(bits, &mut macs_out, &mut ot_out);
+                    labels.extend(macs_out[..chunk.len()].iter().map(|m| WireLabel(m.0)));
+                    ot_blocks.push(ot_out.to_vec());
+                }
+                // the random bits of the last block bind the `Delta` that the verifier reveals:
+                let check_bits = random_bits(&mut rng);
+                let mut check_macs = [MacType(0); BLOCK_SIZE];
+                let mut ot_out = [MacType(0); BLOCK_SIZE];
+                r.new_batch(check_bits, &mut check_macs, &mut ot_out);
+                ot_blocks.push(ot_out.to_vec());
+
+                let reply = (
+                    OtCompletion {
+                        reply: reply.serialize(),
+                    },
+                    OtBlocks { blocks: ot_blocks },
+                );
+                let reply = message::seal(Step::ZkOtExtension, None, &reply)?;
+                let ots = ProverOts {
+                    labels,
+                    check_bits,
+                    check_macs: check_macs.to_vec(),
+                };
+                let state = ProverState::Extended { rng, witness, ots };
+                (state, reply)
+            }
+            ProverState::Extended {
+                mut rng,
+                witness,
+                ots,
+            } => {
+                let tables: Vec<[WireLabel; 2]> = message::open(msg, Step::ZkGarbledCircuit, None)?;
+                if tables.len() != circuit.and_gates() {
+                    return Err(Error::unexpected_length(circuit.and_gates(), tables.len()));
+                }
+                let output_labels = evaluate(circuit, &ots.labels, &tables)?;
+                let mut nonce = [0; 32];
+                rng.fill_bytes(&mut nonce);
+                let reply = commit(circuit, &nonce, &output_labels);
+                let reply = message::seal(Step::ZkCommitment, None, &reply)?;
+                let state = ProverState::Committed {
+                    witness,
+                    ots,
+                    tables,
+                    nonce,
+                    output_labels,
+                };
+                (state, reply)
+            }
+            ProverState::Committed { .. } => return Err(Error::ProtocolEnded),
+        };
+        let prover = Self {
+            circuit: self.circuit,
+            state,
+        };
+        Ok((prover, reply))
+    }
+
+    /// Checks the garbling against the `Delta` revealed by the verifier and opens the commitment
+    /// to the output labels, returning the final message for the verifier.
+    ///
+    /// Fails with [`Error::MacError`] if the verifier did not garble the circuit correctly, in
+    /// which case the proof must be aborted.
+    pub fn open(self, msg: &[u8]) -> Result<Msg, Error> {
+        let step = message::step(msg);
+        self.open_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    fn open_step(self, msg: &[u8]) -> Result<Msg, Error> {
+        let circuit = self.circuit.borrow();
+        let ProverState::Committed {
+            witness,
+            ots,
+            tables,
+            nonce,
+            output_labels,
+        } = self.state
+        else {
+            return Err(Error::ProtocolStillInProgress);
+        };
+        let ZkReveal { delta, keys } = message::open(msg, Step::ZkReveal, None)?;
+        if keys.len() != BLOCK_SIZE {
+            return Err(Error::unexpected_length(BLOCK_SIZE, keys.len()));
+        }
+        let delta = Delta(delta);
+        let delta_label = WireLabel(delta.0);
+
+        // `Delta` must be the correlation of the OTs, otherwise the derived labels would depend
+        // on the witness, and must have its lowest bit set to be usable for point-and-permute:
+        let mut checks = MacChecks::new(MacCheck::ProofDelta);
+        for (i, (key, mac)) in keys.iter().zip(ots.check_macs.iter()).enumerate() {
+            let bit = (ots.check_bits >> i) & 1 == 1;
+            let expected = if bit {
+                delta.xor(MacType(key.0))
+            } else {
+                MacType(key.0)
+            };
+            checks.verify(i, *mac == expected && delta.0 & 1 == 1);
+        }
+        checks.finish()?;
+
+        let input_labels: Vec<WireLabel> = ots
+            .labels
+            .iter()
+            .zip(witness.iter())
+            .map(|(label, bit)| {
+                if *bit {
+                    label.xor(&delta_label)
+                } else {
+                    label.clone()
+                }
+            })
+            .collect();
+        let (expected_tables, _) = garble(circuit, &input_labels, &delta);
+        let mut checks = MacChecks::new(MacCheck::ProofTable);
+        for (i, (table, expected)) in tables.iter().zip(expected_tables.iter()).enumerate() {
+            checks.verify(i, table == expected);
+        }
+        checks.finish()?;
+
+        let opening = ZkOpening {
+            nonce,
+            labels: output_labels,
+        };
+        message::seal(Step::ZkOpening, None, &opening)
+    }
+}
+
+impl<C: Borrow<Circuit>> Verifier<C> {
+    /// Initializes the verifier, which has no input of its own.
+    pub fn new(circuit: C, rng: ChaCha20Rng) -> Result<Self, Error> {
+        validate(circuit.borrow())?;
+        let state = VerifierState::Init;
+        Ok(Self {
+            circuit,
+            rng,
+            state,
+        })
+    }
+
+    /// Executes the next step of the protocol, returning the next state and the reply for the
+    /// prover.
+    ///
+    /// The verifier expects three messages here, the base OTs, the extended OTs and the commitment
+    /// of the prover, followed by the opening that is handled by [`Verifier::output`].
+    pub fn run(self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let step = message::step(msg);
+        self.run_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    /// The number of messages that the verifier expects in [`Verifier::run`], before the message
+    /// for [`Verifier::output`].
+    pub fn steps(&self) -> u32 {
+        3
+    }
+
+    fn run_step(mut self, msg: &[u8]) -> Result<(Self, Msg), Error> {
+        let circuit = self.circuit.borrow();
+        let rng = &mut self.rng;
+        let (state, reply) = match self.state {
+            VerifierState::Init => {
+                let ot_init: SerializedOtInit = message::open(msg, Step::ZkOtInit, None)?;
+                let ot_init = ot_init.deserialize()?;
+                let delta = Delta(Delta::gen_random(rng).0 | 1);
+                let (s_init, reply) = SenderInitializer::init(rng, delta.clone(), &ot_init);
+                let reply = message::seal(Step::ZkOtResponse, None, &reply.serialize())?;
+                (VerifierState::OtResponse { delta, s_init }, reply)
+            }
+            VerifierState::OtResponse { delta, s_init } => {
+                let (reply, ot_blocks): (OtCompletion, OtBlocks) =
+                    message::open(msg, Step::ZkOtExtension, None)?;
+                let ot_blocks = ot_blocks.blocks;
+                if ot_blocks.len() != blocks(circuit) {
+                    return Err(Error::unexpected_length(blocks(circuit), ot_blocks.len()));
+                }
+                let reply = OtInitReply::deserialize(reply.reply)?;
+                let mut s = s_init.recv(&reply);
+                let mut keys = Vec::with_capacity(ot_blocks.len() * BLOCK_SIZE);
+                for block in ot_blocks {
+                    let ot_rx: [MacType; BLOCK_SIZE] = block
+                        .try_into()
+                        .map_err(|_| Error::OtBlockDeserializationError)?;
+                    let mut keys_out = [MacType(0); BLOCK_SIZE];
+                    s.send(&ot_rx, &mut keys_out);
+                    keys.extend(keys_out);
+                }
+                let check_keys = keys.split_off(keys.len() - BLOCK_SIZE);
+                let check_keys = check_keys.into_iter().map(|k| KeyType(k.0)).collect();
+
+                // the keys of the OTs are the labels for `false` of the witness bits
+                let input_labels: Vec<WireLabel> = keys
+                    .iter()
+                    .take(witness_len(circuit))
+                    .map(|k| WireLabel(k.0))
+                    .collect();
+                let (tables, output_labels) = garble(circuit, &input_labels, &delta);
+                let reply = message::seal(Step::ZkGarbledCircuit, None, &tables)?;
+                let state = VerifierState::Garbled {
+                    delta,
+                    check_keys,
+                    output_labels,
+                };
+                (state, reply)
+            }
+            VerifierState::Garbled {
+                delta,
+                check_keys,
+                output_labels,
+            } => {
+                let commitment: [u8; 32] = message::open(msg, Step::ZkCommitment, None)?;
+                let reveal = ZkReveal {
+                    delta: delta.0,
+                    keys: check_keys,
+                };
+                let reply = message::seal(Step::ZkReveal, None, &reveal)?;
+                let state = VerifierState::Revealed {
+                    delta,
+                    output_labels,
+                    commitment,
+                };
+                (state, reply)
+            }
+            VerifierState::Revealed { .. } => return Err(Error::ProtocolEnded),
+        };
+        let verifier = Self {
+            circuit: self.circuit,
+            rng: self.rng,
+            state,
+        };
+        Ok((verifier, reply))
+    }
+
+    /// Checks the opened output labels of the prover, returning `true` if the proof is accepted,
+    /// meaning that every output of the circuit is `true` for the witness of the prover, and
+    /// `false` if some output is `false`.
+    ///
+    /// Fails with [`Error::MacError`] if the opening does not match the commitment or contains
+    /// labels that are invalid for both values of an output.
+    pub fn output(self, msg: &[u8]) -> Result<bool, Error> {
+        let step = message::step(msg);
+        self.output_step(msg).map_err(|e| e.at_step(step))
+    }
+
+    fn output_step(self, msg: &[u8]) -> Result<bool, Error> {
+        let circuit = self.circuit.borrow();
+        let VerifierState::Revealed {
+            delta,
+            output_labels,
+            commitment,
+        } = self.state
+        else {
+            return Err(Error::ProtocolStillInProgress);
+        };
+        let ZkOpening { nonce, labels } = message::open(msg, Step::ZkOpening, None)?;
+        if labels.len() != output_labels.len() {
+            return Err(Error::unexpected_length(output_labels.len(), labels.len()));
+        }
+        if commit(circuit, &nonce, &labels) != commitment {
+            return Err(Error::MacError {
+                step: None,
+                check: MacCheck::ProofCommitment,
+                failed: 1,
+                index: None,
+            });
+        }
+        let delta_label = WireLabel(delta.0);
+        let mut checks = MacChecks::new(MacCheck::Output);
+        let mut proven = true;
+        for (i, (label, label_0)) in labels.iter().zip(output_labels.iter()).enumerate() {
+            let bit = *label != *label_0;
+            checks.verify(i, !bit || *label == label_0.xor(&delta_label));
+            proven &= bit;
+        }
+        checks.finish()?;
+        Ok(proven)
+    }
+}
+
+fn random_bits(rng: &mut ChaCha20Rng) -> u128 {
+    rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)
+}
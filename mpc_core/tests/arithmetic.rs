@@ -0,0 +1,244 @@
+use mpc_core::{
+    arithmetic::{ArithCircuit, ArithGate, Contributor, Evaluator},
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    simulate, Error,
+};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// Runs the arithmetic engine and returns the shares of the contributor and the evaluator.
+fn run(
+    circuit: &ArithCircuit,
+    contrib_input: &[u64],
+    contrib_shares: &[u64],
+    eval_input: &[u64],
+    eval_shares: &[u64],
+) -> Result<(Vec<u64>, Vec<u64>), Error> {
+    let rng = ChaCha20Rng::from_entropy();
+    let (mut contrib, msg) = Contributor::new(circuit, contrib_input, contrib_shares, rng)?;
+    let rng = ChaCha20Rng::from_entropy();
+    let mut eval = Evaluator::new(circuit, eval_input, eval_shares, rng)?;
+    let mut msg = Some(msg);
+    while let Some(m) = msg.take() {
+        let (next_state, reply) = eval.run(&m)?;
+        eval = next_state;
+        if let Some(reply) = reply {
+            let (next_state, reply) = contrib.run(&reply)?;
+            contrib = next_state;
+            msg = reply;
+        }
+    }
+    Ok((contrib.output()?, eval.output()?))
+}
+
+fn reconstruct(contrib: &[u64], eval: &[u64]) -> Vec<u64> {
+    contrib
+        .iter()
+        .zip(eval)
+        .map(|(c, e)| c.wrapping_add(*e))
+        .collect()
+}
+
+fn assert_plaintext(circuit: &ArithCircuit, contrib_input: &[u64], eval_input: &[u64]) {
+    let expected = circuit.eval(contrib_input, eval_input, &[]).unwrap();
+    let (contrib, eval) = run(circuit, contrib_input, &[], eval_input, &[]).unwrap();
+    assert_eq!(reconstruct(&contrib, &eval), expected);
+}
+
+/// Computes `n * sum(x^2) - sum(x)^2`, the variance of the values of both parties scaled by
+/// `n^2`, together with the sum and a polynomial of higher multiplicative depth.
+fn variance(n_contrib: usize, n_eval: usize) -> ArithCircuit {
+    let mut gates = vec![];
+    for _ in 0..n_contrib {
+        gates.push(ArithGate::InContrib);
+    }
+    for _ in 0..n_eval {
+        gates.push(ArithGate::InEval);
+    }
+    let n = gates.len() as u32;
+    let mut sum = 0;
+    let mut sum_sq = n;
+    gates.push(ArithGate::Mul(0, 0));
+    for i in 1..n {
+        gates.push(ArithGate::Add(sum, i));
+        sum = gates.len() as u32 - 1;
+        gates.push(ArithGate::Mul(i, i));
+        gates.push(ArithGate::Add(sum_sq, gates.len() as u32 - 1));
+        sum_sq = gates.len() as u32 - 1;
+    }
+    gates.push(ArithGate::MulConst(sum_sq, n as u64));
+    let scaled = gates.len() as u32 - 1;
+    gates.push(ArithGate::Mul(sum, sum));
+    let sum_squared = gates.len() as u32 - 1;
+    gates.push(ArithGate::Sub(scaled, sum_squared));
+    let variance = gates.len() as u32 - 1;
+    // (x_0 * x_1 * x_1 + 7) * sum, with 3 layers of multiplications:
+    gates.push(ArithGate::Mul(0, n - 1));
+    gates.push(ArithGate::Mul(gates.len() as u32 - 1, n - 1));
+    gates.push(ArithGate::Const(7));
+    gates.push(ArithGate::Add(
+        gates.len() as u32 - 2,
+        gates.len() as u32 - 1,
+    ));
+    gates.push(ArithGate::Mul(gates.len() as u32 - 1, sum));
+    let poly = gates.len() as u32 - 1;
+    ArithCircuit::new(gates, vec![sum, variance, poly])
+}
+
+#[test]
+fn test_sums_and_products() {
+    let circuit = ArithCircuit::new(
+        vec![
+            ArithGate::InContrib,
+            ArithGate::InEval,
+            ArithGate::Const(1000),
+            ArithGate::Add(0, 1),
+            ArithGate::Sub(0, 1),
+            ArithGate::Mul(0, 1),
+            ArithGate::MulConst(2, 3),
+            ArithGate::Mul(3, 4),
+            ArithGate::Add(6, 7),
+        ],
+        vec![3, 4, 5, 6, 7, 8],
+    );
+    assert_eq!(circuit.mul_gates(), 2);
+    assert_eq!(circuit.mul_depth(), 1);
+    for (x, y) in [(3, 5), (0, 0), (u64::MAX, 2), (1 << 40, 1 << 30)] {
+        assert_plaintext(&circuit, &[x], &[y]);
+    }
+}
+
+#[test]
+fn test_variance_of_both_parties() {
+    let circuit = variance(3, 4);
+    assert_eq!(circuit.mul_depth(), 3);
+    assert_plaintext(&circuit, &[4, 8, 15], &[16, 23, 42, 0]);
+    assert_plaintext(&circuit, &[u64::MAX, 1, 2], &[3, u64::MAX - 4, 5, 6]);
+}
+
+#[test]
+fn test_circuit_without_multiplications() {
+    let circuit = ArithCircuit::new(
+        vec![
+            ArithGate::InContrib,
+            ArithGate::InEval,
+            ArithGate::Sub(0, 1),
+        ],
+        vec![2],
+    );
+    assert_plaintext(&circuit, &[10], &[30]);
+}
+
+#[test]
+fn test_invalid_inputs_are_rejected() {
+    let circuit = variance(1, 1);
+    let rng = ChaCha20Rng::from_entropy();
+    let contrib = Contributor::new(&circuit, &[1, 2][..], &[][..], rng);
+    assert_eq!(contrib.err(), Some(Error::InsufficientInput));
+
+    let circuit = ArithCircuit::new(vec![ArithGate::InEval, ArithGate::Mul(0, 1)], vec![1]);
+    let rng = ChaCha20Rng::from_entropy();
+    let eval = Evaluator::new(&circuit, &[1][..], &[][..], rng);
+    assert_eq!(eval.err(), Some(Error::InvalidCircuit));
+}
+
+/// Computes an inner product arithmetically, then decides in a garbled circuit whether it exceeds
+/// a threshold.
+#[test]
+fn test_arithmetic_to_boolean() {
+    let dot = ArithCircuit::new(
+        vec![
+            ArithGate::InContrib,
+            ArithGate::InContrib,
+            ArithGate::InContrib,
+            ArithGate::InEval,
+            ArithGate::InEval,
+            ArithGate::InEval,
+            ArithGate::Mul(0, 3),
+            ArithGate::Mul(1, 4),
+            ArithGate::Mul(2, 5),
+            ArithGate::Add(6, 7),
+            ArithGate::Add(9, 8),
+        ],
+        vec![10],
+    );
+
+    let mut b = CircuitBuilder::new();
+    let value = b.input_arith();
+    let threshold = b.input_word(Party::Evaluator, 64);
+    let above = int::lt(&mut b, &threshold, &value);
+    let mut outputs = value.clone();
+    outputs.push(above);
+    let compare = b.build(outputs);
+
+    for (weights, features, threshold) in [
+        ([3, 1, 4], [1, 5, 9], 40),
+        ([3, 1, 4], [1, 5, 9], 44),
+        ([1 << 60, 2, 0], [4, 1, 7], 1),
+    ] {
+        let (contrib, eval) = run(&dot, &weights, &[], &features, &[]).unwrap();
+        let expected = dot.eval(&weights, &features, &[]).unwrap()[0];
+
+        let contrib_bits = to_bits(contrib[0], 64);
+        let mut eval_bits = to_bits(eval[0], 64);
+        eval_bits.extend(to_bits(threshold, 64));
+        let output = simulate(&compare, &contrib_bits, &eval_bits).unwrap();
+        assert_eq!(from_bits(&output[..64]), expected);
+        assert_eq!(output[64], expected > threshold);
+    }
+}
+
+/// Computes the maximum of two values in a garbled circuit, then uses it in an arithmetic circuit.
+#[test]
+fn test_boolean_to_arithmetic() {
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, 32);
+    let y = b.input_word(Party::Evaluator, 32);
+    let y_greater = int::lt(&mut b, &x, &y);
+    let max = b.mux_word(y_greater, &x, &y);
+    let masked = b.output_arith(&max);
+    let max_circuit = b.build(masked);
+
+    // max(x, y) * z + max(x, y)
+    let scale = ArithCircuit::new(
+        vec![
+            ArithGate::InShared,
+            ArithGate::InEval,
+            ArithGate::Mul(0, 1),
+            ArithGate::Add(2, 0),
+        ],
+        vec![3],
+    );
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    for (x, y, z) in [
+        (17u64, 5u64, 3u64),
+        (5, 17, 3),
+        (u32::MAX as u64, 0, u64::MAX),
+    ] {
+        let mask = rng.next_u64();
+        let mut contrib_bits = to_bits(x, 32);
+        contrib_bits.extend(to_bits(mask, 64));
+        let output = simulate(&max_circuit, &contrib_bits, &to_bits(y, 32)).unwrap();
+        let eval_share = from_bits(&output);
+        assert_ne!(eval_share, x.max(y));
+
+        let (contrib, eval) = run(&scale, &[], &[mask], &[z], &[eval_share]).unwrap();
+        let expected = scale.eval(&[], &[z], &[x.max(y)]).unwrap();
+        assert_eq!(reconstruct(&contrib, &eval), expected);
+    }
+}
+
+#[test]
+fn test_messages_after_the_end_are_rejected() {
+    let circuit = variance(1, 1);
+    let rng = ChaCha20Rng::from_entropy();
+    let (contrib, msg) = Contributor::new(&circuit, &[1], &[], rng).unwrap();
+    let rng = ChaCha20Rng::from_entropy();
+    let eval = Evaluator::new(&circuit, &[2], &[], rng).unwrap();
+    assert!(contrib.output().is_err());
+    let (eval, reply) = eval.run(&msg).unwrap();
+    let reply = reply.unwrap();
+    let (_, _) = contrib.run(&reply).unwrap();
+    assert!(eval.run(&msg).is_err());
+}
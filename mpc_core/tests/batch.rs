@@ -0,0 +1,119 @@
+use mpc_core::{
+    batch::{simulate_batch, Batch},
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    simulate, Circuit, Error, Gate,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+const WIDTH: usize = 8;
+
+/// Scores an applicant by adding the contributor's bonus to the evaluator's score, revealing the
+/// score and whether it reaches the contributor's threshold.
+fn scoring() -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let bonus = b.input_word(Party::Contributor, WIDTH);
+    let threshold = b.input_word(Party::Contributor, WIDTH);
+    let score = b.input_word(Party::Evaluator, WIDTH);
+    let mut score = int::add(&mut b, &score, &bonus);
+    let rejected = int::lt(&mut b, &score, &threshold);
+    score.push(b.not(rejected));
+    b.build(score)
+}
+
+#[test]
+fn test_batch_matches_separate_executions() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let circuit = scoring();
+    let instances = 5;
+    let mut inputs_contributor = vec![];
+    let mut inputs_evaluator = vec![];
+    for _ in 0..instances {
+        let mut input = to_bits(rng.gen_range(0..20), WIDTH);
+        input.extend(to_bits(rng.gen_range(50..150), WIDTH));
+        inputs_contributor.push(input);
+        inputs_evaluator.push(to_bits(rng.gen_range(0..120), WIDTH));
+    }
+
+    let outputs = simulate_batch(&circuit, &inputs_contributor, &inputs_evaluator)?;
+    assert_eq!(outputs.len(), instances);
+    for ((output, input_contributor), input_evaluator) in outputs
+        .iter()
+        .zip(inputs_contributor.iter())
+        .zip(inputs_evaluator.iter())
+    {
+        assert_eq!(
+            *output,
+            simulate(&circuit, input_contributor, input_evaluator)?
+        );
+        let bonus = from_bits(&input_contributor[..WIDTH]);
+        let threshold = from_bits(&input_contributor[WIDTH..]);
+        let score = from_bits(input_evaluator) + bonus;
+        assert_eq!(from_bits(&output[..WIDTH]), score);
+        assert_eq!(output[WIDTH], score >= threshold);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_batch_circuit() -> Result<(), Error> {
+    let circuit = Circuit::new(
+        vec![
+            Gate::InContrib,
+            Gate::InEval,
+            Gate::InEval,
+            Gate::And(0, 1),
+            Gate::Xor(3, 2),
+            Gate::Not(4),
+        ],
+        vec![5, 3],
+    );
+    let batch = Batch::new(&circuit, 3)?;
+    assert_eq!(batch.instances(), 3);
+    assert_eq!(batch.circuit().gates().len(), 3 * circuit.gates().len());
+    assert_eq!(batch.circuit().and_gates(), 3 * circuit.and_gates());
+    assert_eq!(batch.circuit().contrib_inputs(), 3);
+    assert_eq!(batch.circuit().eval_inputs(), 6);
+    assert_eq!(batch.circuit().output_gates(), &vec![5, 3, 11, 9, 17, 15]);
+
+    let inputs_contributor = [[true], [true], [false]];
+    let inputs_evaluator = [[true, false], [false, true], [true, true]];
+    let output = batch.circuit().eval(
+        &batch.join_contributor_inputs(&inputs_contributor)?,
+        &batch.join_evaluator_inputs(&inputs_evaluator)?,
+    )?;
+    let outputs = batch.split_output(&output)?;
+    for ((output, input_contributor), input_evaluator) in outputs
+        .iter()
+        .zip(inputs_contributor.iter())
+        .zip(inputs_evaluator.iter())
+    {
+        assert_eq!(*output, circuit.eval(input_contributor, input_evaluator)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_invalid_batch() -> Result<(), Error> {
+    let circuit = scoring();
+    assert!(matches!(
+        Batch::new(&circuit, 0),
+        Err(Error::EmptyBatch)
+    ));
+    let invalid = Circuit::new(vec![Gate::InContrib, Gate::Not(1)], vec![1]);
+    assert!(matches!(Batch::new(&invalid, 2), Err(Error::InvalidCircuit)));
+
+    let batch = Batch::new(&circuit, 2)?;
+    let too_few = batch.join_evaluator_inputs(&[to_bits(1, WIDTH)]);
+    assert_eq!(too_few, Err(Error::InsufficientInput));
+    let too_short = batch.join_evaluator_inputs(&[to_bits(1, WIDTH), to_bits(1, WIDTH - 1)]);
+    assert_eq!(too_short, Err(Error::InsufficientInput));
+
+    let result = simulate_batch(
+        &circuit,
+        &[vec![false; 2 * WIDTH], vec![false; 2 * WIDTH]],
+        &[vec![false; WIDTH]],
+    );
+    assert_eq!(result, Err(Error::InsufficientInput));
+    Ok(())
+}
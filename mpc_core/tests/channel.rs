@@ -0,0 +1,178 @@
+use std::{
+    future::Future,
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    task::{Context, Poll, Wake},
+    thread::{self, Thread},
+};
+
+use mpc_core::{
+    channel::{
+        duplex, run_contributor, run_contributor_async, run_evaluator, run_evaluator_async,
+        run_evaluator_revealing, run_evaluator_revealing_async, Channel, MpscChannel, StreamChannel,
+    },
+    states::{Contributor, Evaluator},
+    Error,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+const INPUT_CONTRIBUTOR: [bool; 2] = [true, false];
+const INPUT_EVALUATOR: [bool; 2] = [true, true];
+
+/// Runs the contributor on a new thread and the evaluator on the current one.
+fn run_blocking(
+    mut channel_contrib: impl Channel + Send + 'static,
+    mut channel_eval: impl Channel,
+) -> Result<Vec<bool>, Error> {
+    let contrib = thread::spawn(move || -> Result<(), Error> {
+        let circuit = circuit();
+        let (contrib, msg) =
+            Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+        run_contributor(contrib, msg, &mut channel_contrib)?;
+        Ok(())
+    });
+    let circuit = circuit();
+    let eval = Evaluator::new(&circuit, &INPUT_EVALUATOR[..], ChaCha20Rng::from_entropy())?;
+    let output = run_evaluator(eval, &mut channel_eval)?;
+    contrib.join().unwrap()?;
+    Ok(output)
+}
+
+#[test]
+fn test_mpsc_channel() -> Result<(), Error> {
+    let (channel_contrib, channel_eval) = MpscChannel::pair();
+    let output = run_blocking(channel_contrib, channel_eval)?;
+    assert_eq!(output, circuit().eval(&INPUT_CONTRIBUTOR, &INPUT_EVALUATOR)?);
+    Ok(())
+}
+
+#[test]
+fn test_duplex_pipe() -> Result<(), Error> {
+    let (channel_contrib, channel_eval) = duplex();
+    let output = run_blocking(channel_contrib, channel_eval)?;
+    assert_eq!(output, circuit().eval(&INPUT_CONTRIBUTOR, &INPUT_EVALUATOR)?);
+    Ok(())
+}
+
+#[test]
+fn test_stream_channel() -> Result<(), Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream_contrib = TcpStream::connect(listener.local_addr()?)?;
+    let (stream_eval, _) = listener.accept()?;
+    let output = run_blocking(
+        StreamChannel::new(stream_contrib),
+        StreamChannel::new(stream_eval),
+    )?;
+    assert_eq!(output, circuit().eval(&INPUT_CONTRIBUTOR, &INPUT_EVALUATOR)?);
+    Ok(())
+}
+
+/// Wakes up the thread that is blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread, without any async runtime.
+fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn test_async_duplex_pipe() -> Result<(), Error> {
+    let (mut channel_contrib, mut channel_eval) = duplex();
+    let contrib = thread::spawn(move || -> Result<(), Error> {
+        let circuit = circuit();
+        let (contrib, msg) =
+            Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+        block_on(run_contributor_async(contrib, msg, &mut channel_contrib))?;
+        Ok(())
+    });
+    let circuit = circuit();
+    let eval = Evaluator::new(&circuit, &INPUT_EVALUATOR[..], ChaCha20Rng::from_entropy())?;
+    let output = block_on(run_evaluator_async(eval, &mut channel_eval))?;
+    contrib.join().unwrap()?;
+    assert_eq!(output, circuit.eval(&INPUT_CONTRIBUTOR, &INPUT_EVALUATOR)?);
+    Ok(())
+}
+
+#[test]
+fn test_revealed_output() -> Result<(), Error> {
+    let (mut channel_contrib, mut channel_eval) = duplex();
+    let contrib = thread::spawn(move || -> Result<Vec<bool>, Error> {
+        let circuit = circuit();
+        let (contrib, msg) =
+            Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+        let contrib = run_contributor(contrib, msg, &mut channel_contrib)?;
+        contrib.output(&channel_contrib.recv()?)
+    });
+    let circuit = circuit();
+    let eval = Evaluator::new(&circuit, &INPUT_EVALUATOR[..], ChaCha20Rng::from_entropy())?;
+    let output = run_evaluator_revealing(eval, &mut channel_eval)?;
+    assert_eq!(output, circuit.eval(&INPUT_CONTRIBUTOR, &INPUT_EVALUATOR)?);
+    assert_eq!(contrib.join().unwrap()?, output);
+    Ok(())
+}
+
+#[test]
+fn test_async_revealed_output() -> Result<(), Error> {
+    let (mut channel_contrib, mut channel_eval) = duplex();
+    let contrib = thread::spawn(move || -> Result<Vec<bool>, Error> {
+        let circuit = circuit();
+        let (contrib, msg) =
+            Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+        let contrib = block_on(run_contributor_async(contrib, msg, &mut channel_contrib))?;
+        contrib.output(&channel_contrib.recv()?)
+    });
+    let circuit = circuit();
+    let eval = Evaluator::new(&circuit, &INPUT_EVALUATOR[..], ChaCha20Rng::from_entropy())?;
+    let output = block_on(run_evaluator_revealing_async(eval, &mut channel_eval))?;
+    assert_eq!(contrib.join().unwrap()?, output);
+    Ok(())
+}
+
+#[test]
+fn test_closed_channel() -> Result<(), Error> {
+    let circuit = circuit();
+
+    // the contributor gives up after its initial message:
+    let (mut channel_contrib, mut channel_eval) = duplex();
+    let (_, msg) = Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+    channel_contrib.send(msg)?;
+    drop(channel_contrib);
+    let eval = Evaluator::new(&circuit, &INPUT_EVALUATOR[..], ChaCha20Rng::from_entropy())?;
+    assert_eq!(
+        run_evaluator(eval, &mut channel_eval),
+        Err(Error::ChannelClosed)
+    );
+
+    // the connection of the evaluator is closed mid-message:
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream_contrib = TcpStream::connect(listener.local_addr()?)?;
+    let (mut stream_eval, _) = listener.accept()?;
+    std::io::Write::write_all(&mut stream_eval, &100u64.to_le_bytes())?;
+    std::io::Write::write_all(&mut stream_eval, &[0; 10])?;
+    drop(stream_eval);
+    let (contrib, msg) =
+        Contributor::new(&circuit, &INPUT_CONTRIBUTOR[..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        run_contributor(contrib, msg, &mut StreamChannel::new(stream_contrib)),
+        Err(Error::ChannelClosed)
+    ));
+    Ok(())
+}
@@ -4,8 +4,8 @@ use mpc_core::{Circuit, Error, Gate};
 fn test_missing_output_gates() -> Result<(), Error> {
     let program = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::Xor(0, 1)], vec![]);
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -31,8 +31,8 @@ fn test_invalid_gates() -> Result<(), Error> {
     let invalid_not = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::Not(500)], vec![2]);
 
     for program in [invalid_xor, invalid_and, invalid_not] {
-        for in_a in vec![true, false] {
-            for in_b in vec![true, false] {
+        for in_a in [true, false] {
+            for in_b in [true, false] {
                 let input_a = vec![in_a];
                 let input_b = vec![in_b];
 
@@ -52,8 +52,8 @@ fn test_invalid_output_gates() -> Result<(), Error> {
         vec![3],
     );
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -123,8 +123,8 @@ fn test_xor() -> Result<(), Error> {
         vec![2],
     );
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -151,8 +151,8 @@ fn test_not_simple() -> Result<(), Error> {
         vec![2, 3, 4, 5],
     );
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -188,8 +188,8 @@ fn test_not() -> Result<(), Error> {
         vec![2, 3, 6, 7, 8, 10],
     );
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -224,8 +224,8 @@ fn test_and_xor() -> Result<(), Error> {
         vec![2, 3],
     );
 
-    for in_a in vec![true, false] {
-        for in_b in vec![true, false] {
+    for in_a in [true, false] {
+        for in_b in [true, false] {
             let input_a = vec![in_a];
             let input_b = vec![in_b];
 
@@ -0,0 +1,120 @@
+//! Circuits and drivers shared by the integration tests of the two-party protocol.
+
+// every test crate only uses some of the fixtures:
+#![allow(dead_code)]
+
+use std::borrow::Borrow;
+
+use mpc_core::{
+    states::{Contributor, Evaluator, Msg},
+    Circuit, Error, Gate,
+};
+
+/// Both parties after their last step, together with the last message of the contributor.
+pub type Finished<C, I, D, J> = (Contributor<C, I>, Evaluator<D, J>, Msg);
+
+/// The gates of [`circuit`], computing `(a & b) ^ (c & d)` at gate 6 and `!(a & b)` at gate 7
+/// for 2-bit inputs of both parties.
+pub fn gates() -> Vec<Gate> {
+    vec![
+        Gate::InContrib,
+        Gate::InContrib,
+        Gate::InEval,
+        Gate::InEval,
+        Gate::And(0, 2),
+        Gate::And(1, 3),
+        Gate::Xor(4, 5),
+        Gate::Not(4),
+    ]
+}
+
+/// Computes `(a & b) ^ (c & d)` and `!(a & b)` for 2-bit inputs of both parties.
+pub fn circuit() -> Circuit {
+    Circuit::new(gates(), vec![6, 7])
+}
+
+/// Computes `(a & b) ^ (c & d)` for 2-bit inputs of both parties, using 3 AND gates.
+pub fn circuit_with_3_and_gates() -> Circuit {
+    let mut gates = gates();
+    gates[7] = Gate::And(4, 6);
+    gates.push(Gate::Not(7));
+    Circuit::new(gates, vec![6, 7, 8])
+}
+
+/// Computes `(a & b) ^ (c & d)`, `!(a & b)` and `c` for 2-bit inputs of both parties.
+pub fn circuit_with_3_outputs() -> Circuit {
+    Circuit::new(gates(), vec![6, 7, 1])
+}
+
+/// Runs all remaining steps of both parties locally, starting with the message for the
+/// evaluator, and returns both parties together with the last message of the contributor.
+pub fn run_steps<C, I, D, J>(
+    contrib: Contributor<C, I>,
+    eval: Evaluator<D, J>,
+    msg_for_eval: Msg,
+) -> Result<Finished<C, I, D, J>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    D: Borrow<Circuit>,
+    J: Borrow<[bool]>,
+{
+    run_steps_intercepted(contrib, eval, msg_for_eval, |_| {})
+}
+
+/// Like [`run_steps`], but calls `intercept` on every message before it is received, starting
+/// with the message for the evaluator and ending with the last message of the contributor.
+pub fn run_steps_intercepted<C, I, D, J>(
+    mut contrib: Contributor<C, I>,
+    mut eval: Evaluator<D, J>,
+    mut msg_for_eval: Msg,
+    mut intercept: impl FnMut(&mut Msg),
+) -> Result<Finished<C, I, D, J>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]>,
+    D: Borrow<Circuit>,
+    J: Borrow<[bool]>,
+{
+    intercept(&mut msg_for_eval);
+    for _ in 0..eval.steps() {
+        let (next_state, mut msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        intercept(&mut msg_for_contrib);
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+        intercept(&mut msg_for_eval);
+    }
+    Ok((contrib, eval, msg_for_eval))
+}
+
+/// Like [`run_steps`], but provides the inputs of both parties as soon as a party needs them.
+pub fn run_steps_with_late_inputs<C, I, D, J>(
+    mut contrib: Contributor<C, I>,
+    mut eval: Evaluator<D, J>,
+    mut msg_for_eval: Msg,
+    input_contributor: I,
+    input_evaluator: J,
+) -> Result<Finished<C, I, D, J>, Error>
+where
+    C: Borrow<Circuit>,
+    I: Borrow<[bool]> + Clone,
+    D: Borrow<Circuit>,
+    J: Borrow<[bool]> + Clone,
+{
+    for _ in 0..eval.steps() {
+        if eval.needs_input() {
+            eval.provide_input(input_evaluator.clone())?;
+        }
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        if contrib.needs_input() {
+            contrib.provide_input(input_contributor.clone())?;
+        }
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+    }
+    Ok((contrib, eval, msg_for_eval))
+}
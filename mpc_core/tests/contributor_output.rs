@@ -0,0 +1,86 @@
+use mpc_core::{
+    message::{Step, HEADER_LEN},
+    states::{Contributor, Evaluator},
+    Circuit, Error, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit_with_3_outputs;
+
+/// The evaluator's output, the message revealing it to the contributor and the contributor's final
+/// state.
+type Execution<'a> = (Vec<bool>, Vec<u8>, Contributor<&'a Circuit, &'a [bool]>);
+
+/// Runs the protocol locally, up to the point where the contributor receives the output.
+fn execute<'a>(
+    circuit: &'a Circuit,
+    input_contributor: &'a [bool],
+    input_evaluator: &'a [bool],
+) -> Result<Execution<'a>, Error> {
+    let (contrib, msg_for_eval) =
+        Contributor::new(circuit, input_contributor, ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(circuit, input_evaluator, ChaCha20Rng::from_entropy())?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    let (output, msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    Ok((output, msg_for_contrib, contrib))
+}
+
+#[test]
+fn test_contributor_output() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    for (input_contributor, input_evaluator) in [
+        ([false, false], [true, true]),
+        ([true, true], [true, false]),
+        ([true, true], [true, true]),
+        ([false, true], [false, true]),
+    ] {
+        let (output, msg, contrib) = execute(&circuit, &input_contributor, &input_evaluator)?;
+        let expected = circuit.eval(&input_contributor, &input_evaluator)?;
+        assert_eq!(output, expected);
+        assert_eq!(contrib.output(&msg)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tampered_contributor_output() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    let input_contributor = [true, false];
+    let input_evaluator = [true, true];
+    // every output is revealed as a label (16 bytes), a masked value, a MAC (16 bytes) and a bit:
+    let offsets = [0, 15, 16, 17, 32, 33];
+    let outputs = HEADER_LEN + 8;
+    for (output, offset) in (0..3).flat_map(|output| offsets.map(|offset| (output, offset))) {
+        let i = outputs + 34 * output + offset;
+        let (_, mut msg, contrib) = execute(&circuit, &input_contributor, &input_evaluator)?;
+        msg[i] ^= 1;
+        let expected = Error::MacError {
+            step: Some(Step::RevealedOutput),
+            check: MacCheck::Output,
+            failed: 1,
+            index: Some(output),
+        };
+        assert_eq!(contrib.output(&msg), Err(expected), "byte {i}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_contributor_output_before_end() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    let (contrib, _) = Contributor::new(&circuit, vec![true, true], ChaCha20Rng::from_entropy())?;
+    assert_eq!(contrib.output(&[]), Err(Error::ProtocolStillInProgress));
+
+    // the message must contain exactly one label and share for every output:
+    let (_, msg, contrib) = execute(&circuit, &[true, true], &[false, true])?;
+    let (_, _, other_contrib) = execute(&circuit, &[true, true], &[false, true])?;
+    assert_eq!(
+        contrib.output(&msg[..msg.len() - 1]),
+        Err(Error::InvalidMessageLength)
+    );
+    // the output of another execution is rejected before its labels are checked:
+    assert_eq!(other_contrib.output(&msg), Err(Error::SessionMismatch));
+    Ok(())
+}
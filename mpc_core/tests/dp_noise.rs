@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use mpc_core::{
+    gadgets::{
+        from_bits,
+        noise::{add_noise, Distribution, NoiseSampler},
+        to_bits, CircuitBuilder, Party,
+    },
+    simulate, Error,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+const WIDTH: usize = 16;
+
+/// The probability of a value under the ideal (untruncated) distribution.
+fn ideal_probability(distribution: Distribution, x: i64) -> f64 {
+    match distribution {
+        Distribution::Laplace { scale } => {
+            let alpha = (-1.0 / scale).exp();
+            (1.0 - alpha) / (1.0 + alpha) * alpha.powi(x.abs() as i32)
+        }
+        Distribution::Gaussian { sigma } => {
+            let weight = |x: i64| (-((x * x) as f64) / (2.0 * sigma * sigma)).exp();
+            let total: f64 = (-1000..=1000).map(weight).sum();
+            weight(x) / total
+        }
+    }
+}
+
+fn random_bits(rng: &mut ChaCha20Rng, len: usize) -> Vec<bool> {
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+/// Checks that the observed counts are within 5 standard deviations of the expected counts.
+fn check_histogram(counts: &HashMap<i64, usize>, samples: usize, probability: impl Fn(i64) -> f64) {
+    let max = counts.keys().map(|x| x.abs()).max().unwrap() + 2;
+    for x in -max..=max {
+        let observed = *counts.get(&x).unwrap_or(&0) as f64;
+        let p = probability(x);
+        let expected = samples as f64 * p;
+        let tolerance = 5.0 * (expected * (1.0 - p)).sqrt() + 3.0;
+        assert!(
+            (observed - expected).abs() <= tolerance,
+            "{x}: observed {observed}, expected {expected}"
+        );
+    }
+}
+
+fn check_distribution(distribution: Distribution) -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let sampler = NoiseSampler::new(distribution, 32);
+
+    let mut b = CircuitBuilder::new();
+    let value = b.input_word(Party::Contributor, WIDTH);
+    let noisy = add_noise(&mut b, &sampler, &value);
+    let circuit = b.build(noisy);
+
+    // the circuit must sample exactly like the plaintext algorithm with the same random bits:
+    let samples = 3000;
+    let mut counts = HashMap::new();
+    for _ in 0..samples {
+        let value = rng.gen_range(-1000..1000i64);
+        let contrib_random = random_bits(&mut rng, sampler.random_bits());
+        let eval_random = random_bits(&mut rng, sampler.random_bits());
+        let mut contrib = to_bits(value as u64, WIDTH);
+        contrib.extend(contrib_random.iter());
+        let output = circuit.eval(&contrib, &eval_random)?;
+        let noise = from_bits(&output) as i16 as i64 - value;
+
+        let random: Vec<bool> = contrib_random
+            .iter()
+            .zip(eval_random.iter())
+            .map(|(a, b)| a ^ b)
+            .collect();
+        assert_eq!(noise, sampler.sample(&random));
+        *counts.entry(noise).or_insert(0) += 1;
+    }
+    check_histogram(&counts, samples, |x| sampler.probability(x));
+
+    // the truncated distribution of the sampler must be close to the ideal distribution:
+    let samples = 200_000;
+    let mut counts = HashMap::new();
+    for _ in 0..samples {
+        let random = random_bits(&mut rng, sampler.random_bits());
+        *counts.entry(sampler.sample(&random)).or_insert(0) += 1;
+    }
+    check_histogram(&counts, samples, |x| ideal_probability(distribution, x));
+    for x in -100..=100 {
+        let ideal = ideal_probability(distribution, x);
+        assert!((sampler.probability(x) - ideal).abs() < 1e-9, "{x}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_discrete_laplace_noise() -> Result<(), Error> {
+    check_distribution(Distribution::Laplace { scale: 0.7 })?;
+    check_distribution(Distribution::Laplace { scale: 4.0 })
+}
+
+#[test]
+fn test_discrete_gaussian_noise() -> Result<(), Error> {
+    check_distribution(Distribution::Gaussian { sigma: 1.0 })?;
+    check_distribution(Distribution::Gaussian { sigma: 3.5 })
+}
+
+#[test]
+fn test_noise_secure_execution() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let sampler = NoiseSampler::new(Distribution::Laplace { scale: 2.0 }, 16);
+
+    // the sum of both parties' private values is released with noise:
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, WIDTH);
+    let y = b.input_word(Party::Evaluator, WIDTH);
+    let sum = mpc_core::gadgets::int::add(&mut b, &x, &y);
+    let noisy = add_noise(&mut b, &sampler, &sum);
+    let circuit = b.build(noisy);
+
+    let (x, y) = (1234u64, 4321u64);
+    let contrib_random = random_bits(&mut rng, sampler.random_bits());
+    let eval_random = random_bits(&mut rng, sampler.random_bits());
+    let mut contrib = to_bits(x, WIDTH);
+    contrib.extend(contrib_random.iter());
+    let mut eval = to_bits(y, WIDTH);
+    eval.extend(eval_random.iter());
+    let output = simulate(&circuit, &contrib, &eval)?;
+
+    let random: Vec<bool> = contrib_random
+        .iter()
+        .zip(eval_random.iter())
+        .map(|(a, b)| a ^ b)
+        .collect();
+    let expected = (x + y) as i64 + sampler.sample(&random);
+    assert_eq!(from_bits(&output) as i64, expected);
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use mpc_core::{
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    message::{Step, HEADER_LEN},
+    simulate_with_security_level,
+    states::{Contributor, Evaluator, OutputMode, SecurityLevel},
+    Circuit, Error, Gate, MacCheck,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+
+/// Runs dual execution locally, with the contributor garbling `contrib_circuit`, letting `tamper`
+/// modify every message, identified by the number of messages sent before it. Returns the output
+/// of the evaluator and the output of the contributor.
+fn execute(
+    contrib_circuit: &Circuit,
+    eval_circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+    mut tamper: impl FnMut(usize, &mut Vec<u8>),
+) -> Result<(Vec<bool>, Vec<bool>), Error> {
+    let (contrib, msg_for_eval) = Contributor::with_security_level(
+        contrib_circuit,
+        input_contributor,
+        SecurityLevel::DualExecution,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_security_level(
+        eval_circuit,
+        input_evaluator,
+        SecurityLevel::DualExecution,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert_eq!(contrib.steps(), 3);
+    assert_eq!(eval.steps(), 3);
+    let mut sent = 0;
+    let (contrib, eval, msg_for_eval) =
+        common::run_steps_intercepted(contrib, eval, msg_for_eval, |msg| {
+            tamper(sent, msg);
+            sent += 1;
+        })?;
+    let (output, mut msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    tamper(sent, &mut msg_for_contrib);
+    Ok((output, contrib.output(&msg_for_contrib)?))
+}
+
+fn mismatch(step: Step) -> Error {
+    Error::MacError {
+        step: Some(step),
+        check: MacCheck::OutputEquality,
+        failed: 1,
+        index: None,
+    }
+}
+
+#[test]
+fn test_all_gates() -> Result<(), Error> {
+    // computes `(a & b) ^ (c & d)`, `!(a & b)`, `!(a ^ c)` and `c`:
+    let mut gates = common::gates();
+    gates.extend([Gate::Xor(0, 1), Gate::Not(8)]);
+    let circuit = Circuit::new(gates, vec![6, 7, 9, 1]);
+    for input in 0..16 {
+        let input_contributor = [input & 1 != 0, input & 2 != 0];
+        let input_evaluator = [input & 4 != 0, input & 8 != 0];
+        let expected = circuit.eval(&input_contributor, &input_evaluator)?;
+        let (output, contrib_output) = execute(
+            &circuit,
+            &circuit,
+            &input_contributor,
+            &input_evaluator,
+            |_, _| {},
+        )?;
+        assert_eq!(output, expected);
+        assert_eq!(contrib_output, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_matches_other_security_levels() -> Result<(), Error> {
+    // more than a single block of extended OTs in both executions:
+    let mut b = CircuitBuilder::new();
+    let xs: Vec<_> = (0..10)
+        .map(|_| b.input_word(Party::Contributor, 16))
+        .collect();
+    let ys: Vec<_> = (0..10)
+        .map(|_| b.input_word(Party::Evaluator, 16))
+        .collect();
+    let mut sum = b.constant_word(0, 16);
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        let product = int::mul(&mut b, x, y);
+        sum = int::add(&mut b, &sum, &product);
+    }
+    let circuit = b.build(sum);
+    assert!(circuit.contrib_inputs() > 128);
+    assert!(circuit.eval_inputs() > 128);
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    let xs: Vec<u64> = (0..10).map(|_| rng.gen_range(0..100)).collect();
+    let ys: Vec<u64> = (0..10).map(|_| rng.gen_range(0..100)).collect();
+    let input_contributor: Vec<bool> = xs.iter().flat_map(|x| to_bits(*x, 16)).collect();
+    let input_evaluator: Vec<bool> = ys.iter().flat_map(|y| to_bits(*y, 16)).collect();
+
+    let dual = simulate_with_security_level(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        SecurityLevel::DualExecution,
+    )?;
+    let semi_honest = simulate_with_security_level(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        SecurityLevel::SemiHonest,
+    )?;
+    assert_eq!(dual, semi_honest);
+    let expected: u64 = xs.iter().zip(ys.iter()).map(|(x, y)| x * y).sum();
+    assert_eq!(from_bits(&dual), expected);
+    Ok(())
+}
+
+#[test]
+fn test_cheating_garbler_is_detected() -> Result<(), Error> {
+    let circuit = Circuit::new(
+        vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)],
+        vec![2],
+    );
+
+    // wrong decoding bits, which flip the output of the contributor's execution:
+    let result = execute(&circuit, &circuit, &[true], &[true], |sent, msg| {
+        if sent == 4 {
+            let decoding = HEADER_LEN + 8 + 16 + 8;
+            msg[decoding] ^= 1;
+        }
+    });
+    assert_eq!(result, Err(mismatch(Step::DualDigest)));
+
+    // a circuit that reveals the input of the evaluator, which is only detected if it computes a
+    // different output than the agreed circuit, leaking whether it does:
+    let leaky = Circuit::new(
+        vec![Gate::InContrib, Gate::InEval, Gate::And(1, 1)],
+        vec![2],
+    );
+    let result = execute(&leaky, &circuit, &[false], &[true], |_, _| {});
+    assert_eq!(result, Err(mismatch(Step::DualDigest)));
+    Ok(())
+}
+
+#[test]
+fn test_cheating_evaluator_is_detected() -> Result<(), Error> {
+    let circuit = Circuit::new(
+        vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)],
+        vec![2],
+    );
+
+    // a commitment that does not match the digest:
+    let result = execute(&circuit, &circuit, &[true], &[false], |sent, msg| {
+        if sent == 5 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert_eq!(result, Err(mismatch(Step::DualOpening)));
+
+    // an opening that does not match the commitment:
+    let result = execute(&circuit, &circuit, &[true], &[false], |sent, msg| {
+        if sent == 7 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert_eq!(result, Err(mismatch(Step::DualOpening)));
+
+    // a digest of the contributor that was modified in transit:
+    let result = execute(&circuit, &circuit, &[true], &[false], |sent, msg| {
+        if sent == 6 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert_eq!(result, Err(mismatch(Step::DualDigest)));
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_features() -> Result<(), Error> {
+    let circuit = Circuit::new(
+        vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)],
+        vec![2],
+    );
+    let eval = Evaluator::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::DualExecution,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(matches!(
+        eval.initiate(),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+
+    let parties = || -> Result<_, Error> {
+        let (contrib, msg_for_eval) = Contributor::with_security_level(
+            &circuit,
+            &[true][..],
+            SecurityLevel::DualExecution,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        let eval = Evaluator::with_security_level(
+            &circuit,
+            &[true][..],
+            SecurityLevel::DualExecution,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        Ok((contrib, eval, msg_for_eval))
+    };
+
+    // the shared output mode is rejected before any party sends its garbled circuit or labels:
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let eval = eval.with_output_mode(OutputMode::Shared);
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    let (_, eval, msg_for_eval) = parties()?;
+    let (_, msg_for_contrib) = eval.run(&msg_for_eval)?;
+    let contrib = contrib.with_output_mode(OutputMode::Shared);
+    assert!(matches!(
+        contrib.run(&msg_for_contrib),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+
+    // ...and does not reveal the output if it is only set after the last step:
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    let (_, msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    assert!(matches!(
+        contrib
+            .with_output_mode(OutputMode::Shared)
+            .output(&msg_for_contrib),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    assert!(matches!(
+        eval.with_output_mode(OutputMode::Shared)
+            .reveal_output(&msg_for_eval),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    assert!(matches!(
+        contrib.output_shares(),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use mpc_core::{
+    batch::Batch,
+    message::{Step, HEADER_LEN},
+    states::{Contributor, Evaluator, Msg},
+    Error, MacCheck, MessageFault,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+/// Runs the protocol, letting `tamper` modify the last message of the contributor before the
+/// evaluator computes the output.
+fn run(tamper: impl Fn(&mut Msg)) -> Result<Vec<bool>, Error> {
+    let circuit = circuit();
+    let (contrib, msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    let (_, eval, mut msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    assert_eq!(msg_for_eval[6], Step::EvaluationInputs as u8);
+    tamper(&mut msg_for_eval);
+    eval.output(&msg_for_eval)
+}
+
+#[test]
+fn test_and_table_mac_error() -> Result<(), Error> {
+    assert_eq!(run(|_| {})?, vec![true, false]);
+
+    // the garbled table of the first AND gate starts after the length of the tables and the index
+    // of the gate, each of its 4 rows is a key (16 bytes), a MAC (16 bytes) and a bit:
+    let table = HEADER_LEN + 8 + 4;
+    let result = run(|msg| {
+        for row in 0..4 {
+            msg[table + 33 * row + 16] ^= 1;
+        }
+    });
+    assert_eq!(
+        result,
+        Err(Error::MacError {
+            step: Some(Step::EvaluationInputs),
+            check: MacCheck::AndTable,
+            failed: 1,
+            index: Some(4),
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bincode_error_names_step() -> Result<(), Error> {
+    let circuit = circuit();
+    let (_, msg) = Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    // a valid header, announcing an empty payload:
+    let mut msg = msg[..HEADER_LEN].to_vec();
+    msg[HEADER_LEN - 8..].copy_from_slice(&0u64.to_le_bytes());
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        eval.run(&msg),
+        Err(Error::BincodeError {
+            step: Some(Step::BaseOtInit)
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_message_fault() -> Result<(), Error> {
+    let batch = Batch::new(&circuit(), 3)?;
+    assert_eq!(
+        batch.split_output(&[true; 5]),
+        Err(Error::UnexpectedMessageType {
+            step: None,
+            fault: MessageFault::Length {
+                expected: 6,
+                received: 5,
+            },
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_display() {
+    let errors = [
+        (
+            Error::MacError {
+                step: Some(Step::EvaluationInputs),
+                check: MacCheck::AndTable,
+                failed: 2,
+                index: Some(4),
+            },
+            "2 AND table MAC check(s) failed at step EvaluationInputs, first at index 4",
+        ),
+        (
+            Error::MacError {
+                step: Some(Step::BaseOtResponse),
+                check: MacCheck::CoinCommitment,
+                failed: 1,
+                index: None,
+            },
+            "1 coin commitment check(s) failed at step BaseOtResponse",
+        ),
+        (
+            Error::LeakyAndNotEqual {
+                step: Some(Step::LeakyAndOpenings),
+                failed: 3,
+                index: 7,
+            },
+            "The equality check of the authenticated AND triples failed for 3 value(s) at step \
+            LeakyAndOpenings, first at index 7",
+        ),
+        (
+            Error::UnexpectedMessageType {
+                step: Some(Step::InputMasks),
+                fault: MessageFault::Gate(2),
+            },
+            "Unexpected message content at step InputMasks: unexpected reference to gate 2",
+        ),
+        (
+            Error::UnexpectedMessageType {
+                step: None,
+                fault: MessageFault::UnknownStep(200),
+            },
+            "Unexpected message content: unknown step 200",
+        ),
+        (
+            Error::BincodeError { step: None },
+            "The message could not be serialized to / deserialized from bincode",
+        ),
+    ];
+    for (error, expected) in errors {
+        assert_eq!(error.to_string(), expected);
+    }
+}
@@ -0,0 +1,194 @@
+use mpc_core::{
+    message::Step,
+    states::{Contributor, Evaluator, Msg, SecurityLevel, StatisticalSecurity},
+    Error,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+/// Runs the protocol, opened by the evaluator if `evaluator_first` is true, and returns all
+/// messages that were sent together with the output.
+fn run(
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+    evaluator_first: bool,
+) -> Result<(Vec<Msg>, Vec<bool>), Error> {
+    let circuit = circuit();
+    let (mut contrib, msg_for_eval) =
+        Contributor::new(&circuit, input_contributor, ChaCha20Rng::from_entropy())?;
+    let mut eval = Evaluator::new(&circuit, input_evaluator, ChaCha20Rng::from_entropy())?;
+    let mut messages = vec![];
+    let msg_for_eval = if evaluator_first {
+        // the initial message of the contributor is never sent on its own:
+        let (next_state, opening) = eval.initiate()?;
+        eval = next_state;
+        messages.push(opening.clone());
+        let (next_state, reply) = contrib.run(&opening)?;
+        contrib = next_state;
+        reply
+    } else {
+        msg_for_eval
+    };
+    let (contrib, eval, msg_for_eval) =
+        common::run_steps_intercepted(contrib, eval, msg_for_eval, |msg| {
+            messages.push(msg.clone())
+        })?;
+    assert!(matches!(
+        contrib.run(&msg_for_eval),
+        Err(Error::ProtocolEnded)
+    ));
+    let output = eval.output(&msg_for_eval)?;
+    Ok((messages, output))
+}
+
+#[test]
+fn test_both_initiation_orders() -> Result<(), Error> {
+    let circuit = circuit();
+    for evaluator_first in [false, true] {
+        for x in 0..4 {
+            for y in 0..4 {
+                let input_contributor = [x & 1 != 0, x & 2 != 0];
+                let input_evaluator = [y & 1 != 0, y & 2 != 0];
+                let (_, output) = run(&input_contributor, &input_evaluator, evaluator_first)?;
+                let expected = circuit.eval(&input_contributor, &input_evaluator)?;
+                assert_eq!(output, expected);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_evaluator_first_schedule() -> Result<(), Error> {
+    let (messages, _) = run(&[true, false], &[true, true], true)?;
+    let expected = [
+        Step::EvaluatorOtInit,
+        Step::ContributorOtResponse,
+        Step::EvaluatorOtCompletion,
+        Step::ContributorOtExtension,
+        Step::EvaluatorLeakyAndStart,
+        Step::ContributorLeakyAndShares,
+        Step::LeakyAndCorrections,
+        Step::LeakyAndCommitments,
+        Step::LeakyAndOpenings,
+        Step::BucketingAndMasks,
+        Step::InputMasks,
+        Step::EvaluationInputs,
+    ];
+    let steps: Vec<u8> = messages.iter().map(|msg| msg[6]).collect();
+    assert_eq!(steps, expected.map(|step| step as u8));
+    Ok(())
+}
+
+#[test]
+fn test_contributor_steps() -> Result<(), Error> {
+    let circuit = circuit();
+    let input = [true, false];
+    let (contrib, _) = Contributor::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let steps = contrib.steps();
+    let (eval, opening) = eval.initiate()?;
+    assert_eq!(eval.steps(), 5);
+    let (contrib, _) = contrib.run(&opening)?;
+    assert_eq!(contrib.steps(), steps + 1);
+    Ok(())
+}
+
+#[test]
+fn test_evaluator_first_save_restore() -> Result<(), Error> {
+    let circuit = circuit();
+    let input_contributor = [false, true];
+    let input_evaluator = [true, true];
+
+    let (mut contrib, _) = Contributor::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (mut eval, mut msg_for_contrib) = eval.initiate()?;
+    for _ in 0..eval.steps() {
+        let saved = contrib.save(None)?;
+        contrib = Contributor::restore(&circuit, None, &saved, None)?;
+        if contrib.needs_input() {
+            contrib.provide_input(&input_contributor[..])?;
+        }
+        let (next_state, msg_for_eval) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+
+        let saved = eval.save(None)?;
+        eval = Evaluator::restore(&circuit, None, &saved, None)?;
+        if eval.needs_input() {
+            eval.provide_input(&input_evaluator[..])?;
+        }
+        let (next_state, reply) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        msg_for_contrib = reply;
+    }
+    // the contributor runs 1 step more than the evaluator, the one that needs its input:
+    assert!(contrib.needs_input());
+    contrib.provide_input(&input_contributor[..])?;
+    let (_, msg_for_eval) = contrib.run(&msg_for_contrib)?;
+    let output = eval.output(&msg_for_eval)?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    Ok(())
+}
+
+#[test]
+fn test_initiation_errors() -> Result<(), Error> {
+    let circuit = circuit();
+    let input = [true, false];
+
+    // an evaluator can only open the protocol once:
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let (eval, _) = eval.initiate()?;
+    assert!(matches!(eval.initiate(), Err(Error::CannotInitiate)));
+
+    // ...and not if the contributor already opened it:
+    let (contrib, msg) = Contributor::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let (eval, reply) = eval.run(&msg)?;
+    assert!(matches!(eval.initiate(), Err(Error::CannotInitiate)));
+
+    // both parties opened the protocol:
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let (eval, _) = eval.initiate()?;
+    assert!(matches!(
+        eval.run(&msg),
+        Err(Error::UnexpectedStep {
+            expected: Step::ContributorOtResponse,
+            received: Step::BaseOtInit,
+        })
+    ));
+
+    // the contributor cannot switch to the other order after its first step:
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let (_, opening) = eval.initiate()?;
+    let (contrib, _) = contrib.run(&reply)?;
+    assert!(matches!(
+        contrib.run(&opening),
+        Err(Error::UnexpectedStep {
+            expected: Step::OtExtension,
+            received: Step::EvaluatorOtInit,
+        })
+    ));
+
+    let eval = Evaluator::with_security_level(
+        &circuit,
+        &input[..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(matches!(
+        eval.initiate(),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    Ok(())
+}
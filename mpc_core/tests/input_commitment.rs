@@ -0,0 +1,143 @@
+use mpc_core::{
+    commitment::{check_output, CommittedInput, InputCommitment},
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    simulate,
+    states::{Contributor, Evaluator},
+    Circuit, Error, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+
+const WIDTH: usize = 8;
+
+/// Scores the features of the evaluator with the weights of the contributor, revealing the score
+/// and whether it exceeds a fresh threshold of the contributor.
+fn scoring() -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let weights: Vec<_> = (0..2)
+        .map(|_| b.input_word(Party::Contributor, WIDTH))
+        .collect();
+    let threshold = b.input_word(Party::Contributor, WIDTH);
+    let features: Vec<_> = (0..2)
+        .map(|_| b.input_word(Party::Evaluator, WIDTH))
+        .collect();
+    let mut score = b.constant_word(0, WIDTH);
+    for (w, f) in weights.iter().zip(features.iter()) {
+        let product = int::mul(&mut b, w, f);
+        score = int::add(&mut b, &score, &product);
+    }
+    let above = int::lt(&mut b, &threshold, &score);
+    let mut outputs = score;
+    outputs.push(above);
+    b.build(outputs)
+}
+
+fn words(values: &[u64]) -> Vec<bool> {
+    values.iter().flat_map(|v| to_bits(*v, WIDTH)).collect()
+}
+
+fn is_commitment_error(result: Result<Vec<bool>, Error>) -> bool {
+    matches!(
+        result,
+        Err(Error::MacError {
+            check: MacCheck::InputCommitment,
+            ..
+        })
+    )
+}
+
+#[test]
+fn test_committed_input_across_executions() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let model = CommittedInput::new(words(&[3, 5]), &mut rng);
+    let published = model.commitment().to_bytes()?;
+    let stored = model.to_bytes()?;
+
+    let commitment = InputCommitment::from_bytes(&published)?;
+    assert_eq!(commitment, model.commitment());
+    assert_eq!(commitment.bits(), 2 * WIDTH);
+    let circuit = commitment.circuit(&scoring(), Party::Contributor)?;
+
+    for (features, threshold) in [([1, 2], 10), ([4, 6], 40)] {
+        let model = CommittedInput::from_bytes(&stored)?;
+        let input = model.execution_input(&to_bits(threshold, WIDTH));
+        let output = check_output(simulate(&circuit, &input, &words(&features))?)?;
+        let score = 3 * features[0] + 5 * features[1];
+        assert_eq!(from_bits(&output[..WIDTH]), score);
+        assert_eq!(output[WIDTH], score > threshold);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_changed_input_is_detected() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let model = CommittedInput::new(words(&[3, 5]), &mut rng);
+    let circuit = model.commitment().circuit(&scoring(), Party::Contributor)?;
+
+    // a different model with the opening of the committed one:
+    let mut input = model.execution_input(&to_bits(10, WIDTH));
+    input[WIDTH] = !input[WIDTH];
+    let output = simulate(&circuit, &input, &words(&[1, 2]))?;
+    assert!(output.iter().all(|bit| !bit));
+    assert!(is_commitment_error(check_output(output)));
+
+    // a different model with its own opening:
+    let other = CommittedInput::new(words(&[3, 4]), &mut rng);
+    let input = other.execution_input(&to_bits(10, WIDTH));
+    let output = simulate(&circuit, &input, &words(&[1, 2]))?;
+    assert!(is_commitment_error(check_output(output)));
+    Ok(())
+}
+
+#[test]
+fn test_committed_input_of_the_evaluator() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let features = CommittedInput::new(words(&[1, 2]), &mut rng);
+    let circuit = features
+        .commitment()
+        .circuit(&scoring(), Party::Evaluator)?;
+
+    // the contributor checks the commitment of the evaluator in the revealed output:
+    for committed in [true, false] {
+        let mut input_evaluator = features.execution_input(&[]);
+        if !committed {
+            input_evaluator[0] = !input_evaluator[0];
+        }
+        let input_contributor = words(&[3, 5, 10]);
+        let (contrib, msg_for_eval) = Contributor::new(
+            &circuit,
+            &input_contributor[..],
+            ChaCha20Rng::from_entropy(),
+        )?;
+        let eval = Evaluator::new(&circuit, &input_evaluator[..], ChaCha20Rng::from_entropy())?;
+        let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+        let (_, msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+        let output = check_output(contrib.output(&msg_for_contrib)?);
+        if committed {
+            assert_eq!(from_bits(&output?[..WIDTH]), 13);
+        } else {
+            assert!(is_commitment_error(output));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_commitments_use_fresh_nonces() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let input = words(&[3, 5]);
+    let first = CommittedInput::new(input.clone(), &mut rng);
+    let second = CommittedInput::new(input.clone(), &mut rng);
+    assert_eq!(first.input(), &input[..]);
+    assert_ne!(first.commitment().id(), second.commitment().id());
+    assert_eq!(first.commitment(), first.commitment());
+
+    // the circuit must provide enough input bits for the commitment:
+    let long = CommittedInput::new(words(&[1, 2, 3, 4]), &mut rng);
+    let circuit = long.commitment().circuit(&scoring(), Party::Contributor);
+    assert_eq!(circuit.err(), Some(Error::InvalidCircuit));
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use mpc_core::{
+    states::{Contributor, Evaluator, StatisticalSecurity, StepError},
+    Error,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit_with_3_and_gates;
+
+#[test]
+fn test_inputs_provided_after_preprocessing() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let input_contributor = vec![true, false];
+    let input_evaluator = vec![true, true];
+
+    let (contrib, msg_for_eval) = Contributor::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (_, eval, msg_for_eval) = common::run_steps_with_late_inputs(
+        contrib,
+        eval,
+        msg_for_eval,
+        input_contributor.clone(),
+        input_evaluator.clone(),
+    )?;
+    let output = eval.output(&msg_for_eval)?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    Ok(())
+}
+
+#[test]
+fn test_missing_input() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let (mut contrib, mut msg_for_eval) = Contributor::<_, Vec<bool>>::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut eval = Evaluator::new(&circuit, vec![true, true], ChaCha20Rng::from_entropy())?;
+    loop {
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        let needs_input = contrib.needs_input();
+        match contrib.run(&msg_for_contrib) {
+            Ok((next_state, reply)) => {
+                assert!(!needs_input);
+                contrib = next_state;
+                msg_for_eval = reply;
+            }
+            Err(e) => {
+                assert!(needs_input);
+                assert_eq!(e, Error::MissingInput);
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_input_provided_after_missing_input() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let input_contributor = vec![false, true];
+    let input_evaluator = vec![true, true];
+
+    let (mut contrib, mut msg_for_eval) = Contributor::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut eval = Evaluator::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut missing = (0, 0);
+    for _ in 0..eval.steps() {
+        // the parties only provide their inputs once a step fails without them:
+        let (next_state, msg_for_contrib) = match eval.try_run(&msg_for_eval) {
+            Err(StepError::MissingInput(mut waiting)) => {
+                missing.1 += 1;
+                waiting.provide_input(input_evaluator.clone())?;
+                waiting.try_run(&msg_for_eval)?
+            }
+            result => result?,
+        };
+        eval = next_state;
+        let (next_state, reply) = match contrib.try_run(&msg_for_contrib) {
+            Err(StepError::MissingInput(mut waiting)) => {
+                missing.0 += 1;
+                waiting.provide_input(input_contributor.clone())?;
+                waiting.try_run(&msg_for_contrib)?
+            }
+            result => result?,
+        };
+        contrib = next_state;
+        msg_for_eval = reply;
+    }
+    assert_eq!(missing, (1, 1));
+    let output = eval.output(&msg_for_eval)?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_late_inputs() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let (mut contrib, _) = Contributor::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(!contrib.needs_input());
+    assert_eq!(
+        contrib.provide_input(vec![true]),
+        Err(Error::InsufficientInput)
+    );
+    contrib.provide_input(vec![true, true])?;
+    assert_eq!(
+        contrib.provide_input(vec![true, true]),
+        Err(Error::InputAlreadyProvided)
+    );
+
+    let mut eval = Evaluator::new(&circuit, vec![false, true], ChaCha20Rng::from_entropy())?;
+    assert_eq!(
+        eval.provide_input(vec![false, true]),
+        Err(Error::InputAlreadyProvided)
+    );
+    let mut eval = Evaluator::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert_eq!(eval.provide_input(vec![]), Err(Error::InsufficientInput));
+    eval.provide_input(vec![false, true])?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use mpc_core::{
+    message::{Step, HEADER_LEN, PROTOCOL_VERSION},
+    states::{Contributor, Evaluator, SecurityLevel},
+    Error, MessageFault,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+#[test]
+fn test_unexpected_step() -> Result<(), Error> {
+    let circuit = circuit();
+    let (contrib, msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    let (eval, msg_for_contrib) = eval.run(&msg_for_eval)?;
+
+    // a message sent back to its sender:
+    let (other_contrib, other_msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        other_contrib.run(&other_msg_for_eval),
+        Err(Error::UnexpectedStep {
+            expected: Step::BaseOtResponse,
+            received: Step::BaseOtInit,
+        })
+    ));
+
+    // a message that is replayed in a later step:
+    let (contrib, msg_for_eval) = contrib.run(&msg_for_contrib)?;
+    eval.run(&msg_for_eval)?;
+    assert!(matches!(
+        contrib.run(&msg_for_contrib),
+        Err(Error::UnexpectedStep {
+            expected: Step::OtExtension,
+            received: Step::BaseOtResponse,
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_security_level_mismatch_is_an_unexpected_step() -> Result<(), Error> {
+    let circuit = circuit();
+    let (_, msg_for_eval) = Contributor::with_security_level(
+        &circuit,
+        &[true, false][..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::UnexpectedStep {
+            expected: Step::BaseOtInit,
+            received: Step::HalfGatesCircuit,
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_incompatible_protocol_version() -> Result<(), Error> {
+    let circuit = circuit();
+    let (_, msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+
+    let mut newer = msg_for_eval.clone();
+    newer[4..6].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        eval.run(&newer),
+        Err(Error::IncompatibleProtocolVersion { local, remote })
+            if local == PROTOCOL_VERSION && remote == PROTOCOL_VERSION + 1
+    ));
+
+    // earlier versions sent the bincode payload without any header:
+    let unframed = msg_for_eval[HEADER_LEN..].to_vec();
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        eval.run(&unframed),
+        Err(Error::IncompatibleProtocolVersion { remote: 0, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_message_length() -> Result<(), Error> {
+    let circuit = circuit();
+    let (_, msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    let mut longer = msg_for_eval.clone();
+    longer.push(0);
+    for msg in [
+        &msg_for_eval[..msg_for_eval.len() - 1],
+        &msg_for_eval[..HEADER_LEN - 1],
+        &longer[..],
+    ] {
+        let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+        assert!(matches!(eval.run(msg), Err(Error::InvalidMessageLength)));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_unknown_step() -> Result<(), Error> {
+    let circuit = circuit();
+    let (_, mut msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    msg_for_eval[6] = 0;
+    let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::UnexpectedMessageType {
+            step: None,
+            fault: MessageFault::UnknownStep(0),
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_message_of_other_session() -> Result<(), Error> {
+    let circuit = circuit();
+    let mut sessions = vec![];
+    for _ in 0..2 {
+        let (contrib, msg_for_eval) =
+            Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+        let eval = Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?;
+        let (eval, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        let (contrib, msg_for_eval) = contrib.run(&msg_for_contrib)?;
+        let (_, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        sessions.push((contrib, msg_for_contrib));
+    }
+    // the contributor has established its session and rejects the messages of the other session:
+    let (_, other_msg_for_contrib) = sessions.pop().unwrap();
+    let (contrib, _) = sessions.pop().unwrap();
+    assert!(matches!(
+        contrib.run(&other_msg_for_contrib),
+        Err(Error::SessionMismatch)
+    ));
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use mpc_core::{
+    gadgets::ml::{FixedPoint, Layer, Model, ModelShape, Prediction, TreeNode},
+    simulate, Error,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+fn random_tree(rng: &mut ChaCha20Rng, features: usize, depth: usize) -> TreeNode {
+    if depth == 0 || rng.gen_range(0..4) == 0 {
+        TreeNode::Leaf {
+            value: rng.gen_range(-4.0..4.0),
+        }
+    } else {
+        TreeNode::Split {
+            feature: rng.gen_range(0..features),
+            threshold: rng.gen_range(-2.0..2.0),
+            left: Box::new(random_tree(rng, features, depth - 1)),
+            right: Box::new(random_tree(rng, features, depth - 1)),
+        }
+    }
+}
+
+fn random_weights(rng: &mut ChaCha20Rng, len: usize) -> Vec<f64> {
+    (0..len).map(|_| rng.gen_range(-2.0..2.0)).collect()
+}
+
+fn random_network(rng: &mut ChaCha20Rng, sizes: &[usize]) -> Model {
+    let layers = sizes
+        .windows(2)
+        .map(|w| Layer {
+            weights: (0..w[1]).map(|_| random_weights(rng, w[0])).collect(),
+            bias: random_weights(rng, w[1]),
+        })
+        .collect();
+    Model::Network { layers }
+}
+
+fn check_model(
+    rng: &mut ChaCha20Rng,
+    model: &Model,
+    fp: FixedPoint,
+    samples: usize,
+) -> Result<(), Error> {
+    // every model must survive a round trip through its JSON description:
+    let model = Model::from_json(&serde_json::to_string(model).unwrap())?;
+    let shape = model.shape()?;
+    let circuit = shape.circuit(fp);
+    let params = model.contributor_input(fp)?;
+    for _ in 0..samples {
+        let features: Vec<f64> = (0..shape.features())
+            .map(|_| rng.gen_range(-3.0..3.0))
+            .collect();
+        let output = circuit.eval(&params, &shape.evaluator_input(&features, fp)?)?;
+        let expected = model.predict(&features, fp)?;
+        assert_eq!(shape.decode_output(&output, fp), expected, "{model:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tree_ensembles() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let fp = FixedPoint::default();
+    for _ in 0..10 {
+        let features = rng.gen_range(1..6);
+        let depth = rng.gen_range(0..5);
+        let trees = rng.gen_range(1..4);
+        let trees = (0..trees)
+            .map(|_| random_tree(&mut rng, features, depth))
+            .collect();
+        let model = Model::TreeEnsemble {
+            features,
+            depth,
+            trees,
+        };
+        check_model(&mut rng, &model, fp, 20)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_linear_and_logistic_models() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let fp = FixedPoint::default();
+    for _ in 0..10 {
+        let features = rng.gen_range(1..8);
+        let weights = random_weights(&mut rng, features);
+        let bias = rng.gen_range(-2.0..2.0);
+        let linear = Model::Linear {
+            weights: weights.clone(),
+            bias,
+        };
+        check_model(&mut rng, &linear, fp, 10)?;
+        check_model(&mut rng, &Model::Logistic { weights, bias }, fp, 10)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_relu_networks() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let fp = FixedPoint::new(24, 10);
+    for sizes in [&[2, 2][..], &[3, 4, 3], &[4, 3, 3, 2]] {
+        let model = random_network(&mut rng, sizes);
+        check_model(&mut rng, &model, fp, 20)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_fixed_point_precision() -> Result<(), Error> {
+    let json = r#"{ "type": "linear", "weights": [0.5, -1.25, 2.0], "bias": 0.1 }"#;
+    let model = Model::from_json(json)?;
+    let features = [1.5, 0.75, -2.25];
+    let exact = 0.5 * 1.5 - 1.25 * 0.75 - 2.0 * 2.25 + 0.1;
+    match model.predict(&features, FixedPoint::default())? {
+        Prediction::Value(v) => assert!((v - exact).abs() < 0.001, "{v} != {exact}"),
+        Prediction::Class(_) => panic!("linear models must predict a value"),
+    }
+
+    let json = r#"{ "type": "logistic", "weights": [1.0], "bias": 0.0 }"#;
+    let model = Model::from_json(json)?;
+    for (x, y) in [(-10.0, 0.0), (0.0, 0.5), (1.0, 0.75), (10.0, 1.0)] {
+        assert_eq!(
+            model.predict(&[x], FixedPoint::default())?,
+            Prediction::Value(y)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_invalid_models() {
+    for json in [
+        r#"{ "type": "linear", "weights": [], "bias": 0.0 }"#,
+        r#"{ "type": "linear", "weights": [1.0] }"#,
+        r#"{ "type": "tree_ensemble", "features": 1, "depth": 0, "trees": [
+            { "feature": 0, "threshold": 0.0, "left": { "value": 1 }, "right": { "value": 2 } }
+        ] }"#,
+        r#"{ "type": "tree_ensemble", "features": 1, "depth": 1, "trees": [
+            { "feature": 1, "threshold": 0.0, "left": { "value": 1 }, "right": { "value": 2 } }
+        ] }"#,
+        r#"{ "type": "network", "layers": [] }"#,
+        r#"{ "type": "network", "layers": [
+            { "weights": [[1.0, 2.0]], "bias": [0.0] },
+            { "weights": [[1.0, 2.0]], "bias": [0.0] }
+        ] }"#,
+        r#"{ "type": "svm" }"#,
+    ] {
+        assert_eq!(Model::from_json(json), Err(Error::InvalidModel), "{json}");
+    }
+}
+
+#[test]
+fn test_ml_secure_execution() -> Result<(), Error> {
+    let json = r#"{ "type": "tree_ensemble", "features": 2, "depth": 2, "trees": [
+        { "feature": 0, "threshold": 0.5,
+          "left": { "value": -1.0 },
+          "right": { "feature": 1, "threshold": 2.0,
+                     "left": { "value": 0.5 }, "right": { "value": 1.0 } } },
+        { "feature": 1, "threshold": 0.0, "left": { "value": 0.25 }, "right": { "value": 0.0 } }
+    ] }"#;
+    let model = Model::from_json(json)?;
+    let fp = FixedPoint::new(16, 8);
+    let shape = model.shape()?;
+    assert_eq!(
+        shape,
+        ModelShape::TreeEnsemble {
+            features: 2,
+            depth: 2,
+            trees: 2
+        }
+    );
+    let circuit = shape.circuit(fp);
+    let params = model.contributor_input(fp)?;
+    for (features, expected) in [([0.0, 0.0], -0.75), ([1.0, 1.0], 0.5), ([1.0, 3.0], 1.0)] {
+        let output = simulate(&circuit, &params, &shape.evaluator_input(&features, fp)?)?;
+        assert_eq!(
+            shape.decode_output(&output, fp),
+            Prediction::Value(expected)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use std::{collections::BTreeMap, thread};
+
+use mpc_core::{
+    channel::{mesh, run_party},
+    multiparty::{Msgs, Party},
+    Circuit, Error, Gate, MessageFault, PartyIndex,
+};
+use mpc_garble_interop::{
+    check_program, compile_multiparty_program, compile_program, deserialize_output,
+    serialize_party_input,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Computes the majority of one bit of each of 3 parties, the XOR of all bits and whether party 1
+/// and party 2 hold the same 2-bit value.
+fn circuit_3() -> Circuit {
+    Circuit::new(
+        vec![
+            Gate::InParty(0),
+            Gate::InParty(1),
+            Gate::InParty(1),
+            Gate::InParty(2),
+            Gate::InParty(2),
+            Gate::And(0, 1),
+            Gate::And(0, 3),
+            Gate::And(1, 3),
+            Gate::Xor(5, 6),
+            Gate::Xor(8, 7),
+            Gate::Xor(0, 1),
+            Gate::Xor(10, 3),
+            Gate::Xor(1, 3),
+            Gate::Xor(2, 4),
+            Gate::Not(12),
+            Gate::Not(13),
+            Gate::And(14, 15),
+        ],
+        vec![9, 11, 16],
+    )
+}
+
+/// Computes the AND of one bit of each of 4 parties, together with the AND of the negated bits.
+fn circuit_4() -> Circuit {
+    Circuit::new(
+        vec![
+            Gate::InParty(0),
+            Gate::InParty(1),
+            Gate::InParty(2),
+            Gate::InParty(3),
+            Gate::And(0, 1),
+            Gate::And(2, 3),
+            Gate::And(4, 5),
+            Gate::Not(0),
+            Gate::Not(1),
+            Gate::Not(2),
+            Gate::Not(3),
+            Gate::And(7, 8),
+            Gate::And(9, 10),
+            Gate::And(11, 12),
+        ],
+        vec![6, 13],
+    )
+}
+
+/// Runs every party on its own thread, over in-memory pipes to all other parties.
+fn run_all(circuit: &Circuit, inputs: &[Vec<bool>]) -> Vec<Result<Vec<bool>, Error>> {
+    let parties = inputs.len();
+    let threads: Vec<_> = mesh(parties)
+        .into_iter()
+        .zip(inputs.iter().cloned())
+        .enumerate()
+        .map(|(index, (mut channels, input))| {
+            let circuit = circuit.clone();
+            thread::spawn(move || {
+                let rng = ChaCha20Rng::from_entropy();
+                let (party, msgs) = Party::new(&circuit, parties, index as PartyIndex, input, rng)?;
+                run_party(party, msgs, &mut channels)
+            })
+        })
+        .collect();
+    threads
+        .into_iter()
+        .map(|t| t.join().expect("party panicked"))
+        .collect()
+}
+
+fn assert_outputs(circuit: &Circuit, inputs: &[Vec<bool>]) {
+    let plain: Vec<&[bool]> = inputs.iter().map(|i| &i[..]).collect();
+    let expected = circuit.eval_parties(&plain).unwrap();
+    for output in run_all(circuit, inputs) {
+        assert_eq!(output.unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_three_parties() {
+    let circuit = circuit_3();
+    circuit.validate_parties(3).unwrap();
+    for inputs in [
+        vec![vec![true], vec![true, false], vec![false, false]],
+        vec![vec![false], vec![true, true], vec![true, true]],
+        vec![vec![true], vec![false, true], vec![true, true]],
+    ] {
+        assert_outputs(&circuit, &inputs);
+    }
+}
+
+#[test]
+fn test_four_parties() {
+    let circuit = circuit_4();
+    for inputs in [
+        vec![vec![true], vec![true], vec![true], vec![true]],
+        vec![vec![false], vec![false], vec![false], vec![false]],
+        vec![vec![true], vec![false], vec![true], vec![true]],
+    ] {
+        assert_outputs(&circuit, &inputs);
+    }
+}
+
+#[test]
+fn test_garble_program_of_three_parties() -> Result<(), String> {
+    let program = "
+pub fn main(a: u8, b: u8, c: u8) -> (u8, bool) {
+    let total = a + b + c;
+    (total, total > 50u8)
+}
+";
+    let prg = check_program(program)?;
+    assert!(compile_program(&prg, "main").is_err());
+    let circuit = compile_multiparty_program(&prg, "main")?;
+    let inputs = ["20u8", "7u8", "30u8"]
+        .iter()
+        .enumerate()
+        .map(|(party, input)| serialize_party_input(party, &prg, &circuit.fn_def, input))
+        .collect::<Result<Vec<_>, String>>()?;
+    for output in run_all(&circuit.gates, &inputs) {
+        let output = output.map_err(|e| e.to_string())?;
+        let output = deserialize_output(&prg, &circuit.fn_def, &output)?;
+        assert_eq!(output.to_string(), "(57, true)");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_two_party_gates_are_rejected() {
+    let circuit = Circuit::new(
+        vec![Gate::InContrib, Gate::InParty(1), Gate::And(0, 1)],
+        vec![2],
+    );
+    let rng = ChaCha20Rng::from_entropy();
+    let party = Party::new(&circuit, 3, 0, &[][..], rng);
+    assert_eq!(party.err(), Some(Error::InvalidCircuit));
+
+    let circuit = Circuit::new(vec![Gate::InParty(0), Gate::InParty(3)], vec![0, 1]);
+    assert_eq!(circuit.validate_parties(3), Err(Error::InvalidCircuit));
+
+    let circuit = circuit_3();
+    let rng = ChaCha20Rng::from_entropy();
+    let party = Party::new(&circuit, 3, 3, &[][..], rng);
+    assert_eq!(party.err(), Some(Error::InvalidParties));
+}
+
+/// Runs all parties step by step in the same thread, letting `tamper` modify every message.
+fn run_tampered(
+    circuit: &Circuit,
+    inputs: &[Vec<bool>],
+    mut tamper: impl FnMut(u32, PartyIndex, PartyIndex, &mut Vec<u8>),
+) -> Result<Vec<Vec<bool>>, Error> {
+    let n = inputs.len();
+    let mut parties = vec![];
+    let mut outgoing: Vec<Msgs> = vec![];
+    for (i, input) in inputs.iter().enumerate() {
+        let rng = ChaCha20Rng::from_entropy();
+        let (party, msgs) = Party::new(circuit, n, i as PartyIndex, &input[..], rng)?;
+        parties.push(party);
+        outgoing.push(msgs);
+    }
+    let steps = parties[0].steps();
+    for step in 0..=steps {
+        let mut incoming: BTreeMap<PartyIndex, Msgs> = BTreeMap::new();
+        for (from, msgs) in outgoing.drain(..).enumerate() {
+            for (to, mut msg) in msgs {
+                tamper(step, from as PartyIndex, to, &mut msg);
+                incoming
+                    .entry(to)
+                    .or_default()
+                    .push((from as PartyIndex, msg));
+            }
+        }
+        if step == steps {
+            let mut outputs = vec![];
+            for (i, party) in parties.drain(..).enumerate() {
+                let msgs = incoming.remove(&(i as PartyIndex)).unwrap_or_default();
+                outputs.push(party.output(msgs)?);
+            }
+            return Ok(outputs);
+        }
+        let mut next = vec![];
+        for (i, party) in parties.drain(..).enumerate() {
+            let msgs = incoming.remove(&(i as PartyIndex)).unwrap_or_default();
+            let (party, msgs) = party.run(msgs)?;
+            next.push(party);
+            outgoing.push(msgs);
+        }
+        parties = next;
+    }
+    unreachable!()
+}
+
+#[test]
+fn test_step_by_step() {
+    let circuit = circuit_3();
+    let inputs = vec![vec![true], vec![true, false], vec![true, false]];
+    let outputs = run_tampered(&circuit, &inputs, |_, _, _, _| {}).unwrap();
+    for output in outputs {
+        assert_eq!(output, vec![true, true, true]);
+    }
+}
+
+#[test]
+fn test_tampered_messages_never_change_the_output() {
+    let circuit = circuit_3();
+    let inputs = vec![vec![true], vec![true, false], vec![true, false]];
+    let steps = Party::new(&circuit, 3, 0, &inputs[0][..], ChaCha20Rng::from_entropy())
+        .unwrap()
+        .0
+        .steps();
+    for tampered_step in 0..=steps {
+        let result = run_tampered(&circuit, &inputs, |step, from, to, msg| {
+            if step == tampered_step && from == 2 && to == 0 {
+                let last = msg.len() - 1;
+                msg[last] ^= 1;
+            }
+        });
+        // a flipped bit in a value that is never used, such as a garbled row that the evaluator
+        // does not decrypt, goes unnoticed, but must not affect the output either:
+        if let Ok(outputs) = result {
+            assert!(outputs.iter().all(|o| o == &vec![true, true, true]));
+        }
+    }
+
+    // the digest of the broadcast values is the last value of the last message:
+    let result = run_tampered(&circuit, &inputs, |step, from, to, msg| {
+        if step == steps && from == 2 && to == 1 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert!(matches!(result, Err(Error::InconsistentBroadcast)));
+}
+
+#[test]
+fn test_messages_of_unknown_parties_are_rejected() {
+    let circuit = circuit_3();
+    let inputs = [vec![true], vec![true, false], vec![true, false]];
+    let mut parties = vec![];
+    let mut msgs = vec![];
+    for (i, input) in inputs.iter().enumerate() {
+        let rng = ChaCha20Rng::from_entropy();
+        let (party, m) = Party::new(&circuit, 3, i as PartyIndex, &input[..], rng).unwrap();
+        parties.push(party);
+        msgs.push(m);
+    }
+    let to_0 = |from: usize| {
+        let msg = msgs[from].iter().find(|(to, _)| *to == 0).unwrap();
+        msg.1.clone()
+    };
+    let duplicated = vec![(1, to_0(1)), (1, to_0(2))];
+    let party = parties.remove(0);
+    match party.run(duplicated) {
+        Err(Error::UnexpectedMessageType {
+            fault: MessageFault::Sender(1),
+            ..
+        }) => {}
+        Err(e) => panic!("unexpected error {e}"),
+        Ok(_) => panic!("duplicate sender was accepted"),
+    }
+}
@@ -0,0 +1,218 @@
+use mpc_core::{
+    gadgets::{
+        array::{ObliviousArray, ReadStrategy},
+        from_bits, to_bits, CircuitBuilder, Party,
+    },
+    Error,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+const WIDTH: usize = 8;
+const INDEX_WIDTH: usize = 4;
+
+fn word_input(values: &[u64], width: usize) -> Vec<bool> {
+    values.iter().flat_map(|&v| to_bits(v, width)).collect()
+}
+
+#[test]
+fn test_read_contributor_array() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    for len in [1, 5, 8, 11, 16] {
+        for strategy in [ReadStrategy::MuxTree, ReadStrategy::OneHot] {
+            let mut b = CircuitBuilder::new();
+            let array = ObliviousArray::from_inputs(&mut b, Party::Contributor, len, WIDTH);
+            let index = b.input_word(Party::Evaluator, INDEX_WIDTH);
+            let and_gates_before = b.and_gates();
+            let value = array.read(&mut b, &index, strategy);
+            assert_eq!(
+                b.and_gates() - and_gates_before,
+                array.read_cost(strategy, INDEX_WIDTH)
+            );
+            let circuit = b.build(value);
+
+            let table: Vec<u64> = (0..len).map(|_| rng.gen_range(0..256)).collect();
+            for i in 0..(1 << INDEX_WIDTH) {
+                let output = circuit.eval(&word_input(&table, WIDTH), &to_bits(i, INDEX_WIDTH))?;
+                let expected = table.get(i as usize).copied().unwrap_or(0);
+                assert_eq!(
+                    from_bits(&output),
+                    expected,
+                    "len={len}, i={i}, {strategy:?}"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_read_mixed_array() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let len = 12;
+
+    // the first half of the array belongs to the contributor, the second half to the evaluator,
+    // while the index is the XOR of an index share of each party:
+    let mut b = CircuitBuilder::new();
+    let contrib = ObliviousArray::from_inputs(&mut b, Party::Contributor, len / 2, WIDTH);
+    let eval = ObliviousArray::from_inputs(&mut b, Party::Evaluator, len / 2, WIDTH);
+    let index_contrib = b.input_word(Party::Contributor, INDEX_WIDTH);
+    let index_eval = b.input_word(Party::Evaluator, INDEX_WIDTH);
+    let index = b.xor_word(&index_contrib, &index_eval);
+    let elements = [contrib.elements(), eval.elements()].concat();
+    let array = ObliviousArray::new(elements, WIDTH);
+    let value = array.read(&mut b, &index, ReadStrategy::MuxTree);
+    let circuit = b.build(value);
+
+    for _ in 0..32 {
+        let table: Vec<u64> = (0..len).map(|_| rng.gen_range(0..256)).collect();
+        let i: u64 = rng.gen_range(0..len as u64);
+        let share: u64 = rng.gen_range(0..(1 << INDEX_WIDTH));
+
+        let mut input_contrib = word_input(&table[..len / 2], WIDTH);
+        input_contrib.extend(to_bits(share, INDEX_WIDTH));
+        let mut input_eval = word_input(&table[len / 2..], WIDTH);
+        input_eval.extend(to_bits(share ^ i, INDEX_WIDTH));
+
+        let output = circuit.eval(&input_contrib, &input_eval)?;
+        assert_eq!(from_bits(&output), table[i as usize]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_write_then_read() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let len = 10;
+    let writes = 3;
+
+    // the contributor owns the array, the evaluator performs a sequence of (conditional) writes
+    // and finally reads a single element:
+    let mut b = CircuitBuilder::new();
+    let mut array = ObliviousArray::from_inputs(&mut b, Party::Contributor, len, WIDTH);
+    for _ in 0..writes {
+        let enable = b.input(Party::Evaluator);
+        let index = b.input_word(Party::Evaluator, INDEX_WIDTH);
+        let value = b.input_word(Party::Evaluator, WIDTH);
+        let and_gates_before = b.and_gates();
+        array.cond_write(&mut b, enable, &index, &value);
+        assert_eq!(
+            b.and_gates() - and_gates_before,
+            array.write_cost(INDEX_WIDTH) + len
+        );
+    }
+    let index = b.input_word(Party::Evaluator, INDEX_WIDTH);
+    let value = array.read(&mut b, &index, ReadStrategy::OneHot);
+    let mut outputs = value;
+    for element in array.elements() {
+        outputs.extend(element);
+    }
+    let circuit = b.build(outputs);
+
+    for _ in 0..32 {
+        let mut table: Vec<u64> = (0..len).map(|_| rng.gen_range(0..256)).collect();
+        let input_contrib = word_input(&table, WIDTH);
+
+        let mut input_eval = vec![];
+        for _ in 0..writes {
+            let enable = rng.gen_bool(0.75);
+            let i: u64 = rng.gen_range(0..(1 << INDEX_WIDTH));
+            let value: u64 = rng.gen_range(0..256);
+            input_eval.push(enable);
+            input_eval.extend(to_bits(i, INDEX_WIDTH));
+            input_eval.extend(to_bits(value, WIDTH));
+            if enable && (i as usize) < len {
+                table[i as usize] = value;
+            }
+        }
+        let i: u64 = rng.gen_range(0..len as u64);
+        input_eval.extend(to_bits(i, INDEX_WIDTH));
+
+        let output = circuit.eval(&input_contrib, &input_eval)?;
+        assert_eq!(from_bits(&output[..WIDTH]), table[i as usize]);
+        for (j, element) in output[WIDTH..].chunks(WIDTH).enumerate() {
+            assert_eq!(from_bits(element), table[j]);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_read_secure_execution() -> Result<(), Error> {
+    let table = [17, 4, 255, 0, 99, 128, 3];
+
+    let mut b = CircuitBuilder::new();
+    let array = ObliviousArray::from_inputs(&mut b, Party::Contributor, table.len(), WIDTH);
+    let index = b.input_word(Party::Evaluator, 3);
+    let value = array.read(&mut b, &index, ReadStrategy::MuxTree);
+    let circuit = b.build(value);
+
+    for i in [0, 2, 6, 7] {
+        let output = mpc_core::simulate(&circuit, &word_input(&table, WIDTH), &to_bits(i, 3))?;
+        assert_eq!(
+            from_bits(&output),
+            table.get(i as usize).copied().unwrap_or(0)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_array_longer_than_index_range() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let len = 5;
+    let index_width = 2;
+
+    // only the first 4 elements can be reached by a 2-bit index:
+    let mut b = CircuitBuilder::new();
+    let mut array = ObliviousArray::from_inputs(&mut b, Party::Contributor, len, WIDTH);
+    let index = b.input_word(Party::Evaluator, index_width);
+    let mut outputs = vec![];
+    for strategy in [ReadStrategy::MuxTree, ReadStrategy::OneHot] {
+        let and_gates_before = b.and_gates();
+        outputs.extend(array.read(&mut b, &index, strategy));
+        assert_eq!(
+            b.and_gates() - and_gates_before,
+            array.read_cost(strategy, index_width)
+        );
+    }
+    let value = b.input_word(Party::Evaluator, WIDTH);
+    let and_gates_before = b.and_gates();
+    array.write(&mut b, &index, &value);
+    assert_eq!(
+        b.and_gates() - and_gates_before,
+        array.write_cost(index_width)
+    );
+    let enable = b.input(Party::Evaluator);
+    let and_gates_before = b.and_gates();
+    array.cond_write(&mut b, enable, &index, &value);
+    assert_eq!(
+        b.and_gates() - and_gates_before,
+        array.write_cost(index_width) + len
+    );
+    for element in array.elements() {
+        outputs.extend(element);
+    }
+    let circuit = b.build(outputs);
+
+    for i in 0..(1 << index_width) {
+        for enable in [false, true] {
+            let table: Vec<u64> = (0..len).map(|_| rng.gen_range(0..256)).collect();
+            let value: u64 = rng.gen_range(0..256);
+            let mut input_eval = to_bits(i, index_width);
+            input_eval.extend(to_bits(value, WIDTH));
+            input_eval.push(enable);
+
+            let output = circuit.eval(&word_input(&table, WIDTH), &input_eval)?;
+            let mut expected = table.clone();
+            expected[i as usize] = value;
+            assert_eq!(from_bits(&output[..WIDTH]), table[i as usize]);
+            assert_eq!(from_bits(&output[WIDTH..2 * WIDTH]), table[i as usize]);
+            for (j, element) in output[2 * WIDTH..].chunks(WIDTH).enumerate() {
+                assert_eq!(from_bits(element), expected[j], "i={i}, j={j}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use mpc_core::{
+    channel::duplex,
+    gadgets::Party,
+    oram::{OramContributor, OramEvaluator},
+    Error,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::thread;
+
+const WIDTH: usize = 16;
+
+/// A read or a write at a secret index (and of a secret value) of the party that issues it.
+#[derive(Debug, Clone, Copy)]
+enum Access {
+    Read(Party, u64),
+    Write(Party, u64, u64),
+}
+
+/// The share of `value` of the party `me`, if `value` is the private input of `owner`.
+fn share(me: Party, owner: Party, value: u64) -> u64 {
+    if me == owner {
+        value
+    } else {
+        0
+    }
+}
+
+fn random_party(rng: &mut ChaCha20Rng) -> Party {
+    if rng.gen() {
+        Party::Contributor
+    } else {
+        Party::Evaluator
+    }
+}
+
+/// Runs the accesses on an array owned by `owner`, with each party in a separate thread, and
+/// returns the values of all reads.
+fn execute(values: &[u64], owner: Party, accesses: &[Access]) -> Result<Vec<u64>, Error> {
+    let (mut channel, mut channel_eval) = duplex();
+    let shares: Vec<u64> = values
+        .iter()
+        .map(|&v| share(Party::Contributor, owner, v))
+        .collect();
+    let contrib_accesses = accesses.to_vec();
+    let contributor = thread::spawn(move || -> Result<(Vec<u64>, usize, usize), Error> {
+        let me = Party::Contributor;
+        let mut oram = OramContributor::new(&mut channel, &shares, WIDTH)?;
+        assert_eq!(oram.len(), shares.len());
+        let mut reads = vec![];
+        for access in contrib_accesses {
+            match access {
+                Access::Read(party, i) => reads.push(oram.read(&mut channel, share(me, party, i))?),
+                Access::Write(party, i, v) => {
+                    oram.write(&mut channel, share(me, party, i), share(me, party, v))?
+                }
+            }
+        }
+        Ok((reads, oram.executions(), oram.and_gates()))
+    });
+
+    let me = Party::Evaluator;
+    let channel = &mut channel_eval;
+    let shares: Vec<u64> = values.iter().map(|&v| share(me, owner, v)).collect();
+    let mut oram = OramEvaluator::new(channel, &shares, WIDTH)?;
+    let mut reads = vec![];
+    for &access in accesses {
+        match access {
+            Access::Read(party, i) => reads.push(oram.read(channel, share(me, party, i))?),
+            Access::Write(party, i, v) => {
+                oram.write(channel, share(me, party, i), share(me, party, v))?
+            }
+        }
+    }
+    let (contrib_reads, executions, and_gates) =
+        contributor.join().expect("the contributor panicked")?;
+    assert_eq!(executions, oram.executions());
+    assert_eq!(and_gates, oram.and_gates());
+    Ok(reads
+        .iter()
+        .zip(contrib_reads)
+        .map(|(eval, contrib)| eval ^ contrib)
+        .collect())
+}
+
+#[test]
+fn test_oram_matches_plaintext_array() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    for len in [1, 5, 20] {
+        let values: Vec<u64> = (0..len).map(|_| rng.gen_range(0..1 << WIDTH)).collect();
+        let mut expected = values.clone();
+        let mut accesses = vec![];
+        let mut expected_reads = vec![];
+        for _ in 0..16 {
+            // favor a few hot indices, so that blocks are frequently found in the stash:
+            let index = if rng.gen_bool(0.3) {
+                rng.gen_range(0..len.min(3))
+            } else {
+                rng.gen_range(0..len)
+            };
+            let party = random_party(&mut rng);
+            if rng.gen() {
+                let value = rng.gen_range(0..1 << WIDTH);
+                accesses.push(Access::Write(party, index as u64, value));
+                expected[index] = value;
+            } else {
+                accesses.push(Access::Read(party, index as u64));
+                expected_reads.push(expected[index]);
+            }
+        }
+        let reads = execute(&values, random_party(&mut rng), &accesses)?;
+        assert_eq!(reads, expected_reads, "len={len}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_oram_out_of_bounds() -> Result<(), Error> {
+    let values = [7, 8, 9];
+    // the period of an ORAM of 3 elements is at most 5, so this includes a reshuffle:
+    let accesses = [
+        Access::Write(Party::Evaluator, 1, 42),
+        Access::Read(Party::Contributor, 3),
+        Access::Write(Party::Contributor, 4, 1),
+        Access::Write(Party::Evaluator, u64::MAX, 1),
+        Access::Read(Party::Evaluator, 1 << 40),
+        Access::Read(Party::Evaluator, 0),
+        Access::Read(Party::Contributor, 1),
+        Access::Read(Party::Evaluator, 2),
+    ];
+    let reads = execute(&values, Party::Contributor, &accesses)?;
+    assert_eq!(reads, vec![0, 0, 7, 42, 9]);
+    Ok(())
+}
+
+#[test]
+fn test_oram_out_of_bounds_aliasing_a_stashed_block() -> Result<(), Error> {
+    let values: Vec<u64> = (100..120).collect();
+    // the lowest bits of 1 << 40 are those of index 0, which is in the stash after the first read:
+    let accesses = [
+        Access::Read(Party::Evaluator, 0),
+        Access::Read(Party::Contributor, 1 << 40),
+        Access::Write(Party::Evaluator, 1 << 40, 1),
+        Access::Read(Party::Contributor, 0),
+        Access::Read(Party::Evaluator, 1),
+    ];
+    let reads = execute(&values, Party::Evaluator, &accesses)?;
+    assert_eq!(reads, vec![100, 0, 100, 101]);
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use mpc_core::{
+    preprocessing::{Pool, PoolContributor, PoolEvaluator},
+    states::{Contributor, Evaluator, StatisticalSecurity},
+    Circuit, Error, Gate,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit_with_3_and_gates;
+
+/// Runs the offline phase locally, returning the pools of the contributor and the evaluator.
+fn generate_pools(
+    abits: usize,
+    ands: usize,
+    security: StatisticalSecurity,
+) -> Result<(Pool, Pool), Error> {
+    let mut eval = PoolEvaluator::new(abits, ands, security, ChaCha20Rng::from_entropy())?;
+    let (mut contrib, mut msg_for_eval) =
+        PoolContributor::new(abits, ands, security, ChaCha20Rng::from_entropy())?;
+    assert_eq!(contrib.steps(), eval.steps());
+    for _ in 0..contrib.steps() {
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+    }
+    Ok((contrib.pool()?, eval.pool(&msg_for_eval)?))
+}
+
+/// Runs the online phase locally, consuming parts of both pools.
+fn simulate_with_pools(
+    circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+    contrib_pool: &mut Pool,
+    eval_pool: &mut Pool,
+) -> Result<Vec<bool>, Error> {
+    let (contrib, msg_for_eval) = Contributor::with_pool(
+        circuit,
+        input_contributor,
+        contrib_pool,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_pool(
+        circuit,
+        input_evaluator,
+        eval_pool,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert_eq!(contrib.steps(), eval.steps());
+    let (_, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    eval.output(&msg_for_eval)
+}
+
+#[test]
+fn test_pool_runs_multiple_circuits() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let (mut contrib_pool, mut eval_pool) =
+        generate_pools(4 * 7, 4 * 3, StatisticalSecurity::default())?;
+    assert_eq!((contrib_pool.abits(), contrib_pool.ands()), (28, 12));
+    assert_eq!((eval_pool.abits(), eval_pool.ands()), (28, 12));
+
+    let inputs = [
+        ([true, false], [true, true]),
+        ([true, true], [true, true]),
+        ([false, true], [false, true]),
+        ([false, false], [true, false]),
+    ];
+    for (input_contributor, input_evaluator) in inputs {
+        assert!(contrib_pool.fits(&circuit) && eval_pool.fits(&circuit));
+        let output = simulate_with_pools(
+            &circuit,
+            &input_contributor,
+            &input_evaluator,
+            &mut contrib_pool,
+            &mut eval_pool,
+        )?;
+        assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    }
+
+    // everything has been consumed, the pool cannot be used again:
+    assert_eq!((contrib_pool.abits(), contrib_pool.ands()), (0, 0));
+    assert!(!contrib_pool.fits(&circuit));
+    let result = Contributor::with_pool(
+        &circuit,
+        vec![true, true],
+        &mut contrib_pool,
+        ChaCha20Rng::from_entropy(),
+    );
+    assert!(matches!(result, Err(Error::PoolExhausted)));
+    let result = Evaluator::with_pool(
+        &circuit,
+        vec![true, true],
+        &mut eval_pool,
+        ChaCha20Rng::from_entropy(),
+    );
+    assert!(matches!(result, Err(Error::PoolExhausted)));
+    Ok(())
+}
+
+#[test]
+fn test_pool_with_higher_statistical_security() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let input_contributor = [true, false];
+    let input_evaluator = [true, true];
+    for bits in [60, 80] {
+        let security = StatisticalSecurity::new(bits)?;
+        let (mut contrib_pool, mut eval_pool) = generate_pools(7, 3, security)?;
+        assert_eq!(contrib_pool.security(), security);
+        assert_eq!(eval_pool.security(), security);
+        let output = simulate_with_pools(
+            &circuit,
+            &input_contributor,
+            &input_evaluator,
+            &mut contrib_pool,
+            &mut eval_pool,
+        )?;
+        assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_persisted_pool() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let (contrib_pool, eval_pool) = generate_pools(2 * 7, 2 * 3, StatisticalSecurity::default())?;
+    let contrib_bytes = contrib_pool.to_bytes()?;
+    let eval_bytes = eval_pool.to_bytes()?;
+
+    let mut contrib_pool = Pool::from_bytes(&contrib_bytes)?;
+    let mut eval_pool = Pool::from_bytes(&eval_bytes)?;
+    let input_contributor = [true, true];
+    let input_evaluator = [false, true];
+    let output = simulate_with_pools(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        &mut contrib_pool,
+        &mut eval_pool,
+    )?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+
+    // restoring the contributor's pool from before the execution must not allow reusing it:
+    let mut stale_contrib_pool = Pool::from_bytes(&contrib_bytes)?;
+    let result = simulate_with_pools(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        &mut stale_contrib_pool,
+        &mut eval_pool,
+    );
+    assert_eq!(result, Err(Error::PoolMismatch));
+    Ok(())
+}
+
+#[test]
+fn test_pools_of_different_sessions() -> Result<(), Error> {
+    let circuit = circuit_with_3_and_gates();
+    let (mut contrib_pool, _) = generate_pools(7, 3, StatisticalSecurity::default())?;
+    let (_, mut eval_pool) = generate_pools(7, 3, StatisticalSecurity::default())?;
+    let result = simulate_with_pools(
+        &circuit,
+        &[true, false],
+        &[true, true],
+        &mut contrib_pool,
+        &mut eval_pool,
+    );
+    assert_eq!(result, Err(Error::PoolMismatch));
+    Ok(())
+}
+
+#[test]
+fn test_pool_for_larger_circuit() -> Result<(), Error> {
+    let mut gates = vec![Gate::InContrib, Gate::InEval];
+    for i in 0..500 {
+        gates.push(Gate::And(i, i + 1));
+        gates.push(Gate::Xor(i + 1, i + 2));
+    }
+    let output = gates.len() as u32 - 1;
+    let circuit = Circuit::new(gates, vec![output]);
+
+    let (mut contrib_pool, mut eval_pool) =
+        generate_pools(2000, 1000, StatisticalSecurity::default())?;
+    for (input_contributor, input_evaluator) in [([true], [false]), ([true], [true])] {
+        let output = simulate_with_pools(
+            &circuit,
+            &input_contributor,
+            &input_evaluator,
+            &mut contrib_pool,
+            &mut eval_pool,
+        )?;
+        assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    }
+    assert_eq!(contrib_pool.ands(), 0);
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use mpc_core::{
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    reactive::{ReactiveState, StateBindings},
+    states::{Contributor, Evaluator, StatisticalSecurity},
+    Circuit, Error, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+const WIDTH: usize = 8;
+
+/// Runs one execution of a reactive computation locally, returning the revealed outputs.
+fn execute(
+    circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+    contrib_state: &mut ReactiveState,
+    eval_state: &mut ReactiveState,
+    bindings: &StateBindings,
+) -> Result<Vec<bool>, Error> {
+    let (mut contrib, mut msg_for_eval) = Contributor::reactive(
+        circuit,
+        input_contributor,
+        contrib_state,
+        bindings,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut eval = Evaluator::reactive(
+        circuit,
+        input_evaluator,
+        eval_state,
+        bindings,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    for _ in 0..eval.steps() {
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+    }
+    contrib.update_state(contrib_state)?;
+    let (output, msg_for_contrib) = eval.update_state(&msg_for_eval, eval_state)?;
+    assert_eq!(contrib.output(&msg_for_contrib)?, output);
+    Ok(output)
+}
+
+/// Stores the sum of the inputs of both parties as the new balance.
+fn open_account() -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, WIDTH);
+    let y = b.input_word(Party::Evaluator, WIDTH);
+    let balance = int::add(&mut b, &x, &y);
+    b.build(balance)
+}
+
+/// Adds the evaluator's input to the balance if it stays below the contributor's limit, revealing
+/// whether the deposit was accepted.
+fn deposit() -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let balance = b.input_shared(WIDTH);
+    let limit = b.input_word(Party::Contributor, WIDTH);
+    let amount = b.input_word(Party::Evaluator, WIDTH);
+    let new_balance = int::add(&mut b, &balance, &amount);
+    let accepted = int::lt(&mut b, &new_balance, &limit);
+    let mut outputs = b.mux_word(accepted, &balance, &new_balance);
+    outputs.push(accepted);
+    b.build(outputs)
+}
+
+/// Reveals the balance.
+fn reveal_balance() -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let balance = b.input_shared(WIDTH);
+    b.build(balance)
+}
+
+fn bits(value: u64) -> Vec<bool> {
+    to_bits(value, WIDTH)
+}
+
+#[test]
+fn test_multi_step_workflow() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let mut contrib_state = ReactiveState::new(&mut rng);
+    let mut eval_state = ReactiveState::new(&mut rng);
+
+    let bindings = StateBindings::new().store("balance", WIDTH);
+    let output = execute(
+        &open_account(),
+        &bits(10),
+        &bits(5),
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+    assert!(output.is_empty());
+    assert_eq!(contrib_state.handle_len("balance"), Some(WIDTH));
+    assert_eq!(eval_state.handles().collect::<Vec<_>>(), vec!["balance"]);
+
+    let bindings = StateBindings::new().load("balance").store("balance", WIDTH);
+    for (amount, accepted) in [(20, true), (100, true), (80, false), (14, true), (1, false)] {
+        // the state can be persisted between executions:
+        let mut contrib_restored = ReactiveState::from_bytes(&contrib_state.to_bytes()?)?;
+        let output = execute(
+            &deposit(),
+            &bits(150),
+            &bits(amount),
+            &mut contrib_restored,
+            &mut eval_state,
+            &bindings,
+        )?;
+        assert_eq!(output, vec![accepted]);
+        contrib_state = contrib_restored;
+    }
+
+    let bindings = StateBindings::new().load("balance");
+    let output = execute(
+        &reveal_balance(),
+        &[],
+        &[],
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+    assert_eq!(from_bits(&output), 10 + 5 + 20 + 100 + 14);
+    Ok(())
+}
+
+#[test]
+fn test_multiple_handles() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let mut contrib_state = ReactiveState::new(&mut rng);
+    let mut eval_state = ReactiveState::new(&mut rng);
+
+    // stores the inputs of both parties as separate handles, revealing their XOR:
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, WIDTH);
+    let y = b.input_word(Party::Evaluator, WIDTH);
+    let mut outputs = x.clone();
+    outputs.extend(y.iter());
+    outputs.extend(b.xor_word(&x, &y));
+    let store_both = b.build(outputs);
+    let bindings = StateBindings::new().store("x", WIDTH).store("y", WIDTH);
+    let output = execute(
+        &store_both,
+        &bits(0b1100),
+        &bits(0b1010),
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+    assert_eq!(from_bits(&output), 0b0110);
+
+    // loads the handles in reverse order and subtracts them:
+    let mut b = CircuitBuilder::new();
+    let y = b.input_shared(WIDTH);
+    let x = b.input_shared(WIDTH);
+    let difference = int::sub(&mut b, &x, &y);
+    let subtract = b.build(difference);
+    let bindings = StateBindings::new().load("y").load("x");
+    let output = execute(
+        &subtract,
+        &[],
+        &[],
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+    assert_eq!(from_bits(&output), 0b1100 - 0b1010);
+
+    assert!(contrib_state.remove("x"));
+    assert!(!contrib_state.remove("x"));
+    assert_eq!(contrib_state.handles().collect::<Vec<_>>(), vec!["y"]);
+    Ok(())
+}
+
+#[test]
+fn test_stale_state_is_rejected() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let mut contrib_state = ReactiveState::new(&mut rng);
+    let mut eval_state = ReactiveState::new(&mut rng);
+    let bindings = StateBindings::new().store("balance", WIDTH);
+    execute(
+        &open_account(),
+        &bits(1),
+        &bits(2),
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+    let contrib_bytes = contrib_state.to_bytes()?;
+    let eval_bytes = eval_state.to_bytes()?;
+
+    let bindings = StateBindings::new().load("balance").store("balance", WIDTH);
+    execute(
+        &deposit(),
+        &bits(200),
+        &bits(100),
+        &mut contrib_state,
+        &mut eval_state,
+        &bindings,
+    )?;
+
+    // neither party can go back to the balance before the deposit:
+    let bindings = StateBindings::new().load("balance");
+    let mut stale_contrib_state = ReactiveState::from_bytes(&contrib_bytes)?;
+    let result = execute(
+        &reveal_balance(),
+        &[],
+        &[],
+        &mut stale_contrib_state,
+        &mut eval_state,
+        &bindings,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::MacError {
+            check: MacCheck::LinkedInput,
+            ..
+        })
+    ));
+    let mut stale_eval_state = ReactiveState::from_bytes(&eval_bytes)?;
+    let result = execute(
+        &reveal_balance(),
+        &[],
+        &[],
+        &mut contrib_state,
+        &mut stale_eval_state,
+        &bindings,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::MacError {
+            check: MacCheck::LinkedInput,
+            ..
+        })
+    ));
+
+    // the state of another session cannot be used either:
+    let mut other_state = ReactiveState::new(&mut rng);
+    let store = StateBindings::new().store("balance", WIDTH);
+    execute(
+        &open_account(),
+        &bits(1),
+        &bits(2),
+        &mut other_state,
+        &mut ReactiveState::new(&mut rng),
+        &store,
+    )?;
+    let result = execute(
+        &reveal_balance(),
+        &[],
+        &[],
+        &mut other_state,
+        &mut eval_state,
+        &bindings,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::MacError {
+            check: MacCheck::LinkedInput,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_state_bindings() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let state = ReactiveState::new(&mut rng);
+    let circuit = reveal_balance();
+    let result = Contributor::reactive(
+        &circuit,
+        vec![],
+        &state,
+        &StateBindings::new().load("balance"),
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    );
+    assert!(matches!(result, Err(Error::UnknownStateHandle)));
+
+    let open = open_account();
+    let result = Evaluator::reactive(
+        &open,
+        vec![],
+        &state,
+        &StateBindings::new().store("balance", WIDTH + 1),
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    );
+    assert!(matches!(result, Err(Error::InvalidStateBindings)));
+
+    // without any loaded handles, the input must match the circuit:
+    let result = Evaluator::reactive(
+        &open,
+        bits(0)[1..].to_vec(),
+        &state,
+        &StateBindings::new().store("balance", WIDTH),
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    );
+    assert!(matches!(result, Err(Error::InsufficientInput)));
+    Ok(())
+}
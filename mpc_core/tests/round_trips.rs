@@ -0,0 +1,99 @@
+use mpc_core::{
+    message::{Step, HEADER_LEN},
+    states::{Contributor, Evaluator, Msg},
+    Error, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+/// Runs the protocol, flipping the bit `(byte, 1)` of the `tampered`-th message if specified, and
+/// returns all messages that were sent together with the output.
+///
+/// Messages are counted across both parties, starting with the initial message of the contributor.
+fn run(tampered: Option<(usize, usize)>) -> Result<(Vec<Msg>, Vec<bool>), Error> {
+    let circuit = circuit();
+    let input_contributor = [true, false];
+    let input_evaluator = [true, true];
+
+    let (contrib, msg_for_eval) = Contributor::new(
+        &circuit,
+        &input_contributor[..],
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::new(&circuit, &input_evaluator[..], ChaCha20Rng::from_entropy())?;
+    let mut messages = vec![];
+    let (_, eval, msg_for_eval) =
+        common::run_steps_intercepted(contrib, eval, msg_for_eval, |msg| {
+            match tampered {
+                Some((i, byte)) if i == messages.len() => msg[byte] ^= 1,
+                _ => {}
+            }
+            messages.push(msg.clone());
+        })?;
+    let output = eval.output(&msg_for_eval)?;
+    Ok((messages, output))
+}
+
+#[test]
+fn test_message_schedule() -> Result<(), Error> {
+    let (messages, output) = run(None)?;
+    assert_eq!(output, circuit().eval(&[true, false], &[true, true])?);
+    let expected = [
+        Step::BaseOtInit,
+        Step::BaseOtResponse,
+        Step::BaseOtCompletion,
+        Step::OtExtension,
+        Step::LeakyAndStart,
+        Step::LeakyAndCorrections,
+        Step::LeakyAndCommitments,
+        Step::LeakyAndOpenings,
+        Step::BucketingAndMasks,
+        Step::InputMasks,
+        Step::EvaluationInputs,
+    ];
+    let steps: Vec<u8> = messages.iter().map(|msg| msg[6]).collect();
+    assert_eq!(steps, expected.map(|step| step as u8));
+    Ok(())
+}
+
+#[test]
+fn test_merged_checks_still_run() -> Result<(), Error> {
+    let (messages, _) = run(None)?;
+    let openings = 7;
+    assert_eq!(messages[openings][6], Step::LeakyAndOpenings as u8);
+    let payload = HEADER_LEN + 8;
+
+    // the first commitment of the evaluator for the equality check of the leaky AND triples:
+    assert!(matches!(
+        run(Some((openings, payload))),
+        Err(Error::LeakyAndNotEqual {
+            step: Some(Step::LeakyAndOpenings),
+            failed: 1,
+            index: 0,
+        })
+    ));
+    // the last MAC of the evaluator's bucketing bits, sent in the same message:
+    let last = messages[openings].len() - 1;
+    assert!(matches!(
+        run(Some((openings, last))),
+        Err(Error::MacError {
+            step: Some(Step::LeakyAndOpenings),
+            check: MacCheck::Bucketing,
+            failed: 1,
+            ..
+        })
+    ));
+    // the first value opened by the contributor, sent together with its masked AND triple bits:
+    assert!(matches!(
+        run(Some((openings + 1, payload))),
+        Err(Error::LeakyAndNotEqual {
+            step: Some(Step::BucketingAndMasks),
+            failed: 1,
+            index: 0,
+        })
+    ));
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use mpc_core::{
+    states::{Contributor, Evaluator, Msg, SecurityLevel, StatisticalSecurity},
+    Circuit, Error, Gate,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+/// Runs the protocol with seeded RNGs, saving and restoring both parties after every step in
+/// which `restore` is true, and returns all messages together with the output.
+fn run(
+    security_level: SecurityLevel,
+    key: Option<&[u8; 32]>,
+    restore: impl Fn(usize) -> bool,
+) -> Result<(Vec<Msg>, Vec<bool>), Error> {
+    let circuit = circuit();
+    let input_contributor = [true, false];
+    let input_evaluator = [true, true];
+
+    let (mut contrib, mut msg_for_eval) = Contributor::with_security_level(
+        &circuit,
+        &input_contributor[..],
+        security_level,
+        ChaCha20Rng::from_seed([1; 32]),
+    )?;
+    let mut eval = Evaluator::with_security_level(
+        &circuit,
+        &input_evaluator[..],
+        security_level,
+        ChaCha20Rng::from_seed([2; 32]),
+    )?;
+    let mut messages = vec![msg_for_eval.clone()];
+    for step in 0..eval.steps() as usize {
+        if restore(step) {
+            let saved = eval.save(key)?;
+            drop(eval);
+            eval = Evaluator::restore(&circuit, Some(&input_evaluator[..]), &saved, key)?;
+        }
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+        messages.push(msg_for_contrib.clone());
+
+        if restore(step) {
+            let saved = contrib.save(key)?;
+            drop(contrib);
+            contrib = Contributor::restore(&circuit, Some(&input_contributor[..]), &saved, key)?;
+        }
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+        messages.push(msg_for_eval.clone());
+    }
+    if restore(eval.steps() as usize) {
+        let saved = eval.save(key)?;
+        eval = Evaluator::restore(&circuit, Some(&input_evaluator[..]), &saved, key)?;
+    }
+    let output = eval.output(&msg_for_eval)?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    Ok((messages, output))
+}
+
+#[test]
+fn test_restore_at_every_step() -> Result<(), Error> {
+    let key = [7; 32];
+    for security_level in [SecurityLevel::Malicious, SecurityLevel::SemiHonest] {
+        let (expected_messages, expected_output) = run(security_level, None, |_| false)?;
+        let steps = expected_messages.len() / 2;
+        for key in [None, Some(&key)] {
+            // the restored parties continue with the same RNG position and Delta and therefore
+            // send exactly the same messages as parties that were never saved:
+            for restored in 0..=steps {
+                let (messages, output) = run(security_level, key, |step| step == restored)?;
+                assert_eq!(messages, expected_messages);
+                assert_eq!(output, expected_output);
+            }
+            let (messages, output) = run(security_level, key, |_| true)?;
+            assert_eq!(messages, expected_messages);
+            assert_eq!(output, expected_output);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_restore_without_input() -> Result<(), Error> {
+    let circuit = circuit();
+    let input_contributor = [false, true];
+    let input_evaluator = [true, true];
+
+    let (mut contrib, mut msg_for_eval) = Contributor::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut eval = Evaluator::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    for _ in 0..eval.steps() {
+        let saved = eval.save(None)?;
+        eval = Evaluator::restore(&circuit, None, &saved, None)?;
+        if eval.needs_input() {
+            eval.provide_input(&input_evaluator[..])?;
+        }
+        let (next_state, msg_for_contrib) = eval.run(&msg_for_eval)?;
+        eval = next_state;
+
+        let saved = contrib.save(None)?;
+        contrib = Contributor::restore(&circuit, None, &saved, None)?;
+        if contrib.needs_input() {
+            contrib.provide_input(&input_contributor[..])?;
+        }
+        let (next_state, reply) = contrib.run(&msg_for_contrib)?;
+        contrib = next_state;
+        msg_for_eval = reply;
+    }
+    let output = eval.output(&msg_for_eval)?;
+    assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_saved_state() -> Result<(), Error> {
+    let circuit = circuit();
+    let input = [true, false];
+    let (contrib, _) = Contributor::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let saved = contrib.save(None)?;
+
+    // saved by the other party:
+    assert!(matches!(
+        Evaluator::restore(&circuit, Some(&input[..]), &saved, None),
+        Err(Error::InvalidSavedState)
+    ));
+
+    // saved for a different circuit:
+    let mut gates = circuit.gates().clone();
+    gates[5] = Gate::And(0, 3);
+    let other = Circuit::new(gates, circuit.output_gates().clone());
+    assert!(matches!(
+        Contributor::restore(&other, Some(&input[..]), &saved, None),
+        Err(Error::InvalidSavedState)
+    ));
+
+    // not a saved state at all:
+    assert!(matches!(
+        Contributor::restore(&circuit, Some(&input[..]), &saved[..10], None),
+        Err(Error::InvalidSavedState)
+    ));
+    assert!(matches!(
+        Contributor::restore(&circuit, Some(&input[..]), &saved[..saved.len() - 1], None),
+        Err(Error::BincodeError { step: None })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_state_key() -> Result<(), Error> {
+    let circuit = circuit();
+    let input = [true, false];
+    let key = [7; 32];
+    let eval = Evaluator::new(&circuit, &input[..], ChaCha20Rng::from_entropy())?;
+    let saved = eval.save(None)?;
+    let encrypted = eval.save(Some(&key))?;
+    assert_ne!(saved, encrypted);
+    // every encryption uses a fresh nonce:
+    let encrypted_again = eval.save(Some(&key))?;
+    assert_ne!(encrypted, encrypted_again);
+    Evaluator::restore(&circuit, Some(&input[..]), &encrypted_again, Some(&key))?;
+
+    // the wrong key, a missing key or a key for an unencrypted state:
+    for (saved, key) in [
+        (&encrypted, Some(&[8; 32])),
+        (&encrypted, None),
+        (&saved, Some(&key)),
+    ] {
+        assert!(matches!(
+            Evaluator::restore(&circuit, Some(&input[..]), saved, key),
+            Err(Error::InvalidStateKey)
+        ));
+    }
+
+    // modifications of the header, the nonce or the encrypted state:
+    for i in [9, 40, 72, encrypted.len() - 1] {
+        let mut modified = encrypted.clone();
+        modified[i] ^= 1;
+        assert!(matches!(
+            Evaluator::restore(&circuit, Some(&input[..]), &modified, Some(&key)),
+            Err(Error::InvalidSavedState | Error::InvalidStateKey)
+        ));
+    }
+    Evaluator::restore(&circuit, Some(&input[..]), &encrypted, Some(&key))?;
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use mpc_core::{
+    gadgets::{from_bits, int, to_bits, CircuitBuilder, Party},
+    message::Step,
+    simulate_with_security_level,
+    states::{Contributor, Evaluator, OutputMode, SecurityLevel},
+    Circuit, Error, Gate, MacCheck,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+
+/// Runs the semi-honest protocol locally, returning the output of the evaluator and the output
+/// revealed to the contributor.
+fn execute(
+    circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+) -> Result<(Vec<bool>, Vec<bool>), Error> {
+    let (contrib, msg_for_eval) = Contributor::with_security_level(
+        circuit,
+        input_contributor,
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_security_level(
+        circuit,
+        input_evaluator,
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert_eq!(contrib.steps(), 2);
+    assert_eq!(eval.steps(), 2);
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    let (output, msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    Ok((output, contrib.output(&msg_for_contrib)?))
+}
+
+#[test]
+fn test_all_gates() -> Result<(), Error> {
+    // computes `(a & b) ^ (c & d)`, `!(a & b)`, `!(a ^ c)` and `c`:
+    let mut gates = common::gates();
+    gates.extend([Gate::Xor(0, 1), Gate::Not(8)]);
+    let circuit = Circuit::new(gates, vec![6, 7, 9, 1]);
+    for input in 0..16 {
+        let input_contributor = [input & 1 != 0, input & 2 != 0];
+        let input_evaluator = [input & 4 != 0, input & 8 != 0];
+        let expected = circuit.eval(&input_contributor, &input_evaluator)?;
+        let (output, contrib_output) = execute(&circuit, &input_contributor, &input_evaluator)?;
+        assert_eq!(output, expected);
+        assert_eq!(contrib_output, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_matches_malicious_protocol() -> Result<(), Error> {
+    // more than a single block of extended OTs for the evaluator's input:
+    let mut b = CircuitBuilder::new();
+    let x = b.input_word(Party::Contributor, 16);
+    let ys: Vec<_> = (0..10).map(|_| b.input_word(Party::Evaluator, 16)).collect();
+    let mut sum = x.clone();
+    for y in ys.iter() {
+        let product = int::mul(&mut b, &x, y);
+        sum = int::add(&mut b, &sum, &product);
+    }
+    let circuit = b.build(sum);
+    assert!(circuit.eval_inputs() > 128);
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    let x: u64 = rng.gen_range(0..100);
+    let ys: Vec<u64> = (0..10).map(|_| rng.gen_range(0..100)).collect();
+    let input_contributor = to_bits(x, 16);
+    let input_evaluator: Vec<bool> = ys.iter().flat_map(|y| to_bits(*y, 16)).collect();
+
+    let semi_honest = simulate_with_security_level(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        SecurityLevel::SemiHonest,
+    )?;
+    let malicious = simulate_with_security_level(
+        &circuit,
+        &input_contributor,
+        &input_evaluator,
+        SecurityLevel::Malicious,
+    )?;
+    assert_eq!(semi_honest, malicious);
+    assert_eq!(from_bits(&semi_honest), x + ys.iter().map(|y| x * y).sum::<u64>());
+    Ok(())
+}
+
+#[test]
+fn test_modified_output_is_rejected() -> Result<(), Error> {
+    let circuit = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)], vec![2]);
+    let (contrib, msg_for_eval) = Contributor::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_security_level(
+        &circuit,
+        &[false][..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    let (output, mut msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    assert_eq!(output, vec![false]);
+    let last = msg_for_contrib.len() - 1;
+    msg_for_contrib[last] ^= 1;
+    assert_eq!(
+        contrib.output(&msg_for_contrib),
+        Err(Error::MacError {
+            step: Some(Step::HalfGatesOutputLabels),
+            check: MacCheck::Output,
+            failed: 1,
+            index: Some(0),
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_features() -> Result<(), Error> {
+    let circuit = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)], vec![2]);
+    let parties = || -> Result<_, Error> {
+        let (contrib, msg_for_eval) = Contributor::with_security_level(
+            &circuit,
+            &[true][..],
+            SecurityLevel::SemiHonest,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        let eval = Evaluator::with_security_level(
+            &circuit,
+            &[true][..],
+            SecurityLevel::SemiHonest,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        Ok((contrib, eval, msg_for_eval))
+    };
+
+    // the shared output mode is rejected before any party sends its garbled circuit or labels:
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let eval = eval.with_output_mode(OutputMode::Shared);
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    let (_, eval, msg_for_eval) = parties()?;
+    let (_, msg_for_contrib) = eval.run(&msg_for_eval)?;
+    let contrib = contrib.with_output_mode(OutputMode::Shared);
+    assert!(matches!(
+        contrib.run(&msg_for_contrib),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+
+    // ...and does not reveal the output if it is only set after the last step:
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    let (_, msg_for_contrib) = eval.reveal_output(&msg_for_eval)?;
+    assert!(matches!(
+        contrib
+            .with_output_mode(OutputMode::Shared)
+            .output(&msg_for_contrib),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    let (contrib, eval, msg_for_eval) = parties()?;
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    assert!(matches!(
+        eval.with_output_mode(OutputMode::Shared)
+            .reveal_output(&msg_for_eval),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    assert!(matches!(
+        contrib.output_shares(),
+        Err(Error::UnsupportedSecurityLevel)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_security_level_mismatch() -> Result<(), Error> {
+    let circuit = Circuit::new(vec![Gate::InContrib, Gate::InEval, Gate::And(0, 1)], vec![2]);
+    let (_, msg_for_eval) = Contributor::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::Malicious,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(eval.run(&msg_for_eval).is_err());
+
+    let (_, msg_for_eval) = Contributor::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::Malicious,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_security_level(
+        &circuit,
+        &[true][..],
+        SecurityLevel::SemiHonest,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(eval.run(&msg_for_eval).is_err());
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use mpc_core::{
+    message::Step,
+    states::{Contributor, Evaluator},
+    Circuit, Error, Gate, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+/// Runs two sessions of the same circuit with the same inputs in parallel, replacing the
+/// `replaced`-th message of the first session with the corresponding message of the second one,
+/// and returns the output of the first session.
+///
+/// Messages are counted across both parties, starting with the initial message of the contributor.
+fn run_with_replaced_message(replaced: usize) -> Result<Vec<bool>, Error> {
+    let circuit = circuit();
+    let input_contributor = [true, false];
+    let input_evaluator = [true, true];
+
+    let (mut contrib_a, mut msg_for_eval_a) = Contributor::new(
+        &circuit,
+        &input_contributor[..],
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (mut contrib_b, mut msg_for_eval_b) = Contributor::new(
+        &circuit,
+        &input_contributor[..],
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let mut eval_a = Evaluator::new(&circuit, &input_evaluator[..], ChaCha20Rng::from_entropy())?;
+    let mut eval_b = Evaluator::new(&circuit, &input_evaluator[..], ChaCha20Rng::from_entropy())?;
+    let mut sent = 0;
+    for _ in 0..eval_a.steps() {
+        if sent == replaced {
+            msg_for_eval_a = msg_for_eval_b.clone();
+        }
+        sent += 1;
+        let (next_state, mut msg_for_contrib_a) = eval_a.run(&msg_for_eval_a)?;
+        eval_a = next_state;
+        let (next_state, msg_for_contrib_b) = eval_b.run(&msg_for_eval_b)?;
+        eval_b = next_state;
+
+        if sent == replaced {
+            msg_for_contrib_a = msg_for_contrib_b.clone();
+        }
+        sent += 1;
+        let (next_state, reply) = contrib_a.run(&msg_for_contrib_a)?;
+        contrib_a = next_state;
+        msg_for_eval_a = reply;
+        let (next_state, reply) = contrib_b.run(&msg_for_contrib_b)?;
+        contrib_b = next_state;
+        msg_for_eval_b = reply;
+    }
+    if sent == replaced {
+        msg_for_eval_a = msg_for_eval_b;
+    }
+    eval_a.output(&msg_for_eval_a)
+}
+
+#[test]
+fn test_parallel_sessions() -> Result<(), Error> {
+    let circuit = circuit();
+    let expected = circuit.eval(&[true, false], &[true, true])?;
+    let messages = 2 * Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?
+        .steps() as usize
+        + 1;
+    assert_eq!(run_with_replaced_message(messages)?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_messages_of_parallel_session_are_rejected() -> Result<(), Error> {
+    let circuit = circuit();
+    let messages = 2 * Evaluator::new(&circuit, &[true, true][..], ChaCha20Rng::from_entropy())?
+        .steps() as usize
+        + 1;
+    for replaced in 0..messages {
+        assert!(
+            run_with_replaced_message(replaced).is_err(),
+            "message {replaced} of a parallel session was accepted"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_session_is_bound_to_circuit() -> Result<(), Error> {
+    // the same number and kinds of gates, but a different output:
+    let circuit = circuit();
+    let mut gates = circuit.gates().clone();
+    gates[5] = Gate::And(0, 3);
+    let other = Circuit::new(gates, circuit.output_gates().clone());
+
+    let (contrib, msg_for_eval) =
+        Contributor::new(&circuit, &[true, false][..], ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&other, &[true, true][..], ChaCha20Rng::from_entropy())?;
+    let (_, msg_for_contrib) = eval.run(&msg_for_eval)?;
+    // the coin commitment of the evaluator was made for the other circuit:
+    assert_eq!(
+        contrib.run(&msg_for_contrib).err(),
+        Some(Error::MacError {
+            step: Some(Step::BaseOtResponse),
+            check: MacCheck::CoinCommitment,
+            failed: 1,
+            index: None,
+        })
+    );
+    Ok(())
+}
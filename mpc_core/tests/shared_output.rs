@@ -0,0 +1,133 @@
+use mpc_core::{
+    gadgets::CircuitBuilder,
+    message::{Step, HEADER_LEN},
+    simulate,
+    states::{Contributor, Evaluator, OutputMode, OutputShares},
+    Circuit, Error, MacCheck,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit_with_3_outputs;
+
+/// Runs the protocol locally in the shared output mode, returning the shares of both parties.
+fn execute_shared(
+    circuit: &Circuit,
+    input_contributor: &[bool],
+    input_evaluator: &[bool],
+) -> Result<(OutputShares, OutputShares), Error> {
+    let (contrib, msg_for_eval) =
+        Contributor::new(circuit, input_contributor, ChaCha20Rng::from_entropy())?;
+    let contrib = contrib.with_output_mode(OutputMode::Shared);
+    let eval = Evaluator::new(circuit, input_evaluator, ChaCha20Rng::from_entropy())?
+        .with_output_mode(OutputMode::Shared);
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    Ok((contrib.output_shares()?, eval.output_shares(&msg_for_eval)?))
+}
+
+#[test]
+fn test_open_shared_output() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    for (input_contributor, input_evaluator) in [
+        ([false, false], [true, true]),
+        ([true, true], [true, false]),
+        ([true, true], [true, true]),
+        ([false, true], [false, true]),
+    ] {
+        let (contrib_shares, eval_shares) =
+            execute_shared(&circuit, &input_contributor, &input_evaluator)?;
+        let expected = circuit.eval(&input_contributor, &input_evaluator)?;
+        let xored: Vec<bool> = contrib_shares
+            .bits()
+            .iter()
+            .zip(eval_shares.bits())
+            .map(|(a, b)| a ^ b)
+            .collect();
+        assert_eq!(xored, expected);
+
+        // both parties must open the same output after exchanging their shares:
+        assert_eq!(contrib_shares.open(&eval_shares.disclose()?)?, expected);
+        assert_eq!(eval_shares.open(&contrib_shares.disclose()?)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_shares_as_input_of_next_circuit() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    let input_contributor = [true, true];
+    let input_evaluator = [false, true];
+    let (contrib_shares, eval_shares) =
+        execute_shared(&circuit, &input_contributor, &input_evaluator)?;
+
+    // the next circuit computes the AND of the previous output bits:
+    let mut b = CircuitBuilder::new();
+    let previous = b.input_shared(3);
+    let all = b.and_all(&previous);
+    let next_circuit = b.build(vec![all]);
+    let output = simulate(&next_circuit, &contrib_shares.bits(), &eval_shares.bits())?;
+
+    let previous = circuit.eval(&input_contributor, &input_evaluator)?;
+    assert_eq!(output, vec![previous.iter().all(|bit| *bit)]);
+    Ok(())
+}
+
+#[test]
+fn test_tampered_shares() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    let (contrib_shares, eval_shares) = execute_shared(&circuit, &[true, false], &[true, true])?;
+    // every share is disclosed as a MAC (16 bytes) and a bit:
+    for (shares, other_shares) in [
+        (&contrib_shares, &eval_shares),
+        (&eval_shares, &contrib_shares),
+    ] {
+        let shares_start = HEADER_LEN + 8;
+        for offset in [0, 15, 16, 17 * 2 + 16] {
+            let i = shares_start + offset;
+            let mut msg = other_shares.disclose()?;
+            msg[i] ^= 1;
+            let expected = Error::MacError {
+                step: Some(Step::DisclosedShares),
+                check: MacCheck::OutputShare,
+                failed: 1,
+                index: Some(offset / 17),
+            };
+            assert_eq!(shares.open(&msg), Err(expected), "byte {i}");
+        }
+        let msg = other_shares.disclose()?;
+        assert_eq!(
+            shares.open(&msg[..msg.len() - 1]),
+            Err(Error::InvalidMessageLength)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_output_mode_mismatch() -> Result<(), Error> {
+    let circuit = circuit_with_3_outputs();
+    let input_contributor: &[bool] = &[true, false];
+    let input_evaluator: &[bool] = &[true, true];
+    let (contrib, msg_for_eval) =
+        Contributor::new(&circuit, input_contributor, ChaCha20Rng::from_entropy())?;
+    let eval = Evaluator::new(&circuit, input_evaluator, ChaCha20Rng::from_entropy())?
+        .with_output_mode(OutputMode::Shared);
+    let (contrib, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+    assert!(matches!(
+        contrib.output_shares(),
+        Err(Error::OutputModeMismatch)
+    ));
+    assert!(matches!(
+        eval.output_shares(&msg_for_eval),
+        Err(Error::OutputModeMismatch)
+    ));
+
+    let (contrib, _) = Contributor::new(&circuit, input_contributor, ChaCha20Rng::from_entropy())?;
+    let contrib = contrib.with_output_mode(OutputMode::Shared);
+    assert!(matches!(
+        contrib.output_shares(),
+        Err(Error::ProtocolStillInProgress)
+    ));
+    Ok(())
+}
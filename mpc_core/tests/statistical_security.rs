@@ -0,0 +1,172 @@
+use mpc_core::{
+    states::{Contributor, Evaluator, StatisticalSecurity},
+    Error,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+mod common;
+use common::circuit;
+
+#[test]
+fn test_bucket_sizes() -> Result<(), Error> {
+    let bits_40 = StatisticalSecurity::default();
+    assert_eq!(bits_40.bits(), 40);
+    assert_eq!(bits_40.bucket_size(1), 40);
+    assert_eq!(bits_40.bucket_size(1_000), 5);
+    assert_eq!(bits_40.bucket_size(3_100), 4);
+    assert_eq!(bits_40.bucket_size(280_000), 3);
+
+    let bits_60 = StatisticalSecurity::new(60)?;
+    assert_eq!(bits_60.bucket_size(1_000), 6);
+    assert_eq!(bits_60.bucket_size(10_000), 5);
+    assert_eq!(bits_60.bucket_size(280_000), 4);
+
+    let bits_80 = StatisticalSecurity::new(80)?;
+    assert_eq!(bits_80.bucket_size(1_000), 8);
+    assert_eq!(bits_80.bucket_size(100_000), 6);
+    assert_eq!(bits_80.bucket_size(1_000_000), 5);
+
+    // larger circuits never need larger buckets, more security never needs smaller buckets:
+    for bits in [1, 40, 60, 80, 128] {
+        let security = StatisticalSecurity::new(bits)?;
+        let mut previous = usize::MAX;
+        for and_gates in [1, 10, 100, 1_000, 10_000, 100_000, 1_000_000] {
+            let bucket_size = security.bucket_size(and_gates);
+            assert!(bucket_size >= 2 && bucket_size <= previous);
+            if bits < 128 {
+                let more_secure = StatisticalSecurity::new(bits + 1)?;
+                assert!(more_secure.bucket_size(and_gates) >= bucket_size);
+            }
+            previous = bucket_size;
+        }
+    }
+
+    assert_eq!(
+        StatisticalSecurity::new(0),
+        Err(Error::InvalidStatisticalSecurity)
+    );
+    assert_eq!(
+        StatisticalSecurity::new(129),
+        Err(Error::InvalidStatisticalSecurity)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_higher_statistical_security() -> Result<(), Error> {
+    let circuit = circuit();
+    let input_contributor = vec![true, false];
+    let input_evaluator = vec![true, true];
+    for bits in [60, 80] {
+        let security = StatisticalSecurity::new(bits)?;
+        let (contrib, msg_for_eval) = Contributor::with_statistical_security(
+            &circuit,
+            &input_contributor[..],
+            security,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        let eval = Evaluator::with_statistical_security(
+            &circuit,
+            &input_evaluator[..],
+            security,
+            ChaCha20Rng::from_entropy(),
+        )?;
+        let (_, eval, msg_for_eval) = common::run_steps(contrib, eval, msg_for_eval)?;
+        let output = eval.output(&msg_for_eval)?;
+        assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_higher_statistical_security_with_late_inputs() -> Result<(), Error> {
+    let circuit = circuit();
+    let input_contributor = vec![false, true];
+    let input_evaluator = vec![true, true];
+    for bits in [60, 80] {
+        let security = StatisticalSecurity::new(bits)?;
+        let (contrib, msg_for_eval) =
+            Contributor::new_without_input(&circuit, security, ChaCha20Rng::from_entropy())?;
+        let eval = Evaluator::new_without_input(&circuit, security, ChaCha20Rng::from_entropy())?;
+        let (_, eval, msg_for_eval) = common::run_steps_with_late_inputs(
+            contrib,
+            eval,
+            msg_for_eval,
+            input_contributor.clone(),
+            input_evaluator.clone(),
+        )?;
+        let output = eval.output(&msg_for_eval)?;
+        assert_eq!(output, circuit.eval(&input_contributor, &input_evaluator)?);
+    }
+
+    // the parties must still agree on the statistical security:
+    let bits_80 = StatisticalSecurity::new(80)?;
+    let (_, msg_for_eval) = Contributor::<_, Vec<bool>>::new_without_input(
+        &circuit,
+        bits_80,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::<_, Vec<bool>>::new_without_input(
+        &circuit,
+        StatisticalSecurity::default(),
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::StatisticalSecurityMismatch)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_statistical_security_mismatch() -> Result<(), Error> {
+    let circuit = circuit();
+    let input = [true, false];
+    let bits_40 = StatisticalSecurity::default();
+    let bits_80 = StatisticalSecurity::new(80)?;
+
+    // the evaluator rejects the initial message of the contributor:
+    let (_, msg_for_eval) = Contributor::with_statistical_security(
+        &circuit,
+        &input[..],
+        bits_80,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_statistical_security(
+        &circuit,
+        &input[..],
+        bits_40,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(matches!(
+        eval.run(&msg_for_eval),
+        Err(Error::StatisticalSecurityMismatch)
+    ));
+
+    // the contributor rejects the first message of the evaluator:
+    let (_, msg_for_eval) = Contributor::with_statistical_security(
+        &circuit,
+        &input[..],
+        bits_40,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let eval = Evaluator::with_statistical_security(
+        &circuit,
+        &input[..],
+        bits_40,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    let (_, msg_for_contrib) = eval.run(&msg_for_eval)?;
+    let (contrib, _) = Contributor::with_statistical_security(
+        &circuit,
+        &input[..],
+        bits_80,
+        ChaCha20Rng::from_entropy(),
+    )?;
+    assert!(matches!(
+        contrib.run(&msg_for_contrib),
+        Err(Error::StatisticalSecurityMismatch)
+    ));
+    Ok(())
+}
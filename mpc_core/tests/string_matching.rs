@@ -0,0 +1,160 @@
+use mpc_core::{
+    gadgets::{
+        dfa::{Dfa, PrivateDfa},
+        strings::{self, constant_string, input_string, string_to_bits},
+        CircuitBuilder, Party,
+    },
+    simulate, Error,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use regex::bytes::Regex;
+
+const ALPHABET: &[u8] = b"abcx01_. \n";
+
+const PATTERNS: &[&str] = &[
+    "abc",
+    "^ab",
+    "b$",
+    "^a.*b$",
+    "a|bc",
+    "(ab)+",
+    "^(?:ab|ba){2}$",
+    "a{2,3}",
+    "^a{2,}$",
+    "[a-c]x?",
+    "[^ab]",
+    "^[^ab]*$",
+    "\\d+\\.\\d",
+    "^$",
+    "a*",
+    "(a|b)*c",
+    "x.y",
+    "\\w\\s\\w",
+    "\\S\\x2e",
+    "^[0-9]{2}$",
+    "[]a]b",
+    "a+?b",
+    "^\\W",
+    "(^a|b$)",
+];
+
+fn random_string(rng: &mut ChaCha20Rng, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())])
+        .collect()
+}
+
+#[test]
+fn test_string_gadgets() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    for _ in 0..50 {
+        let len = rng.gen_range(1..8);
+        let needle_len = rng.gen_range(0..3);
+        let s = random_string(&mut rng, len);
+        let needle = random_string(&mut rng, needle_len);
+
+        let mut b = CircuitBuilder::new();
+        let x = input_string(&mut b, Party::Evaluator, s.len());
+        let y = input_string(&mut b, Party::Contributor, needle.len());
+        let blocklist = vec![constant_string(&mut b, b"ab"), y.clone()];
+        let outputs = vec![
+            strings::eq(&mut b, &x, &y),
+            strings::starts_with(&mut b, &x, &y),
+            strings::ends_with(&mut b, &x, &y),
+            strings::contains(&mut b, &x, &y),
+            strings::eq_any(&mut b, &x, &blocklist),
+        ];
+        let circuit = b.build(outputs);
+        let output = circuit.eval(&string_to_bits(&needle), &string_to_bits(&s))?;
+
+        let contains = needle.is_empty() || s.windows(needle.len()).any(|w| w == needle);
+        let expected = vec![
+            s == needle,
+            s.starts_with(&needle),
+            s.ends_with(&needle),
+            contains,
+            s == b"ab" || s == needle,
+        ];
+        assert_eq!(output, expected, "{s:?} / {needle:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dfa_matches_regex_semantics() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    for pattern in PATTERNS {
+        let regex = Regex::new(&format!("(?-u){pattern}")).unwrap();
+        let dfa = Dfa::from_regex(pattern)?;
+        assert_eq!(
+            dfa.matches(b""),
+            regex.is_match(b""),
+            "{pattern} on empty string"
+        );
+        for len in 1..7 {
+            let mut b = CircuitBuilder::new();
+            let input = input_string(&mut b, Party::Evaluator, len);
+            let matches = dfa.matches_public(&mut b, &input);
+            let circuit = b.build(vec![matches]);
+
+            for _ in 0..20 {
+                let s = random_string(&mut rng, len);
+                let expected = regex.is_match(&s);
+                assert_eq!(dfa.matches(&s), expected, "{pattern} on {s:?}");
+                let output = circuit.eval(&[], &string_to_bits(&s))?;
+                assert_eq!(output, vec![expected], "{pattern} on {s:?}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_private_dfa() -> Result<(), Error> {
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    let states = 6;
+    let len = 5;
+    let mut b = CircuitBuilder::new();
+    let dfa = PrivateDfa::from_inputs(&mut b, Party::Contributor, states);
+    let input = input_string(&mut b, Party::Evaluator, len);
+    let matches = dfa.matches(&mut b, &input);
+    let circuit = b.build(vec![matches]);
+
+    for pattern in ["abc", "^ab", "b$", "a|bc", "[^ab]", "^[0-9]{2}"] {
+        let regex = Regex::new(&format!("(?-u){pattern}")).unwrap();
+        let dfa = Dfa::from_regex(pattern)?;
+        let table = dfa.to_private_input(states)?;
+        assert_eq!(table.len(), PrivateDfa::input_len(states));
+        for _ in 0..20 {
+            let s = random_string(&mut rng, len);
+            let output = circuit.eval(&table, &string_to_bits(&s))?;
+            assert_eq!(output, vec![regex.is_match(&s)], "{pattern} on {s:?}");
+        }
+    }
+
+    let too_large = Dfa::from_regex("abcdefgh")?;
+    assert_eq!(
+        too_large.to_private_input(states),
+        Err(Error::InvalidPattern)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dfa_secure_execution() -> Result<(), Error> {
+    let dfa = Dfa::from_regex("^[a-z]+@[a-z]+\\.(com|org)$")?;
+    let mut b = CircuitBuilder::new();
+    let input = input_string(&mut b, Party::Evaluator, 9);
+    let matches = dfa.matches_public(&mut b, &input);
+    let circuit = b.build(vec![matches]);
+
+    for (s, expected) in [(b"ab@cd.com", true), (b"ab@cd.net", false)] {
+        let output = simulate(&circuit, &[], &string_to_bits(s))?;
+        assert_eq!(output, vec![expected]);
+    }
+    Ok(())
+}
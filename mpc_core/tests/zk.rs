@@ -0,0 +1,151 @@
+use mpc_core::{
+    gadgets::{int, to_bits, CircuitBuilder, Party},
+    zk::{Prover, Verifier},
+    Circuit, Error, MacCheck,
+};
+use mpc_garble_interop::{check_program, compile_program, serialize_input, Role};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Runs the proof, letting `tamper` modify every message, identified by the number of messages
+/// sent before it.
+fn prove(
+    circuit: &Circuit,
+    witness: &[bool],
+    mut tamper: impl FnMut(usize, &mut Vec<u8>),
+) -> Result<bool, Error> {
+    let rng = ChaCha20Rng::from_entropy();
+    let (mut prover, mut msg) = Prover::new(circuit, witness, rng)?;
+    let rng = ChaCha20Rng::from_entropy();
+    let mut verifier = Verifier::new(circuit, rng)?;
+    let mut sent = 0;
+    for _ in 0..prover.steps() {
+        tamper(sent, &mut msg);
+        let (next_verifier, mut reply) = verifier.run(&msg)?;
+        tamper(sent + 1, &mut reply);
+        let (next_prover, next_msg) = prover.run(&reply)?;
+        (verifier, prover, msg) = (next_verifier, next_prover, next_msg);
+        sent += 2;
+    }
+    tamper(sent, &mut msg);
+    let (verifier, mut reveal) = verifier.run(&msg)?;
+    tamper(sent + 1, &mut reveal);
+    let mut opening = prover.open(&reveal)?;
+    tamper(sent + 2, &mut opening);
+    verifier.output(&opening)
+}
+
+/// Proves that a secret 32-bit balance is at least `threshold`.
+fn balance_at_least(threshold: u64) -> Circuit {
+    let mut b = CircuitBuilder::new();
+    let balance = b.input_word(Party::Evaluator, 32);
+    let enough = int::ge_const(&mut b, &balance, threshold);
+    b.build(vec![enough])
+}
+
+#[test]
+fn test_true_statements_are_accepted() {
+    let circuit = balance_at_least(1000);
+    for balance in [1000, 1001, 123_456, u32::MAX as u64] {
+        let witness = to_bits(balance, 32);
+        assert_eq!(prove(&circuit, &witness, |_, _| {}), Ok(true));
+    }
+}
+
+#[test]
+fn test_false_statements_are_rejected() {
+    let circuit = balance_at_least(1000);
+    for balance in [0, 1, 999] {
+        let witness = to_bits(balance, 32);
+        assert_eq!(prove(&circuit, &witness, |_, _| {}), Ok(false));
+    }
+
+    // every output must be true:
+    let mut b = CircuitBuilder::new();
+    let x = b.input(Party::Evaluator);
+    let not_x = b.not(x);
+    let circuit = b.build(vec![x, not_x]);
+    assert_eq!(prove(&circuit, &[true], |_, _| {}), Ok(false));
+    assert_eq!(prove(&circuit, &[false], |_, _| {}), Ok(false));
+}
+
+#[test]
+fn test_prove_garble_program() -> Result<(), String> {
+    let program = "
+pub fn main(balance: u32, debt: u32) -> bool {
+    balance > debt + 500u32
+}
+";
+    let prg = check_program(program)?;
+    let circuit = compile_program(&prg, "main")?;
+    // the compiled circuit also outputs whether and why it panicked, the statement is the `bool`:
+    let gates = circuit.gates.gates().to_vec();
+    let result = *circuit.gates.output_gates().last().unwrap();
+    let statement = Circuit::new(gates, vec![result]);
+    for (balance, debt, expected) in [("2000u32", "1000u32", true), ("1400u32", "1000u32", false)] {
+        let mut witness = serialize_input(Role::Contributor, &prg, &circuit.fn_def, balance)?;
+        witness.extend(serialize_input(
+            Role::Evaluator,
+            &prg,
+            &circuit.fn_def,
+            debt,
+        )?);
+        let proven = prove(&statement, &witness, |_, _| {}).map_err(|e| e.to_string())?;
+        assert_eq!(proven, expected);
+    }
+    Ok(())
+}
+
+fn failed_check(result: Result<bool, Error>) -> Option<MacCheck> {
+    match result {
+        Err(Error::MacError { check, .. }) => Some(check),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_cheating_verifier_is_detected_by_the_prover() {
+    let circuit = balance_at_least(1000);
+    let witness = to_bits(5000, 32);
+
+    // a wrong garbled table, the 4th message:
+    let result = prove(&circuit, &witness, |sent, msg| {
+        if sent == 3 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert_eq!(failed_check(result), Some(MacCheck::ProofTable));
+
+    // a wrong `Delta`, revealed in the 6th message before the keys of the additional OTs:
+    let result = prove(&circuit, &witness, |sent, msg| {
+        if sent == 5 {
+            let delta = mpc_core::message::HEADER_LEN;
+            msg[delta] ^= 2;
+        }
+    });
+    assert_eq!(failed_check(result), Some(MacCheck::ProofDelta));
+}
+
+#[test]
+fn test_cheating_prover_is_detected_by_the_verifier() {
+    let circuit = balance_at_least(1000);
+    let witness = to_bits(5, 32);
+
+    // an opening that does not match the commitment:
+    let result = prove(&circuit, &witness, |sent, msg| {
+        if sent == 6 {
+            let last = msg.len() - 1;
+            msg[last] ^= 1;
+        }
+    });
+    assert_eq!(failed_check(result), Some(MacCheck::ProofCommitment));
+}
+
+#[test]
+fn test_invalid_witness_is_rejected() {
+    let circuit = balance_at_least(1000);
+    let rng = ChaCha20Rng::from_entropy();
+    let prover = Prover::new(&circuit, &[true; 31], rng);
+    assert!(matches!(prover, Err(Error::InsufficientInput)));
+}
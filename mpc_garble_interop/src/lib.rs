@@ -46,7 +46,10 @@ pub fn compile_program(prg: &TypedProgram, fn_name: &str) -> Result<TypedCircuit
     let (circuit, fn_def) = prg.compile(fn_name).map_err(|e| format!("{:?}", e))?;
     let info_about_gates = circuit.report_gates();
     if circuit.input_gates.len() != 2 {
-        return Err("The main function is not a 2-Party function".to_string());
+        return Err(
+            "The main function is not a 2-Party function, see `compile_multiparty_program`"
+                .to_string(),
+        );
     }
 
     // Garble script semantics are as follows: input at index `i` implicitly belongs to party `i`
@@ -61,6 +64,40 @@ pub fn compile_program(prg: &TypedProgram, fn_name: &str) -> Result<TypedCircuit
     gates.resize(input_party_a, mpc_core::Gate::InContrib);
     gates.resize(input_party_a + input_party_b, mpc_core::Gate::InEval);
 
+    Ok(typed_circuit(gates, circuit, fn_def, info_about_gates))
+}
+
+/// Compiles the (type-checked) program of three or more parties for the
+/// [`multiparty`](mpc_core::multiparty) protocol, producing a circuit of gates.
+///
+/// The input of the `i`-th function parameter belongs to party `i`, which is also the index of
+/// the party in the protocol.
+pub fn compile_multiparty_program(prg: &TypedProgram, fn_name: &str) -> Result<TypedCircuit> {
+    let (circuit, fn_def) = prg.compile(fn_name).map_err(|e| format!("{:?}", e))?;
+    let info_about_gates = circuit.report_gates();
+    let parties = circuit.input_gates.len();
+    if parties < 2 || parties > mpc_core::PartyIndex::MAX as usize + 1 {
+        return Err(format!(
+            "The main function has {parties} parameters, but the protocol supports 2 to 256 parties"
+        ));
+    }
+
+    let inputs: usize = circuit.input_gates.iter().sum();
+    let mut gates: Vec<mpc_core::Gate> = Vec::with_capacity(circuit.gates.len() + inputs);
+    for (party, input_gates) in circuit.input_gates.iter().enumerate() {
+        let gate = mpc_core::Gate::InParty(party as mpc_core::PartyIndex);
+        gates.resize(gates.len() + input_gates, gate);
+    }
+    Ok(typed_circuit(gates, circuit, fn_def, info_about_gates))
+}
+
+/// Appends the gates of the Garble circuit to the input gates.
+fn typed_circuit(
+    mut gates: Vec<mpc_core::Gate>,
+    circuit: garble_lang::circuit::Circuit,
+    fn_def: &TypedFnDef,
+    info_about_gates: String,
+) -> TypedCircuit {
     // as Garble and mpc_core are independent code bases right now, we must currently map
     // between the 2 type systems in this rather straight-forward way.
     for gate in circuit.gates {
@@ -84,13 +121,12 @@ pub fn compile_program(prg: &TypedProgram, fn_name: &str) -> Result<TypedCircuit
         .collect();
     let program = mpc_core::Circuit::new(gates, output_gates);
 
-    Ok(TypedCircuit {
+    TypedCircuit {
         gates: program,
         fn_def: fn_def.clone(),
         info_about_gates,
-    })
+    }
 }
-
 /// Returns the Garble type of the input associated with the specified role.
 ///
 /// In the case of the contributor, the result will be the type of the _first_ function parameter.
@@ -102,6 +138,15 @@ pub fn input_type(role: Role, fn_def: &TypedFnDef) -> &'_ Type {
     }
 }
 
+/// Returns the Garble type of the input of the specified party of a multi-party program, which is
+/// the type of the function parameter at the index of the party.
+pub fn party_input_type(party: usize, fn_def: &TypedFnDef) -> Result<&'_ Type> {
+    match fn_def.params.get(party) {
+        Some(param) => Ok(&param.ty),
+        None => Err(format!("The main function has no parameter for party {party}")),
+    }
+}
+
 /// Parses an input string as a Garble literal.
 pub fn parse_input(
     role: Role,
@@ -126,6 +171,20 @@ pub fn serialize_input(
     Ok(input.as_bits(prg, &const_sizes))
 }
 
+/// Parses an input string of the specified party of a multi-party program as a Garble literal and
+/// encodes it as input bits for the mpc_core engine.
+pub fn serialize_party_input(
+    party: usize,
+    prg: &TypedProgram,
+    fn_def: &TypedFnDef,
+    input: &str,
+) -> Result<Vec<bool>> {
+    let input_ty = party_input_type(party, fn_def)?;
+    let input = Literal::parse(prg, input_ty, input).map_err(|e| e.prettify(input))?;
+    let const_sizes = std::collections::HashMap::new();
+    Ok(input.as_bits(prg, &const_sizes))
+}
+
 /// Decodes output bits from the mpc_core engine as a Garble literal.
 pub fn deserialize_output(
     prg: &TypedProgram,
@@ -1,5 +1,5 @@
 use assert_cmd::prelude::CommandCargoExt;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use std::process::{Child, Command, Stdio};
 
 const SERVER_CRATE: &str = "mpc_http_server";
@@ -29,9 +29,9 @@ where
 }
 
 fn start_server(path: &str) -> Result<(Child, String), Box<dyn std::error::Error>> {
-    let port: u16 = thread_rng().gen_range(8001..=9000);
+    let port: u16 = rand::rng().random_range(8001..=9000);
     let port_str = port.to_string();
-    let mut cmd = Command::cargo_bin(&SERVER_CRATE)?;
+    let mut cmd = Command::cargo_bin(SERVER_CRATE)?;
     let mut proc = cmd
         .current_dir(path)
         .env("ROCKET_PORT", port_str)
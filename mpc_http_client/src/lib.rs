@@ -21,7 +21,10 @@ use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
 use reqwest::Response;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt};
-use mpc_core::{states::Msg, Circuit, CircuitBlake3Hash};
+use mpc_core::{
+    states::{Evaluator, Msg},
+    Circuit, CircuitBlake3Hash,
+};
 use mpc_garble_interop::{
     check_program, compile_program, deserialize_output, parse_input, Role, TypedCircuit,
 };
@@ -108,12 +111,12 @@ impl MpcData {
     /// }";
     ///
     /// let card_guess_program =
-    ///     mpc_core_http_client::MpcProgram::new(source_code.to_string(), "card_guess".to_string()).unwrap();
+    ///     mpc_http_client::MpcProgram::new(source_code.to_string(), "card_guess".to_string()).unwrap();
     ///
     /// let player_card_string = "Card {suit: Suit::Diamonds, value: Value::Jack}";
     ///
     /// let player_card =
-    ///     mpc_core_http_client::MpcData::from_string(&card_guess_program, player_card_string.to_string())
+    ///     mpc_http_client::MpcData::from_string(&card_guess_program, player_card_string.to_string())
     ///         .unwrap();
     ///
     /// assert_eq!(
@@ -135,7 +138,7 @@ impl MpcData {
     /// Type-checks a Garble literal, returning it as MpcData.
     /// ```
     ///
-    /// use mpc_core_http_client::{Literal, VariantLiteral};
+    /// use mpc_http_client::{Literal, VariantLiteral};
     ///
     /// let source_code = "pub fn card_guess(house: Card, player: Card) -> bool {
     ///     house == player
@@ -182,10 +185,10 @@ impl MpcData {
     /// );
     ///
     /// let card_guess_program =
-    ///     mpc_core_http_client::MpcProgram::new(source_code.to_string(), "card_guess".to_string()).unwrap();
+    ///     mpc_http_client::MpcProgram::new(source_code.to_string(), "card_guess".to_string()).unwrap();
     ///
     /// let player_card =
-    ///     mpc_core_http_client::MpcData::from_literal(&card_guess_program, player_card_literal)
+    ///     mpc_http_client::MpcData::from_literal(&card_guess_program, player_card_literal)
     ///         .unwrap();
     ///
     /// assert_eq!(
@@ -233,7 +236,7 @@ impl MpcData {
     /// }";
     ///
     /// let card_game_program =
-    ///     mpc_core_http_client::MpcProgram::new(source_code.to_string(), "card_game".to_string()).unwrap();
+    ///     mpc_http_client::MpcProgram::new(source_code.to_string(), "card_game".to_string()).unwrap();
     ///
     /// let json_string = "{
     ///     \"Struct\": [
@@ -265,7 +268,7 @@ impl MpcData {
     ///
     /// let js_value_literal = serde_json::from_str(json_string);
     ///
-    /// let player_card = mpc_core_http_client::MpcData::from_object(&card_guess_program, js_value_literal);
+    /// let player_card = mpc_http_client::MpcData::from_object(&card_guess_program, js_value_literal);
     ///
     /// assert_eq!(
     ///     player_card.to_literal_string(),
@@ -335,17 +338,23 @@ pub async fn compute(
         return Err(ValidationError::InvalidInput.into());
     }
 
-    let client = mpc_coreClient::new(&url);
+    let client = MpcCoreClient::new(&url);
     let TypedCircuit { gates, fn_def, .. } = program.circuit;
-    let session = client
+    let circuit_hash = gates.blake3_hash();
+    let evaluator = Evaluator::new(gates, my_input, ChaCha20Rng::from_entropy())?;
+    // the client opens the protocol, so that the request creating the session already carries
+    // the first message and the server can reply in the same response:
+    let (evaluator, opening) = evaluator.initiate()?;
+    let (session, upstream_msgs) = client
         .new_session(
-            &gates,
+            circuit_hash,
             program.source_code.clone(),
             program.function_name.clone(),
             plaintext_metadata,
+            opening.clone(),
         )
         .await?;
-    let result = session.evaluate(gates, my_input).await?;
+    let result = session.evaluate(evaluator, opening, upstream_msgs).await?;
     let literal =
         deserialize_output(&program.ast, &fn_def, &result).map_err(GarbleCompileTimeError)?;
     Ok(MpcData { literal })
@@ -354,11 +363,11 @@ pub async fn compute(
 type MessageLog = Vec<(Msg, MessageId)>;
 
 #[derive(Debug)]
-struct mpc_coreClient {
+struct MpcCoreClient {
     url: Url,
 }
 
-struct mpc_coreSession {
+struct MpcCoreSession {
     url: Url,
     request_headers: HashMap<String, String>,
 }
@@ -370,6 +379,7 @@ struct NewSession {
     function: String,
     circuit_hash: CircuitBlake3Hash,
     client_version: String,
+    opening_message: Option<Msg>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -377,62 +387,64 @@ struct EngineCreationResult {
     engine_id: String,
     request_headers: HashMap<String, String>,
     server_version: String,
+    messages: MessageLog,
 }
 
-impl mpc_coreClient {
+impl MpcCoreClient {
     fn new(url: &Url) -> Self {
         Self { url: url.clone() }
     }
 
+    /// Creates a session on the server, returning it together with the first messages of the
+    /// server, which already reply to the `opening` message.
     async fn new_session(
         &self,
-        circuit: &Circuit,
+        circuit_hash: CircuitBlake3Hash,
         source_code: String,
         function: String,
         plaintext_metadata: String,
-    ) -> Result<mpc_coreSession, Error> {
+        opening: Msg,
+    ) -> Result<(MpcCoreSession, MessageLog), Error> {
         let client_version = env!("CARGO_PKG_VERSION").to_string();
         let req = NewSession {
             plaintext_metadata,
             program: source_code,
             function,
-            circuit_hash: circuit.blake3_hash(),
+            circuit_hash,
             client_version: client_version.clone(),
+            opening_message: Some(opening),
         };
         let EngineCreationResult {
             engine_id,
             request_headers,
             server_version: _server_version,
+            messages,
         } = send_new_session(self.url.clone(), &req).await?;
         let url = self.url.join(&engine_id)?;
 
-        Ok(mpc_coreSession {
+        let session = MpcCoreSession {
             url,
             request_headers,
-        })
+        };
+        Ok((session, messages))
     }
 }
 
-impl mpc_coreSession {
-    async fn evaluate(self, circuit: Circuit, input: Vec<bool>) -> Result<Vec<bool>, Error> {
+impl MpcCoreSession {
+    async fn evaluate(
+        self,
+        mut evaluator: Evaluator<Circuit, Vec<bool>>,
+        opening: Msg,
+        mut upstream_msgs: MessageLog,
+    ) -> Result<Vec<bool>, Error> {
         let mut context = MsgQueue::new();
-        let mut evaluator =
-            mpc_core::states::Evaluator::new(circuit, input, ChaCha20Rng::from_entropy())?;
+        // the opening message was received by the server together with the new session:
+        context.send(opening);
+        context.flush_queue(0);
 
         let mut last_durably_received_offset: Option<MessageId> = None;
         let mut steps_remaining = evaluator.steps();
         loop {
-            let messages: Vec<(&Msg, MessageId)> = context.msgs_iter().collect();
-            let (upstream_msgs, server_commited_offset) =
-                self.dialog(last_durably_received_offset, &messages).await?;
-            if messages.last().map(|v| v.1) != server_commited_offset {
-                return Err(Error::MessageOffsetMismatch);
-            }
-
-            if let Some(last_durably_received_offset) = server_commited_offset {
-                context.flush_queue(last_durably_received_offset);
-            }
-
             for (msg, server_offset) in &upstream_msgs {
                 if *server_offset != last_durably_received_offset.map(|o| o + 1).unwrap_or(0) {
                     return Err(Error::MessageOffsetMismatch);
@@ -448,6 +460,18 @@ impl mpc_coreSession {
                 }
                 last_durably_received_offset = Some(*server_offset);
             }
+
+            let messages: Vec<(&Msg, MessageId)> = context.msgs_iter().collect();
+            let (msgs, server_commited_offset) =
+                self.dialog(last_durably_received_offset, &messages).await?;
+            if messages.last().map(|v| v.1) != server_commited_offset {
+                return Err(Error::MessageOffsetMismatch);
+            }
+
+            if let Some(last_durably_received_offset) = server_commited_offset {
+                context.flush_queue(last_durably_received_offset);
+            }
+            upstream_msgs = msgs;
         }
     }
 
@@ -523,6 +547,7 @@ pub enum Error {
     /// The MPC program or the input is invalid.
     ValidationError(ValidationError),
     /// An error occurred during the client's execution of the MPC protocol.
+    #[allow(non_camel_case_types)]
     mpc_coreError(mpc_core::Error),
     /// A message could not be serialized/deserialized.
     BincodeError,
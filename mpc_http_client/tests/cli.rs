@@ -2,8 +2,9 @@
 
 use assert_cmd::prelude::*; // Add methods on commands
 use predicates::prelude::*; // Used for writing assertions
-use rand::{prelude::*, random, thread_rng};
+use rand::{prelude::*, random};
 use std::process::{Child, Command, Stdio}; // Run programs
+use std::sync::Once;
 
 const CRATE_NAME: &str = "mpc_http_client";
 const SERVER_CRATE: &str = "mpc_server";
@@ -51,7 +52,7 @@ fn integration_test_and() -> Result<(), Box<dyn std::error::Error>> {
 
             let mut cmd = new_command(
                 connection_string,
-                "tests/.add.garble.rs",
+                "tests/.add.garble.sdfl",
                 "main",
                 &format!("{party_a}u8"),
                 &format!("{party_b}u8"),
@@ -103,6 +104,7 @@ fn new_command(
     input: &str,
     metadata: &str,
 ) -> Result<Command, Box<dyn std::error::Error>> {
+    build_binaries();
     let mut cmd = Command::cargo_bin(CRATE_NAME)?;
     cmd.arg(program)
         .args(["--function", function, "--url", url])
@@ -114,19 +116,34 @@ fn new_command(
     Ok(cmd)
 }
 
-fn start_server() -> Result<(Child, String), Box<dyn std::error::Error>> {
-    if cfg!(not(tarpaulin)) {
-        println!("Compiling mpc_http_server, this might take a few minutes");
-        Command::new("cargo")
-            .arg("build")
-            .arg("--features=bin")
+/// Builds the client and the server binaries once, in the same profile as the tests, so that
+/// `cargo_bin` finds them in `target/debug` or `target/release`.
+fn build_binaries() {
+    static BUILD: Once = Once::new();
+    BUILD.call_once(|| {
+        if cfg!(tarpaulin) {
+            return;
+        }
+        println!("Compiling mpc_http_client and mpc_http_server, this might take a few minutes");
+        let mut cmd = Command::new("cargo");
+        cmd.args(["build", "-p", CRATE_NAME, "-p", "mpc_http_server"])
+            .arg("--features=mpc_http_client/bin,mpc_http_server/bin");
+        if !cfg!(debug_assertions) {
+            cmd.arg("--release");
+        }
+        let status = cmd
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
-            .output()
+            .status()
             .unwrap();
+        assert!(status.success(), "could not compile the binaries");
         println!("Compilation finished");
-    }
-    let port: u16 = thread_rng().gen_range(8001..=9000);
+    });
+}
+
+fn start_server() -> Result<(Child, String), Box<dyn std::error::Error>> {
+    build_binaries();
+    let port: u16 = rand::rng().random_range(8001..=9000);
     let port_str = port.to_string();
     let mut cmd = Command::cargo_bin(SERVER_CRATE)?;
     let mut proc = cmd
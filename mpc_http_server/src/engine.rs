@@ -7,7 +7,6 @@ use crate::{
     state::{EngineRef, EngineRegistry},
     types::{EngineCreationResult, HandleMpcRequestFn},
 };
-use rand::Rng;
 use rand_chacha::{rand_core::{SeedableRng, RngCore}, ChaCha20Rng};
 use rocket::{
     data::ToByteUnit,
@@ -72,11 +71,18 @@ pub(crate) fn create_session(
     ];
     let engine_id = uuid::Builder::from_random_bytes(random_bytes).into_uuid();
     let engine_id = engine_id.to_string();
-    let er = Arc::new(Mutex::new(EngineRef::new(
+    let engine = EngineRef::new(
         rng,
         handled.circuit,
         handled.input_from_server,
-    )?));
+        request.opening_message.as_ref(),
+    )?;
+    let messages = engine
+        .dump_messages()
+        .into_iter()
+        .map(|(msg, id)| (msg.clone(), id))
+        .collect();
+    let er = Arc::new(Mutex::new(engine));
     let inserted = r.insert_engine(engine_id.clone(), er);
 
     if !inserted {
@@ -87,6 +93,7 @@ pub(crate) fn create_session(
         engine_id: engine_id.clone(),
         request_headers: handled.request_headers,
         server_version,
+        messages,
     };
 
     let c = Created::new(uri!(dialog(engine_id)).to_string()).body(Json(body));
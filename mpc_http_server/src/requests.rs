@@ -1,5 +1,5 @@
 use rocket::serde::{Deserialize, Serialize};
-use mpc_core::CircuitBlake3Hash;
+use mpc_core::{states::Msg, CircuitBlake3Hash};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(crate = "rocket::serde")]
@@ -9,4 +9,7 @@ pub struct NewSession {
     pub function: String,
     pub circuit_hash: CircuitBlake3Hash,
     pub client_version: String,
+    /// The opening message of a client that starts the protocol itself.
+    #[serde(default)]
+    pub opening_message: Option<Msg>,
 }
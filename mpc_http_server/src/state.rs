@@ -24,18 +24,26 @@ pub(crate) struct EngineRef {
 }
 
 impl EngineRef {
-    pub fn new(rng: ChaCha20Rng, program: Circuit, input: Vec<bool>) -> Result<Self, Error> {
-        let mut context = MsgQueue::new();
+    /// Starts the protocol, in reply to the `opening` message of the client if the client opened
+    /// it, which then counts as the first message of the client.
+    pub fn new(
+        rng: ChaCha20Rng,
+        program: Circuit,
+        input: Vec<bool>,
+        opening: Option<&Msg>,
+    ) -> Result<Self, Error> {
         let (contrib, initial_msg) = Contributor::new(program, input, rng)?;
-        let steps_remaining = contrib.steps();
-        context.send(initial_msg);
-
-        Ok(Self {
-            context,
+        let mut engine = Self {
+            context: MsgQueue::new(),
+            steps_remaining: contrib.steps(),
             mpc: Some(contrib),
-            steps_remaining,
             last_durably_received_client_event_offset: None,
-        })
+        };
+        match opening {
+            Some(opening) => engine.process_message(opening, 0)?,
+            None => engine.context.send(initial_msg),
+        }
+        Ok(engine)
     }
 
     pub fn process_message(&mut self, msg: &Msg, offset: MessageId) -> Result<(), Error> {
@@ -18,7 +18,7 @@ use mpc_core::{
 use mpc_garble_interop::{
     check_program, compile_program, deserialize_output, serialize_input, Role, TypedCircuit,
 };
-use rand::SeedableRng;
+use rand_chacha::rand_core::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 use rocket::{
     http::Status,
@@ -52,10 +52,10 @@ pub fn _rocket() -> _ {
 fn test_multiple_engines() {
     let client = &Client::tracked(_rocket()).unwrap();
 
-    let r1 = new_session(client, xor_and_program(), "false".to_string());
+    let r1 = new_session(client, xor_and_program(), "false".to_string(), None);
     assert_eq!(r1.status(), Status::Created);
 
-    let r2 = new_session(client, xor_and_program(), "false".to_string());
+    let r2 = new_session(client, xor_and_program(), "false".to_string(), None);
     assert_eq!(r2.status(), Status::Created);
 
     assert_ne!(
@@ -68,14 +68,14 @@ fn test_multiple_engines() {
 fn test_delete_session() {
     let client = &Client::tracked(_rocket()).unwrap();
 
-    let r1 = new_session(client, xor_and_program(), "false".to_string());
+    let r1 = new_session(client, xor_and_program(), "false".to_string(), None);
     assert_eq!(r1.status(), Status::Created);
 
     let EngineCreationResult { engine_id, .. } = r1.into_json().unwrap();
     let r3 = delete_session(client, &engine_id);
     assert_eq!(r3.status(), Status::Ok);
 
-    let r4 = new_session(client, xor_and_program(), "false".to_string());
+    let r4 = new_session(client, xor_and_program(), "false".to_string(), None);
     assert_eq!(r4.status(), Status::Created);
 }
 
@@ -84,54 +84,70 @@ fn test_protocol_xor_and() {
     let client = &Client::tracked(_rocket()).unwrap();
     let program = xor_and_program();
 
-    for input_party_a in [false, true] {
-        for input_party_b in [false, true] {
-            let r1 = new_session(client, program.clone(), input_party_a.to_string());
-            assert_eq!(r1.status(), Status::Created);
-
-            let EngineCreationResult { engine_id, .. } = r1.into_json().unwrap();
-            let prg = check_program(&program).unwrap();
-            let TypedCircuit { gates, fn_def, .. } = compile_program(&prg, "main").unwrap();
-            let result = mpc_http_protocol(client, &engine_id, gates, vec![input_party_b]);
-            let const_sizes = HashMap::new();
-            let result = deserialize_output(&prg, &fn_def, &result)
-                .unwrap()
-                .as_bits(&prg, &const_sizes);
-            println!("{input_party_a}, {input_party_b} -> {result:?}");
-            assert_eq!(
-                result,
-                vec![input_party_a ^ input_party_b, input_party_a & input_party_b]
-            );
+    for evaluator_first in [false, true] {
+        for input_party_a in [false, true] {
+            for input_party_b in [false, true] {
+                let prg = check_program(&program).unwrap();
+                let TypedCircuit { gates, fn_def, .. } = compile_program(&prg, "main").unwrap();
+                let evaluator =
+                    Evaluator::new(gates, vec![input_party_b], ChaCha20Rng::from_entropy())
+                        .unwrap();
+                let mut context = MsgQueue::new();
+                let (evaluator, opening) = if evaluator_first {
+                    let (evaluator, opening) = evaluator.initiate().unwrap();
+                    // the opening message is sent with the new session, as the message 0:
+                    context.send(opening.clone());
+                    context.flush_queue(0);
+                    (evaluator, Some(opening))
+                } else {
+                    (evaluator, None)
+                };
+
+                let r1 = new_session(client, program.clone(), input_party_a.to_string(), opening);
+                assert_eq!(r1.status(), Status::Created);
+
+                let EngineCreationResult {
+                    engine_id,
+                    messages,
+                    ..
+                } = r1.into_json().unwrap();
+                assert_eq!(messages.len(), 1);
+                let result = mpc_http_protocol(client, &engine_id, evaluator, context, messages);
+                let const_sizes = HashMap::new();
+                let result = deserialize_output(&prg, &fn_def, &result)
+                    .unwrap()
+                    .as_bits(&prg, &const_sizes);
+                println!("{input_party_a}, {input_party_b} -> {result:?}");
+                assert_eq!(
+                    result,
+                    vec![input_party_a ^ input_party_b, input_party_a & input_party_b]
+                );
+            }
         }
     }
+}
 
-    // create engine session
+#[test]
+fn test_invalid_opening_message() {
+    let client = &Client::tracked(_rocket()).unwrap();
+    let r1 = new_session(client, xor_and_program(), "false".to_string(), Some(vec![1, 2, 3]));
+    assert_eq!(r1.status(), Status::InternalServerError);
 }
 
 /// runs protocol with upstream
 ///
-/// assumes upstream session was already created
+/// assumes upstream session was already created, with `upstream_msgs` as the messages that were
+/// received when creating it
 fn mpc_http_protocol(
     client: &Client,
     engine_id: &String,
-    program: Circuit,
-    input: Vec<bool>,
+    mut evaluator: Evaluator<Circuit, Vec<bool>>,
+    mut context: MsgQueue,
+    mut upstream_msgs: MessageLog,
 ) -> Vec<bool> {
-    let mut context = MsgQueue::new();
-    let mut evaluator = Evaluator::new(program, input, ChaCha20Rng::from_entropy()).unwrap();
-
     let mut last_durably_received_offset: Option<MessageId> = None;
     let mut steps_remaining = evaluator.steps();
     loop {
-        let messages: Vec<(&Msg, MessageId)> = context.msgs_iter().collect();
-        let (upstream_msgs, server_commited_offset) =
-            dialog(client, engine_id, last_durably_received_offset, &messages);
-        assert_eq!(messages.last().map(|v| v.1), server_commited_offset);
-
-        if let Some(last_durably_received_offset) = server_commited_offset {
-            context.flush_queue(last_durably_received_offset);
-        }
-
         for (msg, server_offset) in &upstream_msgs {
             assert_eq!(
                 *server_offset,
@@ -148,11 +164,21 @@ fn mpc_http_protocol(
             }
             last_durably_received_offset = Some(*server_offset);
         }
+
+        let messages: Vec<(&Msg, MessageId)> = context.msgs_iter().collect();
+        let (msgs, server_commited_offset) =
+            dialog(client, engine_id, last_durably_received_offset, &messages);
+        assert_eq!(messages.last().map(|v| v.1), server_commited_offset);
+
+        if let Some(last_durably_received_offset) = server_commited_offset {
+            context.flush_queue(last_durably_received_offset);
+        }
+        upstream_msgs = msgs;
     }
 }
 
-fn dialog<'a>(
-    client: &'a Client,
+fn dialog(
+    client: &Client,
     engine_id: &String,
     last_durably_received_offset: Option<u32>,
     messages: &Vec<(&Msg, MessageId)>,
@@ -165,7 +191,12 @@ fn dialog<'a>(
     bincode::deserialize(&res.into_bytes().unwrap()).unwrap()
 }
 
-fn new_session<'a>(client: &'a Client, program: String, input: String) -> LocalResponse<'a> {
+fn new_session<'a>(
+    client: &'a Client,
+    program: String,
+    input: String,
+    opening_message: Option<Msg>,
+) -> LocalResponse<'a> {
     let prg = check_program(&program).unwrap();
     let circuit = compile_program(&prg, "main").unwrap();
     let create_sess_uri = uri!(engine::create_session());
@@ -175,6 +206,7 @@ fn new_session<'a>(client: &'a Client, program: String, input: String) -> LocalR
         function: "main".to_string(),
         circuit_hash: circuit.gates.blake3_hash(),
         client_version: env!("CARGO_PKG_VERSION").to_string(),
+        opening_message,
     };
     client.post(create_sess_uri).json(&session).dispatch()
 }
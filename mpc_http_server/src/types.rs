@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
-use mpc_core::Circuit;
+use mpc_core::{states::Msg, Circuit};
 use rocket::serde::{Deserialize, Serialize};
 
+use crate::msg_queue::MessageId;
+
 pub type EngineId = String;
 
 /// Custom logic to choose a server's circuit and input.
@@ -39,4 +41,6 @@ pub(crate) struct EngineCreationResult {
     pub engine_id: String,
     pub request_headers: HashMap<String, String>,
     pub server_version: String,
+    /// The first messages of the server, so that the client can reply without another request.
+    pub messages: Vec<(Msg, MessageId)>,
 }